@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+use wordie_srs::srs::{SrsAlgorithm, SrsResult, Sentence};
+use wordie_srs::srs::import::ImportReport;
+use wordie_srs::srs::audio_source::AudioFetcher;
+use wordie_srs::srs::sources::SentenceSource;
+
+/// Progress messages sent from the add thread back to the ui
+enum AddProgress {
+    Added { sentence: Sentence, report: ImportReport },
+    Failed { sentence: Sentence, error: String },
+    Finished,
+}
+
+/// A batch of sentences being added on a background thread, so big imports don't block the ui
+pub struct AddJob {
+    sentences: Vec<Sentence>,
+    added: Vec<Sentence>,
+    /// The number of sentences that weren't added because they had no words left after the blacklist
+    skipped: usize,
+    failures: Vec<(Sentence, String)>,
+    report: ImportReport,
+    finished: bool,
+    cancel: Arc<AtomicBool>,
+    receiver: Receiver<AddProgress>,
+}
+
+impl AddJob {
+    /// Start adding sentences on a worker thread. The worker gets its own connection from
+    /// `connect`, since the app's srs algorithm can't be shared across threads. Sentences are
+    /// tagged with their entry in `tags`, by text, and given their source from `sources`, or a
+    /// manual source if they aren't in it. Audio is fetched for each sentence as it's added if
+    /// there's an `audio_fetcher`.
+    pub fn start<A, F>(connect: F, sentences: Vec<Sentence>, tags: HashMap<String, Vec<String>>,
+                       sources: HashMap<String, SentenceSource>, mut audio_fetcher: Option<AudioFetcher>) -> Self
+        where A: SrsAlgorithm,
+              F: FnOnce() -> SrsResult<A> + Send + 'static
+    {
+        let (sender, receiver) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        let thread_cancel = cancel.clone();
+        let thread_sentences = sentences.clone();
+        thread::spawn(move || {
+            let sentences = thread_sentences;
+
+            let mut srs_algorithm = match connect() {
+                Ok(srs_algorithm) => srs_algorithm,
+                Err(err) => {
+                    // Report every sentence as failed so they end up back in the input box
+                    let error = err.to_string();
+                    for sentence in sentences {
+                        sender.send(AddProgress::Failed { sentence, error: error.clone() }).ok();
+                    }
+                    sender.send(AddProgress::Finished).ok();
+                    return;
+                }
+            };
+
+            // Add sentences one at a time so one bad sentence doesn't abort the whole batch
+            for sentence in sentences {
+                if thread_cancel.load(Ordering::Relaxed) {
+                    log::info!("Add cancelled");
+                    break;
+                }
+
+                let result = srs_algorithm.add_sentences(std::slice::from_ref(&sentence))
+                    .and_then(|report| {
+                        for tag in tags.get(&sentence.text).into_iter().flatten() {
+                            srs_algorithm.tag_sentences(std::slice::from_ref(&sentence.id), tag)?;
+                        }
+                        let source = sources.get(&sentence.text).cloned().unwrap_or_else(SentenceSource::manual);
+                        srs_algorithm.set_sentence_source(std::slice::from_ref(&sentence.id), &source)?;
+                        Ok(report)
+                    });
+
+                let progress = match result {
+                    Ok(report) => {
+                        // Audio is optional, so failing to get it doesn't fail the sentence
+                        if let Some(audio_fetcher) = audio_fetcher.as_mut() {
+                            if let Err(err) = audio_fetcher.fetch(&sentence.text) {
+                                log::warn!("Failed to fetch audio for {}: {err}", sentence.text);
+                            }
+                        }
+
+                        AddProgress::Added { sentence, report }
+                    },
+                    Err(err) => AddProgress::Failed { sentence, error: err.to_string() },
+                };
+
+                if sender.send(progress).is_err() {
+                    // The ui went away, no point carrying on
+                    return;
+                }
+            }
+
+            sender.send(AddProgress::Finished).ok();
+        });
+
+        Self {
+            sentences,
+            added: Vec::new(),
+            skipped: 0,
+            failures: Vec::new(),
+            report: ImportReport::default(),
+            finished: false,
+            cancel,
+            receiver,
+        }
+    }
+
+    /// Process any progress messages from the worker, returns true once the job is finished
+    pub fn poll(&mut self) -> bool {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(AddProgress::Added { sentence, report }) => {
+                    if report.skipped_sentences.iter().any(|skipped| skipped.id == sentence.id) {
+                        self.skipped += 1;
+                    }
+                    else {
+                        self.added.push(sentence);
+                    }
+                    self.report.merge(report);
+                },
+                Ok(AddProgress::Failed { sentence, error }) => {
+                    log::warn!("Failed to add sentence {}: {error}", sentence.text);
+                    self.failures.push((sentence, error));
+                },
+                Ok(AddProgress::Finished) | Err(TryRecvError::Disconnected) => {
+                    self.finished = true;
+                    break;
+                },
+                Err(TryRecvError::Empty) => break,
+            }
+        }
+
+        self.finished
+    }
+
+    /// Ask the worker to stop after the sentence it's currently adding
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    /// The number of sentences the worker has got through so far
+    pub fn processed(&self) -> usize {
+        self.added.len() + self.skipped + self.failures.len()
+    }
+
+    /// The fraction of sentences processed so far
+    pub fn progress(&self) -> f32 {
+        if self.sentences.is_empty() {
+            1.0
+        }
+        else {
+            self.processed() as f32 / self.sentences.len() as f32
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.sentences.len()
+    }
+
+    /// The sentences the worker never got to, e.g. because the job was cancelled
+    pub fn unprocessed(&self) -> &[Sentence] {
+        &self.sentences[usize::min(self.processed(), self.sentences.len())..]
+    }
+
+    pub fn added(&self) -> usize {
+        self.added.len()
+    }
+
+    /// The sentences that have been added so far
+    pub fn added_sentences(&self) -> &[Sentence] {
+        &self.added
+    }
+
+    pub fn failures(&self) -> &[(Sentence, String)] {
+        &self.failures
+    }
+
+    /// Take the combined import report for all the sentences added so far
+    pub fn take_report(&mut self) -> ImportReport {
+        std::mem::take(&mut self.report)
+    }
+}
@@ -0,0 +1,195 @@
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+
+use chrono::{DateTime, Local};
+use uuid::Uuid;
+use wordie_srs::srs::SrsResult;
+use wordie_srs::srs::vacuum::MediaDirs;
+
+/// The folder media files are kept in, next to the config file
+pub const MEDIA_DIR: &str = "media";
+
+/// The folder media files are kept in, resolved from the config file's folder so it doesn't
+/// depend on which folder the app is started from
+pub fn media_dir() -> PathBuf {
+    std::fs::canonicalize(crate::CONFIG_FILE)
+        .ok()
+        .and_then(|config| config.parent().map(Path::to_path_buf))
+        .unwrap_or_default()
+        .join(MEDIA_DIR)
+}
+
+/// The folder audio fetched for sentences is cached in
+pub fn sentence_audio_dir() -> PathBuf {
+    media_dir().join("audio")
+}
+
+/// The folders of media for sentences, for cleaning up after sentences that are gone
+pub fn media_dirs() -> MediaDirs {
+    MediaDirs {
+        sentence_audio: sentence_audio_dir(),
+        recordings: recordings_dir(),
+    }
+}
+
+/// The folder recordings are kept in, with a folder in it for each sentence
+fn recordings_dir() -> PathBuf {
+    media_dir().join("recordings")
+}
+
+/// Where the recording for a review is stored, keyed by the sentence and when it was reviewed
+pub fn recording_path(sentence_id: &Uuid, time: DateTime<Local>) -> PathBuf {
+    recordings_dir()
+        .join(sentence_id.to_string())
+        .join(format!("{}.wav", time.format("%Y%m%d-%H%M%S")))
+}
+
+/// A recording from the default microphone in progress. Recording and playback use sox, so we
+/// don't need an audio library for each platform.
+pub struct Recorder {
+    child: Child,
+    path: PathBuf,
+}
+
+impl Recorder {
+    /// Start recording to `path`
+    pub fn start(path: &Path) -> SrsResult<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        log::info!("Recording to {path:?}");
+
+        let child = Command::new("sox")
+            .args(["-q", "-d"])
+            .arg(path)
+            .stdin(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("Failed to start sox for recording, is it installed? {e}"))?;
+
+        Ok(Self {
+            child,
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Stop recording, returning the path of the recording
+    pub fn stop(mut self) -> SrsResult<PathBuf> {
+        // Interrupting sox lets it finish writing the file, killing it would leave the wav
+        // header with the wrong length
+        if cfg!(unix) {
+            Command::new("kill").args(["-INT", &self.child.id().to_string()]).status()?;
+        }
+        else {
+            self.child.kill()?;
+        }
+
+        self.child.wait()?;
+
+        log::info!("Finished recording {:?}", self.path);
+
+        Ok(std::mem::take(&mut self.path))
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        // A recording that's dropped without being stopped is abandoned, e.g. when leaving the
+        // screen mid-recording, so stop sox and throw away the partial file
+        if matches!(self.child.try_wait(), Ok(None)) {
+            log::info!("Abandoning recording {:?}", self.path);
+            self.child.kill().ok();
+            self.child.wait().ok();
+            std::fs::remove_file(&self.path).ok();
+        }
+    }
+}
+
+/// Play an audio file in the background
+pub fn play(path: &Path) -> SrsResult<()> {
+    let mut child = start_playback(path)?;
+
+    // Wait for it on another thread so it doesn't block the ui or get left as a zombie
+    thread::spawn(move || child.wait());
+
+    Ok(())
+}
+
+/// Audio playing in the background, which is stopped when it's dropped
+pub struct Playback {
+    child: Child,
+}
+
+impl Playback {
+    pub fn is_finished(&mut self) -> bool {
+        !matches!(self.child.try_wait(), Ok(None))
+    }
+}
+
+impl Drop for Playback {
+    fn drop(&mut self) {
+        // Killing a finished player fails, which is fine
+        self.child.kill().ok();
+        self.child.wait().ok();
+    }
+}
+
+/// Play the part of an audio file between `start` and `end` seconds in the background, at `speed`
+/// times normal speed without changing the pitch
+pub fn play_segment(path: &Path, start: f32, end: f32, speed: f32) -> SrsResult<Playback> {
+    log::info!("Playing {path:?} from {start:.1}s to {end:.1}s at {speed:.2}x");
+
+    let child = Command::new("sox")
+        .arg("-q")
+        .arg(path)
+        .arg("-d")
+        .args(["trim", &format!("{start}"), &format!("={end}")])
+        // The speech option tunes the time stretching for voices
+        .args(["tempo", "-s", &format!("{speed}")])
+        .stdin(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to start sox for playback, is it installed? {e}"))?;
+
+    Ok(Playback { child })
+}
+
+/// Get the length of an audio file in seconds
+pub fn duration(path: &Path) -> SrsResult<f32> {
+    let output = Command::new("soxi")
+        .arg("-D")
+        .arg(path)
+        .output()
+        .map_err(|e| format!("Failed to run soxi, is sox installed? {e}"))?;
+
+    if !output.status.success() {
+        return Err(format!("soxi failed to read {path:?}: {}", String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().parse()?)
+}
+
+/// Play an audio file, returning once it's finished. Don't call this from the ui thread.
+pub fn play_to_end(path: &Path) -> SrsResult<()> {
+    let status = start_playback(path)?.wait()?;
+
+    if !status.success() {
+        return Err(format!("sox failed to play {path:?}: {status}").into());
+    }
+
+    Ok(())
+}
+
+fn start_playback(path: &Path) -> SrsResult<Child> {
+    log::info!("Playing {path:?}");
+
+    let child = Command::new("sox")
+        .arg("-q")
+        .arg(path)
+        .arg("-d")
+        .stdin(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to start sox for playback, is it installed? {e}"))?;
+
+    Ok(child)
+}
@@ -0,0 +1,70 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use wordie_srs::srs::{SrsAlgorithm, SrsResult};
+
+/// Value of the due count before the first successful poll
+const UNKNOWN: i32 = -1;
+
+/// Polls the number of cards due today on a background thread, so the ui can show it without
+/// querying the database every frame. Counts are only fetched again when the collection changes
+/// or the day rolls over.
+pub struct DuePoller {
+    due_count: Arc<AtomicI32>,
+}
+
+impl DuePoller {
+    /// Start polling every `interval`. The thread gets its own connection from `connect`.
+    pub fn start<A, F>(connect: F, interval: Duration) -> Self
+        where A: SrsAlgorithm,
+              F: Fn() -> SrsResult<A> + Send + 'static
+    {
+        let due_count = Arc::new(AtomicI32::new(UNKNOWN));
+
+        let thread_due_count = due_count.clone();
+        thread::spawn(move || {
+            let mut srs_algorithm = None;
+            let mut revision = None;
+
+            loop {
+                // Reconnect if we haven't connected yet or the last poll failed
+                if srs_algorithm.is_none() {
+                    srs_algorithm = connect()
+                        .map_err(|err| log::error!("Due poller failed to connect: {err}"))
+                        .ok();
+
+                    // A new connection might be to a different collection, so fetch the counts again
+                    revision = None;
+                }
+
+                if let Some(algorithm) = srs_algorithm.as_mut() {
+                    match algorithm.counts_if_changed(revision) {
+                        Ok(Some((stats, latest))) => {
+                            thread_due_count.store(stats.due_cards, Ordering::Relaxed);
+                            revision = Some(latest);
+                        },
+                        Ok(None) => {},
+                        Err(err) => {
+                            log::error!("Failed to poll due count: {err}");
+                            srs_algorithm = None;
+                        }
+                    }
+                }
+
+                thread::sleep(interval);
+            }
+        });
+
+        Self { due_count }
+    }
+
+    /// The number of cards due today, if it's been polled yet
+    pub fn due_count(&self) -> Option<i32> {
+        match self.due_count.load(Ordering::Relaxed) {
+            UNKNOWN => None,
+            count => Some(count),
+        }
+    }
+}
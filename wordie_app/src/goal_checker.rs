@@ -0,0 +1,72 @@
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+
+use wordie_srs::srs::{SrsAlgorithm, SrsResult};
+use wordie_srs::srs::daily_stats::DailyStats;
+use wordie_srs::srs::goals::DailyGoals;
+
+/// Checks whether the daily goals have been met on a background thread, so answering a review
+/// doesn't wait on a query for today's stats
+pub struct GoalChecker {
+    requests: Sender<()>,
+    receiver: Receiver<DailyStats>,
+}
+
+impl GoalChecker {
+    /// Start the checker. The thread gets its own connection from `connect`, and reconnects if a
+    /// check fails.
+    pub fn start<A, F>(connect: F, goals: DailyGoals) -> Self
+        where A: SrsAlgorithm,
+              F: Fn() -> SrsResult<A> + Send + 'static
+    {
+        let (requests, request_receiver) = mpsc::channel::<()>();
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            let mut srs_algorithm = None;
+
+            while request_receiver.recv().is_ok() {
+                // Checks asked for while the last one was running are all answered by this one
+                while request_receiver.try_recv().is_ok() {}
+
+                if srs_algorithm.is_none() {
+                    srs_algorithm = connect()
+                        .map_err(|err| log::error!("Goal checker failed to connect: {err}"))
+                        .ok();
+                }
+
+                if let Some(algorithm) = srs_algorithm.as_ref() {
+                    match algorithm.daily_stats(1) {
+                        Ok(stats) => {
+                            if let Some(today) = stats.into_iter().last().filter(|today| goals.is_met(today)) {
+                                if sender.send(today).is_err() {
+                                    return;
+                                }
+                            }
+                        },
+                        Err(err) => {
+                            log::error!("Failed to load daily stats: {err}");
+                            srs_algorithm = None;
+                        }
+                    }
+                }
+            }
+        });
+
+        Self { requests, receiver }
+    }
+
+    /// Ask for the goals to be checked, e.g. after a review is answered
+    pub fn check(&self) {
+        self.requests.send(()).ok();
+    }
+
+    /// Get today's stats if a check found the daily goals met, or None if none has since the last
+    /// call
+    pub fn poll(&self) -> Option<DailyStats> {
+        match self.receiver.try_recv() {
+            Ok(today) => Some(today),
+            Err(TryRecvError::Empty | TryRecvError::Disconnected) => None,
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+use wordie_srs::srs::{SrsAlgorithm, SrsResult};
+
+/// Detecting the grammar points in every sentence on a background thread, as tokenizing the whole
+/// collection takes a while and would freeze the ui
+pub struct GrammarJob {
+    receiver: Receiver<Result<usize, String>>,
+}
+
+impl GrammarJob {
+    /// Start detecting grammar points on a worker thread. The worker gets its own connection from
+    /// `connect`, like a `RebuildJob`.
+    pub fn start<A, F>(connect: F) -> Self
+        where A: SrsAlgorithm,
+              F: FnOnce() -> SrsResult<A> + Send + 'static
+    {
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            let result = connect()
+                .and_then(|mut srs_algorithm| srs_algorithm.detect_grammar_points())
+                .map_err(|err| err.to_string());
+
+            sender.send(result).ok();
+        });
+
+        Self { receiver }
+    }
+
+    /// Get the number of grammar points found, or None if it's still going
+    pub fn poll(&self) -> Option<Result<usize, String>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Disconnected) => Some(Err("The grammar point thread stopped unexpectedly".to_string())),
+            Err(TryRecvError::Empty) => None,
+        }
+    }
+}
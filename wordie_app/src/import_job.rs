@@ -0,0 +1,155 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+use wordie_srs::srs::{SrsAlgorithm, SrsResult, Sentence};
+use wordie_srs::srs::import::{self, ImportOptions, ImportProgress, SentenceStream};
+use wordie_srs::srs::sentence_splitter::SentenceSplitter;
+use wordie_srs::srs::sources::SentenceSource;
+
+/// Progress messages sent from the import thread back to the ui
+enum ImportMessage {
+    Chunk { sentences: Vec<Sentence>, progress: ImportProgress },
+    Finished,
+    Failed { error: String },
+}
+
+/// A text file being imported a chunk at a time on a background thread, for files too big to load
+/// into the input box. Unlike an `AddJob`, the file is never read into memory all at once, and no
+/// audio is fetched, as it'd take far too long for a file this size. For the same reason there's no
+/// import report, just the counts in its progress.
+pub struct ImportJob {
+    file_size: u64,
+    bytes_read: Arc<AtomicU64>,
+    progress: ImportProgress,
+    /// The sentences added so far, for passing on to plugins once the import is done
+    added: Vec<Sentence>,
+    error: Option<String>,
+    finished: bool,
+    cancel: Arc<AtomicBool>,
+    receiver: Receiver<ImportMessage>,
+}
+
+impl ImportJob {
+    /// Start importing a text file on a worker thread, splitting it into sentences with `splitter`
+    /// and filtering them with `options`. The worker gets its own connection from `connect`, like
+    /// an `AddJob`, and every sentence added is given `source`.
+    pub fn start<A, F>(connect: F, path: &Path, splitter: SentenceSplitter, options: ImportOptions,
+                       source: SentenceSource) -> SrsResult<Self>
+        where A: SrsAlgorithm,
+              F: FnOnce() -> SrsResult<A> + Send + 'static
+    {
+        log::info!("Streaming import of {path:?}");
+
+        let file = File::open(path)
+            .map_err(|err| format!("Failed to open {path:?}: {err}"))?;
+        let file_size = file.metadata()?.len();
+
+        let stream = SentenceStream::new(BufReader::new(file), splitter);
+        let bytes_read = stream.bytes_read();
+
+        let (sender, receiver) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        let thread_cancel = cancel.clone();
+        thread::spawn(move || {
+            let result = connect().and_then(|mut srs_algorithm| {
+                import::import_stream(&mut srs_algorithm, stream, &options, &thread_cancel, |srs_algorithm, sentences, _, progress| {
+                    if !sentences.is_empty() {
+                        let ids = sentences.iter().map(|sentence| sentence.id).collect::<Vec<_>>();
+                        srs_algorithm.set_sentence_source(&ids, &source)?;
+                    }
+
+                    // The ui going away stops the import
+                    sender.send(ImportMessage::Chunk { sentences: sentences.to_vec(), progress: progress.clone() })
+                        .map_err(|_| "Import cancelled, the ui went away")?;
+
+                    Ok(())
+                })
+            });
+
+            let message = match result {
+                Ok(_) => ImportMessage::Finished,
+                Err(err) => ImportMessage::Failed { error: err.to_string() },
+            };
+            sender.send(message).ok();
+        });
+
+        Ok(Self {
+            file_size,
+            bytes_read,
+            progress: ImportProgress::default(),
+            added: Vec::new(),
+            error: None,
+            finished: false,
+            cancel,
+            receiver,
+        })
+    }
+
+    /// Process any progress messages from the worker, returns true once the job is finished
+    pub fn poll(&mut self) -> bool {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(ImportMessage::Chunk { sentences, progress }) => {
+                    self.added.extend(sentences);
+                    self.progress = progress;
+                },
+                Ok(ImportMessage::Finished) => {
+                    self.finished = true;
+                    break;
+                },
+                Ok(ImportMessage::Failed { error }) => {
+                    log::error!("Import failed: {error}");
+                    self.error = Some(error);
+                    self.finished = true;
+                    break;
+                },
+                Err(TryRecvError::Disconnected) => {
+                    self.finished = true;
+                    break;
+                },
+                Err(TryRecvError::Empty) => break,
+            }
+        }
+
+        self.finished
+    }
+
+    /// Ask the worker to stop after the chunk it's currently adding
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    /// The fraction of the file read so far
+    pub fn fraction_read(&self) -> f32 {
+        if self.file_size == 0 {
+            1.0
+        }
+        else {
+            self.bytes_read.load(Ordering::Relaxed) as f32 / self.file_size as f32
+        }
+    }
+
+    pub fn progress(&self) -> &ImportProgress {
+        &self.progress
+    }
+
+    /// The error that stopped the import, if it didn't get to the end of the file
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// The sentences that have been added so far
+    pub fn added_sentences(&self) -> &[Sentence] {
+        &self.added
+    }
+}
@@ -0,0 +1,165 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use wordie_srs::srs::Sentence;
+use wordie_srs::srs::audio_source::{self, AudioFetcher};
+
+use crate::audio;
+
+/// How often the gap between sentences checks whether the job has been stopped
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Progress messages sent from the playback thread back to the ui
+enum ListenProgress {
+    Playing(usize),
+    Failed { index: usize, error: String },
+    Finished,
+}
+
+/// Sentences being played one after another on a background thread, for listening to reviews
+/// without grading them
+pub struct ListenJob {
+    sentences: Vec<Sentence>,
+    current: Option<usize>,
+    errors: Vec<String>,
+    finished: bool,
+    stop: Arc<AtomicBool>,
+    gap_millis: Arc<AtomicU64>,
+    receiver: Receiver<ListenProgress>,
+}
+
+impl ListenJob {
+    /// Start playing `sentences` in order with `gap` between them. Audio that isn't cached yet is
+    /// fetched first if there's an `audio_fetcher`, otherwise sentences without audio are skipped.
+    pub fn start(sentences: Vec<Sentence>, gap: Duration, mut audio_fetcher: Option<AudioFetcher>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let gap_millis = Arc::new(AtomicU64::new(gap.as_millis() as u64));
+
+        let thread_stop = stop.clone();
+        let thread_gap_millis = gap_millis.clone();
+        let thread_sentences = sentences.clone();
+        thread::spawn(move || {
+            for (index, sentence) in thread_sentences.iter().enumerate() {
+                if thread_stop.load(Ordering::Relaxed) {
+                    log::info!("Listening stopped");
+                    break;
+                }
+
+                if sender.send(ListenProgress::Playing(index)).is_err() {
+                    // The ui went away, no point carrying on
+                    return;
+                }
+
+                let audio_path = match audio_fetcher.as_mut() {
+                    Some(audio_fetcher) => audio_fetcher.fetch(&sentence.text),
+                    None => {
+                        let path = audio_source::cache_path(&audio::sentence_audio_dir(), &sentence.text);
+                        Ok(path.exists().then_some(path))
+                    }
+                };
+
+                let result = match audio_path {
+                    Ok(Some(path)) => audio::play_to_end(&path),
+                    Ok(None) => {
+                        log::info!("No audio for {}, skipping it", sentence.text);
+                        continue;
+                    },
+                    Err(err) => Err(err),
+                };
+
+                if let Err(err) = result {
+                    sender.send(ListenProgress::Failed { index, error: err.to_string() }).ok();
+                    continue;
+                }
+
+                wait_for_gap(&thread_gap_millis, &thread_stop);
+            }
+
+            sender.send(ListenProgress::Finished).ok();
+        });
+
+        Self {
+            sentences,
+            current: None,
+            errors: Vec::new(),
+            finished: false,
+            stop,
+            gap_millis,
+            receiver,
+        }
+    }
+
+    /// Process any progress messages from the playback thread, returns true once the job is finished
+    pub fn poll(&mut self) -> bool {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(ListenProgress::Playing(index)) => self.current = Some(index),
+                Ok(ListenProgress::Failed { index, error }) => {
+                    log::warn!("Failed to play sentence {}: {error}", self.sentences[index].text);
+                    self.errors.push(error);
+                },
+                Ok(ListenProgress::Finished) | Err(TryRecvError::Disconnected) => {
+                    self.finished = true;
+                    break;
+                },
+                Err(TryRecvError::Empty) => break,
+            }
+        }
+
+        self.finished
+    }
+
+    /// Ask the playback thread to stop after the sentence it's currently playing
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    /// Change the gap between sentences, from the next gap on
+    pub fn set_gap(&self, gap: Duration) {
+        self.gap_millis.store(gap.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// The sentence being played, or the last one played once the job is finished
+    pub fn current(&self) -> Option<(usize, &Sentence)> {
+        self.current.map(|index| (index, &self.sentences[index]))
+    }
+
+    pub fn total(&self) -> usize {
+        self.sentences.len()
+    }
+
+    /// Take the errors from sentences that failed to play since the last call
+    pub fn take_errors(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.errors)
+    }
+}
+
+impl Drop for ListenJob {
+    fn drop(&mut self) {
+        // Don't keep playing after leaving the listen screen
+        self.stop();
+    }
+}
+
+/// Wait out the gap between sentences, returning early if the job is stopped
+fn wait_for_gap(gap_millis: &AtomicU64, stop: &AtomicBool) {
+    let start = Instant::now();
+
+    while !stop.load(Ordering::Relaxed) {
+        let gap = Duration::from_millis(gap_millis.load(Ordering::Relaxed));
+        let elapsed = start.elapsed();
+        if elapsed >= gap {
+            break;
+        }
+
+        thread::sleep(Duration::min(gap - elapsed, STOP_CHECK_INTERVAL));
+    }
+}
@@ -0,0 +1,44 @@
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+use wordie_srs::srs::ocr;
+
+/// Reading the text in a screenshot on a background thread, as tesseract takes a few seconds and
+/// would freeze the ui
+pub struct OcrJob {
+    path: PathBuf,
+    receiver: Receiver<Result<String, String>>,
+}
+
+impl OcrJob {
+    /// Start reading the text in the image at `path`, see `ocr::recognize`
+    pub fn start(path: PathBuf, language: &str) -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        let image_path = path.clone();
+        let language = language.to_string();
+        thread::spawn(move || {
+            let result = ocr::recognize(&image_path, &language)
+                .map_err(|err| err.to_string());
+
+            sender.send(result).ok();
+        });
+
+        Self { path, receiver }
+    }
+
+    /// The image being read
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+
+    /// Get the recognized text, or None if it's still going
+    pub fn poll(&self) -> Option<Result<String, String>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Disconnected) => Some(Err("The ocr thread stopped unexpectedly".to_string())),
+            Err(TryRecvError::Empty) => None,
+        }
+    }
+}
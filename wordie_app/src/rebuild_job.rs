@@ -0,0 +1,41 @@
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+use wordie_srs::srs::{SrsAlgorithm, SrsResult};
+use wordie_srs::srs::rebuild::RebuildReport;
+
+/// Rebuilding the cards from the sentences on a background thread, as retokenizing every sentence
+/// takes a while on a large collection and would freeze the ui
+pub struct RebuildJob {
+    receiver: Receiver<Result<RebuildReport, String>>,
+}
+
+impl RebuildJob {
+    /// Start rebuilding the cards on a worker thread. The worker gets its own connection from
+    /// `connect`, like an `AddJob`.
+    pub fn start<A, F>(connect: F) -> Self
+        where A: SrsAlgorithm,
+              F: FnOnce() -> SrsResult<A> + Send + 'static
+    {
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            let result = connect()
+                .and_then(|mut srs_algorithm| srs_algorithm.rebuild_cards())
+                .map_err(|err| err.to_string());
+
+            sender.send(result).ok();
+        });
+
+        Self { receiver }
+    }
+
+    /// Get what the rebuild changed, or None if it's still going
+    pub fn poll(&self) -> Option<Result<RebuildReport, String>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Disconnected) => Some(Err("The rebuild thread stopped unexpectedly".to_string())),
+            Err(TryRecvError::Empty) => None,
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+use chrono::{Local, NaiveDate, NaiveTime};
+use wordie_srs::srs::{SrsAlgorithm, SrsResult};
+
+/// How often the reminder thread checks whether it's time to remind
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Check whether there are still reviews due today, and show a desktop notification if so.
+/// Returns whether a notification was shown.
+pub fn remind(srs_algorithm: &dyn SrsAlgorithm) -> SrsResult<bool> {
+    let due_today = srs_algorithm.forecast(1)?.first().cloned().unwrap_or(0);
+
+    if due_today == 0 {
+        log::info!("No reviews left today, not reminding");
+        return Ok(false);
+    }
+
+    log::info!("{due_today} cards still due today, sending reminder");
+    notify("Wordie", &format!("You still have {due_today} cards to review today"))?;
+
+    Ok(true)
+}
+
+/// Start a thread that reminds once a day at `time` if there are still reviews due. The thread
+/// gets its own connection from `connect`, like the add job.
+pub fn start_reminder_thread<A, F>(connect: F, time: NaiveTime)
+    where A: SrsAlgorithm,
+          F: Fn() -> SrsResult<A> + Send + 'static
+{
+    thread::spawn(move || {
+        let mut last_reminded: Option<NaiveDate> = None;
+
+        loop {
+            let now = Local::now();
+            let today = now.naive_local().date();
+
+            if now.time() >= time && last_reminded != Some(today) {
+                // Only try once a day, even if it fails, so a dead database doesn't spam errors
+                last_reminded = Some(today);
+
+                let result = connect().and_then(|srs_algorithm| remind(&srs_algorithm));
+
+                if let Err(err) = result {
+                    log::error!("Failed to send reminder: {err}");
+                }
+            }
+
+            thread::sleep(CHECK_INTERVAL);
+        }
+    });
+}
+
+/// Show a desktop notification using the platform's own tools, so we don't need a notification
+/// library for each platform
+fn notify(title: &str, body: &str) -> SrsResult<()> {
+    let status = if cfg!(target_os = "windows") {
+        let script = format!(
+            "[reflection.assembly]::loadwithpartialname('System.Windows.Forms') | Out-Null; \
+             $n = New-Object System.Windows.Forms.NotifyIcon; \
+             $n.Icon = [System.Drawing.SystemIcons]::Information; \
+             $n.Visible = $true; \
+             $n.ShowBalloonTip(10000, '{}', '{}', 'Info'); \
+             Start-Sleep -Seconds 10",
+            title.replace('\'', "''"), body.replace('\'', "''"));
+        Command::new("powershell").args(["-NoProfile", "-Command", &script]).status()?
+    }
+    else if cfg!(target_os = "macos") {
+        let script = format!("display notification {body:?} with title {title:?}");
+        Command::new("osascript").args(["-e", &script]).status()?
+    }
+    else {
+        Command::new("notify-send").args([title, body]).status()?
+    };
+
+    if !status.success() {
+        return Err(format!("Notification command failed: {status}").into());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use wordie_srs::srs::{SrsAlgorithm, SrsResult, Review, Difficulty};
+use wordie_srs::srs::scheduler::AnswerButtons;
+use wordie_srs::srs::session_state::SavedSession;
+
+/// A study session that keeps serving reviews until a time limit runs out, rather than stopping
+/// after a number of cards
+pub struct TimedSession {
+    started: Instant,
+    limit: Duration,
+    answers: HashMap<Difficulty, i32>,
+}
+
+impl TimedSession {
+    /// Start a session that lasts `limit`
+    pub fn start(limit: Duration) -> Self {
+        log::info!("Starting a {} minute session", limit.as_secs() / 60);
+
+        Self {
+            started: Instant::now(),
+            limit,
+            answers: HashMap::new(),
+        }
+    }
+
+    /// Carry on with a session saved when the app was closed, see `saved`
+    pub fn resume(limit: Duration, elapsed: Duration, answers: Vec<(Difficulty, i32)>) -> Self {
+        log::info!("Resuming a {} minute session", limit.as_secs() / 60);
+
+        Self {
+            started: Instant::now().checked_sub(elapsed).unwrap_or_else(Instant::now),
+            limit,
+            answers: answers.into_iter().collect(),
+        }
+    }
+
+    /// The session's time and answers so far, for saving when the app is closed
+    pub fn saved(&self) -> SavedSession {
+        SavedSession::Timed {
+            limit_secs: self.limit.as_secs(),
+            elapsed_secs: self.elapsed().as_secs(),
+            answers: self.answers.iter().map(|(difficulty, count)| (*difficulty, *count)).collect(),
+        }
+    }
+
+    /// Whether the time is up
+    pub fn is_over(&self) -> bool {
+        self.started.elapsed() >= self.limit
+    }
+
+    /// The time left in the session
+    pub fn remaining(&self) -> Duration {
+        self.limit.saturating_sub(self.started.elapsed())
+    }
+
+    /// The time spent in the session so far, up to the limit
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed().min(self.limit)
+    }
+
+    /// Get the next review, dues first so the time goes on keeping up before learning new cards,
+    /// or None if the time is up or there's nothing left
+    pub fn next_review(&self, srs_algorithm: &dyn SrsAlgorithm) -> SrsResult<Option<Review>> {
+        if self.is_over() {
+            return Ok(None);
+        }
+
+        // Only learn new cards once there's nothing due
+        match srs_algorithm.get_due_queue(1)?.into_iter().next() {
+            Some(review) => Ok(Some(review)),
+            None => Ok(srs_algorithm.get_review_queue(1)?.into_iter().next()),
+        }
+    }
+
+    /// Record an answer for the summary
+    pub fn record_answer(&mut self, difficulty: Difficulty) {
+        *self.answers.entry(difficulty).or_insert(0) += 1;
+    }
+
+    /// The number of reviews answered in the session
+    pub fn reviewed(&self) -> i32 {
+        self.answers.values().sum()
+    }
+
+    /// The number of reviews answered with a difficulty
+    pub fn answers(&self, difficulty: Difficulty) -> i32 {
+        self.answers.get(&difficulty).cloned().unwrap_or(0)
+    }
+}
+
+/// A session for clearing a backlog of due cards quickly, answered with just pass or fail and
+/// no new cards
+#[derive(Default)]
+pub struct SpeedSession {
+    passed: i32,
+    failed: i32,
+}
+
+impl SpeedSession {
+    /// Carry on with a session saved when the app was closed, see `saved`
+    pub fn resume(passed: i32, failed: i32) -> Self {
+        Self { passed, failed }
+    }
+
+    /// The passes and fails so far, for saving when the app is closed
+    pub fn saved(&self) -> SavedSession {
+        SavedSession::Speed { passed: self.passed, failed: self.failed }
+    }
+
+    /// Get the next due review, or None if there's nothing left due
+    pub fn next_review(&self, srs_algorithm: &dyn SrsAlgorithm) -> SrsResult<Option<Review>> {
+        Ok(srs_algorithm.get_due_queue(1)?.into_iter().next())
+    }
+
+    /// The difficulty a pass or fail is answered with, the same as the pass/fail answer buttons
+    pub fn difficulty(passed: bool) -> Difficulty {
+        AnswerButtons::PassFail.difficulties()[passed as usize]
+    }
+
+    /// Record an answer for the summary
+    pub fn record_answer(&mut self, passed: bool) {
+        if passed {
+            self.passed += 1;
+        }
+        else {
+            self.failed += 1;
+        }
+    }
+
+    pub fn passed(&self) -> i32 {
+        self.passed
+    }
+
+    pub fn failed(&self) -> i32 {
+        self.failed
+    }
+}
@@ -0,0 +1,70 @@
+use egui::{Color32, Stroke, Visuals};
+use wordie_srs::srs::config::Accessibility;
+
+/// The size of the text on review cards
+const CARD_TEXT_SIZE: f32 = 28.0;
+
+/// How much bigger review card text is with `Accessibility::large_text`
+const LARGE_TEXT_SCALE: f32 = 1.5;
+
+/// The selection color in the high contrast palette
+const HIGH_CONTRAST_SELECTION: Color32 = Color32::from_rgb(0, 90, 220);
+
+/// Set up the app's style for the accessibility settings. Everything starts from egui's default
+/// style, so settings can be turned off again by applying them.
+pub fn apply(ctx: &egui::Context, accessibility: &Accessibility) {
+    let mut style = egui::Style::default();
+
+    if accessibility.high_contrast {
+        style.visuals = high_contrast_visuals();
+    }
+
+    style.spacing.item_spacing.y *= accessibility.line_spacing;
+
+    if accessibility.reduced_motion {
+        style.animation_time = 0.0;
+    }
+
+    ctx.set_style(style);
+}
+
+/// The size to show text on review cards at
+pub fn card_text_size(accessibility: &Accessibility) -> f32 {
+    if accessibility.large_text {
+        CARD_TEXT_SIZE * LARGE_TEXT_SCALE
+    }
+    else {
+        CARD_TEXT_SIZE
+    }
+}
+
+/// The vertical spacing for a screen that spaces its items out by `spacing`, which grows with the
+/// line spacing setting
+pub fn item_spacing(accessibility: &Accessibility, spacing: f32) -> f32 {
+    spacing * accessibility.line_spacing
+}
+
+/// White on black, with white outlines around widgets so they stand out from the background
+fn high_contrast_visuals() -> Visuals {
+    let mut visuals = Visuals::dark();
+
+    visuals.override_text_color = Some(Color32::WHITE);
+    visuals.extreme_bg_color = Color32::BLACK;
+    visuals.faint_bg_color = Color32::BLACK;
+    visuals.selection.bg_fill = HIGH_CONTRAST_SELECTION;
+    visuals.selection.stroke = Stroke::new(1.0, Color32::WHITE);
+
+    visuals.widgets.noninteractive.bg_fill = Color32::BLACK;
+    visuals.widgets.noninteractive.fg_stroke = Stroke::new(1.0, Color32::WHITE);
+
+    for widget in [&mut visuals.widgets.inactive, &mut visuals.widgets.hovered, &mut visuals.widgets.active] {
+        widget.bg_stroke = Stroke::new(1.5, Color32::WHITE);
+        widget.fg_stroke = Stroke::new(1.5, Color32::WHITE);
+    }
+
+    visuals.widgets.inactive.bg_fill = Color32::BLACK;
+    visuals.widgets.hovered.bg_fill = Color32::from_gray(60);
+    visuals.widgets.active.bg_fill = HIGH_CONTRAST_SELECTION;
+
+    visuals
+}
@@ -0,0 +1,41 @@
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+use wordie_srs::srs::SrsResult;
+use wordie_srs::srs::sync::{SyncClient, SyncConfig, SyncStore, SyncSummary};
+
+/// A sync with the sync server running on a background thread, so a slow server doesn't freeze
+/// the ui
+pub struct SyncJob {
+    receiver: Receiver<Result<SyncSummary, String>>,
+}
+
+impl SyncJob {
+    /// Start syncing with the server in `config` on a worker thread. The worker gets its own
+    /// connection from `connect`, like an `AddJob`.
+    pub fn start<S, F>(connect: F, config: SyncConfig) -> Self
+        where S: SyncStore,
+              F: FnOnce() -> SrsResult<S> + Send + 'static
+    {
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            let result = SyncClient::from_config(&config)
+                .and_then(|client| connect().and_then(|mut store| client.sync(&mut store)))
+                .map_err(|err| err.to_string());
+
+            sender.send(result).ok();
+        });
+
+        Self { receiver }
+    }
+
+    /// Get the result of the sync, or None if it's still going
+    pub fn poll(&self) -> Option<Result<SyncSummary, String>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Disconnected) => Some(Err("The sync thread stopped unexpectedly".to_string())),
+            Err(TryRecvError::Empty) => None,
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use std::time::{Duration, Instant};
+
+use eframe::egui;
+use egui::{RichText, Color32};
+use wordie_srs::srs::{Difficulty, Review};
+
+/// How long toasts without a retry action stay on screen
+const TOAST_DURATION: Duration = Duration::from_secs(5);
+
+/// An action that failed and can be retried from an error toast
+#[derive(Debug, Clone)]
+pub enum RetryAction {
+    InitializeDb,
+    GetNextReview,
+    /// Answering a review, which is kept so the retry answers it even if the user's moved on
+    AnswerReview(Box<Review>, Difficulty),
+}
+
+impl PartialEq for RetryAction {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (RetryAction::InitializeDb, RetryAction::InitializeDb) => true,
+            (RetryAction::GetNextReview, RetryAction::GetNextReview) => true,
+            (RetryAction::AnswerReview(review, difficulty), RetryAction::AnswerReview(other_review, other_difficulty)) => {
+                review.sentence().id == other_review.sentence().id
+                    && review.card_type() == other_review.card_type()
+                    && difficulty == other_difficulty
+            },
+            _ => false,
+        }
+    }
+}
+
+impl Eq for RetryAction {}
+
+/// A toast notification shown on top of the current screen
+struct Toast {
+    text: String,
+    retry: Option<RetryAction>,
+    created: Instant,
+}
+
+/// The list of active toasts, along with any retries the user requested from them
+#[derive(Default)]
+pub struct Toasts {
+    toasts: Vec<Toast>,
+    retries: Vec<RetryAction>,
+}
+
+impl Toasts {
+    /// Show an error toast, optionally with an action the user can retry
+    pub fn error(&mut self, text: impl Into<String>, retry: Option<RetryAction>) {
+        let text = text.into();
+        log::error!("{text}");
+
+        // Don't stack up identical toasts if the same thing keeps failing
+        self.toasts.retain(|toast| toast.text != text || toast.retry != retry);
+
+        self.toasts.push(Toast {
+            text,
+            retry,
+            created: Instant::now(),
+        });
+    }
+
+    /// Take the retries the user has requested since the last call
+    pub fn take_retries(&mut self) -> Vec<RetryAction> {
+        std::mem::take(&mut self.retries)
+    }
+
+    /// Take a specific retry if the user has requested it, returns whether they had
+    pub fn take_retry(&mut self, action: RetryAction) -> bool {
+        let requested = self.retries.contains(&action);
+        self.retries.retain(|retry| *retry != action);
+        requested
+    }
+
+    /// Draw the toasts in the bottom right corner
+    pub fn show(&mut self, ctx: &egui::Context) {
+        // Toasts with a retry action stay until they're dismissed or retried, others time out
+        self.toasts.retain(|toast| toast.retry.is_some() || toast.created.elapsed() < TOAST_DURATION);
+
+        if self.toasts.is_empty() {
+            return;
+        }
+
+        let mut closed = Vec::new();
+
+        egui::Area::new("toasts")
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::Vec2::new(-10.0, -10.0))
+            .show(ctx, |ui| {
+                for (i, toast) in self.toasts.iter().enumerate() {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new(&toast.text).color(Color32::LIGHT_RED));
+
+                            if let Some(retry) = toast.retry.as_ref() {
+                                if ui.button("Retry").clicked() {
+                                    log::info!("Retrying {retry:?}");
+                                    self.retries.push(retry.clone());
+                                    closed.push(i);
+                                }
+                            }
+
+                            if ui.button("x").clicked() {
+                                closed.push(i);
+                            }
+                        });
+                    });
+                }
+            });
+
+        for i in closed.into_iter().rev() {
+            self.toasts.remove(i);
+        }
+
+        // Keep repainting so timed out toasts disappear without waiting for input
+        ctx.request_repaint_after(Duration::from_millis(500));
+    }
+}
@@ -0,0 +1,140 @@
+use std::sync::mpsc::{self, Receiver};
+
+/// Something the user did with the tray icon
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrayEvent {
+    /// Bring the window back
+    Open,
+    Quit,
+}
+
+/// A system tray icon showing the number of cards due, so it can be seen while the window's
+/// hidden. Only linux desktops with StatusNotifierItem support have one for now.
+pub struct Tray {
+    icon: platform::Icon,
+    events: Receiver<TrayEvent>,
+}
+
+impl Tray {
+    /// Add the icon to the tray, if there is one. `ctx` is repainted when the icon's clicked so
+    /// the event gets picked up even while the window's hidden.
+    pub fn start(ctx: &egui::Context) -> Option<Self> {
+        let (sender, events) = mpsc::channel();
+
+        platform::Icon::start(sender, ctx.clone())
+            .map(|icon| Self { icon, events })
+    }
+
+    /// Show a new due count on the icon
+    pub fn set_due_count(&self, due_count: Option<i32>) {
+        self.icon.set_due_count(due_count);
+    }
+
+    /// Get the next thing the user did with the icon, if any
+    pub fn poll(&self) -> Option<TrayEvent> {
+        self.events.try_recv().ok()
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::sync::mpsc::Sender;
+
+    use ksni::menu::StandardItem;
+
+    use super::TrayEvent;
+
+    /// The tray's state, which ksni serves over dbus from its own thread
+    struct TrayState {
+        due_count: Option<i32>,
+        events: Sender<TrayEvent>,
+        ctx: egui::Context,
+    }
+
+    impl TrayState {
+        fn send(&self, event: TrayEvent) {
+            // The app's gone if the receiver has, so there's nobody to tell
+            self.events.send(event).ok();
+            self.ctx.request_repaint();
+        }
+    }
+
+    impl ksni::Tray for TrayState {
+        fn id(&self) -> String {
+            "wordie".to_string()
+        }
+
+        fn title(&self) -> String {
+            match self.due_count {
+                Some(count) => format!("{} ({count} due)", crate::WINDOW_TITLE),
+                None => crate::WINDOW_TITLE.to_string(),
+            }
+        }
+
+        fn icon_name(&self) -> String {
+            "accessories-dictionary".to_string()
+        }
+
+        fn tool_tip(&self) -> ksni::ToolTip {
+            ksni::ToolTip {
+                title: self.title(),
+                ..Default::default()
+            }
+        }
+
+        fn activate(&mut self, _x: i32, _y: i32) {
+            self.send(TrayEvent::Open);
+        }
+
+        fn menu(&self) -> Vec<ksni::MenuItem<Self>> {
+            vec![
+                StandardItem {
+                    label: "Review".into(),
+                    activate: Box::new(|tray: &mut Self| tray.send(TrayEvent::Open)),
+                    ..Default::default()
+                }.into(),
+                StandardItem {
+                    label: "Quit".into(),
+                    icon_name: "application-exit".into(),
+                    activate: Box::new(|tray: &mut Self| tray.send(TrayEvent::Quit)),
+                    ..Default::default()
+                }.into(),
+            ]
+        }
+    }
+
+    pub struct Icon {
+        handle: ksni::Handle<TrayState>,
+    }
+
+    impl Icon {
+        pub fn start(events: Sender<TrayEvent>, ctx: egui::Context) -> Option<Self> {
+            let service = ksni::TrayService::new(TrayState { due_count: None, events, ctx });
+            let handle = service.handle();
+            service.spawn();
+
+            Some(Self { handle })
+        }
+
+        pub fn set_due_count(&self, due_count: Option<i32>) {
+            self.handle.update(|tray| tray.due_count = due_count);
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod platform {
+    use std::sync::mpsc::Sender;
+
+    use super::TrayEvent;
+
+    pub struct Icon;
+
+    impl Icon {
+        pub fn start(_events: Sender<TrayEvent>, _ctx: egui::Context) -> Option<Self> {
+            None
+        }
+
+        pub fn set_due_count(&self, _due_count: Option<i32>) {}
+    }
+}
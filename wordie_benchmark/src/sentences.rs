@@ -3,6 +3,7 @@ use serde::{Serialize, Deserialize, de::DeserializeOwned};
 use uuid::Uuid;
 
 use wordie_srs::srs::Sentence;
+use wordie_srs::srs::targets::TargetWord;
 
 /// The sentences.csv file
 const CORE_6K: &'static [u8] = include_bytes!("../../resources/sentences.csv");
@@ -32,17 +33,25 @@ struct CoreSentence {
     sentence_cloze: String,
 }
 
-impl From<CoreSentence> for Sentence {
+impl From<CoreSentence> for (Sentence, TargetWord) {
     fn from(cs: CoreSentence) -> Self {
-        Sentence {
+        let sentence = Sentence {
             id: Uuid::new_v4(),
             text: cs.sentence_expression,
-        }
+        };
+
+        let target = TargetWord {
+            word: cs.vocab_expression,
+            reading: cs.vocab_kana,
+            meaning: cs.vocab_meaning,
+        };
+
+        (sentence, target)
     }
 }
 
 /// Load sentences from a csv in a &[u8] up to an (optional) maximum number
-fn from_csv<T: Into<Sentence> + DeserializeOwned>(csv: &[u8], max_sentences: Option<usize>) -> Result<Vec<Sentence>, Box<dyn Error>> {
+fn from_csv<T: DeserializeOwned, U: From<T>>(csv: &[u8], max_sentences: Option<usize>) -> Result<Vec<U>, Box<dyn Error>> {
     let cursor = Cursor::new(csv);
     let mut reader = csv::Reader::from_reader(cursor);
 
@@ -63,5 +72,10 @@ fn from_csv<T: Into<Sentence> + DeserializeOwned>(csv: &[u8], max_sentences: Opt
 
 /// Load core 6k sentences
 pub fn core_6k(max_sentences: Option<usize>) -> Result<Vec<Sentence>, Box<dyn Error>> {
-    from_csv::<CoreSentence>(CORE_6K, max_sentences)
+    Ok(core_6k_with_targets(max_sentences)?.into_iter().map(|(sentence, _)| sentence).collect())
+}
+
+/// Load core 6k sentences along with the word each one was written to teach
+pub fn core_6k_with_targets(max_sentences: Option<usize>) -> Result<Vec<(Sentence, TargetWord)>, Box<dyn Error>> {
+    from_csv::<CoreSentence, (Sentence, TargetWord)>(CORE_6K, max_sentences)
 }
@@ -0,0 +1,178 @@
+use std::error::Error;
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use wordie_srs::srs::SrsAlgorithm;
+use wordie_srs::srs::wordie::WordieSrsAlgorithm;
+
+/// How long a scrape can take to send its request before it's dropped. Scrapes are handled one
+/// at a time, so a client that connects and never sends anything would otherwise block the rest.
+const SCRAPE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The new card limit to time the queue with, the same as the app's, so the timing includes
+/// looking for new sentences like the app does
+const QUEUE_NEW_CARD_LIMIT: i32 = 50;
+
+/// Counters updated by the sync server and reported on the metrics endpoint
+#[derive(Debug, Default)]
+pub struct Metrics {
+    /// Sync requests handled since the server started
+    pub sync_requests: AtomicU64,
+    /// Database operations that failed since the server started, from syncing or scraping
+    pub db_errors: AtomicU64,
+}
+
+impl Metrics {
+    pub fn count_sync_request(&self) {
+        self.sync_requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn count_db_error(&self) {
+        self.db_errors.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Start a thread serving metrics in the prometheus text format on `addr`, at `/metrics`. The
+/// thread gets its own connection to the database at `db_url`, so scrapes don't wait on syncs.
+/// Without a database only the sync counters are reported.
+pub fn start_server_thread(db_url: Option<String>, addr: &'static str, metrics: Arc<Metrics>) {
+    thread::spawn(move || {
+        let result = db_url
+            .map(|db_url| WordieSrsAlgorithm::new(&db_url, QUEUE_NEW_CARD_LIMIT))
+            .transpose()
+            .and_then(|store| serve(store.as_ref(), addr, &metrics));
+
+        if let Err(e) = result {
+            log::error!("Metrics server stopped: {e}");
+        }
+    });
+}
+
+/// Serve scrapes on `addr` until the listener fails
+fn serve(store: Option<&WordieSrsAlgorithm>, addr: &str, metrics: &Metrics) -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind(addr)?;
+    log::info!("Serving metrics on {addr}");
+
+    for stream in listener.incoming() {
+        let result = stream
+            .map_err(Box::<dyn Error>::from)
+            .and_then(|stream| handle_scrape(store, metrics, stream));
+
+        if let Err(e) = result {
+            log::error!("Metrics request failed: {e}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Handle a single http request
+fn handle_scrape(store: Option<&WordieSrsAlgorithm>, metrics: &Metrics, stream: TcpStream) -> Result<(), Box<dyn Error>> {
+    stream.set_read_timeout(Some(SCRAPE_TIMEOUT))?;
+    stream.set_write_timeout(Some(SCRAPE_TIMEOUT))?;
+
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    // Skip the headers, nothing in them matters here
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => write_response(&mut writer, "200 OK", &render(store, metrics)?),
+        _ => write_response(&mut writer, "404 Not Found", ""),
+    }
+}
+
+/// Gather the current metrics. Stats that fail to load are left out and counted as database
+/// errors, so a scrape still reports the error count while the database is down.
+fn render(store: Option<&WordieSrsAlgorithm>, metrics: &Metrics) -> Result<String, Box<dyn Error>> {
+    let mut out = String::new();
+
+    if let Some(store) = store {
+        render_collection(&mut out, store, metrics)?;
+    }
+
+    write_metric(&mut out, "wordie_sync_requests_total", "counter", "Sync requests handled",
+                 metrics.sync_requests.load(Ordering::Relaxed) as f64)?;
+    write_metric(&mut out, "wordie_db_errors_total", "counter", "Database operations that failed",
+                 metrics.db_errors.load(Ordering::Relaxed) as f64)?;
+
+    Ok(out)
+}
+
+/// Gather the stats of the collection
+fn render_collection(out: &mut String, store: &WordieSrsAlgorithm, metrics: &Metrics) -> Result<(), Box<dyn Error>> {
+    match store.daily_stats(1) {
+        Ok(stats) => {
+            let today = stats.last();
+            write_metric(out, "wordie_reviews_today", "gauge", "Cards reviewed today",
+                         today.map_or(0, |today| today.cards_reviewed) as f64)?;
+            write_metric(out, "wordie_cards_learned_today", "gauge", "New cards learned today",
+                         today.map_or(0, |today| today.cards_learned) as f64)?;
+        },
+        Err(e) => {
+            log::error!("Failed to load daily stats for metrics: {e}");
+            metrics.count_db_error();
+        }
+    }
+
+    match store.collection_stats() {
+        Ok(stats) => {
+            write_metric(out, "wordie_due_cards", "gauge", "Cards due now", stats.due_cards as f64)?;
+            write_metric(out, "wordie_new_cards", "gauge", "Cards that haven't been learned yet", stats.new_cards as f64)?;
+            write_metric(out, "wordie_learning_cards", "gauge", "Cards in the learning steps", stats.learning_cards as f64)?;
+            write_metric(out, "wordie_known_cards", "gauge", "Cards that have graduated from learning", stats.known_cards as f64)?;
+        },
+        Err(e) => {
+            log::error!("Failed to load collection stats for metrics: {e}");
+            metrics.count_db_error();
+        }
+    }
+
+    // How long it takes to pick the next card, which is what the user waits on between reviews.
+    // This times the queue query rather than get_next_card, as that records finishing the dues
+    // and scrapes shouldn't write to the collection.
+    let start = Instant::now();
+    match store.get_review_queue(1) {
+        Ok(_) => write_metric(out, "wordie_queue_latency_seconds", "gauge", "Time taken to pick the next card to review",
+                              start.elapsed().as_secs_f64())?,
+        Err(e) => {
+            log::error!("Failed to get the review queue for metrics: {e}");
+            metrics.count_db_error();
+        }
+    }
+
+    Ok(())
+}
+
+/// Write a single metric with its help and type lines
+fn write_metric(out: &mut String, name: &str, kind: &str, help: &str, value: f64) -> std::fmt::Result {
+    writeln!(out, "# HELP {name} {help}")?;
+    writeln!(out, "# TYPE {name} {kind}")?;
+    writeln!(out, "{name} {value}")
+}
+
+/// Write an http response and close the connection
+fn write_response(writer: &mut impl Write, status: &str, body: &str) -> Result<(), Box<dyn Error>> {
+    let response = format!("HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                           body.len());
+
+    writer.write_all(response.as_bytes())?;
+    writer.flush()?;
+
+    Ok(())
+}
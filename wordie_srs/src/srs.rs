@@ -1,32 +1,344 @@
+#[cfg(feature = "mysql")]
 pub mod anki;
+#[cfg(feature = "mysql")]
 pub mod wordie;
+pub mod tokenizer;
+pub mod sentence_splitter;
+pub mod import;
+pub mod daily_stats;
+pub mod goals;
+pub mod connection;
+pub mod sync;
+#[cfg(feature = "import")]
+pub mod deck;
+#[cfg(feature = "mysql")]
+pub mod migrate;
+#[cfg(feature = "mysql")]
+pub mod blacklist;
+#[cfg(feature = "mysql")]
+mod names;
+#[cfg(feature = "mysql")]
+mod word_merges;
+#[cfg(feature = "mysql")]
+mod expressions;
+pub mod kanji;
+pub mod levels;
+pub mod integrity;
+pub mod vacuum;
+pub mod rebuild;
+pub mod trash;
+pub mod cloze;
+pub mod answer;
+pub mod normalize;
+pub mod page;
+pub mod config;
+pub mod scheduler;
+pub mod sim;
+pub mod revision;
+pub mod companion;
+#[cfg(feature = "import")]
+pub mod texthooker;
+pub mod audio_source;
+#[cfg(feature = "ocr")]
+pub mod ocr;
+#[cfg(feature = "mysql")]
+pub mod tags;
+#[cfg(feature = "pdf")]
+pub mod pdf;
+#[cfg(feature = "import")]
+pub mod watch;
+pub mod targets;
+pub mod grammar;
+pub mod flags;
+pub mod script;
+pub mod plugins;
+pub mod command_plugin;
+pub mod webhooks;
+pub mod retention;
+pub mod revlog;
+#[cfg(feature = "import")]
+pub mod known_words;
+pub mod sources;
+pub mod stats_export;
+pub mod report;
+pub mod clock;
+pub mod session_state;
+pub mod suggestions;
+pub mod spread;
+pub mod vacation;
+pub mod hash;
+#[cfg(feature = "mysql")]
+mod db;
+#[cfg(feature = "mysql")]
+mod forecast;
 
-use chrono::{Local, DateTime};
+use std::collections::HashSet;
+use std::time::Duration;
+
+use chrono::{Local, DateTime, NaiveDateTime, Timelike};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use strum_macros::EnumIter;
 
+use import::ImportReport;
+use daily_stats::DailyStats;
+use connection::ConnectionState;
+use kanji::KanjiStats;
+use levels::LevelProgress;
+use integrity::IntegrityReport;
+use vacuum::VacuumReport;
+use rebuild::RebuildReport;
+use trash::TrashedSentence;
+use cloze::Cloze;
+use page::{Page, PageRequest};
+use scheduler::Schedule;
+use revision::CountsRevision;
+use tokenizer::Token;
+use targets::TargetWord;
+use grammar::GrammarStats;
+use flags::Flag;
+use revlog::RevlogEntry;
+use sources::{SentenceSource, SourceStats};
+use stats_export::StatsExport;
+use suggestions::UnlockingWord;
+use spread::SpreadReport;
+use vacation::Vacation;
+
 /// A result type that boxes errors to a Box<dyn Error>
 pub type SrsResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
 /// Type for a review
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Review {
-    New { sentence: Sentence, unknown_words: i32 },
-    Due { sentence: Sentence, words_due: i32 },
+    New {
+        sentence: Sentence,
+        unknown_words: i32,
+        /// Why the queue picked the sentence, for reviews that came from the queue
+        #[serde(skip)]
+        reason: Option<SelectionReason>,
+    },
+    Due {
+        sentence: Sentence,
+        words_due: i32,
+        card_type: CardType,
+        /// Why the queue picked the sentence, for reviews that came from the queue
+        #[serde(skip)]
+        reason: Option<SelectionReason>,
+    },
 }
 
 impl Review {
+    /// A review of a new sentence that didn't come from the queue, e.g. one picked to learn now.
+    /// Code outside the algorithms should use this rather than the variant, so it keeps compiling
+    /// when the queue adds more to it.
+    pub fn new_sentence(sentence: Sentence, unknown_words: i32) -> Self {
+        Review::New { sentence, unknown_words, reason: None }
+    }
+
     pub fn sentence(&self) -> &Sentence {
         match &self {
-            Review::New { sentence, .. } => &sentence,
-            Review::Due { sentence, ..} => &sentence,
+            Review::New { sentence, .. } => sentence,
+            Review::Due { sentence, ..} => sentence,
+        }
+    }
+
+    pub fn sentence_mut(&mut self) -> &mut Sentence {
+        match self {
+            Review::New { sentence, .. } => sentence,
+            Review::Due { sentence, ..} => sentence,
+        }
+    }
+
+    /// The direction the sentence is reviewed in, new sentences are always learned by recognition
+    pub fn card_type(&self) -> CardType {
+        match &self {
+            Review::New { .. } => CardType::Recognition,
+            Review::Due { card_type, .. } => *card_type,
+        }
+    }
+
+    /// Why the queue picked the sentence, so the queue's choices can be checked while tuning it.
+    /// It's worked out by the queue query, so it's None for reviews that didn't come from one.
+    pub fn reason(&self) -> Option<&SelectionReason> {
+        match &self {
+            Review::New { reason, .. } => reason.as_ref(),
+            Review::Due { reason, .. } => reason.as_ref(),
+        }
+    }
+}
+
+/// Why the queue picked a sentence, see `Review::reason`
+#[derive(Debug, Clone, Default)]
+pub struct SelectionReason {
+    /// The words the sentence introduces
+    pub new_words: Vec<NewWord>,
+    /// The words in the sentence that are due now
+    pub due_words: Vec<String>,
+    /// The other words in the sentence that are already mature
+    pub mature_words: usize,
+    /// When the card was due, for algorithms where cards are sentences
+    pub due: Option<NaiveDateTime>,
+}
+
+impl SelectionReason {
+    /// Describe the reason in a line, e.g. "introduces 雨 (N5, in 12 sentences); 3 other words
+    /// mature" or "2 words due: 走る, まだ"
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+
+        if !self.new_words.is_empty() {
+            let new_words = self.new_words.iter()
+                .map(|new_word| match new_word.level.as_ref() {
+                    Some(level) => format!("{} ({level}, in {} sentences)", new_word.word, new_word.frequency),
+                    None => format!("{} (in {} sentences)", new_word.word, new_word.frequency),
+                })
+                .collect::<Vec<String>>();
+            parts.push(format!("introduces {}", new_words.join(", ")));
+        }
+
+        if !self.due_words.is_empty() {
+            parts.push(format!("{} words due: {}", self.due_words.len(), self.due_words.join(", ")));
+        }
+
+        if self.mature_words > 0 {
+            parts.push(format!("{} other words mature", self.mature_words));
+        }
+
+        if parts.is_empty() {
+            match self.due {
+                Some(due) => format!("due since {}", due.format("%Y-%m-%d %H:%M")),
+                None => "new sentence".to_string(),
+            }
+        }
+        else {
+            parts.join("; ")
         }
     }
 }
 
+/// A word a sentence introduces, see `SelectionReason`
+#[derive(Debug, Clone)]
+pub struct NewWord {
+    pub word: String,
+    /// The word's level, if it has one
+    pub level: Option<String>,
+    /// How many sentences the word is in, so common words can be told apart from rare ones
+    pub frequency: usize,
+}
+
+/// The direction a card is reviewed in, each direction is scheduled separately
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone, EnumIter, Serialize, Deserialize)]
+pub enum CardType {
+    /// Read the sentence and understand it
+    Recognition,
+    /// Read the translation and recall the sentence
+    Production,
+}
+
+impl CardType {
+    /// The name of the card type in the db
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CardType::Recognition => "recognition",
+            CardType::Production => "production",
+        }
+    }
+
+    /// Get a card type from its name in the db
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "recognition" => Some(CardType::Recognition),
+            "production" => Some(CardType::Production),
+            _ => None,
+        }
+    }
+}
+
+/// Aggregate counts of the cards in a collection
+#[derive(Debug, Clone, Default)]
+pub struct CollectionStats {
+    /// Cards to review now: learning queue cards whose due time has passed, and other cards due
+    /// before the end of today
+    pub due_cards: i32,
+    /// Learning queue cards that come back later today, which aren't due until their due time.
+    /// Ones that come back after midnight are due tomorrow instead.
+    pub learning_due_later: i32,
+    /// Cards that haven't been learned yet
+    pub new_cards: i32,
+    /// Cards that are still in the learning steps
+    pub learning_cards: i32,
+    /// Cards that have graduated from learning
+    pub known_cards: i32,
+}
+
+/// What's next in the review queue, see `SrsAlgorithm::get_next_card`. Everything but `Review`
+/// says why there's nothing to review.
+#[derive(Debug, Clone)]
+pub enum QueueState {
+    /// A card to review
+    Review(Review),
+    /// Everything due today has been reviewed, and there are no new cards left to introduce
+    NothingDue { reviewed_today: i32 },
+    /// Nothing is due, and the new card limit for today has been reached
+    NewCardLimit { learned_today: i32, limit: i32 },
+    /// Nothing is due, and there are too many cards in learning to introduce new ones until
+    /// they've been reviewed
+    TooManyLearning { learning: i32, limit: i32 },
+    /// Nothing is due, and the next new sentence has more new words than the limit
+    NextIsIPlusN { unknown_words: i32, limit: i32 },
+    /// There are no sentences to review
+    CollectionEmpty,
+    /// Reviews are paused for a vacation, which started at `since` in UTC
+    OnVacation { since: NaiveDateTime },
+}
+
+impl QueueState {
+    /// The card to review, if there is one
+    pub fn review(self) -> Option<Review> {
+        match self {
+            QueueState::Review(review) => Some(review),
+            _ => None,
+        }
+    }
+}
+
+/// A card that's still in the learning steps, see `scheduler::INITIAL_INTERVALS`
+#[derive(Debug, Clone)]
+pub struct LearningCard {
+    /// The card's word, or its sentence for algorithms where cards are sentences
+    pub text: String,
+    /// When the card is next due, in UTC
+    pub due: NaiveDateTime,
+}
+
+/// Everything known about a word, for looking it up from a review
+#[derive(Debug, Clone)]
+pub struct WordInfo {
+    pub word: String,
+    pub reading: Option<String>,
+    /// The word's level, e.g. N5, if levels have been imported
+    pub level: Option<String>,
+    /// The number of sentences the word is in, not counting ones in the trash
+    pub sentences: i32,
+    pub review_count: i32,
+    pub ease: f32,
+    pub interval: Option<Duration>,
+    /// When the word is next due, in UTC, or None if it hasn't been learned yet
+    pub due: Option<NaiveDateTime>,
+    pub blacklisted: bool,
+    pub suspended: bool,
+}
+
+/// A review of a word, from the review history
+#[derive(Debug, Clone)]
+pub struct ReviewRecord {
+    pub word: String,
+    /// When the word was reviewed, in UTC
+    pub review_date: NaiveDateTime,
+}
+
 /// Review difficulties
-#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone, EnumIter)]
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone, EnumIter, Serialize, Deserialize)]
 pub enum Difficulty {
     Again = 0,
     Hard = 1,
@@ -41,19 +353,174 @@ pub struct Sentence {
     pub text: String,
 }
 
+/// A preview of how a sentence will be tokenized when it's added
+#[derive(Debug, Clone)]
+pub struct SentencePreview {
+    pub text: String,
+    pub words: Vec<String>,
+    pub new_words: Vec<String>,
+}
+
+/// How well a word is known, according to its card
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KnowledgeState {
+    /// The word has no card, or its card hasn't been learned yet
+    New,
+    /// The word's card has been learned but isn't mature yet
+    Learning,
+    /// The word's card has a long enough interval to count as known
+    Mature,
+    /// The word is blacklisted or flagged as a name, so it's never studied
+    Blacklisted,
+}
+
 /// Trait for an SRS algorithm
 pub trait SrsAlgorithm {
-    /// Clear the db, resetting the db structure and clearing all data
-    fn reinitialize_db(&mut self) -> SrsResult<()>;
+    /// Clear the db, resetting the db structure and clearing all data. Refuses to if any cards
+    /// have been reviewed, unless `force` is set. The tables are backed up first, to tables with
+    /// a _backup suffix, replacing the last backup.
+    fn reinitialize_db(&mut self, force: bool) -> SrsResult<()>;
 
     /// Initialise the db
     fn initialize_db(&mut self) -> SrsResult<()>;
 
-    /// Add sentences
-    fn add_sentences(&mut self, sentences: &[Sentence]) -> SrsResult<()>;
+    /// Add sentences, returning a report of the new words they introduced
+    fn add_sentences(&mut self, sentences: &[Sentence]) -> SrsResult<ImportReport>;
+
+    /// Preview how sentences will be tokenized, and which of their words are new, without adding them
+    fn preview_sentences(&self, sentences: &[Sentence]) -> SrsResult<Vec<SentencePreview>>;
+
+    /// Tokenize any text, e.g. from a reader, and tag each word with how well it's known. The
+    /// text doesn't need to be in the collection.
+    fn annotate(&self, text: &str) -> SrsResult<Vec<(Token, KnowledgeState)>>;
+
+    /// Get all the sentences in the collection, apart from the ones in the trash. Use
+    /// `list_sentences` instead for showing them, large collections can have a lot of sentences.
+    fn get_sentences(&self) -> SrsResult<Vec<Sentence>>;
+
+    /// Get which of `texts` are already sentences in the collection, apart from the ones in the
+    /// trash, e.g. so a file that failed to import part way through can be retried without adding
+    /// its sentences twice
+    fn existing_sentences(&self, texts: &[String]) -> SrsResult<HashSet<String>>;
+
+    /// Get a page of the sentences in the collection, apart from the ones in the trash, in
+    /// alphabetical order
+    fn list_sentences(&self, page: PageRequest) -> SrsResult<Page<Sentence>>;
+
+    /// Get a page of the sentences containing `query`, in alphabetical order
+    fn search_sentences(&self, query: &str, page: PageRequest) -> SrsResult<Page<Sentence>>;
+
+    /// Get a page of the words reviewed, most recent first
+    fn get_review_history(&self, page: PageRequest) -> SrsResult<Page<ReviewRecord>>;
 
-    /// Get next card (new or review, depending on settings and algorithm)
-    fn get_next_card(&self) -> SrsResult<Option<Review>>;
+    /// Hide the word being tested in a sentence for a cloze review: the new word for new
+    /// sentences, or the most overdue word for due ones. Returns None if there's no word to hide.
+    fn get_cloze(&self, sentence: &Sentence) -> SrsResult<Option<Cloze>>;
+
+    /// Get the translation of a sentence, if it has one
+    fn get_translation(&self, sentence_id: &Uuid) -> SrsResult<Option<String>>;
+
+    /// Set or clear the translation of a sentence, which is shown for production reviews
+    fn set_translation(&mut self, sentence_id: &Uuid, translation: Option<&str>) -> SrsResult<()>;
+
+    /// Get the user's notes on a sentence, if it has any
+    fn get_notes(&self, sentence_id: &Uuid) -> SrsResult<Option<String>>;
+
+    /// Set or clear the user's notes on a sentence
+    fn set_notes(&mut self, sentence_id: &Uuid, notes: Option<&str>) -> SrsResult<()>;
+
+    /// Correct the text of a sentence, e.g. to fix a typo. The sentence is split into words again,
+    /// adding cards for new words and cleaning up unstudied ones no longer in any sentence, and
+    /// any words set by hand are replaced.
+    fn set_sentence_text(&mut self, sentence_id: &Uuid, text: &str) -> SrsResult<()>;
+
+    /// Get the tags on a sentence, e.g. where it was imported from
+    fn get_sentence_tags(&self, sentence_id: &Uuid) -> SrsResult<Vec<String>>;
+
+    /// Add a tag to sentences
+    fn tag_sentences(&mut self, ids: &[Uuid], tag: &str) -> SrsResult<()>;
+
+    /// Get where a sentence came from, if it's known
+    fn get_sentence_source(&self, sentence_id: &Uuid) -> SrsResult<Option<SentenceSource>>;
+
+    /// Record where sentences came from. Sentences keep the first source they're given.
+    fn set_sentence_source(&mut self, ids: &[Uuid], source: &SentenceSource) -> SrsResult<()>;
+
+    /// Get how many sentences and new words each source has given, most words first
+    fn source_stats(&self) -> SrsResult<Vec<SourceStats>>;
+
+    /// Get a page of the sentences matching all the filters given: containing `query`, from
+    /// `source` and with `flag`, in alphabetical order
+    fn find_sentences(&self, query: Option<&str>, source: Option<&SentenceSource>, flag: Option<Flag>, page: PageRequest)
+        -> SrsResult<Page<Sentence>>;
+
+    /// The source new sentences are being introduced from, see `set_source_filter`
+    fn source_filter(&self) -> Option<&SentenceSource>;
+
+    /// Only introduce new sentences from one source, e.g. the book being read, or from any source
+    /// if None. Due cards are still reviewed whichever sentences they're in.
+    fn set_source_filter(&mut self, source: Option<SentenceSource>);
+
+    /// Get the word a sentence was written to teach, if it came with one
+    fn get_target_word(&self, sentence_id: &Uuid) -> SrsResult<Option<TargetWord>>;
+
+    /// Set the words sentences were written to teach, e.g. from a Core6k import
+    fn set_target_words(&mut self, targets: &[(Uuid, TargetWord)]) -> SrsResult<()>;
+
+    /// Get the flag on a sentence, if it has one
+    fn get_flag(&self, sentence_id: &Uuid) -> SrsResult<Option<Flag>>;
+
+    /// Flag a sentence, replacing any flag it had, or clear its flag if None
+    fn set_flag(&mut self, sentence_id: &Uuid, flag: Option<Flag>) -> SrsResult<()>;
+
+    /// Get the grammar points a sentence uses, in alphabetical order
+    fn get_grammar_points(&self, sentence_id: &Uuid) -> SrsResult<Vec<String>>;
+
+    /// Tag sentences with a grammar point
+    fn add_grammar_point(&mut self, ids: &[Uuid], grammar_point: &str) -> SrsResult<()>;
+
+    /// Remove a grammar point from a sentence
+    fn remove_grammar_point(&mut self, sentence_id: &Uuid, grammar_point: &str) -> SrsResult<()>;
+
+    /// Tag every sentence with the grammar points `grammar::detect` finds in it. New sentences are
+    /// tagged when they're added, so this is only needed for older ones. Returns the number found.
+    fn detect_grammar_points(&mut self) -> SrsResult<usize>;
+
+    /// Get how many sentences and reviews each grammar point has had, most reviewed first
+    fn grammar_stats(&self) -> SrsResult<Vec<GrammarStats>>;
+
+    /// Get the review history in the form of anki's revlog table, oldest first
+    fn get_review_log(&self) -> SrsResult<Vec<RevlogEntry>>;
+
+    /// Move sentences to the trash. They aren't reviewed while they're there, and they can be
+    /// restored until they've been there for `trash::TRASH_RETENTION_DAYS`.
+    fn delete_sentences(&mut self, ids: &[Uuid]) -> SrsResult<()>;
+
+    /// Get the sentences in the trash, most recently deleted first
+    fn get_trash(&self) -> SrsResult<Vec<TrashedSentence>>;
+
+    /// Take sentences back out of the trash
+    fn restore_sentences(&mut self, ids: &[Uuid]) -> SrsResult<()>;
+
+    /// Delete the sentences that have been in the trash for longer than the retention period for
+    /// good, returns the number deleted. This happens automatically when the db is initialized.
+    fn purge_trash(&mut self) -> SrsResult<usize>;
+
+    /// Get next card (new or review, depending on settings and algorithm), or why there isn't one
+    fn get_next_card(&self) -> SrsResult<QueueState>;
+
+    /// Get the next `limit` reviews, in the order `get_next_card` would return them. The queue is
+    /// a snapshot: answering a review can change what comes after it (e.g. a sentence sharing
+    /// words with it may no longer be due), so the rest of the queue can be stale once the head
+    /// has been answered. Fetch the queue again after each answer rather than working through it.
+    fn get_review_queue(&self, limit: usize) -> SrsResult<Vec<Review>>;
+
+    /// Get the next `limit` due reviews, the learning queue first, without any new sentences, e.g.
+    /// for catching up on a backlog. Like `get_review_queue`, it's empty while on vacation.
+    fn get_due_queue(&self, limit: usize) -> SrsResult<Vec<Review>>;
+
+    /// Get the cards that are still in the learning steps, earliest due first
+    fn get_learning_cards(&self) -> SrsResult<Vec<LearningCard>>;
 
     /// Complete a review
     fn review(&mut self, review: Review, difficulty: Difficulty) -> SrsResult<()>;
@@ -67,9 +534,179 @@ pub trait SrsAlgorithm {
     /// Reset daily limits
     fn reset_daily_limits(&mut self);
 
-    /// Set the current time
+    /// Set the current time, for simulations. Otherwise the algorithms use the system clock, see
+    /// `clock::Clock`.
+    #[cfg(feature = "simulation")]
     fn set_time_now(&mut self, time: DateTime<Local>);
 
-    /// Get suggested sentences by new word limit
-    fn get_suggested_sentences(&self, new_word_limit: i32) -> SrsResult<Vec<(Sentence, Vec<String>)>>;
+    /// Shuffle the cards due today with a seed, instead of serving them in the algorithm's usual
+    /// order, or stop shuffling if None. Cards back from the learning queue aren't shuffled.
+    fn set_due_shuffle_seed(&mut self, seed: Option<u32>);
+
+    /// Push a sentence back out of the queue for `delay`, without changing its cards' intervals
+    /// or ease. Snoozes only last until the algorithm is dropped.
+    fn snooze(&mut self, sentence_id: &Uuid, delay: Duration);
+
+    /// Get the state of the connection to the database, as of the last query
+    fn connection_state(&self) -> ConnectionState;
+
+    /// The time being used instead of the system clock while it's behind, or None if it isn't, see
+    /// `clock::Clock`
+    fn clock_behind(&self) -> Option<DateTime<Local>>;
+
+    /// Get aggregate counts of the cards in the collection
+    fn collection_stats(&self) -> SrsResult<CollectionStats>;
+
+    /// Get the collection stats only if they might have changed since `since`, which is cheap
+    /// enough to poll often. Returns None if nothing has changed, otherwise the stats along with
+    /// the revision to pass next time.
+    fn counts_if_changed(&self, since: Option<CountsRevision>) -> SrsResult<Option<(CollectionStats, CountsRevision)>>;
+
+    /// Get the stats for the last `days` days (including today), oldest first
+    fn daily_stats(&self, days: usize) -> SrsResult<Vec<DailyStats>>;
+
+    /// Get the study stats for the last `days` days and the forecast for the next
+    /// `forecast_days`, for exporting to other tools
+    fn get_stats_export(&self, days: usize, forecast_days: usize) -> SrsResult<StatsExport>;
+
+    /// Forecast the number of cards due on each of the next `days` days from their current
+    /// intervals. Index 0 is today, and includes overdue cards.
+    fn forecast(&self, days: usize) -> SrsResult<Vec<i32>>;
+
+    /// Forecast due cards as above, but also including the reviews from learning
+    /// `new_cards_per_day` new cards each day, assuming they're all answered Good
+    fn forecast_with_new_cards(&self, days: usize, new_cards_per_day: i32) -> SrsResult<Vec<i32>>;
+
+    /// Get the schedules of the cards that are studied, for simulating them. Cards that haven't
+    /// been learned yet have no due date.
+    fn get_schedules(&self) -> SrsResult<Vec<Schedule>>;
+
+    /// Get sentences whose words are all mature and covered by other sentences, so reviewing them
+    /// adds load without adding anything new
+    fn get_redundant_sentences(&self) -> SrsResult<Vec<Sentence>>;
+
+    /// Retire redundant sentences so they're no longer scheduled, returns the number retired
+    fn retire_redundant_sentences(&mut self) -> SrsResult<usize>;
+
+    /// Get the blacklisted words, which never become cards
+    fn get_blacklist(&self) -> SrsResult<Vec<String>>;
+
+    /// Blacklist a word, so it never becomes a card and isn't counted as an unknown word
+    fn blacklist_word(&mut self, word: &str) -> SrsResult<()>;
+
+    /// Remove a word from the blacklist
+    fn unblacklist_word(&mut self, word: &str) -> SrsResult<()>;
+
+    /// Get the suspended words, in alphabetical order
+    fn get_suspended_words(&self) -> SrsResult<Vec<String>>;
+
+    /// Suspend a word, so it isn't reviewed until it's unsuspended. Unlike blacklisting, its card
+    /// keeps its schedule to pick up from again.
+    fn suspend_word(&mut self, word: &str) -> SrsResult<()>;
+
+    /// Unsuspend a word, so it's reviewed again
+    fn unsuspend_word(&mut self, word: &str) -> SrsResult<()>;
+
+    /// Look up a word's card, or None if there's no such word, or words have no cards of their
+    /// own in this algorithm
+    fn get_word_info(&self, word: &str) -> SrsResult<Option<WordInfo>>;
+
+    /// Get the words flagged as likely proper names when they were imported, which aren't reviewed
+    fn get_flagged_names(&self) -> SrsResult<Vec<String>>;
+
+    /// Mark a word flagged as a name as a real word, so it gets reviewed
+    fn rescue_name(&mut self, word: &str) -> SrsResult<()>;
+
+    /// Get the words a sentence was split into
+    fn get_sentence_words(&self, sentence_id: &Uuid) -> SrsResult<Vec<String>>;
+
+    /// Override the words a sentence is split into, for when the tokenizer gets it wrong. Cards
+    /// are created for any new words, and removed for unstudied words no longer in any sentence.
+    fn set_sentence_words(&mut self, sentence_id: &Uuid, words: &[String]) -> SrsResult<()>;
+
+    /// Get the user-defined multi-word expressions
+    fn get_expressions(&self) -> SrsResult<Vec<String>>;
+
+    /// Add a multi-word expression, e.g. 気にする, which is treated as a single word when sentences
+    /// are imported. Use `retokenize_sentences` to apply it to existing sentences.
+    fn add_expression(&mut self, expression: &str) -> SrsResult<()>;
+
+    /// Remove a multi-word expression
+    fn remove_expression(&mut self, expression: &str) -> SrsResult<()>;
+
+    /// Split existing sentences into words again with the current expressions and merge rules,
+    /// skipping sentences whose words were overridden by hand. Returns the number of sentences
+    /// whose words changed.
+    fn retokenize_sentences(&mut self) -> SrsResult<usize>;
+
+    /// Merge a word into another one, for conjugated forms etc the tokenizer doesn't group
+    /// together. Existing sentences and review history are moved over to `into`'s card, keeping
+    /// the schedule of whichever card has been studied longest, and future imports use `into`
+    /// instead of `word`.
+    fn merge_words(&mut self, word: &str, into: &str) -> SrsResult<()>;
+
+    /// Get the stats for every kanji in the collection's words, most common first
+    fn get_kanji_stats(&self) -> SrsResult<Vec<KanjiStats>>;
+
+    /// Tag words with a level from a level list, e.g. JLPT N5 or CEFR A1
+    fn import_word_levels(&mut self, level: &str, words: &[String]) -> SrsResult<()>;
+
+    /// Mark words as already known, e.g. from another tool's export, by giving their new cards an
+    /// interval of `interval_days`. Cards that have already been studied are left alone. Returns
+    /// how many cards were marked.
+    fn mark_words_known(&mut self, words: &[String], interval_days: u32) -> SrsResult<usize>;
+
+    /// Get the progress through each imported level list
+    fn level_progress(&self) -> SrsResult<Vec<LevelProgress>>;
+
+    /// Check the database for inconsistencies, e.g. rows left behind by a failed edit. If `repair`
+    /// is set, the problems found are fixed too.
+    fn check_integrity(&mut self, repair: bool) -> SrsResult<IntegrityReport>;
+
+    /// Clean up rows nothing uses anymore, delete review history older than
+    /// `review_retention_days`, and compact the database. Media files are cleaned up separately, see
+    /// `vacuum::remove_unreferenced_media`.
+    fn vacuum(&mut self, review_retention_days: u32) -> SrsResult<VacuumReport>;
+
+    /// Forget anything cached from the db, after the collection has been changed through another
+    /// connection, e.g. by a background job
+    fn clear_cache(&mut self);
+
+    /// Rebuild the cards from the stored sentences with the current tokenizer, expressions and
+    /// merge rules, e.g. after a tokenizer upgrade. Words that are still in a sentence keep their
+    /// scheduling, and cards are created for any that are missing one.
+    fn rebuild_cards(&mut self) -> SrsResult<RebuildReport>;
+
+    /// Spread the cards due over the next `days` days out evenly, e.g. after a large import made
+    /// thousands due on the same day. Cards are only ever moved later, by a fraction of their
+    /// interval, see `spread::spread`.
+    fn spread_dues(&mut self, days: u32) -> SrsResult<SpreadReport>;
+
+    /// Get the vacation that's going on, if reviews are paused for one
+    fn get_vacation(&self) -> SrsResult<Option<Vacation>>;
+
+    /// Start a vacation, pausing reviews until it's ended
+    fn start_vacation(&mut self) -> SrsResult<()>;
+
+    /// End the vacation, moving every due date forward by the whole days it lasted, see
+    /// `vacation::Vacation`
+    fn end_vacation(&mut self) -> SrsResult<Vacation>;
+
+    /// Get a page of suggested sentences by new word limit, along with their unknown words,
+    /// fewest unknown words first and then the ones whose unknown words are most useful to learn,
+    /// see `suggestions::WordGraph`
+    fn get_suggested_sentences(&self, new_word_limit: i32, page: PageRequest) -> SrsResult<Page<(Sentence, Vec<String>)>>;
+
+    /// Get up to `limit` unknown words, ranked by how many sentences would come within the max new
+    /// words per sentence or become i+0 if they were learned
+    fn get_unlocking_words(&self, limit: usize) -> SrsResult<Vec<UnlockingWord>>;
+}
+
+/// Get midnight at the end of the day containing `time`, cards due before this are due today
+pub fn end_of_day(time: DateTime<Local>) -> DateTime<Local> {
+    (time + chrono::Duration::days(1))
+        .with_hour(0).unwrap()
+        .with_minute(0).unwrap()
+        .with_second(0).unwrap()
+        .with_nanosecond(0).unwrap()
 }
@@ -1,246 +1,415 @@
+use std::collections::HashSet;
 use std::str::FromStr;
 use std::time::Duration;
-use chrono::{NaiveDateTime, Timelike, Local, DateTime};
-use lazy_static::lazy_static;
+use std::sync::Arc;
+use chrono::{Datelike, NaiveDateTime};
 use uuid::Uuid;
 
-use mysql::{Pool, prelude::Queryable, params};
-use super::{SrsAlgorithm, SrsResult, Sentence, Review, Difficulty};
-
-lazy_static! {
-    /// The initial intervals for new cards
-    static ref INITIAL_INTERVALS: [Duration; 3] = [
-        Duration::from_secs(1 * 60),
-        Duration::from_secs(10 * 60),
-        Duration::from_secs(24 * 60 * 60),
-    ];
-}
-
-/// The default ease
-const DEFAULT_EASE: f32 = 2.5;
-
-/// The minimum ease
-const MINIMUM_EASE: f32 = 1.3;
-
-/// The easy bonus
-const EASY_BONUS: f64 = 1.3;
-
-/// The hard interval
-const HARD_INTERVAL: f64 = 1.2;
+use mysql::{prelude::Queryable, params};
+use super::{SrsAlgorithm, SrsResult, Sentence, Review, Difficulty, SentencePreview, CollectionStats, CardType, ReviewRecord, KnowledgeState, LearningCard,
+    QueueState, SelectionReason, WordInfo};
+use super::tokenizer::{Token, Tokenizer, DefaultTokenizer};
+use super::forecast;
+use super::connection::{ConnectionPool, ConnectionState, ConnectionOptions};
+use super::daily_stats::{self, DailyStats};
+use super::import::ImportReport;
+use super::blacklist::{self, SENTENCE_NOT_SUSPENDED};
+use super::kanji::KanjiStats;
+use super::levels::LevelProgress;
+use super::integrity::{self, IntegrityReport};
+use super::vacuum::{self, VacuumReport};
+use super::rebuild::RebuildReport;
+use super::trash::{self, TrashedSentence};
+use super::cloze::Cloze;
+use super::page::{self, Page, PageRequest};
+use super::db;
+use super::scheduler::{self, Schedule, Snoozes, INITIAL_INTERVALS, DEFAULT_EASE, DEFAULT_NEW_CARD_LIMIT, DUE_NOW, IN_LEARNING_QUEUE, LEARNING_QUEUE_INTERVAL};
+use super::revision::{self, CountsRevision};
+use super::tags;
+use super::targets::{self, TargetWord};
+use super::grammar::{self, GrammarStats};
+use super::flags::{self, Flag};
+use super::revlog::RevlogEntry;
+use super::sources::{self, SentenceSource, SourceStats, FROM_SOURCE};
+use super::stats_export::StatsExport;
+use super::clock::Clock;
+use super::config::WeekdayLimits;
+use super::suggestions::UnlockingWord;
+use super::spread::{self, SpreadReport, MIN_SPREAD_INTERVAL};
+use super::vacation::{self, Vacation};
 
 /// An srs card
 struct Card {
     id: String,
-    due: Option<NaiveDateTime>,
-    interval: Option<Duration>,
-    review_count: i32,
-    ease: f32,
+    schedule: Schedule,
 }
 
-type CardRecord = (Option<NaiveDateTime>, Option<Duration>, i32, f32);
+type CardRecord = (Option<NaiveDateTime>, Option<u64>, i32, f32);
 
 impl Card {
     fn new(id: String, (due, interval, review_count, ease): CardRecord) -> Self {
         Self {
             id,
-            due,
-            interval,
-            review_count,
-            ease,
+            schedule: Schedule { due, interval: interval.map(Duration::from_secs), review_count, ease },
         }
     }
-
-    fn review(&mut self, time_now: DateTime<Local>, score: Difficulty) -> SrsResult<()> {
-        // https://faqs.ankiweb.net/what-spaced-repetition-algorithm.html
-        // For learning/relearning the algorithm is a bit different. We track if a card is
-        // currently in the learning stage by its review count, if there's a corresponding entry in
-        // INITIAL_INTERVALS that's one of the initial learning stages, once it passes out of there
-        // it graduates to no longer being a new card.
-        if self.review_count < INITIAL_INTERVALS.len() as i32 {
-            // For cards in learning/relearning:
-            // * Again moves the card back to the first stage of the new card intervals
-            // * Hard repeats the current step
-            // * Good moves the card to the next step, if the card was on the final step, it is
-            //   converted into a review card
-            // * Easy immediately converts the card into a review card
-            // There are no ease adjustments for new cards.
-            self.review_count = match score {
-                Difficulty::Again => 0,
-                Difficulty::Hard => self.review_count,
-                Difficulty::Good => self.review_count + 1,
-                Difficulty::Easy => INITIAL_INTERVALS.len() as i32,
-            };
-
-            let interval_index = i32::clamp(self.review_count, 0, INITIAL_INTERVALS.len() as i32 - 1);
-            let new_interval = INITIAL_INTERVALS[interval_index as usize];
-            let new_due = time_now + chrono::Duration::from_std(new_interval)?;
-
-            self.interval = Some(new_interval);
-            self.due = Some(new_due.naive_utc());
-        }
-        else {
-            // For cards that have graduated learning:
-            // * Again puts the card back into learning mode, and decreases the ease by 20%
-            // * Hard multiplies the current interval by the hard interval (1.2 by default) and
-            //   decreases the ease by 15%
-            // * Good multiplies the current interval by the ease
-            // * Easy multiplies the current interval by the ease times the easy bonus (1.3 by
-            //   default) and increases the ease by 15%
-            let (new_interval, new_ease, new_review_count) = match score {
-                Difficulty::Again => {
-                    (INITIAL_INTERVALS[0], self.ease - 0.2, 0)
-                },
-                Difficulty::Hard => {
-                    let new_interval = Self::mul_duration(self.interval.unwrap(), HARD_INTERVAL);
-                    (new_interval, self.ease - 0.15, self.review_count + 1)
-                },
-                Difficulty::Good => {
-                    let new_interval = Self::mul_duration(self.interval.unwrap(), self.ease as f64);
-                    (new_interval, self.ease, self.review_count + 1)
-                },
-                Difficulty::Easy => {
-                    let new_interval = Self::mul_duration(self.interval.unwrap(), self.ease as f64 * EASY_BONUS);
-                    (new_interval, self.ease + 0.15, self.review_count + 1)
-                },
-            };
-
-            let new_due = time_now + chrono::Duration::from_std(new_interval)?;
-
-            self.interval = Some(new_interval);
-            self.due = Some(new_due.naive_utc());
-            self.ease = f32::max(MINIMUM_EASE, new_ease);
-            self.review_count = new_review_count;
-        }
-
-        Ok(())
-    }
-
-    fn mul_duration(duration: Duration, multiplier: f64) -> Duration {
-        let new_interval_secs = duration.as_secs() as f64 * multiplier;
-        Duration::from_secs(new_interval_secs as u64)
-    }
 }
 
 /// Anki-style spaced repetition implementation
 pub struct AnkiSrsAlgorithm {
-    pool: Pool,
+    pool: ConnectionPool,
+    tokenizer: Arc<dyn Tokenizer>,
     new_card_limit: i32,
-    // TODO: should store this in db, or it doesn't persist app restarts
+    weekday_new_card_limits: WeekdayLimits,
     cards_learned_today: i32,
     cards_reviewed_today: i32,
-    local_time: DateTime<Local>,
+    clock: Clock,
+    due_shuffle_seed: Option<u32>,
+    snoozes: Snoozes,
+    source_filter: Option<SentenceSource>,
 }
 
-impl AnkiSrsAlgorithm {
-    /// Connect to a database and create a new AnkiSrsAlgorithm
-    pub fn new(db_url: &str, new_card_limit: i32) -> SrsResult<Self> {
-        let pool = Pool::new(db_url)?;
+/// Builds an `AnkiSrsAlgorithm`, like `WordieSrsAlgorithmBuilder`
+#[derive(Debug, Clone)]
+pub struct AnkiSrsAlgorithmBuilder {
+    db_url: Option<String>,
+    tokenizer: Arc<dyn Tokenizer>,
+    new_card_limit: i32,
+    weekday_new_card_limits: WeekdayLimits,
+    connection_options: ConnectionOptions,
+}
+
+impl AnkiSrsAlgorithmBuilder {
+    /// The url of the database to connect to, which has to be set
+    pub fn db_url(mut self, db_url: &str) -> Self {
+        self.db_url = Some(db_url.to_string());
+        self
+    }
+
+    /// The tokenizer to split sentences into words with, `DefaultTokenizer` if not set
+    pub fn tokenizer(mut self, tokenizer: Arc<dyn Tokenizer>) -> Self {
+        self.tokenizer = tokenizer;
+        self
+    }
+
+    /// The max number of new cards to introduce each day, `DEFAULT_NEW_CARD_LIMIT` if not set
+    pub fn new_card_limit(mut self, new_card_limit: i32) -> Self {
+        self.new_card_limit = new_card_limit;
+        self
+    }
+
+    /// Different new card limits for some days of the week, the rest use `new_card_limit`
+    pub fn weekday_new_card_limits(mut self, limits: WeekdayLimits) -> Self {
+        self.weekday_new_card_limits = limits;
+        self
+    }
+
+    pub fn connection_options(mut self, connection_options: ConnectionOptions) -> Self {
+        self.connection_options = connection_options;
+        self
+    }
+
+    /// Connect to the database and create the AnkiSrsAlgorithm
+    pub fn build(self) -> SrsResult<AnkiSrsAlgorithm> {
+        let db_url = self.db_url.ok_or("No database url set for AnkiSrsAlgorithm")?;
+        let pool = ConnectionPool::new(&db_url, &self.connection_options)?;
 
         Ok(AnkiSrsAlgorithm {
             pool,
-            new_card_limit,
+            tokenizer: self.tokenizer,
+            new_card_limit: self.new_card_limit,
+            weekday_new_card_limits: self.weekday_new_card_limits,
             cards_learned_today: 0,
             cards_reviewed_today: 0,
-            local_time: Local::now(),
+            clock: Clock::system(),
+            due_shuffle_seed: None,
+            snoozes: Snoozes::default(),
+            source_filter: None,
         })
     }
+}
+
+impl AnkiSrsAlgorithm {
+    /// Start building an AnkiSrsAlgorithm, for setting options before connecting
+    pub fn builder() -> AnkiSrsAlgorithmBuilder {
+        AnkiSrsAlgorithmBuilder {
+            db_url: None,
+            tokenizer: Arc::new(DefaultTokenizer),
+            new_card_limit: DEFAULT_NEW_CARD_LIMIT,
+            weekday_new_card_limits: WeekdayLimits::default(),
+            connection_options: ConnectionOptions::default(),
+        }
+    }
+
+    /// Connect to a database and create a new AnkiSrsAlgorithm
+    pub fn new(db_url: &str, new_card_limit: i32) -> SrsResult<Self> {
+        Self::builder().db_url(db_url).new_card_limit(new_card_limit).build()
+    }
+
+    /// Connect to a database with the given connection pool options and create a new AnkiSrsAlgorithm
+    pub fn with_connection_options(db_url: &str, new_card_limit: i32, options: &ConnectionOptions) -> SrsResult<Self> {
+        Self::builder()
+            .db_url(db_url)
+            .new_card_limit(new_card_limit)
+            .connection_options(options.clone())
+            .build()
+    }
+
+    /// Add sentences with the schedules brought over from another collection, see
+    /// `migrate::migrate_to_anki`. Sentences that are already here keep their text but take the
+    /// migrated schedule, and sentences without a schedule are left as they are.
+    pub fn import_schedules(&mut self, sentences: &[(Sentence, Option<Schedule>)]) -> SrsResult<()> {
+        let existing: HashSet<String> = self.pool.get_conn()?.query("SELECT id FROM sentences")?.into_iter().collect();
+
+        let new_sentences = sentences.iter()
+            .map(|(sentence, _)| sentence)
+            .filter(|sentence| !existing.contains(&sentence.id.to_string()))
+            .cloned()
+            .collect::<Vec<Sentence>>();
+
+        if !new_sentences.is_empty() {
+            self.add_sentences(&new_sentences)?;
+        }
+
+        let mut conn = self.pool.get_conn()?;
+
+        conn.exec_batch(
+            r"UPDATE cards
+              SET cards.due = :due, cards.interval_secs = :interval_secs, cards.review_count = :review_count, cards.ease = :ease
+              WHERE cards.sentence_id = :sentence_id",
+            sentences.iter().filter_map(|(sentence, schedule)| schedule.map(|schedule| params! {
+                "sentence_id" => sentence.id.to_string(),
+                "due" => schedule.due,
+                "interval_secs" => schedule.interval.map(|interval| interval.as_secs()),
+                "review_count" => schedule.review_count,
+                "ease" => schedule.ease,
+            })))?;
+
+        revision::bump(&mut conn)
+    }
 
     fn get_card(&self, sentence_id: &str) -> SrsResult<Card> {
         let mut conn = self.pool.get_conn()?;
 
         let record: CardRecord = conn.exec_first(
-            r"SELECT cards.due, cards.interval, cards.review_count, cards.ease
+            r"SELECT cards.due, cards.interval_secs, cards.review_count, cards.ease
               FROM cards
               WHERE cards.sentence_id = :sentence_id",
               params! { "sentence_id" => sentence_id.to_string() }
             )?
-            .expect(&format!("No such sentence {}", sentence_id));
+            .ok_or_else(|| format!("No such sentence {sentence_id}"))?;
 
         Ok(Card::new(sentence_id.to_string(), record))
     }
 
-    fn update_card(&mut self, card: Card) -> SrsResult<()> {
-        let mut conn = self.pool.get_conn()?;
-
+    fn update_card(conn: &mut mysql::PooledConn, card: &Card) -> SrsResult<()> {
         conn.exec_drop(
             r"UPDATE cards
-              SET cards.due = :due, cards.interval = :interval, cards.review_count = :review_count, cards.ease = :ease
+              SET cards.due = :due, cards.interval_secs = :interval_secs, cards.review_count = :review_count, cards.ease = :ease
               WHERE cards.sentence_id = :sentence_id",
               params! {
-                "sentence_id" => card.id,
-                "due" => card.due.unwrap(),
-                "interval" => card.interval.unwrap(),
-                "review_count" => card.review_count,
-                "ease" => card.ease,
+                "sentence_id" => card.id.as_str(),
+                "due" => card.schedule.due.unwrap(),
+                "interval_secs" => card.schedule.interval.unwrap().as_secs(),
+                "review_count" => card.schedule.review_count,
+                "ease" => card.schedule.ease,
               })?;
 
         Ok(())
     }
 
-    fn get_next_due(&self) -> SrsResult<Option<Review>> {
+    /// Today's new card limit, which can be different on some days of the week
+    fn new_card_limit_today(&self) -> i32 {
+        self.weekday_new_card_limits.limit(self.clock.now().weekday(), self.new_card_limit)
+    }
+
+    /// Add sentences and their cards, see `add_sentences`
+    fn insert_sentences(&self, conn: &mut mysql::PooledConn, sentences: &[Sentence]) -> SrsResult<()> {
+        conn.exec_batch(
+            r"INSERT INTO sentences (id, text)
+              VALUES (:id, :text)",
+            sentences.iter().map(|s| params! {
+                "id" => s.id.to_string(),
+                "text" => &s.text
+            })
+        )?;
+
+        conn.exec_batch(
+            r"INSERT INTO cards (sentence_id, review_count, ease, added_order)
+              VALUES (:sentence_id, :review_count, :ease, :added_order)",
+            sentences.iter().enumerate().map(|(i, s)| params! {
+                "sentence_id" => s.id.to_string(),
+                "review_count" => 0,
+                "ease" => DEFAULT_EASE,
+                "added_order" => i,
+            })
+        )?;
+
+        self.index_words(conn, sentences)?;
+        grammar::add_detected(conn, sentences)?;
+        revision::bump(conn)?;
+
+        Ok(())
+    }
+
+    /// Record which words a sentence contains, replacing any recorded before. Cards are still whole
+    /// sentences, the words are only kept so reviews can say how many words they cover.
+    fn index_words(&self, conn: &mut mysql::PooledConn, sentences: &[Sentence]) -> SrsResult<()> {
+        conn.exec_batch("DELETE FROM sentence_words WHERE sentence_id = :sentence_id",
+            sentences.iter().map(|s| params! { "sentence_id" => s.id.to_string() }))?;
+
+        conn.exec_batch("INSERT IGNORE INTO sentence_words (sentence_id, word) VALUES (:sentence_id, :word)",
+            sentences.iter().flat_map(|s| self.split_words(&s.text).into_iter()
+                .map(|word| params! {
+                    "sentence_id" => s.id.to_string(),
+                    "word" => word,
+                })))?;
+
+        Ok(())
+    }
+
+    /// Split a sentence into the words it's recorded as containing
+    fn split_words(&self, text: &str) -> Vec<String> {
+        self.tokenizer.tokenize(text)
+            .into_iter()
+            .map(|token| token.word)
+            .collect()
+    }
+
+    /// Whether there are any cards left to review today
+    fn has_dues(&self) -> SrsResult<bool> {
+        Ok(!self.get_due(1, false)?.is_empty())
+    }
+
+    /// Record whether today's dues are finished after a review, for the daily goals
+    fn record_dues_finished(&self) -> SrsResult<()> {
+        let finished = !self.has_dues()?;
+        daily_stats::set_dues_finished(&mut self.pool.get_conn()?, self.clock.now().naive_local().date(), finished)
+    }
+
+    /// Get up to `limit` due cards, earliest due first, optionally only the ones from the learning
+    /// queue
+    fn get_due(&self, limit: usize, learning_queue_only: bool) -> SrsResult<Vec<Review>> {
         let mut conn = self.pool.get_conn()?;
 
-        let midnight = (self.local_time + chrono::Duration::days(1))
-            .with_hour(0).unwrap()
-            .with_minute(0).unwrap()
-            .with_second(0).unwrap()
-            .with_nanosecond(0).unwrap();
+        let midnight = super::end_of_day(self.clock.now());
 
-        let result = conn.exec_first(
-            r"SELECT cards.sentence_id, sentences.text
+        let learning_queue_filter = if learning_queue_only {
+            format!("AND {IN_LEARNING_QUEUE}")
+        }
+        else {
+            String::new()
+        };
+
+        let shuffle_seed = self.due_shuffle_seed.filter(|_| !learning_queue_only);
+        // Shuffled cards are all fetched, so they can be shuffled before taking the first `limit`
+        let query_limit = if shuffle_seed.is_some() { u64::MAX } else { limit as u64 };
+        let not_snoozed = self.snoozes.not_snoozed("sentences.id", self.clock.now().naive_utc());
+
+        let mut result = conn.exec_map(
+            // Every word in a due sentence is due along with it
+            format!(r"SELECT cards.sentence_id, sentences.text,
+                (SELECT COUNT(*) FROM sentence_words
+                 WHERE sentence_words.sentence_id = sentences.id
+                   AND sentence_words.word NOT IN (SELECT word FROM word_blacklist)),
+                cards.due
               FROM cards
               INNER JOIN sentences ON cards.sentence_id = sentences.id
-              WHERE cards.due IS NOT NULL AND cards.due < :latest_time
+              WHERE {DUE_NOW} AND sentences.deleted IS NULL AND {SENTENCE_NOT_SUSPENDED} AND {not_snoozed} {learning_queue_filter}
               ORDER BY cards.due, cards.added_order ASC
-              LIMIT 1",
+              LIMIT :limit"),
             params! {
-                "latest_time" => midnight.naive_utc()
-            })?
-            .map(|(id, text): (String, String)| Review::Due {
+                "now" => self.clock.now().naive_utc(),
+                "latest_time" => midnight.naive_utc(),
+                "learning_queue_secs" => LEARNING_QUEUE_INTERVAL.as_secs(),
+                "limit" => query_limit,
+            },
+            |(id, text, words_due, due): (String, String, i32, NaiveDateTime)| Review::Due {
                 sentence: Sentence {
                     id: Uuid::from_str(&id).unwrap(),
                     text,
                 },
-                words_due: 0,
-            });
+                words_due,
+                // Only recognition cards are supported here so far
+                card_type: CardType::Recognition,
+                // Cards are whole sentences here, so the only reason is when the card was due
+                reason: Some(SelectionReason { due: Some(due), ..Default::default() }),
+            })?;
 
-        let results = result.iter().next().map(|review| review.clone());
+        if let Some(seed) = shuffle_seed {
+            scheduler::shuffle_by_seed(&mut result, seed, |review| review.sentence().id);
+            result.truncate(limit);
+        }
 
-        Ok(results)
+        Ok(result)
     }
 
-    fn get_next_new(&self) -> SrsResult<Option<Review>> {
-        if self.cards_learned_today >= self.new_card_limit {
-            return Ok(None);
+    /// Get up to `limit` new cards, no more than the new card limit has room for
+    fn get_new(&self, limit: usize) -> SrsResult<Vec<Review>> {
+        let new_card_limit = self.new_card_limit_today();
+        if self.cards_learned_today >= new_card_limit {
+            return Ok(Vec::new());
         }
 
+        let limit = limit.min((new_card_limit - self.cards_learned_today) as usize);
+
         let mut conn = self.pool.get_conn()?;
 
-        let result = conn.query_map(
-            r"SELECT cards.sentence_id, sentences.text
+        let not_snoozed = self.snoozes.not_snoozed("sentences.id", self.clock.now().naive_utc());
+        let (source_kind, source_name) = sources::source_params(self.source_filter.as_ref());
+
+        let result = conn.exec_map(
+            // A word is unknown until some sentence containing it has been reviewed
+            format!(r"SELECT cards.sentence_id, sentences.text,
+                (SELECT COUNT(*) FROM sentence_words AS new_words
+                 WHERE new_words.sentence_id = sentences.id
+                   AND new_words.word NOT IN (SELECT word FROM word_blacklist)
+                   AND NOT EXISTS (
+                     SELECT 1 FROM sentence_words AS known_words
+                     INNER JOIN cards AS known_cards ON known_cards.sentence_id = known_words.sentence_id
+                     INNER JOIN sentences AS known_sentences ON known_sentences.id = known_words.sentence_id
+                     WHERE known_words.word = new_words.word
+                       AND known_cards.due IS NOT NULL
+                       AND known_sentences.deleted IS NULL))
               FROM cards
               INNER JOIN sentences ON cards.sentence_id = sentences.id
-              WHERE cards.due IS NULL
+              WHERE cards.due IS NULL AND sentences.deleted IS NULL AND {SENTENCE_NOT_SUSPENDED} AND {FROM_SOURCE} AND {not_snoozed}
               ORDER BY cards.added_order ASC
-              LIMIT 1",
-            |(id, text): (String, String)| Review::New {
+              LIMIT :limit"),
+            params! {
+                "limit" => limit,
+                "source_kind" => source_kind,
+                "source_name" => source_name,
+            },
+            |(id, text, unknown_words): (String, String, i32)| Review::New {
                 sentence: Sentence {
                     id: Uuid::from_str(&id).unwrap(),
                     text,
                 },
-                unknown_words: 0,
+                unknown_words,
+                reason: Some(SelectionReason::default()),
             })?;
 
-        Ok(result.into_iter().next())
+        Ok(result)
     }
 }
 
 impl SrsAlgorithm for AnkiSrsAlgorithm {
-    fn reinitialize_db(&mut self) -> SrsResult<()> {
+    fn reinitialize_db(&mut self, force: bool) -> SrsResult<()> {
+        let mut conn = self.pool.get_conn()?;
+
+        if !force && db::has_reviews(&mut conn)? {
+            return Err("Refusing to reinitialize a database with reviews in it without force".into());
+        }
+
         log::info!("Reinitializing database");
 
+        let tables = ["sentences", "cards", "sentence_words", "daily_stats", "source_daily_stats", "word_blacklist", "word_suspensions", "collection_revision", "sentence_tags",
+            "sentence_targets", "grammar_points", "sentence_sources", "sentence_flags", "vacations"];
+        db::backup_tables(&mut conn, &tables)?;
+
         // Drop all tables
-        self.pool.get_conn()?.query_drop("DROP TABLE IF EXISTS sentences, cards")?;
+        conn.query_drop(format!("DROP TABLE IF EXISTS {}", tables.join(", ")))?;
 
         // Initialise db
         self.initialize_db()
@@ -265,46 +434,378 @@ impl SrsAlgorithm for AnkiSrsAlgorithm {
                 `sentence_id` CHAR(36) NOT NULL,
                 `review_count` INT NOT NULL,
                 `ease` FLOAT NOT NULL,
-                `interval` TIME,
+                `interval_secs` BIGINT,
                 `due` DATETIME,
                 `added_order` INT NOT NULL,
                 PRIMARY KEY (`sentence_id`)
             )
         ")?;
 
+        conn.query_drop(r"
+            CREATE TABLE IF NOT EXISTS sentence_words (
+                `sentence_id` CHAR(36) NOT NULL,
+                `word` VARCHAR(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci NOT NULL,
+                PRIMARY KEY (`sentence_id`, `word`),
+                KEY (`word`)
+            )
+        ")?;
+
+        daily_stats::create_table(&mut conn)?;
+        blacklist::create_table(&mut conn)?;
+        blacklist::normalize_stored(&mut conn)?;
+        revision::create_table(&mut conn)?;
+        tags::create_table(&mut conn)?;
+        targets::create_table(&mut conn)?;
+        grammar::create_table(&mut conn)?;
+        flags::create_table(&mut conn)?;
+        sources::create_table(&mut conn)?;
+        vacation::create_table(&mut conn)?;
+
+        // Columns added since the tables were first created
+        db::migrate_interval_column(&mut conn)?;
+        db::add_column_if_missing(&mut conn, "sentences", "deleted", "DATETIME")?;
+        db::add_column_if_missing(&mut conn, "sentences", "translation", "TEXT CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci")?;
+        db::add_column_if_missing(&mut conn, "sentences", "notes", "TEXT CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci")?;
+
+        // Sentences added before their words were recorded
+        let unindexed: Vec<Sentence> = conn.query_map(
+            r"SELECT id, text FROM sentences
+              WHERE NOT EXISTS (SELECT 1 FROM sentence_words WHERE sentence_words.sentence_id = sentences.id)",
+            |(id, text): (String, String)| Sentence { id: Uuid::from_str(&id).unwrap(), text })?;
+
+        if !unindexed.is_empty() {
+            log::info!("Recording the words of {} sentences", unindexed.len());
+            self.index_words(&mut conn, &unindexed)?;
+        }
+
+        self.purge_trash()?;
+
+        // Pick up today's counts again, in case the app was closed partway through the day
+        let today = daily_stats::load(&mut self.pool.get_conn()?, self.clock.now().naive_local().date(), 1)?;
+        if let Some(today) = today.last() {
+            self.cards_learned_today = today.cards_learned;
+            self.cards_reviewed_today = today.cards_reviewed;
+        }
+
         Ok(())
     }
 
-    fn add_sentences(&mut self, sentences: &[Sentence]) -> SrsResult<()> {
+    fn add_sentences(&mut self, sentences: &[Sentence]) -> SrsResult<ImportReport> {
         log::info!("Adding {} sentences", sentences.len());
 
-        let mut conn = self.pool.get_conn()?;
+        // All or nothing, so a sentence that fails can't leave the ones before it half added
+        db::in_transaction(&mut self.pool.get_conn()?, |conn| self.insert_sentences(conn, sentences))?;
 
-        conn.exec_batch(
-            r"INSERT INTO sentences (id, text)
-              VALUES (:id, :text)",
-            sentences.iter().map(|s| params! {
-                "id" => s.id.to_string(),
-                "text" => &s.text
+        // Cards are per sentence here, so there are no new words to report
+        Ok(ImportReport::default())
+    }
+
+    fn preview_sentences(&self, sentences: &[Sentence]) -> SrsResult<Vec<SentencePreview>> {
+        // Cards are per sentence rather than per word here, so no words are ever new
+        Ok(sentences.iter()
+            .map(|sentence| SentencePreview {
+                text: sentence.text.clone(),
+                words: self.split_words(&sentence.text),
+                new_words: Vec::new(),
             })
-        )?;
+            .collect())
+    }
 
-        conn.exec_batch(
-            r"INSERT INTO cards (sentence_id, review_count, ease, added_order)
-              VALUES (:sentence_id, :review_count, :ease, :added_order)",
-            sentences.iter().enumerate().map(|(i, s)| params! {
-                "sentence_id" => s.id.to_string(),
-                "review_count" => 0,
-                "ease" => DEFAULT_EASE,
-                "added_order" => i,
+    fn annotate(&self, text: &str) -> SrsResult<Vec<(Token, KnowledgeState)>> {
+        let blacklist = blacklist::load(&mut self.pool.get_conn()?)?;
+
+        // Cards are sentences here, so there's no way of knowing how well a single word is known
+        Ok(self.tokenizer.tokenize(text).into_iter()
+            .map(|token| {
+                let state = if blacklist.contains(&token.word) {
+                    KnowledgeState::Blacklisted
+                }
+                else {
+                    KnowledgeState::New
+                };
+
+                (token, state)
             })
-        )?;
+            .collect())
+    }
+
+    fn get_sentences(&self) -> SrsResult<Vec<Sentence>> {
+        Ok(self.pool.get_conn()?.query_map("SELECT id, text FROM sentences WHERE deleted IS NULL",
+            |(id, text): (String, String)| Sentence {
+                id: Uuid::from_str(&id).unwrap(),
+                text,
+            })?)
+    }
+
+    fn existing_sentences(&self, texts: &[String]) -> SrsResult<HashSet<String>> {
+        db::existing_sentences(&mut self.pool.get_conn()?, texts)
+    }
+
+    fn list_sentences(&self, page: PageRequest) -> SrsResult<Page<Sentence>> {
+        page::sentences(&mut self.pool.get_conn()?, None, None, None, page)
+    }
+
+    fn search_sentences(&self, query: &str, page: PageRequest) -> SrsResult<Page<Sentence>> {
+        page::sentences(&mut self.pool.get_conn()?, Some(query), None, None, page)
+    }
+
+    fn get_review_history(&self, _: PageRequest) -> SrsResult<Page<ReviewRecord>> {
+        // The review history is of words, and cards are sentences here
+        Ok(Page::empty())
+    }
+
+    fn get_cloze(&self, _: &Sentence) -> SrsResult<Option<Cloze>> {
+        // Cards are sentences here, so there's no single word being tested to hide
+        Ok(None)
+    }
+
+    fn get_translation(&self, sentence_id: &Uuid) -> SrsResult<Option<String>> {
+        let translation: Option<Option<String>> = self.pool.get_conn()?.exec_first(
+            "SELECT translation FROM sentences WHERE id = :id",
+            params! { "id" => sentence_id.to_string() })?;
+
+        Ok(translation.flatten())
+    }
+
+    fn set_translation(&mut self, sentence_id: &Uuid, translation: Option<&str>) -> SrsResult<()> {
+        self.pool.get_conn()?.exec_drop("UPDATE sentences SET translation = :translation WHERE id = :id",
+            params! {
+                "id" => sentence_id.to_string(),
+                "translation" => translation,
+            })?;
 
         Ok(())
     }
 
-    fn get_next_card(&self) -> SrsResult<Option<Review>> {
-        Ok(self.get_next_new()?.or(self.get_next_due()?))
+    fn get_notes(&self, sentence_id: &Uuid) -> SrsResult<Option<String>> {
+        let notes: Option<Option<String>> = self.pool.get_conn()?.exec_first(
+            "SELECT notes FROM sentences WHERE id = :id",
+            params! { "id" => sentence_id.to_string() })?;
+
+        Ok(notes.flatten())
+    }
+
+    fn set_notes(&mut self, sentence_id: &Uuid, notes: Option<&str>) -> SrsResult<()> {
+        self.pool.get_conn()?.exec_drop("UPDATE sentences SET notes = :notes WHERE id = :id",
+            params! {
+                "id" => sentence_id.to_string(),
+                "notes" => notes,
+            })?;
+
+        Ok(())
+    }
+
+    fn set_sentence_text(&mut self, sentence_id: &Uuid, text: &str) -> SrsResult<()> {
+        log::info!("Changing the text of sentence {sentence_id} to {text}");
+
+        // Cards are whole sentences here, so only the recorded words need splitting again
+        let mut conn = self.pool.get_conn()?;
+        conn.exec_drop("UPDATE sentences SET text = :text WHERE id = :id",
+            params! {
+                "id" => sentence_id.to_string(),
+                "text" => text,
+            })?;
+
+        self.index_words(&mut conn, &[Sentence { id: *sentence_id, text: text.to_string() }])?;
+
+        revision::bump(&mut conn)
+    }
+
+    fn get_sentence_tags(&self, sentence_id: &Uuid) -> SrsResult<Vec<String>> {
+        tags::load(&mut self.pool.get_conn()?, sentence_id)
+    }
+
+    fn tag_sentences(&mut self, ids: &[Uuid], tag: &str) -> SrsResult<()> {
+        log::info!("Tagging {} sentences with {tag}", ids.len());
+        tags::add(&mut self.pool.get_conn()?, ids, tag)
+    }
+
+    fn get_sentence_source(&self, sentence_id: &Uuid) -> SrsResult<Option<SentenceSource>> {
+        sources::load(&mut self.pool.get_conn()?, sentence_id)
+    }
+
+    fn set_sentence_source(&mut self, ids: &[Uuid], source: &SentenceSource) -> SrsResult<()> {
+        log::info!("Setting the source of {} sentences to {}", ids.len(), source.label());
+        sources::set(&mut self.pool.get_conn()?, ids, source, self.clock.now().naive_utc())
+    }
+
+    fn source_stats(&self) -> SrsResult<Vec<SourceStats>> {
+        sources::stats(&mut self.pool.get_conn()?)
+    }
+
+    fn find_sentences(&self, query: Option<&str>, source: Option<&SentenceSource>, flag: Option<Flag>, page: PageRequest)
+        -> SrsResult<Page<Sentence>>
+    {
+        page::sentences(&mut self.pool.get_conn()?, query, source, flag, page)
+    }
+
+    fn source_filter(&self) -> Option<&SentenceSource> {
+        self.source_filter.as_ref()
+    }
+
+    fn set_source_filter(&mut self, source: Option<SentenceSource>) {
+        log::info!("Only learning new sentences from {source:?}");
+        self.source_filter = source;
+    }
+
+    fn get_target_word(&self, sentence_id: &Uuid) -> SrsResult<Option<TargetWord>> {
+        targets::load(&mut self.pool.get_conn()?, sentence_id)
+    }
+
+    fn set_target_words(&mut self, targets: &[(Uuid, TargetWord)]) -> SrsResult<()> {
+        targets::set(&mut self.pool.get_conn()?, targets)
+    }
+
+    fn get_flag(&self, sentence_id: &Uuid) -> SrsResult<Option<Flag>> {
+        flags::load(&mut self.pool.get_conn()?, sentence_id)
+    }
+
+    fn set_flag(&mut self, sentence_id: &Uuid, flag: Option<Flag>) -> SrsResult<()> {
+        log::info!("Setting flag on sentence {sentence_id} to {flag:?}");
+        flags::set(&mut self.pool.get_conn()?, sentence_id, flag)
+    }
+
+    fn get_grammar_points(&self, sentence_id: &Uuid) -> SrsResult<Vec<String>> {
+        grammar::load(&mut self.pool.get_conn()?, sentence_id)
+    }
+
+    fn add_grammar_point(&mut self, ids: &[Uuid], grammar_point: &str) -> SrsResult<()> {
+        log::info!("Tagging {} sentences with grammar point {grammar_point}", ids.len());
+        grammar::add(&mut self.pool.get_conn()?, ids, grammar_point)
+    }
+
+    fn remove_grammar_point(&mut self, sentence_id: &Uuid, grammar_point: &str) -> SrsResult<()> {
+        grammar::remove(&mut self.pool.get_conn()?, sentence_id, grammar_point)
+    }
+
+    fn detect_grammar_points(&mut self) -> SrsResult<usize> {
+        grammar::detect_all(&mut self.pool.get_conn()?)
+    }
+
+    fn grammar_stats(&self) -> SrsResult<Vec<GrammarStats>> {
+        grammar::stats(&mut self.pool.get_conn()?)
+    }
+
+    fn get_review_log(&self) -> SrsResult<Vec<RevlogEntry>> {
+        // There's no review history here, only the cards' current schedules
+        Ok(Vec::new())
+    }
+
+    fn delete_sentences(&mut self, ids: &[Uuid]) -> SrsResult<()> {
+        log::info!("Moving {} sentences to the trash", ids.len());
+        let mut conn = self.pool.get_conn()?;
+        trash::trash(&mut conn, ids, self.clock.now().naive_utc())?;
+        revision::bump(&mut conn)
+    }
+
+    fn get_trash(&self) -> SrsResult<Vec<TrashedSentence>> {
+        trash::load(&mut self.pool.get_conn()?)
+    }
+
+    fn restore_sentences(&mut self, ids: &[Uuid]) -> SrsResult<()> {
+        log::info!("Restoring {} sentences from the trash", ids.len());
+        let mut conn = self.pool.get_conn()?;
+        trash::restore(&mut conn, ids)?;
+        revision::bump(&mut conn)
+    }
+
+    fn purge_trash(&mut self) -> SrsResult<usize> {
+        let mut conn = self.pool.get_conn()?;
+
+        let expired = trash::expired(&mut conn, self.clock.now().naive_utc())?;
+
+        if !expired.is_empty() {
+            log::info!("Deleting {} sentences from the trash", expired.len());
+
+            // Cards are sentences here, so they go along with them
+            for (table, column) in [("cards", "sentence_id"), ("sentence_words", "sentence_id"), ("sentence_tags", "sentence_id"), ("sentence_targets", "sentence_id"),
+                                    ("grammar_points", "sentence_id"), ("sentence_sources", "sentence_id"), ("sentence_flags", "sentence_id"),
+                                    ("sentences", "id")] {
+                conn.exec_batch(format!("DELETE FROM {table} WHERE {column} = :id"),
+                    expired.iter().map(|id| params! { "id" => id.as_str() }))?;
+            }
+
+            revision::bump(&mut conn)?;
+        }
+
+        Ok(expired.len())
+    }
+
+    fn get_next_card(&self) -> SrsResult<QueueState> {
+        // Nothing is served while reviews are paused
+        if let Some(vacation) = vacation::current(&mut self.pool.get_conn()?)? {
+            return Ok(QueueState::OnVacation { since: vacation.started });
+        }
+
+        // Cards from the learning queue that have come back round go first, so cards answered
+        // Again are seen again in the same session
+        if let Some(review) = self.get_due(1, true)?.into_iter().next() {
+            return Ok(QueueState::Review(review));
+        }
+
+        let next_due = self.get_due(1, false)?.into_iter().next();
+
+        if let Some(review) = self.get_new(1)?.into_iter().next().or(next_due) {
+            return Ok(QueueState::Review(review));
+        }
+
+        if !db::has_sentences(&mut self.pool.get_conn()?)? {
+            return Ok(QueueState::CollectionEmpty);
+        }
+
+        // Only say new cards are blocked if there are any left to introduce
+        let new_card_limit = self.new_card_limit_today();
+        if self.cards_learned_today >= new_card_limit && self.collection_stats()?.new_cards > 0 {
+            return Ok(QueueState::NewCardLimit { learned_today: self.cards_learned_today, limit: new_card_limit });
+        }
+
+        Ok(QueueState::NothingDue { reviewed_today: self.cards_reviewed_today })
+    }
+
+    fn get_review_queue(&self, limit: usize) -> SrsResult<Vec<Review>> {
+        if vacation::current(&mut self.pool.get_conn()?)?.is_some() {
+            return Ok(Vec::new());
+        }
+
+        let mut queue = self.get_due(limit, true)?;
+        let learning_queue = queue.iter().map(|review| review.sentence().id).collect::<HashSet<Uuid>>();
+        queue.extend(self.get_new(limit)?);
+        queue.extend(self.get_due(limit, false)?.into_iter()
+            .filter(|review| !learning_queue.contains(&review.sentence().id)));
+        queue.truncate(limit);
+
+        Ok(queue)
+    }
+
+    fn get_due_queue(&self, limit: usize) -> SrsResult<Vec<super::Review>> {
+        if vacation::current(&mut self.pool.get_conn()?)?.is_some() {
+            return Ok(Vec::new());
+        }
+
+        let mut queue = self.get_due(limit, true)?;
+        let learning_queue = queue.iter().map(|review| review.sentence().id).collect::<HashSet<Uuid>>();
+        queue.extend(self.get_due(limit, false)?.into_iter()
+            .filter(|review| !learning_queue.contains(&review.sentence().id)));
+        queue.truncate(limit);
+
+        Ok(queue)
+    }
+
+    fn get_learning_cards(&self) -> SrsResult<Vec<LearningCard>> {
+        let mut conn = self.pool.get_conn()?;
+
+        let cards = conn.exec_map(
+            r"SELECT sentences.text, cards.due
+              FROM cards
+              INNER JOIN sentences ON cards.sentence_id = sentences.id
+              WHERE cards.due IS NOT NULL AND cards.review_count < :graduated_count AND sentences.deleted IS NULL
+              ORDER BY cards.due",
+            params! {
+                "graduated_count" => INITIAL_INTERVALS.len(),
+            },
+            |(text, due)| LearningCard { text, due })?;
+
+        Ok(cards)
     }
 
     // TODO: might be better if we get the record that matches the review from the database,
@@ -315,21 +816,41 @@ impl SrsAlgorithm for AnkiSrsAlgorithm {
 
         // Get card to review
         let mut card = self.get_card(&sentence.id.to_string())?;
+        let is_new = card.schedule.due.is_none();
 
-        // Increment cards reviewed today
-        self.cards_reviewed_today += 1;
+        // Review card
+        card.schedule.review(self.clock.now(), score)?;
+
+        // Either the whole review is written or none of it is, so retrying a failed answer doesn't
+        // count it twice
+        db::in_transaction(&mut self.pool.get_conn()?, |conn| {
+            // A new card teaches each of its words
+            if is_new {
+                sources::record_words_taught(conn, &sentence.id, self.split_words(&sentence.text).len())?;
+            }
+
+            // Record the review in the daily stats
+            daily_stats::record_review(conn, self.clock.now().naive_local().date(), &sentence.id, is_new)?;
+
+            Self::update_card(conn, &card)?;
+            grammar::record_exposure(conn, &sentence.id)
+        })?;
 
-        // Increment new cards learned if this is a new card
-        if card.due.is_none() {
+        self.cards_reviewed_today += 1;
+        if is_new {
             self.cards_learned_today += 1;
         }
 
-        // Review card
-        card.review(self.local_time, score)?;
+        // The review's been committed, so failing now would get it retried and counted twice. None
+        // of these are worth that, so they're only logged.
+        if let Err(err) = self.record_dues_finished() {
+            log::error!("Failed to record whether today's dues are finished: {err}");
+        }
+
+        if let Err(err) = self.pool.get_conn().map_err(Into::into).and_then(|mut conn| revision::bump(&mut conn)) {
+            log::error!("Failed to bump the counts revision: {err}");
+        }
 
-        // Update card
-        self.update_card(card)?;
-        
         Ok(())
     }
 
@@ -338,9 +859,18 @@ impl SrsAlgorithm for AnkiSrsAlgorithm {
         self.cards_learned_today = 0;
     }
 
-    fn set_time_now(&mut self, time: DateTime<Local>) {
-        log::info!("Setting current time to {time:?}");
-        self.local_time = time;
+    #[cfg(feature = "simulation")]
+    fn set_time_now(&mut self, time: chrono::DateTime<chrono::Local>) {
+        self.clock.set(time);
+    }
+
+    fn set_due_shuffle_seed(&mut self, seed: Option<u32>) {
+        self.due_shuffle_seed = seed;
+    }
+
+    fn snooze(&mut self, sentence_id: &Uuid, delay: Duration) {
+        let now = self.clock.now().naive_utc();
+        self.snoozes.snooze(*sentence_id, now + chrono::Duration::seconds(delay.as_secs() as i64), now);
     }
 
     fn cards_learned_today(&self) -> i32 {
@@ -351,7 +881,328 @@ impl SrsAlgorithm for AnkiSrsAlgorithm {
         self.cards_reviewed_today
     }
 
-    fn get_suggested_sentences(&self, _: i32) -> SrsResult<Vec<(Sentence, Vec<String>)>> {
+    fn forecast(&self, days: usize) -> SrsResult<Vec<i32>> {
+        let mut conn = self.pool.get_conn()?;
+
+        let dues: Vec<NaiveDateTime> = conn.exec("SELECT cards.due FROM cards WHERE cards.due IS NOT NULL", ())?;
+
+        Ok(forecast::due_counts_by_day(self.clock.now(), &dues, days))
+    }
+
+    fn forecast_with_new_cards(&self, days: usize, new_cards_per_day: i32) -> SrsResult<Vec<i32>> {
+        let mut forecast = self.forecast(days)?;
+        forecast::add_new_card_reviews(&mut forecast, &Schedule::good_review_days(days)?, new_cards_per_day);
+        Ok(forecast)
+    }
+
+    fn connection_state(&self) -> ConnectionState {
+        self.pool.state()
+    }
+
+    fn clock_behind(&self) -> Option<chrono::DateTime<chrono::Local>> {
+        self.clock.behind()
+    }
+
+    fn collection_stats(&self) -> SrsResult<CollectionStats> {
+        let mut conn = self.pool.get_conn()?;
+
+        let stats = conn.exec_first(
+            format!(r"SELECT CAST(COALESCE(SUM({DUE_NOW}), 0) AS SIGNED),
+                     CAST(COALESCE(SUM(cards.due IS NOT NULL && {IN_LEARNING_QUEUE} && cards.due > :now && cards.due < :latest_time), 0) AS SIGNED),
+                     CAST(COALESCE(SUM(cards.due IS NULL), 0) AS SIGNED),
+                     CAST(COALESCE(SUM(cards.due IS NOT NULL && cards.review_count < :graduated_count), 0) AS SIGNED),
+                     CAST(COALESCE(SUM(cards.review_count >= :graduated_count), 0) AS SIGNED)
+              FROM cards
+              WHERE {SENTENCE_NOT_SUSPENDED}"),
+            params! {
+                "now" => self.clock.now().naive_utc(),
+                "latest_time" => super::end_of_day(self.clock.now()).naive_utc(),
+                "learning_queue_secs" => LEARNING_QUEUE_INTERVAL.as_secs(),
+                "graduated_count" => INITIAL_INTERVALS.len(),
+            })?
+            .map(|(due_cards, learning_due_later, new_cards, learning_cards, known_cards)| CollectionStats {
+                due_cards,
+                learning_due_later,
+                new_cards,
+                learning_cards,
+                known_cards,
+            });
+
+        // Nothing's due while on vacation, as nothing gets reviewed until it ends
+        vacation::pause_counts(&mut conn, stats.unwrap_or_default())
+    }
+
+    fn counts_if_changed(&self, since: Option<CountsRevision>) -> SrsResult<Option<(CollectionStats, CountsRevision)>> {
+        revision::counts_if_changed(&mut self.pool.get_conn()?, since, self.clock.now(), || self.collection_stats())
+    }
+
+    fn daily_stats(&self, days: usize) -> SrsResult<Vec<DailyStats>> {
+        let mut stats = daily_stats::load(&mut self.pool.get_conn()?, self.clock.now().naive_local().date(), days)?;
+
+        // Cards can come due later in the day without a review to unset today's flag
+        if let Some(today) = stats.last_mut().filter(|today| today.dues_finished) {
+            today.dues_finished = !self.has_dues()?;
+        }
+
+        Ok(stats)
+    }
+
+    fn get_stats_export(&self, days: usize, forecast_days: usize) -> SrsResult<StatsExport> {
+        // There's no review history here to measure retention from
+        let daily_stats = self.daily_stats(days)?;
+        let forecast = self.forecast(forecast_days)?;
+
+        Ok(StatsExport::new(&daily_stats, &[], &forecast, self.clock.now().naive_local().date()))
+    }
+
+    fn get_schedules(&self) -> SrsResult<Vec<Schedule>> {
+        Ok(self.pool.get_conn()?.query_map(
+            r"SELECT cards.due, cards.interval_secs, cards.review_count, cards.ease
+              FROM cards
+              INNER JOIN sentences ON sentences.id = cards.sentence_id
+              WHERE sentences.deleted IS NULL",
+            |(due, interval, review_count, ease): (Option<NaiveDateTime>, Option<u64>, i32, f32)| {
+                Schedule { due, interval: interval.map(Duration::from_secs), review_count, ease }
+            })?)
+    }
+
+    fn get_redundant_sentences(&self) -> SrsResult<Vec<Sentence>> {
+        // Each sentence is its own card, so a sentence is never covered by other ones
+        Ok(Vec::new())
+    }
+
+    fn retire_redundant_sentences(&mut self) -> SrsResult<usize> {
+        Ok(0)
+    }
+
+    // Cards are sentences here, so the blacklist is only stored, there are no word cards for it
+    // to exclude. Suspending a word suspends every sentence it's in instead.
+    fn get_blacklist(&self) -> SrsResult<Vec<String>> {
+        blacklist::load(&mut self.pool.get_conn()?)
+    }
+
+    fn blacklist_word(&mut self, word: &str) -> SrsResult<()> {
+        blacklist::add(&mut self.pool.get_conn()?, word)
+    }
+
+    fn unblacklist_word(&mut self, word: &str) -> SrsResult<()> {
+        blacklist::remove(&mut self.pool.get_conn()?, word)
+    }
+
+    fn get_suspended_words(&self) -> SrsResult<Vec<String>> {
+        blacklist::load_suspended(&mut self.pool.get_conn()?)
+    }
+
+    fn suspend_word(&mut self, word: &str) -> SrsResult<()> {
+        log::info!("Suspending {word}");
+
+        let mut conn = self.pool.get_conn()?;
+        blacklist::suspend(&mut conn, word)?;
+        revision::bump(&mut conn)
+    }
+
+    fn unsuspend_word(&mut self, word: &str) -> SrsResult<()> {
+        log::info!("Unsuspending {word}");
+
+        let mut conn = self.pool.get_conn()?;
+        blacklist::unsuspend(&mut conn, word)?;
+        revision::bump(&mut conn)
+    }
+
+    fn get_word_info(&self, _: &str) -> SrsResult<Option<WordInfo>> {
+        // Cards are whole sentences here, so words have no cards of their own
+        Ok(None)
+    }
+
+    fn get_flagged_names(&self) -> SrsResult<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    fn rescue_name(&mut self, _: &str) -> SrsResult<()> {
+        Ok(())
+    }
+
+    fn get_sentence_words(&self, sentence_id: &Uuid) -> SrsResult<Vec<String>> {
+        // Sentences aren't split into word cards here, but their words are still recorded
+        Ok(self.pool.get_conn()?.exec(
+            r"SELECT sentence_words.word
+              FROM sentence_words
+              INNER JOIN sentences ON sentences.id = sentence_words.sentence_id
+              WHERE sentence_words.sentence_id = :sentence_id
+              ORDER BY LOCATE(sentence_words.word, sentences.text)",
+            params! { "sentence_id" => sentence_id.to_string() })?)
+    }
+
+    fn set_sentence_words(&mut self, _: &Uuid, _: &[String]) -> SrsResult<()> {
+        Err("Overriding a sentence's words isn't supported with sentence cards".into())
+    }
+
+    fn get_expressions(&self) -> SrsResult<Vec<String>> {
+        // Sentences aren't split into word cards here, so there's nothing to join expressions in
+        Ok(Vec::new())
+    }
+
+    fn add_expression(&mut self, _: &str) -> SrsResult<()> {
+        Err("Expressions aren't supported with sentence cards".into())
+    }
+
+    fn remove_expression(&mut self, _: &str) -> SrsResult<()> {
+        Ok(())
+    }
+
+    fn retokenize_sentences(&mut self) -> SrsResult<usize> {
+        Ok(0)
+    }
+
+    fn merge_words(&mut self, _: &str, _: &str) -> SrsResult<()> {
+        // Cards are sentences here, so there are no word cards to merge
+        Ok(())
+    }
+
+    fn get_kanji_stats(&self) -> SrsResult<Vec<KanjiStats>> {
+        // There are no word cards here to track kanji through
+        Ok(Vec::new())
+    }
+
+    fn import_word_levels(&mut self, _: &str, _: &[String]) -> SrsResult<()> {
+        // There are no word cards here to track levels through
+        Ok(())
+    }
+
+    fn mark_words_known(&mut self, _: &[String], _: u32) -> SrsResult<usize> {
+        Err("Marking words as known isn't supported with sentence cards".into())
+    }
+
+    fn level_progress(&self) -> SrsResult<Vec<LevelProgress>> {
+        Ok(Vec::new())
+    }
+
+    fn check_integrity(&mut self, repair: bool) -> SrsResult<IntegrityReport> {
+        log::info!("Checking database integrity (repair: {repair})");
+
+        let mut conn = self.pool.get_conn()?;
+
+        // Cards are sentences here, so there are no words to check, only the cards themselves
+        let cards_without_words = integrity::check_rows(&mut conn, "cards",
+            "sentence_id NOT IN (SELECT id FROM sentences)", repair)?;
+
+        let no_interval = "cards.due IS NOT NULL && cards.interval_secs IS NULL";
+        let due_without_interval: Option<i64> = conn.query_first(format!("SELECT count(*) FROM cards WHERE {no_interval}"))?;
+        let due_cards_without_interval = due_without_interval.unwrap_or(0) as usize;
+
+        if repair && due_cards_without_interval > 0 {
+            conn.exec_drop(format!("UPDATE cards SET cards.review_count = 0, cards.interval_secs = :interval_secs WHERE {no_interval}"),
+                params! { "interval_secs" => INITIAL_INTERVALS[0].as_secs() })?;
+        }
+
+        if repair {
+            revision::bump(&mut conn)?;
+        }
+
+        Ok(IntegrityReport {
+            cards_without_words,
+            due_cards_without_interval,
+            repaired: repair,
+            ..Default::default()
+        })
+    }
+
+    fn vacuum(&mut self, _: u32) -> SrsResult<VacuumReport> {
+        log::info!("Vacuuming database");
+
+        let mut conn = self.pool.get_conn()?;
+        let size_before = vacuum::database_size(&mut conn)?;
+
+        // There are no words or review history here, so all there is to do is compact
+        vacuum::compact(&mut conn, &["sentences", "cards"])?;
+
+        Ok(VacuumReport {
+            reclaimed_bytes: size_before - vacuum::database_size(&mut conn)?,
+            ..Default::default()
+        })
+    }
+
+    fn clear_cache(&mut self) {
+        // Nothing is cached here, cards are always loaded from the db
+    }
+
+    fn rebuild_cards(&mut self) -> SrsResult<RebuildReport> {
+        log::info!("Rebuilding cards from sentences");
+
+        let mut conn = self.pool.get_conn()?;
+
+        // Cards are whole sentences, so there's nothing to tokenize, just sentences missing a card
+        conn.exec_drop(
+            r"INSERT INTO cards (sentence_id, review_count, ease, added_order)
+              SELECT sentences.id, 0, :ease, 0
+              FROM sentences
+              LEFT JOIN cards ON cards.sentence_id = sentences.id
+              WHERE cards.sentence_id IS NULL",
+            params! { "ease" => DEFAULT_EASE })?;
+
+        let report = RebuildReport {
+            cards_added: conn.affected_rows() as usize,
+            ..Default::default()
+        };
+
+        if !report.is_unchanged() {
+            revision::bump(&mut conn)?;
+        }
+
+        Ok(report)
+    }
+
+    fn spread_dues(&mut self, days: u32) -> SrsResult<SpreadReport> {
+        log::info!("Spreading dues over {days} days");
+
+        let mut conn = self.pool.get_conn()?;
+
+        let window_end = super::end_of_day(self.clock.now()) + chrono::Duration::days(days.max(1) as i64 - 1);
+
+        let cards = conn.exec_map(
+            r"SELECT sentence_id, due, interval_secs
+              FROM cards
+              WHERE due IS NOT NULL && due < :window_end && interval_secs >= :min_interval_secs",
+            params! {
+                "window_end" => window_end.naive_utc(),
+                "min_interval_secs" => MIN_SPREAD_INTERVAL.as_secs(),
+            },
+            |(sentence_id, due, interval_secs): (String, NaiveDateTime, u64)| (sentence_id, due, Duration::from_secs(interval_secs)))?;
+
+        let (moved, report) = spread::spread(&cards, self.clock.now(), days);
+
+        conn.exec_batch("UPDATE cards SET due = :due WHERE sentence_id = :sentence_id",
+            moved.iter().map(|(sentence_id, due)| params! {
+                "due" => due,
+                "sentence_id" => sentence_id.as_str(),
+            }))?;
+
+        if report.cards_moved > 0 {
+            revision::bump(&mut conn)?;
+        }
+
+        Ok(report)
+    }
+
+    fn get_vacation(&self) -> SrsResult<Option<Vacation>> {
+        vacation::current(&mut self.pool.get_conn()?)
+    }
+
+    fn start_vacation(&mut self) -> SrsResult<()> {
+        log::info!("Starting vacation");
+        vacation::start(&mut self.pool.get_conn()?, self.clock.now().naive_utc())
+    }
+
+    fn end_vacation(&mut self) -> SrsResult<Vacation> {
+        vacation::end(&mut self.pool.get_conn()?, self.clock.now().naive_utc(), false)
+    }
+
+    fn get_suggested_sentences(&self, _: i32, _: PageRequest) -> SrsResult<Page<(Sentence, Vec<String>)>> {
+        Ok(Page::empty())
+    }
+
+    fn get_unlocking_words(&self, _: usize) -> SrsResult<Vec<UnlockingWord>> {
+        // Cards are whole sentences here, so words can't be learned on their own
         Ok(Vec::new())
     }
 }
@@ -0,0 +1,159 @@
+use strum_macros::EnumIter;
+use unicode_normalization::UnicodeNormalization;
+
+use super::Difficulty;
+use super::tokenizer;
+
+/// How typed answers are normalized before they're compared
+#[derive(Debug, PartialEq, Eq, Copy, Clone, EnumIter)]
+pub enum AnswerLanguage {
+    /// Katakana and hiragana are treated the same, so readings can be typed in either
+    Japanese,
+    /// Case is ignored
+    English,
+}
+
+impl AnswerLanguage {
+    /// Normalize an answer for comparing. Width differences (NFKC) and punctuation are always
+    /// ignored, and spaces are ignored for Japanese.
+    pub fn normalize(&self, text: &str) -> String {
+        let text: String = text.nfkc().collect();
+
+        match self {
+            AnswerLanguage::Japanese => {
+                let text: String = text.chars().filter(|c| c.is_alphanumeric()).collect();
+                tokenizer::katakana_to_hiragana(&text)
+            },
+            AnswerLanguage::English => {
+                let text: String = text.to_lowercase()
+                    .chars()
+                    .filter(|c| c.is_alphanumeric() || c.is_whitespace())
+                    .collect();
+                text.split_whitespace().collect::<Vec<&str>>().join(" ")
+            },
+        }
+    }
+}
+
+/// A piece of a typed answer, compared to the expected answer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffPart {
+    /// Typed correctly
+    Same(String),
+    /// In the expected answer, but not typed
+    Missing(String),
+    /// Typed, but not in the expected answer
+    Extra(String),
+}
+
+/// The result of checking a typed answer
+#[derive(Debug, Clone)]
+pub struct AnswerCheck {
+    /// The accepted answer that was closest to what was typed
+    pub expected: String,
+    /// The typed answer compared to the expected one, both normalized
+    pub diff: Vec<DiffPart>,
+    /// The difficulty to suggest for the review
+    pub suggested: Difficulty,
+}
+
+impl AnswerCheck {
+    /// Whether the typed answer matched exactly once normalized
+    pub fn is_correct(&self) -> bool {
+        self.diff.iter().all(|part| matches!(part, DiffPart::Same(_)))
+    }
+
+    /// The number of characters that were missing or extra
+    pub fn mistakes(&self) -> usize {
+        self.diff.iter()
+            .map(|part| match part {
+                DiffPart::Same(_) => 0,
+                DiffPart::Missing(text) | DiffPart::Extra(text) => text.chars().count(),
+            })
+            .sum()
+    }
+}
+
+/// Check a typed answer against the accepted answers (e.g. a word and its reading), comparing it
+/// to whichever is closest. Returns None if there are no accepted answers.
+pub fn check(typed: &str, answers: &[&str], language: AnswerLanguage) -> Option<AnswerCheck> {
+    let typed = language.normalize(typed);
+
+    answers.iter()
+        .map(|expected| {
+            let diff = diff(&typed, &language.normalize(expected));
+            let mut check = AnswerCheck {
+                expected: expected.to_string(),
+                diff,
+                suggested: Difficulty::Again,
+            };
+            check.suggested = suggest_difficulty(&check);
+            check
+        })
+        .min_by_key(|check| check.mistakes())
+}
+
+/// Correct answers are Good, a single typo in a longer answer is Hard, and anything else is Again
+fn suggest_difficulty(check: &AnswerCheck) -> Difficulty {
+    match check.mistakes() {
+        0 => Difficulty::Good,
+        1 if check.expected.chars().count() > 2 => Difficulty::Hard,
+        _ => Difficulty::Again,
+    }
+}
+
+/// Diff two strings by characters, using their longest common subsequence
+fn diff(typed: &str, expected: &str) -> Vec<DiffPart> {
+    let typed: Vec<char> = typed.chars().collect();
+    let expected: Vec<char> = expected.chars().collect();
+
+    // lcs[i][j] is the length of the longest common subsequence of typed[i..] and expected[j..]
+    let mut lcs = vec![vec![0; expected.len() + 1]; typed.len() + 1];
+    for i in (0..typed.len()).rev() {
+        for j in (0..expected.len()).rev() {
+            lcs[i][j] = if typed[i] == expected[j] {
+                lcs[i + 1][j + 1] + 1
+            }
+            else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut parts = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < typed.len() || j < expected.len() {
+        if i < typed.len() && j < expected.len() && typed[i] == expected[j] {
+            push_part(&mut parts, DiffPart::Same(typed[i].to_string()));
+            i += 1;
+            j += 1;
+        }
+        else if j < expected.len() && (i == typed.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            push_part(&mut parts, DiffPart::Missing(expected[j].to_string()));
+            j += 1;
+        }
+        else {
+            push_part(&mut parts, DiffPart::Extra(typed[i].to_string()));
+            i += 1;
+        }
+    }
+
+    parts
+}
+
+/// Add a part to a diff, joining it onto the last part if they're the same kind
+fn push_part(parts: &mut Vec<DiffPart>, part: DiffPart) {
+    let joined = match (parts.last_mut(), &part) {
+        (Some(DiffPart::Same(last)), DiffPart::Same(text))
+            | (Some(DiffPart::Missing(last)), DiffPart::Missing(text))
+            | (Some(DiffPart::Extra(last)), DiffPart::Extra(text)) => {
+            last.push_str(text);
+            true
+        },
+        _ => false,
+    };
+
+    if !joined {
+        parts.push(part);
+    }
+}
@@ -0,0 +1,193 @@
+use std::path::{Path, PathBuf};
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+
+use super::{SrsResult, hash};
+
+/// The least time to leave between requests, so imports don't hammer the source
+const MIN_REQUEST_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Where to fetch sentence audio from, set in the config file, e.g.
+/// `"audio_source": { "type": "Forvo", "api_key": "..." }`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type")]
+pub enum AudioSource {
+    /// Pronunciations recorded by people on forvo.com, mostly of single words
+    Forvo {
+        api_key: String,
+        #[serde(default = "default_language")]
+        language: String,
+    },
+    /// A text to speech server, e.g. running in a local container. `{text}` in the url is replaced
+    /// with the text to speak, and the response body should be the audio.
+    Tts { url: String },
+}
+
+fn default_language() -> String {
+    "ja".to_string()
+}
+
+/// Fetches audio from an `AudioSource`, caching it on disk so each text is only fetched once.
+/// Requests are made with curl, so we don't need an http client with tls support.
+pub struct AudioFetcher {
+    source: AudioSource,
+    cache_dir: PathBuf,
+    last_request: Option<Instant>,
+}
+
+impl AudioFetcher {
+    /// Create a fetcher that caches audio in `cache_dir`
+    pub fn new(source: AudioSource, cache_dir: &Path) -> Self {
+        Self {
+            source,
+            cache_dir: cache_dir.to_path_buf(),
+            last_request: None,
+        }
+    }
+
+    /// Get the audio for some text, fetching it if it isn't cached yet. Returns None if the source
+    /// doesn't have any audio for it, which is cached too so it isn't asked again.
+    pub fn fetch(&mut self, text: &str) -> SrsResult<Option<PathBuf>> {
+        let path = cache_path(&self.cache_dir, text);
+
+        if path.exists() {
+            return Ok(Some(path));
+        }
+        else if missing_path(&path).exists() {
+            return Ok(None);
+        }
+
+        std::fs::create_dir_all(&self.cache_dir)?;
+
+        let audio = match self.source.clone() {
+            AudioSource::Forvo { api_key, language } => self.fetch_forvo(text, &api_key, &language)?,
+            AudioSource::Tts { url } => Some(self.get(&url.replace("{text}", &percent_encode(text)))?),
+        };
+
+        match audio {
+            Some(audio) => {
+                std::fs::write(&path, audio)?;
+                log::info!("Fetched audio for {text} to {path:?}");
+                Ok(Some(path))
+            },
+            None => {
+                std::fs::write(missing_path(&path), "")?;
+                log::info!("No audio found for {text}");
+                Ok(None)
+            }
+        }
+    }
+
+    /// Forvo only has pronunciations of words, so fetch each word of the text and join them up.
+    /// Each word is cached too, as they come up again in other sentences. Returns None if there
+    /// isn't a pronunciation for any of the words.
+    fn fetch_forvo(&mut self, text: &str, api_key: &str, language: &str) -> SrsResult<Option<Vec<u8>>> {
+        let word_dir = self.cache_dir.join("words");
+        std::fs::create_dir_all(&word_dir)?;
+
+        let mut audio = Vec::new();
+
+        for word in words(text) {
+            let path = cache_path(&word_dir, &word);
+
+            if path.exists() {
+                audio.extend(std::fs::read(&path)?);
+                continue;
+            }
+            else if missing_path(&path).exists() {
+                continue;
+            }
+
+            let url = format!("https://apifree.forvo.com/key/{api_key}/format/json/action/word-pronunciations/word/{}/language/{language}/order/rate-desc/limit/1",
+                              percent_encode(&word));
+            let response: serde_json::Value = serde_json::from_slice(&self.get(&url)?)?;
+
+            match response["items"][0]["pathmp3"].as_str() {
+                Some(audio_url) => {
+                    // MP3 frames are self contained, so the files can just be joined together
+                    let word_audio = self.get(audio_url)?;
+                    std::fs::write(&path, &word_audio)?;
+                    audio.extend(word_audio);
+                },
+                None => {
+                    log::info!("Forvo has no pronunciation for {word}");
+                    std::fs::write(missing_path(&path), "")?;
+                }
+            }
+        }
+
+        Ok((!audio.is_empty()).then_some(audio))
+    }
+
+    /// Make a GET request, waiting first if the last one was too recent
+    fn get(&mut self, url: &str) -> SrsResult<Vec<u8>> {
+        if let Some(last_request) = self.last_request {
+            let elapsed = last_request.elapsed();
+            if elapsed < MIN_REQUEST_INTERVAL {
+                thread::sleep(MIN_REQUEST_INTERVAL - elapsed);
+            }
+        }
+
+        self.last_request = Some(Instant::now());
+
+        // The url is passed as a config on stdin rather than as an argument, as it can contain an
+        // api key, which would otherwise show up in the process list
+        let mut child = Command::new("curl")
+            .args(["--silent", "--show-error", "--fail", "--location", "--config", "-"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to run curl, is it installed? {e}"))?;
+
+        let config = format!("url = \"{}\"\n", url.replace('\\', "\\\\").replace('"', "\\\""));
+        child.stdin.take().ok_or("Failed to open curl's stdin")?.write_all(config.as_bytes())?;
+
+        let output = child.wait_with_output()?;
+
+        if !output.status.success() {
+            return Err(format!("Audio request failed: {}", String::from_utf8_lossy(&output.stderr).trim()).into());
+        }
+
+        Ok(output.stdout)
+    }
+}
+
+/// Where the audio for some text is cached, whether or not it's been fetched yet
+pub fn cache_path(cache_dir: &Path, text: &str) -> PathBuf {
+    let hash = hash::fnv1a(text.as_bytes());
+
+    cache_dir.join(format!("{hash:016x}.mp3"))
+}
+
+/// The marker left in the cache when there's no audio for some text, given its `cache_path`
+fn missing_path(path: &Path) -> PathBuf {
+    path.with_extension("missing")
+}
+
+/// The words to look up pronunciations of in some text
+#[cfg(feature = "tokenizer")]
+fn words(text: &str) -> Vec<String> {
+    super::tokenizer::tokenize(text)
+}
+
+/// The words to look up pronunciations of in some text. Without the tokenizer, it's looked up as
+/// a single word.
+#[cfg(not(feature = "tokenizer"))]
+fn words(text: &str) -> Vec<String> {
+    vec![text.to_string()]
+}
+
+/// Encode text for putting in a url
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (byte as char).to_string(),
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
@@ -0,0 +1,103 @@
+use mysql::{PooledConn, prelude::Queryable, params};
+
+use super::SrsResult;
+use super::normalize;
+
+/// A condition for excluding the cards for blacklisted words, suspended words and unrescued names
+/// from queries, e.g. `WHERE cards.due IS NULL && {NOT_EXCLUDED}`
+pub(crate) const NOT_EXCLUDED: &str = r"cards.word_id NOT IN (
+    SELECT words.id FROM words INNER JOIN word_blacklist ON word_blacklist.word = words.word
+    UNION
+    SELECT words.id FROM words INNER JOIN word_suspensions ON word_suspensions.word = words.word
+    UNION
+    SELECT words.id FROM words INNER JOIN name_flags ON name_flags.word = words.word WHERE NOT name_flags.rescued
+)";
+
+/// A condition for excluding the cards for sentences with suspended words, where cards are whole
+/// sentences like in the anki algorithm, e.g. `WHERE cards.due IS NULL && {SENTENCE_NOT_SUSPENDED}`
+pub(crate) const SENTENCE_NOT_SUSPENDED: &str = r"NOT EXISTS (
+    SELECT 1 FROM sentence_words INNER JOIN word_suspensions ON word_suspensions.word = sentence_words.word
+    WHERE sentence_words.sentence_id = cards.sentence_id
+)";
+
+/// Create the blacklist and suspended words tables, shared by all the algorithms
+pub(crate) fn create_table(conn: &mut PooledConn) -> SrsResult<()> {
+    conn.query_drop(r"
+        CREATE TABLE IF NOT EXISTS word_blacklist (
+            word VARCHAR(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci NOT NULL,
+            PRIMARY KEY (word)
+        )
+    ")?;
+
+    conn.query_drop(r"
+        CREATE TABLE IF NOT EXISTS word_suspensions (
+            word VARCHAR(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci NOT NULL,
+            PRIMARY KEY (word)
+        )
+    ")?;
+
+    Ok(())
+}
+
+/// Add a word to the blacklist
+pub(crate) fn add(conn: &mut PooledConn, word: &str) -> SrsResult<()> {
+    conn.exec_drop("INSERT IGNORE INTO word_blacklist (word) VALUES (:word)",
+        params! { "word" => normalize::word(word) })?;
+
+    Ok(())
+}
+
+/// Remove a word from the blacklist
+pub(crate) fn remove(conn: &mut PooledConn, word: &str) -> SrsResult<()> {
+    conn.exec_drop("DELETE FROM word_blacklist WHERE word = :word",
+        params! { "word" => normalize::word(word) })?;
+
+    Ok(())
+}
+
+/// Load the blacklisted words, in alphabetical order
+pub(crate) fn load(conn: &mut PooledConn) -> SrsResult<Vec<String>> {
+    Ok(conn.query("SELECT word FROM word_blacklist ORDER BY word")?)
+}
+
+/// Suspend a word. Its card keeps its schedule, but isn't reviewed until it's unsuspended.
+pub(crate) fn suspend(conn: &mut PooledConn, word: &str) -> SrsResult<()> {
+    conn.exec_drop("INSERT IGNORE INTO word_suspensions (word) VALUES (:word)",
+        params! { "word" => normalize::word(word) })?;
+
+    Ok(())
+}
+
+/// Unsuspend a word
+pub(crate) fn unsuspend(conn: &mut PooledConn, word: &str) -> SrsResult<()> {
+    conn.exec_drop("DELETE FROM word_suspensions WHERE word = :word",
+        params! { "word" => normalize::word(word) })?;
+
+    Ok(())
+}
+
+/// Load the suspended words, in alphabetical order
+pub(crate) fn load_suspended(conn: &mut PooledConn) -> SrsResult<Vec<String>> {
+    Ok(conn.query("SELECT word FROM word_suspensions ORDER BY word")?)
+}
+
+/// Bring blacklisted and suspended words stored before words were normalized in line, see
+/// `normalize::word`
+pub(crate) fn normalize_stored(conn: &mut PooledConn) -> SrsResult<()> {
+    for table in ["word_blacklist", "word_suspensions"] {
+        let words: Vec<String> = conn.query(format!("SELECT word FROM {table}"))?;
+
+        for (word, normalized) in normalize::unnormalized_words(&words) {
+            // If the normalized word is there already the update is ignored, and the old row just goes
+            conn.exec_drop(format!("UPDATE IGNORE {table} SET word = :normalized WHERE word = BINARY :word"),
+                params! {
+                    "word" => word.as_str(),
+                    "normalized" => normalized,
+                })?;
+            conn.exec_drop(format!("DELETE FROM {table} WHERE word = BINARY :word"),
+                params! { "word" => word })?;
+        }
+    }
+
+    Ok(())
+}
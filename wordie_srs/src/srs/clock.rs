@@ -0,0 +1,100 @@
+use std::cell::Cell;
+
+use chrono::{DateTime, Duration, Local};
+
+/// How far the system clock can step backwards without a warning, as small corrections from time
+/// syncing are normal
+const BACKWARDS_JUMP_TOLERANCE_SECS: i64 = 5;
+
+/// How far the system clock can be behind the latest time before it's trusted again. A jump back
+/// this big is more likely the clock being put right after it was set ahead, e.g. by a day of
+/// reviews while it was wrong, and waiting for it to catch up would freeze time for that long.
+const MAX_PINNED_HOURS: i64 = 24;
+
+/// Where the algorithms get the current time from. The system clock is read each time, but never
+/// goes backwards: if it jumps back, e.g. when the clock is changed by hand, the last time read is
+/// used until it catches up again, so answers are never scheduled from an earlier time than the
+/// ones before them. If it's more than `MAX_PINNED_HOURS` behind, the system clock is trusted
+/// again instead. With the `simulation` feature the time can be set instead.
+#[derive(Debug)]
+pub struct Clock {
+    /// The latest time read from the system clock
+    latest: Cell<Option<DateTime<Local>>>,
+    /// Whether the clock is behind `latest` and it's been warned about, so it's only warned about once
+    warned: Cell<bool>,
+    #[cfg(feature = "simulation")]
+    simulated: Option<DateTime<Local>>,
+}
+
+impl Clock {
+    /// A clock that reads the system clock
+    pub fn system() -> Self {
+        Self {
+            latest: Cell::new(None),
+            warned: Cell::new(false),
+            #[cfg(feature = "simulation")]
+            simulated: None,
+        }
+    }
+
+    /// Get the current time
+    pub fn now(&self) -> DateTime<Local> {
+        #[cfg(feature = "simulation")]
+        if let Some(simulated) = self.simulated {
+            return simulated;
+        }
+
+        let now = Local::now();
+
+        match self.latest.get() {
+            Some(latest) if latest - now > Duration::hours(MAX_PINNED_HOURS) => {
+                log::warn!("The system clock is more than {MAX_PINNED_HOURS} hours behind {latest}, trusting it again from {now}");
+                self.latest.set(Some(now));
+                self.warned.set(false);
+                now
+            },
+            Some(latest) if now < latest => {
+                if latest - now > Duration::seconds(BACKWARDS_JUMP_TOLERANCE_SECS) && !self.warned.get() {
+                    log::warn!("The system clock has gone back from {latest} to {now}, using {latest} until it catches up");
+                    self.warned.set(true);
+                }
+
+                latest
+            },
+            _ => {
+                self.latest.set(Some(now));
+                self.warned.set(false);
+                now
+            }
+        }
+    }
+
+    /// Never go back before `time`, e.g. the latest review, so the clock stays monotonic across
+    /// restarts as well as while running
+    pub fn catch_up(&self, time: DateTime<Local>) {
+        if self.latest.get().is_none_or(|latest| time > latest) {
+            self.latest.set(Some(time));
+        }
+    }
+
+    /// The time being used while the system clock is further behind it than a small correction,
+    /// or None if it isn't
+    pub fn behind(&self) -> Option<DateTime<Local>> {
+        // Reading the clock notices if it's caught up
+        self.now();
+        self.latest.get().filter(|_| self.warned.get())
+    }
+
+    /// Set the time for a simulation, which stays the same until it's set again
+    #[cfg(feature = "simulation")]
+    pub fn set(&mut self, time: DateTime<Local>) {
+        log::info!("Setting current time to {time:?}");
+        self.simulated = Some(time);
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::system()
+    }
+}
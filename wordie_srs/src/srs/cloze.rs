@@ -0,0 +1,59 @@
+/// What's shown in place of the hidden word
+pub const CLOZE_BLANK: &str = "＿＿";
+
+/// A sentence with the word being tested hidden, e.g. 私は＿＿を食べた
+#[derive(Debug, Clone)]
+pub struct Cloze {
+    pub text: String,
+    /// The byte range of the hidden word in the text
+    pub byte_start: usize,
+    pub byte_end: usize,
+    /// How the hidden word is read, if it's known
+    pub reading: Option<String>,
+}
+
+impl Cloze {
+    /// Hide the word at a byte range of a sentence, or None if the range isn't in the sentence
+    pub fn new(text: &str, byte_start: usize, byte_end: usize) -> Option<Self> {
+        if byte_start >= byte_end || text.get(byte_start..byte_end).is_none() {
+            return None;
+        }
+
+        Some(Self {
+            text: text.to_string(),
+            byte_start,
+            byte_end,
+            reading: None,
+        })
+    }
+
+    /// Hide the first place a word is written in a sentence, for sentences where we don't know
+    /// where the word is. Conjugated words aren't found, as the word is in dictionary form.
+    pub fn find(text: &str, word: &str) -> Option<Self> {
+        let byte_start = text.find(word)?;
+        Self::new(text, byte_start, byte_start + word.len())
+    }
+
+    /// The question side, with the word blanked out
+    pub fn question(&self) -> String {
+        format!("{}{CLOZE_BLANK}{}", &self.text[..self.byte_start], &self.text[self.byte_end..])
+    }
+
+    /// Set how the hidden word is read
+    pub fn with_reading(mut self, reading: Option<String>) -> Self {
+        self.reading = reading;
+        self
+    }
+
+    /// The hidden word, as it's written in the sentence
+    pub fn answer(&self) -> &str {
+        &self.text[self.byte_start..self.byte_end]
+    }
+
+    /// The answers accepted when typing the hidden word: the word itself, or its reading
+    pub fn accepted_answers(&self) -> Vec<&str> {
+        std::iter::once(self.answer())
+            .chain(self.reading.as_deref())
+            .collect()
+    }
+}
@@ -0,0 +1,178 @@
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{Difficulty, Review, Sentence, SrsResult};
+use super::daily_stats::DailyStats;
+use super::plugins::Plugin;
+
+/// The longest to wait for a plugin to handle an event, so a stuck plugin doesn't back the queue up
+const EVENT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The longest to wait for a plugin's card content, as the review waits for it
+const CARD_CONTENT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How often to check whether a plugin's program has finished
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// An external program to run as a plugin, set in the config file, e.g.
+/// `"plugins": [{ "name": "dictionary", "command": ["python3", "dictionary.py"], "card_content": true }]`
+#[derive(Debug, Clone, Deserialize)]
+pub struct PluginCommand {
+    pub name: String,
+    /// The program to run and its arguments
+    pub command: Vec<String>,
+    /// Whether to ask the program for extra content to show under review cards
+    #[serde(default)]
+    pub card_content: bool,
+}
+
+/// A plugin that runs an external program for each event, so plugins can be added without
+/// rebuilding the app. The event is written to the program's stdin as json, e.g.
+/// `{ "event": "review_answered", "sentence": "...", ... }`. Events are handled on a background
+/// thread, one at a time, and failures are logged and dropped. For `card_content` events, whatever
+/// the program writes to stdout is shown under the card.
+pub struct CommandPlugin {
+    command: PluginCommand,
+    sender: Sender<String>,
+}
+
+impl CommandPlugin {
+    pub fn new(command: PluginCommand) -> Self {
+        let (sender, receiver) = mpsc::channel::<String>();
+
+        let thread_command = command.clone();
+        thread::spawn(move || {
+            for input in receiver {
+                if let Err(err) = run(&thread_command.command, &input, EVENT_TIMEOUT) {
+                    log::error!("Plugin {} failed to handle an event: {err}", thread_command.name);
+                }
+            }
+        });
+
+        Self {
+            command,
+            sender,
+        }
+    }
+
+    /// Queue an event to be sent to the program
+    fn send(&self, event: &str, mut body: serde_json::Value) -> SrsResult<()> {
+        body["event"] = json!(event);
+
+        self.sender.send(body.to_string())
+            .map_err(|_| "The plugin thread has stopped")?;
+
+        Ok(())
+    }
+}
+
+impl Plugin for CommandPlugin {
+    fn name(&self) -> &str {
+        &self.command.name
+    }
+
+    fn on_sentences_imported(&mut self, sentences: &[Sentence]) -> SrsResult<()> {
+        self.send("sentences_imported", json!({
+            "sentences": sentences.iter()
+                .map(|sentence| json!({ "id": sentence.id.to_string(), "text": sentence.text }))
+                .collect::<Vec<serde_json::Value>>(),
+        }))
+    }
+
+    fn on_review_answered(&mut self, review: &Review, difficulty: Difficulty) -> SrsResult<()> {
+        self.send("review_answered", review_json(review, Some(difficulty)))
+    }
+
+    fn on_daily_goals_met(&mut self, stats: &DailyStats) -> SrsResult<()> {
+        self.send("daily_goals_met", json!({
+            "day": stats.day.to_string(),
+            "cards_learned": stats.cards_learned,
+            "cards_reviewed": stats.cards_reviewed,
+        }))
+    }
+
+    fn card_content(&self, review: &Review) -> SrsResult<Option<String>> {
+        if !self.command.card_content {
+            return Ok(None);
+        }
+
+        let mut body = review_json(review, None);
+        body["event"] = json!("card_content");
+
+        let content = run(&self.command.command, &body.to_string(), CARD_CONTENT_TIMEOUT)?;
+        let content = content.trim();
+
+        Ok((!content.is_empty()).then(|| content.to_string()))
+    }
+}
+
+/// A review as json, for sending to a plugin's program
+fn review_json(review: &Review, difficulty: Option<Difficulty>) -> serde_json::Value {
+    let mut body = json!({
+        "sentence_id": review.sentence().id.to_string(),
+        "sentence": review.sentence().text,
+        "card_type": format!("{:?}", review.card_type()).to_lowercase(),
+    });
+
+    if let Some(difficulty) = difficulty {
+        body["difficulty"] = json!(format!("{difficulty:?}").to_lowercase());
+    }
+
+    body
+}
+
+/// Run a program with `input` on its stdin and return what it writes to stdout, killing it if it
+/// takes longer than `timeout`. What it writes to stderr goes to ours, for debugging plugins.
+fn run(command: &[String], input: &str, timeout: Duration) -> SrsResult<String> {
+    let (program, args) = command.split_first().ok_or("The plugin has no command to run")?;
+
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run {program}: {e}"))?;
+
+    // Read stdout as it's written, so a program with a lot to say doesn't block on a full pipe
+    let mut stdout = child.stdout.take().ok_or("Failed to read the plugin's output")?;
+    let reader = thread::spawn(move || {
+        let mut output = String::new();
+        stdout.read_to_string(&mut output).map(|_| output)
+    });
+
+    // Closing stdin lets the program know the event is all there. Programs that don't need the
+    // event can exit without reading it, so a broken pipe isn't an error.
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input.as_bytes()).ok();
+    }
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+
+        if Instant::now() >= deadline {
+            child.kill().ok();
+            child.wait().ok();
+            return Err(format!("{program} took longer than {}s", timeout.as_secs()).into());
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    if !status.success() {
+        return Err(format!("{program} failed with {status}").into());
+    }
+
+    let output = reader.join()
+        .map_err(|_| "Failed to read the plugin's output")??;
+
+    Ok(output)
+}
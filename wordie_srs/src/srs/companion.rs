@@ -0,0 +1,231 @@
+use std::collections::HashSet;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use serde::{Serialize, Deserialize};
+use uuid::Uuid;
+
+use super::{SrsAlgorithm, SrsResult, Sentence, KnowledgeState};
+use super::sources::{SentenceSource, SourceKind};
+
+/// The default address for the companion server. It only listens locally, as anything that can
+/// reach it can add sentences to the collection.
+pub const COMPANION_ADDR: &str = "127.0.0.1:7879";
+
+/// The largest request body accepted, so a bad client can't make us allocate without limit
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// The most that's read for the request line and headers
+const MAX_HEADER_BYTES: usize = 16 * 1024;
+
+/// How long a client can go without sending or receiving anything before it's dropped
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Origins that browser extensions send requests from. Requests from web pages are refused, so a
+/// page can't read what the user knows or add sentences behind their back.
+const EXTENSION_ORIGINS: &[&str] = &["chrome-extension://", "moz-extension://", "safari-web-extension://"];
+
+/// A request from a browser extension, sent as the json body of a POST to `/`, e.g.
+/// `{"type": "Annotate", "text": "猫が好き"}`
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum CompanionRequest {
+    /// Get how well each word in some text is known, e.g. for highlighting a page
+    Annotate { text: String },
+    /// Add sentences mined from a page to the collection
+    AddSentences { sentences: Vec<String> },
+}
+
+/// A word in annotated text. Offsets are byte offsets into the text in the request.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AnnotatedWord {
+    pub word: String,
+    pub byte_start: usize,
+    pub byte_end: usize,
+    pub state: KnowledgeState,
+}
+
+/// The server's response to a `CompanionRequest`
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum CompanionResponse {
+    Annotated { words: Vec<AnnotatedWord> },
+    Added { sentences: usize, new_words: Vec<String> },
+    Error { message: String },
+}
+
+/// Serve requests from browser extensions on `addr` until the listener fails. Each connection is
+/// read on its own thread, so a slow client can't hold up the others, but requests are handled
+/// one at a time so the extension's adds never interleave.
+pub fn serve<A>(srs_algorithm: A, addr: &str) -> SrsResult<()>
+    where A: SrsAlgorithm + Send + 'static
+{
+    let listener = TcpListener::bind(addr)?;
+    log::info!("Companion server listening on {addr}");
+
+    let srs_algorithm = Arc::new(Mutex::new(srs_algorithm));
+
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let srs_algorithm = srs_algorithm.clone();
+
+        thread::spawn(move || {
+            if let Err(e) = handle_connection(&srs_algorithm, stream) {
+                log::error!("Companion request failed: {e}");
+            }
+        });
+    }
+
+    Ok(())
+}
+
+/// Start a thread that serves requests from browser extensions on `addr`. The thread gets its own
+/// connection from `connect`.
+pub fn start_server_thread<A, F>(connect: F, addr: &'static str)
+    where A: SrsAlgorithm + Send + 'static,
+          F: FnOnce() -> SrsResult<A> + Send + 'static
+{
+    thread::spawn(move || {
+        let result = connect().and_then(|srs_algorithm| serve(srs_algorithm, addr));
+
+        if let Err(err) = result {
+            log::error!("Companion server stopped: {err}");
+        }
+    });
+}
+
+/// Handle a single http request
+fn handle_connection<A: SrsAlgorithm>(srs_algorithm: &Mutex<A>, stream: TcpStream) -> SrsResult<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+
+    let mut reader = BufReader::new(stream.try_clone()?.take((MAX_HEADER_BYTES + MAX_BODY_BYTES) as u64));
+    let mut writer = stream;
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let method = request_line.split_whitespace().next().unwrap_or("").to_string();
+
+    let mut content_length = 0;
+    let mut origin = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+
+        if let Some((name, value)) = line.split_once(':') {
+            match name.trim().to_ascii_lowercase().as_str() {
+                "content-length" => content_length = value.trim().parse()?,
+                "origin" => origin = Some(value.trim().to_string()),
+                _ => {},
+            }
+        }
+    }
+
+    // Browsers always send the origin with cross origin requests, so requests without one aren't
+    // from an extension, e.g. a page's form posting to us
+    let origin = match origin {
+        Some(origin) if EXTENSION_ORIGINS.iter().any(|prefix| origin.starts_with(prefix)) => Some(origin),
+        origin => {
+            log::warn!("Refusing companion request from {}", origin.as_deref().unwrap_or("no origin"));
+            return write_response(&mut writer, "403 Forbidden", None, "");
+        }
+    };
+
+    match method.as_str() {
+        // Browsers check that cross origin POSTs with json bodies are allowed first
+        "OPTIONS" => write_response(&mut writer, "204 No Content", origin.as_deref(), ""),
+        "POST" => {
+            if content_length > MAX_BODY_BYTES {
+                return write_response(&mut writer, "413 Payload Too Large", origin.as_deref(), "");
+            }
+
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body)?;
+
+            let response = serde_json::from_slice::<CompanionRequest>(&body)
+                .map_err(|e| e.into())
+                .and_then(|request| {
+                    let mut srs_algorithm = srs_algorithm.lock().map_err(|_| "A companion request panicked")?;
+                    handle_request(&mut *srs_algorithm, request)
+                })
+                .unwrap_or_else(|e| {
+                    log::error!("Failed to handle companion request: {e}");
+                    CompanionResponse::Error { message: e.to_string() }
+                });
+
+            write_response(&mut writer, "200 OK", origin.as_deref(), &serde_json::to_string(&response)?)
+        },
+        _ => write_response(&mut writer, "405 Method Not Allowed", origin.as_deref(), ""),
+    }
+}
+
+/// Handle a request from an extension
+fn handle_request(srs_algorithm: &mut dyn SrsAlgorithm, request: CompanionRequest) -> SrsResult<CompanionResponse> {
+    match request {
+        CompanionRequest::Annotate { text } => {
+            let words = srs_algorithm.annotate(&text)?
+                .into_iter()
+                .map(|(token, state)| AnnotatedWord {
+                    word: token.word,
+                    byte_start: token.byte_start,
+                    byte_end: token.byte_end,
+                    state,
+                })
+                .collect();
+
+            Ok(CompanionResponse::Annotated { words })
+        },
+        CompanionRequest::AddSentences { sentences } => {
+            log::info!("Adding {} sentences from the browser", sentences.len());
+
+            let sentences = sentences.into_iter()
+                .map(|text| text.trim().to_string())
+                .filter(|text| !text.is_empty())
+                .map(|text| Sentence {
+                    id: Uuid::new_v4(),
+                    text,
+                })
+                .collect::<Vec<Sentence>>();
+
+            let report = srs_algorithm.add_sentences(&sentences)?;
+
+            // Sentences skipped for having no words weren't added, so they don't get a source or count
+            let skipped: HashSet<Uuid> = report.skipped_sentences.iter().map(|sentence| sentence.id).collect();
+            let ids = sentences.iter()
+                .map(|sentence| sentence.id)
+                .filter(|id| !skipped.contains(id))
+                .collect::<Vec<Uuid>>();
+            srs_algorithm.set_sentence_source(&ids, &SentenceSource::new(SourceKind::Browser, "companion"))?;
+
+            Ok(CompanionResponse::Added {
+                sentences: ids.len(),
+                new_words: report.new_words,
+            })
+        },
+    }
+}
+
+/// Write an http response and close the connection
+fn write_response(writer: &mut impl Write, status: &str, origin: Option<&str>, body: &str) -> SrsResult<()> {
+    let mut response = format!("HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+                               body.len());
+
+    if let Some(origin) = origin {
+        response.push_str(&format!("Access-Control-Allow-Origin: {origin}\r\n"));
+        response.push_str("Access-Control-Allow-Methods: POST, OPTIONS\r\n");
+        response.push_str("Access-Control-Allow-Headers: Content-Type\r\n");
+    }
+
+    response.push_str("\r\n");
+    response.push_str(body);
+
+    writer.write_all(response.as_bytes())?;
+    writer.flush()?;
+
+    Ok(())
+}
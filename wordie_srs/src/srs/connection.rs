@@ -0,0 +1,43 @@
+#[cfg(feature = "mysql")]
+pub(crate) use self::storage::*;
+
+/// Options for the database connection pool
+#[derive(Debug, Clone)]
+pub struct ConnectionOptions {
+    /// The number of connections to keep open
+    pub min_connections: usize,
+    /// The max number of connections to open at once
+    pub max_connections: usize,
+    /// The number of prepared statements to cache per connection. Queries run with exec reuse
+    /// the cached statement for the same query text, so they only get sent and prepared once.
+    pub statement_cache_size: usize,
+    /// Whether to ping connections before handing them out after getting one has failed, which
+    /// catches dropped connections while reconnecting at the cost of an extra round trip
+    pub health_check: bool,
+}
+
+impl Default for ConnectionOptions {
+    fn default() -> Self {
+        Self {
+            min_connections: 1,
+            max_connections: 10,
+            statement_cache_size: 64,
+            health_check: true,
+        }
+    }
+}
+
+/// The state of the connection to the database
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ConnectionState {
+    /// The last attempt to get a connection succeeded
+    Connected,
+    /// Getting a connection failed, and we're retrying in the background
+    Reconnecting,
+    /// Getting a connection failed after all retries
+    Disconnected,
+}
+
+/// The pool itself, only built with the mysql backend
+#[cfg(feature = "mysql")]
+mod storage;
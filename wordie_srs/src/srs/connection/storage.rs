@@ -0,0 +1,153 @@
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use mysql::{Opts, OptsBuilder, Pool, PoolConstraints, PoolOpts, PooledConn};
+
+use super::*;
+use crate::srs::SrsResult;
+
+/// The number of times to try getting a connection before giving up
+const MAX_CONNECT_ATTEMPTS: u32 = 4;
+
+/// The delay before the first retry, doubled after each failed attempt
+const INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+
+/// A connection pool that's created lazily, and recreates itself with backoff on a background
+/// thread if the database goes away (e.g. mysql restarting), so getting a connection never waits
+/// on the retries
+pub(crate) struct ConnectionPool {
+    shared: Arc<SharedPool>,
+}
+
+/// The parts of a `ConnectionPool` shared with its reconnect thread
+struct SharedPool {
+    opts: Opts,
+    health_check: bool,
+    pool: Mutex<Option<Pool>>,
+    state: Mutex<ConnectionState>,
+}
+
+impl ConnectionPool {
+    /// Create a connection pool for a db url. This doesn't connect until the first connection is
+    /// needed, so it only fails if the url is invalid.
+    pub fn new(db_url: &str, options: &ConnectionOptions) -> SrsResult<Self> {
+        let constraints = PoolConstraints::new(options.min_connections, options.max_connections)
+            .ok_or("Invalid pool size, min_connections must be <= max_connections")?;
+
+        let opts = OptsBuilder::from_opts(Opts::from_url(db_url)?)
+            .pool_opts(PoolOpts::default().with_constraints(constraints))
+            .stmt_cache_size(options.statement_cache_size);
+
+        Ok(Self {
+            shared: Arc::new(SharedPool {
+                opts: opts.into(),
+                health_check: options.health_check,
+                pool: Mutex::new(None),
+                state: Mutex::new(ConnectionState::Connected),
+            }),
+        })
+    }
+
+    /// Get a connection. If that fails, the error is returned straight away and reconnecting is
+    /// retried in the background, and until it's reconnected, getting a connection fails without
+    /// trying.
+    pub fn get_conn(&self) -> SrsResult<PooledConn> {
+        if self.state() == ConnectionState::Reconnecting {
+            return Err("Reconnecting to the database".into());
+        }
+
+        match self.shared.try_get_conn() {
+            Ok(conn) => {
+                if self.state() != ConnectionState::Connected {
+                    log::info!("Reconnected to database");
+                }
+
+                self.shared.set_state(ConnectionState::Connected);
+                Ok(conn)
+            },
+            Err(err) => {
+                log::warn!("Failed to get database connection, reconnecting in the background: {err}");
+                self.start_reconnecting();
+                Err(err)
+            }
+        }
+    }
+
+    /// Get the current connection state
+    pub fn state(&self) -> ConnectionState {
+        self.shared.state()
+    }
+
+    /// Start reconnecting on a background thread, unless it's already going
+    fn start_reconnecting(&self) {
+        {
+            let mut state = self.shared.state.lock().unwrap();
+            if *state == ConnectionState::Reconnecting {
+                return;
+            }
+
+            *state = ConnectionState::Reconnecting;
+        }
+
+        let shared = self.shared.clone();
+        thread::spawn(move || shared.reconnect());
+    }
+}
+
+impl SharedPool {
+    fn state(&self) -> ConnectionState {
+        *self.state.lock().unwrap()
+    }
+
+    fn set_state(&self, state: ConnectionState) {
+        *self.state.lock().unwrap() = state;
+    }
+
+    /// Try to get a connection again with backoff, until it works or we run out of attempts
+    fn reconnect(&self) {
+        let mut backoff = INITIAL_BACKOFF;
+
+        for attempt in 1..=MAX_CONNECT_ATTEMPTS {
+            // Throw away the pool so the attempt starts with fresh connections
+            *self.pool.lock().unwrap() = None;
+
+            thread::sleep(backoff);
+            backoff *= 2;
+
+            match self.try_get_conn() {
+                Ok(_) => {
+                    log::info!("Reconnected to database");
+                    self.set_state(ConnectionState::Connected);
+                    return;
+                },
+                Err(err) => log::warn!("Failed to reconnect to database (attempt {attempt}): {err}"),
+            }
+        }
+
+        log::error!("Failed to reconnect to database after {MAX_CONNECT_ATTEMPTS} attempts");
+        self.set_state(ConnectionState::Disconnected);
+    }
+
+    fn try_get_conn(&self) -> SrsResult<PooledConn> {
+        let pool = {
+            let mut pool = self.pool.lock().unwrap();
+
+            if pool.is_none() {
+                *pool = Some(Pool::new(self.opts.clone())?);
+            }
+
+            pool.as_ref().unwrap().clone()
+        };
+
+        let mut conn = pool.get_conn()?;
+
+        // Connections in the pool might have been dropped by the server if getting one has failed
+        // since, otherwise the ping is skipped to save a round trip on every query
+        if self.health_check && self.state() != ConnectionState::Connected && !conn.ping() {
+            return Err("Database connection failed health check".into());
+        }
+
+        Ok(conn)
+    }
+}
@@ -0,0 +1,51 @@
+use chrono::NaiveDate;
+
+use super::sources::SentenceSource;
+
+#[cfg(feature = "mysql")]
+pub(crate) use self::storage::*;
+
+/// The stats for a single day of reviews
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DailyStats {
+    pub day: NaiveDate,
+    pub cards_learned: i32,
+    pub cards_reviewed: i32,
+    /// Whether every card due that day was reviewed
+    pub dues_finished: bool,
+    /// The reviews of each source's sentences, for sources that had any
+    pub sources: Vec<SourceDailyStats>,
+}
+
+/// The reviews of one source's sentences on a day
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceDailyStats {
+    pub source: SentenceSource,
+    pub cards_learned: i32,
+    pub cards_reviewed: i32,
+}
+
+impl DailyStats {
+    /// Empty stats for a day with no reviews
+    pub fn empty(day: NaiveDate) -> Self {
+        Self {
+            day,
+            cards_learned: 0,
+            cards_reviewed: 0,
+            dues_finished: false,
+            sources: Vec::new(),
+        }
+    }
+
+    /// The reviews of the sentences from sources with this name, e.g. a deck's file name, added
+    /// up over the kinds of source
+    pub fn source_named(&self, name: &str) -> (i32, i32) {
+        self.sources.iter()
+            .filter(|stats| stats.source.name == name)
+            .fold((0, 0), |(learned, reviewed), stats| (learned + stats.cards_learned, reviewed + stats.cards_reviewed))
+    }
+}
+
+/// Reading and writing the daily stats tables
+#[cfg(feature = "mysql")]
+mod storage;
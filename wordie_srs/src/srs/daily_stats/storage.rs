@@ -0,0 +1,124 @@
+use chrono::Duration;
+use mysql::{PooledConn, prelude::Queryable, params};
+use uuid::Uuid;
+
+use super::*;
+use crate::srs::SrsResult;
+use crate::srs::sources::SourceKind;
+
+/// Create the daily stats table, shared by all the algorithms
+pub(crate) fn create_table(conn: &mut PooledConn) -> SrsResult<()> {
+    conn.query_drop(r"
+        CREATE TABLE IF NOT EXISTS daily_stats (
+            day DATE NOT NULL,
+            cards_learned INT NOT NULL DEFAULT 0,
+            cards_reviewed INT NOT NULL DEFAULT 0,
+            dues_finished BOOL NOT NULL DEFAULT FALSE,
+            PRIMARY KEY (day)
+        )
+    ")?;
+
+    conn.query_drop(r"
+        CREATE TABLE IF NOT EXISTS source_daily_stats (
+            day DATE NOT NULL,
+            kind VARCHAR(16) NOT NULL,
+            name VARCHAR(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci NOT NULL,
+            cards_learned INT NOT NULL DEFAULT 0,
+            cards_reviewed INT NOT NULL DEFAULT 0,
+            PRIMARY KEY (day, kind, name)
+        )
+    ")?;
+
+    Ok(())
+}
+
+/// Record a card review for a day, counting it towards the sentence's source too if it has one
+pub(crate) fn record_review(conn: &mut PooledConn, day: NaiveDate, sentence_id: &Uuid, new_card: bool) -> SrsResult<()> {
+    conn.exec_drop(
+        r"INSERT INTO daily_stats (day, cards_learned, cards_reviewed)
+          VALUES (:day, :learned, 1)
+          ON DUPLICATE KEY UPDATE cards_learned = cards_learned + VALUES(cards_learned),
+                                  cards_reviewed = cards_reviewed + 1",
+        params! {
+            "day" => day,
+            "learned" => new_card as i32,
+        })?;
+
+    conn.exec_drop(
+        r"INSERT INTO source_daily_stats (day, kind, name, cards_learned, cards_reviewed)
+          SELECT :day, kind, name, :learned, 1
+          FROM sentence_sources
+          WHERE sentence_id = :sentence_id
+          ON DUPLICATE KEY UPDATE cards_learned = source_daily_stats.cards_learned + VALUES(cards_learned),
+                                  cards_reviewed = source_daily_stats.cards_reviewed + 1",
+        params! {
+            "day" => day,
+            "sentence_id" => sentence_id.to_string(),
+            "learned" => new_card as i32,
+        })?;
+
+    Ok(())
+}
+
+/// Record whether there are any cards left due on a day. It's set after each review, so cards
+/// that come back round after being answered Again unset it until they're done too.
+pub(crate) fn set_dues_finished(conn: &mut PooledConn, day: NaiveDate, finished: bool) -> SrsResult<()> {
+    conn.exec_drop(
+        r"INSERT INTO daily_stats (day, dues_finished)
+          VALUES (:day, :finished)
+          ON DUPLICATE KEY UPDATE dues_finished = VALUES(dues_finished)",
+        params! {
+            "day" => day,
+            "finished" => finished,
+        })?;
+
+    Ok(())
+}
+
+/// Load the stats for the `days` days up to and including `today`, oldest first. Days without
+/// any reviews are filled in with empty stats.
+pub(crate) fn load(conn: &mut PooledConn, today: NaiveDate, days: usize) -> SrsResult<Vec<DailyStats>> {
+    let first_day = today - Duration::days(days as i64 - 1);
+
+    let rows: Vec<(NaiveDate, i32, i32, bool)> = conn.exec(
+        r"SELECT day, cards_learned, cards_reviewed, dues_finished
+          FROM daily_stats
+          WHERE day >= :first_day && day <= :today
+          ORDER BY day",
+        params! {
+            "first_day" => first_day,
+            "today" => today,
+        })?;
+
+    let mut stats = (0..days)
+        .map(|i| DailyStats::empty(first_day + Duration::days(i as i64)))
+        .collect::<Vec<DailyStats>>();
+
+    for (day, cards_learned, cards_reviewed, dues_finished) in rows {
+        let index = (day - first_day).num_days() as usize;
+        stats[index] = DailyStats { day, cards_learned, cards_reviewed, dues_finished, sources: Vec::new() };
+    }
+
+    let source_rows: Vec<(NaiveDate, String, String, i32, i32)> = conn.exec(
+        r"SELECT day, kind, name, cards_learned, cards_reviewed
+          FROM source_daily_stats
+          WHERE day >= :first_day && day <= :today
+          ORDER BY day, kind, name",
+        params! {
+            "first_day" => first_day,
+            "today" => today,
+        })?;
+
+    for (day, kind, name, cards_learned, cards_reviewed) in source_rows {
+        if let Some(kind) = SourceKind::from_name(&kind) {
+            let index = (day - first_day).num_days() as usize;
+            stats[index].sources.push(SourceDailyStats {
+                source: SentenceSource::new(kind, &name),
+                cards_learned,
+                cards_reviewed,
+            });
+        }
+    }
+
+    Ok(stats)
+}
@@ -0,0 +1,177 @@
+use std::collections::HashSet;
+
+use mysql::{PooledConn, Value, prelude::Queryable, params};
+
+use super::SrsResult;
+
+/// The most texts to look up in one query in `existing_sentences`
+const EXISTING_SENTENCES_CHUNK_SIZE: usize = 1000;
+
+/// Add a column to an existing table if it isn't there yet. CREATE TABLE IF NOT EXISTS won't add
+/// new columns to databases created by older versions, and mysql 5.7 has no ADD COLUMN IF NOT
+/// EXISTS, so we have to check information_schema ourselves.
+/// Returns whether the column was added.
+pub(crate) fn add_column_if_missing(conn: &mut PooledConn, table: &str, column: &str, definition: &str) -> SrsResult<bool> {
+    if column_exists(conn, table, column)? {
+        return Ok(false);
+    }
+
+    log::info!("Adding column {column} to table {table}");
+    conn.query_drop(format!("ALTER TABLE {table} ADD COLUMN `{column}` {definition}"))?;
+
+    Ok(true)
+}
+
+/// Run `f` in a transaction, committing if it succeeds and rolling back if it fails, so it's
+/// either all done or not done at all
+pub(crate) fn in_transaction<T>(conn: &mut PooledConn, f: impl FnOnce(&mut PooledConn) -> SrsResult<T>) -> SrsResult<T> {
+    conn.query_drop("START TRANSACTION")?;
+
+    match f(conn) {
+        Ok(result) => {
+            conn.query_drop("COMMIT")?;
+            Ok(result)
+        },
+        Err(err) => {
+            conn.query_drop("ROLLBACK")?;
+            Err(err)
+        }
+    }
+}
+
+/// The longest interval the old `interval` TIME column could hold, 838:59:59. Longer intervals
+/// were silently cut down to this.
+pub(crate) const MAX_TIME_INTERVAL_SECS: u64 = 838 * 60 * 60 + 59 * 60 + 59;
+
+/// Move card intervals from the old `interval` TIME column to `interval_secs`, as TIME caps out
+/// at 838 hours, which mature cards soon pass. Returns whether there was anything to move.
+pub(crate) fn migrate_interval_column(conn: &mut PooledConn) -> SrsResult<bool> {
+    if !column_exists(conn, "cards", "interval")? {
+        return Ok(false);
+    }
+
+    log::info!("Converting card intervals to seconds");
+    add_column_if_missing(conn, "cards", "interval_secs", "BIGINT")?;
+    conn.query_drop("UPDATE cards SET interval_secs = TIME_TO_SEC(`interval`) WHERE `interval` IS NOT NULL")?;
+    conn.query_drop("ALTER TABLE cards DROP COLUMN `interval`")?;
+
+    Ok(true)
+}
+
+/// A card's interval in seconds, in databases from before `migrate_interval_column` as well as
+/// after, for reading databases we don't own, e.g. when migrating
+pub(crate) fn interval_secs_column(conn: &mut PooledConn) -> SrsResult<&'static str> {
+    if column_exists(conn, "cards", "interval_secs")? {
+        Ok("cards.interval_secs")
+    }
+    else {
+        Ok("TIME_TO_SEC(cards.`interval`)")
+    }
+}
+
+/// Check whether a table in the current database has a column
+pub(crate) fn column_exists(conn: &mut PooledConn, table: &str, column: &str) -> SrsResult<bool> {
+    let exists: Option<i32> = conn.exec_first(
+        r"SELECT 1
+          FROM information_schema.columns
+          WHERE table_schema = DATABASE() && table_name = :table && column_name = :column",
+        params! {
+            "table" => table,
+            "column" => column,
+        })?;
+
+    Ok(exists.is_some())
+}
+
+/// Check whether a table exists in the current database
+pub(crate) fn table_exists(conn: &mut PooledConn, table: &str) -> SrsResult<bool> {
+    let exists: Option<i32> = conn.exec_first(
+        "SELECT 1 FROM information_schema.tables WHERE table_schema = DATABASE() && table_name = :table",
+        params! { "table" => table })?;
+
+    Ok(exists.is_some())
+}
+
+/// Check whether any cards have been reviewed, i.e. whether clearing the db would lose progress
+pub(crate) fn has_reviews(conn: &mut PooledConn) -> SrsResult<bool> {
+    if !table_exists(conn, "cards")? {
+        return Ok(false);
+    }
+
+    let reviewed: Option<i32> = conn.query_first("SELECT 1 FROM cards WHERE due IS NOT NULL LIMIT 1")?;
+    Ok(reviewed.is_some())
+}
+
+/// Check whether there are any sentences that haven't been deleted
+pub(crate) fn has_sentences(conn: &mut PooledConn) -> SrsResult<bool> {
+    if !table_exists(conn, "sentences")? {
+        return Ok(false);
+    }
+
+    let sentence: Option<i32> = conn.query_first("SELECT 1 FROM sentences WHERE deleted IS NULL LIMIT 1")?;
+    Ok(sentence.is_some())
+}
+
+/// A `?` for each of `count` values, to bind a list of values in e.g. `WHERE word IN ({})`
+pub(crate) fn placeholders(count: usize) -> String {
+    vec!["?"; count].join(",")
+}
+
+/// Named parameters for each of `values`, e.g. `:level0,:level1`, to bind a list in a query that
+/// already has named parameters, which can't be mixed with `?`. Returns the list for the query and
+/// the parameters to add. An empty list is `NULL`, so e.g. `level IN (NULL)` matches nothing.
+pub(crate) fn named_placeholders(prefix: &str, values: &[String]) -> (String, Vec<(String, Value)>) {
+    if values.is_empty() {
+        return ("NULL".to_string(), Vec::new());
+    }
+
+    let params = values.iter().enumerate()
+        .map(|(i, value)| (format!("{prefix}{i}"), Value::from(value.as_str())))
+        .collect::<Vec<(String, Value)>>();
+    let placeholders = params.iter().map(|(name, _)| format!(":{name}")).collect::<Vec<String>>().join(",");
+
+    (placeholders, params)
+}
+
+/// Get which of `texts` are sentences that haven't been deleted, shared by all the algorithms
+pub(crate) fn existing_sentences(conn: &mut PooledConn, texts: &[String]) -> SrsResult<HashSet<String>> {
+    let mut existing = HashSet::new();
+
+    for chunk in texts.chunks(EXISTING_SENTENCES_CHUNK_SIZE) {
+        let found: Vec<String> = conn.exec(
+            format!("SELECT text FROM sentences WHERE deleted IS NULL && text IN ({})", placeholders(chunk.len())),
+            chunk.to_vec())?;
+        existing.extend(found);
+    }
+
+    Ok(existing)
+}
+
+/// Copy tables to backup tables (e.g. cards to cards_backup), replacing the previous backup.
+/// Tables that don't exist are skipped.
+pub(crate) fn backup_tables(conn: &mut PooledConn, tables: &[&str]) -> SrsResult<()> {
+    for table in tables.iter() {
+        if !table_exists(conn, table)? {
+            continue;
+        }
+
+        log::info!("Backing up table {table} to {table}_backup");
+
+        // CREATE TABLE ... LIKE leaves out foreign keys, so the backups don't stop the tables
+        // being dropped
+        conn.query_drop(format!("DROP TABLE IF EXISTS {table}_backup"))?;
+        conn.query_drop(format!("CREATE TABLE {table}_backup LIKE {table}"))?;
+        conn.query_drop(format!("INSERT INTO {table}_backup SELECT * FROM {table}"))?;
+    }
+
+    Ok(())
+}
+
+/// A LIKE pattern matching text containing `text`, with LIKE's wildcards in it escaped
+pub(crate) fn contains_pattern(text: &str) -> String {
+    let escaped = text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+
+    format!("%{escaped}%")
+}
@@ -0,0 +1,96 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+use serde::{Serialize, Deserialize};
+use uuid::Uuid;
+
+use super::{SrsResult, Sentence};
+use super::tokenizer;
+
+/// The current version of the deck format, bumped whenever it changes incompatibly
+pub const DECK_FORMAT_VERSION: u32 = 1;
+
+/// The file extension for deck packages
+pub const DECK_EXTENSION: &str = "wordiedeck";
+
+/// A sentence in a shared deck
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeckSentence {
+    pub id: Uuid,
+    pub text: String,
+    /// The words the sentence was tokenized into when the deck was made, so decks can be checked
+    /// without a tokenizer. Sentences are tokenized again when they're imported.
+    pub words: Vec<String>,
+    #[serde(default)]
+    pub translation: Option<String>,
+    /// Path to an audio file for the sentence, relative to the deck file
+    #[serde(default)]
+    pub audio: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// A read-only package of sentences for sharing, without any scheduling information
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Deck {
+    pub format_version: u32,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub sentences: Vec<DeckSentence>,
+}
+
+impl Deck {
+    /// Create a deck from sentences, tokenizing them
+    pub fn from_sentences(name: &str, description: &str, sentences: &[Sentence]) -> Self {
+        Self {
+            format_version: DECK_FORMAT_VERSION,
+            name: name.to_string(),
+            description: description.to_string(),
+            sentences: sentences.iter()
+                .map(|sentence| DeckSentence {
+                    id: sentence.id,
+                    text: sentence.text.clone(),
+                    words: tokenizer::tokenize(&sentence.text),
+                    translation: None,
+                    audio: None,
+                    tags: Vec::new(),
+                })
+                .collect(),
+        }
+    }
+
+    /// The sentences in the deck, ready to be added to a collection
+    pub fn sentences(&self) -> Vec<Sentence> {
+        self.sentences.iter()
+            .map(|sentence| Sentence {
+                id: sentence.id,
+                text: sentence.text.clone(),
+            })
+            .collect()
+    }
+
+    /// Load a deck from a file
+    pub fn load(path: &Path) -> SrsResult<Self> {
+        let deck: Deck = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+
+        if deck.format_version > DECK_FORMAT_VERSION {
+            return Err(format!("Deck {path:?} uses format version {}, but only versions up to {DECK_FORMAT_VERSION} are supported",
+                               deck.format_version).into());
+        }
+
+        log::info!("Loaded deck {} with {} sentences", deck.name, deck.sentences.len());
+
+        Ok(deck)
+    }
+
+    /// Save the deck to a file
+    pub fn save(&self, path: &Path) -> SrsResult<()> {
+        serde_json::to_writer_pretty(BufWriter::new(File::create(path)?), self)?;
+
+        log::info!("Saved deck {} with {} sentences to {path:?}", self.name, self.sentences.len());
+
+        Ok(())
+    }
+}
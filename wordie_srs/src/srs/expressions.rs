@@ -0,0 +1,98 @@
+use mysql::{PooledConn, prelude::Queryable, params};
+
+use super::SrsResult;
+use super::tokenizer::{self, Token};
+
+/// A multi-word expression that's treated as a single word, e.g. 気にする or "in spite of"
+pub(crate) struct Expression {
+    pub text: String,
+    /// The words the tokenizer splits the expression into
+    pub words: Vec<String>,
+}
+
+/// Create the table of user-defined expressions
+pub(crate) fn create_table(conn: &mut PooledConn) -> SrsResult<()> {
+    conn.query_drop(r"
+        CREATE TABLE IF NOT EXISTS expressions (
+            expression VARCHAR(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci NOT NULL,
+            PRIMARY KEY (expression)
+        )
+    ")?;
+
+    Ok(())
+}
+
+/// Add an expression
+pub(crate) fn add(conn: &mut PooledConn, expression: &str) -> SrsResult<()> {
+    conn.exec_drop("INSERT IGNORE INTO expressions (expression) VALUES (:expression)",
+        params! { "expression" => expression })?;
+
+    Ok(())
+}
+
+/// Remove an expression
+pub(crate) fn remove(conn: &mut PooledConn, expression: &str) -> SrsResult<()> {
+    conn.exec_drop("DELETE FROM expressions WHERE expression = :expression",
+        params! { "expression" => expression })?;
+
+    Ok(())
+}
+
+/// Load the expressions, in alphabetical order
+pub(crate) fn load_text(conn: &mut PooledConn) -> SrsResult<Vec<String>> {
+    Ok(conn.query("SELECT expression FROM expressions ORDER BY expression")?)
+}
+
+/// Load the expressions along with the words they're made of, longest first so that longer
+/// expressions win over ones they contain
+pub(crate) fn load(conn: &mut PooledConn) -> SrsResult<Vec<Expression>> {
+    let mut expressions = load_text(conn)?
+        .into_iter()
+        .map(|text| Expression {
+            words: tokenizer::tokenize(&text),
+            text,
+        })
+        // Single words don't need joining
+        .filter(|expression| expression.words.len() > 1)
+        .collect::<Vec<Expression>>();
+
+    expressions.sort_by_key(|expression| std::cmp::Reverse(expression.words.len()));
+
+    Ok(expressions)
+}
+
+/// Join runs of tokenized words that make up an expression into a single word
+pub(crate) fn join(tokens: Vec<Token>, expressions: &[Expression]) -> Vec<Token> {
+    if expressions.is_empty() {
+        return tokens;
+    }
+
+    let mut result = Vec::new();
+    let mut i = 0;
+
+    while i < tokens.len() {
+        let expression = expressions.iter()
+            .find(|expression| {
+                tokens.len() - i >= expression.words.len()
+                    && tokens[i..].iter().zip(expression.words.iter()).all(|(token, word)| &token.word == word)
+            });
+
+        match expression {
+            Some(expression) => {
+                // The expression covers everything from its first word to its last
+                result.push(Token {
+                    word: expression.text.clone(),
+                    byte_start: tokens[i].byte_start,
+                    byte_end: tokens[i + expression.words.len() - 1].byte_end,
+                });
+                i += expression.words.len();
+            },
+            None => {
+                result.push(tokens[i].clone());
+                i += 1;
+            },
+        }
+    }
+
+    result
+}
@@ -0,0 +1,52 @@
+use serde::{Serialize, Deserialize};
+use strum_macros::EnumIter;
+
+#[cfg(feature = "mysql")]
+pub(crate) use self::storage::*;
+
+/// A colored marker on a sentence, like Anki's flags, for picking it out later without stopping a
+/// review session. The colors don't mean anything to wordie, it's up to the user what they're for.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone, EnumIter, Serialize, Deserialize)]
+pub enum Flag {
+    Red,
+    Orange,
+    Green,
+    Blue,
+}
+
+impl Flag {
+    /// The name of the flag in the db
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Flag::Red => "red",
+            Flag::Orange => "orange",
+            Flag::Green => "green",
+            Flag::Blue => "blue",
+        }
+    }
+
+    /// Get a flag from its name in the db
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "red" => Some(Flag::Red),
+            "orange" => Some(Flag::Orange),
+            "green" => Some(Flag::Green),
+            "blue" => Some(Flag::Blue),
+            _ => None,
+        }
+    }
+
+    /// The flag's name for showing to the user
+    pub fn label(&self) -> &'static str {
+        match self {
+            Flag::Red => "Red",
+            Flag::Orange => "Orange",
+            Flag::Green => "Green",
+            Flag::Blue => "Blue",
+        }
+    }
+}
+
+/// Storing each sentence's flag
+#[cfg(feature = "mysql")]
+mod storage;
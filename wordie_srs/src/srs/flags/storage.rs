@@ -0,0 +1,42 @@
+use mysql::{PooledConn, prelude::Queryable, params};
+use uuid::Uuid;
+
+use super::*;
+use crate::srs::SrsResult;
+
+/// Create the sentence flags table, shared by all the algorithms. Each sentence has at most one
+/// flag.
+pub(crate) fn create_table(conn: &mut PooledConn) -> SrsResult<()> {
+    conn.query_drop(r"
+        CREATE TABLE IF NOT EXISTS sentence_flags (
+            sentence_id CHAR(36) NOT NULL,
+            flag VARCHAR(16) NOT NULL,
+            PRIMARY KEY (sentence_id)
+        )
+    ")?;
+
+    Ok(())
+}
+
+/// Set a sentence's flag, replacing the one it had, or clear it if None
+pub(crate) fn set(conn: &mut PooledConn, sentence_id: &Uuid, flag: Option<Flag>) -> SrsResult<()> {
+    match flag {
+        Some(flag) => conn.exec_drop("REPLACE INTO sentence_flags (sentence_id, flag) VALUES (:sentence_id, :flag)",
+            params! {
+                "sentence_id" => sentence_id.to_string(),
+                "flag" => flag.as_str(),
+            })?,
+        None => conn.exec_drop("DELETE FROM sentence_flags WHERE sentence_id = :sentence_id",
+            params! { "sentence_id" => sentence_id.to_string() })?,
+    }
+
+    Ok(())
+}
+
+/// Load a sentence's flag, if it has one
+pub(crate) fn load(conn: &mut PooledConn, sentence_id: &Uuid) -> SrsResult<Option<Flag>> {
+    let flag: Option<String> = conn.exec_first("SELECT flag FROM sentence_flags WHERE sentence_id = :sentence_id",
+        params! { "sentence_id" => sentence_id.to_string() })?;
+
+    Ok(flag.as_deref().and_then(Flag::from_name))
+}
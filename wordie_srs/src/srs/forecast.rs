@@ -0,0 +1,31 @@
+use chrono::{DateTime, Local, NaiveDateTime, Utc};
+
+/// Count due dates by day relative to `time_now`, for the next `days` days. Index 0 is today, and
+/// overdue cards are counted as due today.
+pub(crate) fn due_counts_by_day(time_now: DateTime<Local>, dues: &[NaiveDateTime], days: usize) -> Vec<i32> {
+    let mut forecast = vec![0; days];
+    let today = time_now.naive_local().date();
+
+    for due in dues.iter() {
+        let due_day = DateTime::<Utc>::from_utc(*due, Utc).with_timezone(&Local).naive_local().date();
+        let day = i64::max(0, (due_day - today).num_days()) as usize;
+
+        if let Some(count) = forecast.get_mut(day) {
+            *count += 1;
+        }
+    }
+
+    forecast
+}
+
+/// Add the reviews from learning `new_cards_per_day` new cards every day to a forecast.
+/// `review_days` are the days after a card is learned that it gets reviewed on.
+pub(crate) fn add_new_card_reviews(forecast: &mut [i32], review_days: &[usize], new_cards_per_day: i32) {
+    for learned_day in 0..forecast.len() {
+        for review_day in review_days.iter() {
+            if let Some(count) = forecast.get_mut(learned_day + review_day) {
+                *count += new_cards_per_day;
+            }
+        }
+    }
+}
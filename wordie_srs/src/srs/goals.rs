@@ -0,0 +1,69 @@
+use serde::Deserialize;
+
+use super::daily_stats::DailyStats;
+
+/// Goals to meet each day to keep a streak going
+#[derive(Debug, Clone)]
+pub struct DailyGoals {
+    /// Review every card that's due
+    pub finish_all_dues: bool,
+    /// Learn at least this many new cards
+    pub min_new_cards: i32,
+    /// Goals for particular decks, on top of the goals for the whole collection
+    pub deck_goals: Vec<DeckGoal>,
+}
+
+/// A goal for the sentences from one deck (or any other source, e.g. a subtitle file), e.g.
+/// `{ "deck": "core6k.wordiedeck", "min_new_cards": 5 }`
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeckGoal {
+    /// The name of the source the sentences came from, usually the file they were imported from
+    pub deck: String,
+    /// Learn at least this many new cards from the deck
+    #[serde(default)]
+    pub min_new_cards: i32,
+    /// Review at least this many cards from the deck, including new ones
+    #[serde(default)]
+    pub min_reviews: i32,
+}
+
+impl DeckGoal {
+    /// Check whether a day's stats meet the goal
+    pub fn is_met(&self, stats: &DailyStats) -> bool {
+        let (learned, reviewed) = stats.source_named(&self.deck);
+        learned >= self.min_new_cards && reviewed >= self.min_reviews
+    }
+}
+
+impl Default for DailyGoals {
+    fn default() -> Self {
+        Self {
+            finish_all_dues: true,
+            min_new_cards: 0,
+            deck_goals: Vec::new(),
+        }
+    }
+}
+
+impl DailyGoals {
+    /// Check whether a day's stats meet the goals
+    pub fn is_met(&self, stats: &DailyStats) -> bool {
+        (!self.finish_all_dues || stats.dues_finished)
+            && stats.cards_learned >= self.min_new_cards
+            && self.deck_goals.iter().all(|goal| goal.is_met(stats))
+    }
+
+    /// Count the current streak of days meeting the goals, from daily stats ordered oldest first
+    /// and ending today. Today doesn't break the streak if the goals haven't been met yet, since
+    /// there's still time to meet them.
+    pub fn streak(&self, stats: &[DailyStats]) -> i32 {
+        let mut days = stats.iter().rev().peekable();
+
+        // Skip today if it isn't done yet
+        if days.peek().is_some_and(|today| !self.is_met(today)) {
+            days.next();
+        }
+
+        days.take_while(|day| self.is_met(day)).count() as i32
+    }
+}
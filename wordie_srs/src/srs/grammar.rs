@@ -0,0 +1,64 @@
+#[cfg(feature = "tokenizer")]
+pub use self::detection::*;
+
+#[cfg(feature = "mysql")]
+pub(crate) use self::storage::*;
+
+/// How much a grammar point has been studied
+#[derive(Debug, Clone)]
+pub struct GrammarStats {
+    pub grammar_point: String,
+    /// The number of sentences with the grammar point
+    pub sentences: i32,
+    /// The number of times sentences with the grammar point have been reviewed
+    pub exposures: i32,
+}
+
+/// Storing the grammar points found in each sentence
+#[cfg(feature = "mysql")]
+mod storage;
+
+/// Finding grammar points in sentences, which needs them split into words
+#[cfg(feature = "tokenizer")]
+mod detection;
+
+#[cfg(all(test, feature = "tokenizer"))]
+mod tests {
+    use super::*;
+
+    /// The byte ranges of the tokens in a text split up with |
+    fn split(text: &str) -> (String, Vec<(usize, usize)>) {
+        let mut tokens = Vec::new();
+        let mut start = 0;
+
+        for word in text.split('|') {
+            tokens.push((start, start + word.len()));
+            start += word.len();
+        }
+
+        (text.replace('|', ""), tokens)
+    }
+
+    fn contains(text: &str, form: &str) -> bool {
+        let (text, tokens) = split(text);
+        contains_form(&text, form, &tokens)
+    }
+
+    #[test]
+    fn forms_match_whole_tokens() {
+        assert!(contains("食べ|たい", "たい"));
+        assert!(contains("食べ|て|いる", "ている"));
+    }
+
+    #[test]
+    fn forms_inside_words_dont_match() {
+        assert!(!contains("たいてい|忙しい", "たい"));
+        assert!(!contains("ありがたい", "たい"));
+    }
+
+    #[test]
+    fn forms_can_start_inside_the_word_they_conjugate() {
+        assert!(contains("食べれ|ば", "れば"));
+        assert!(!contains("食べれば", "れば"));
+    }
+}
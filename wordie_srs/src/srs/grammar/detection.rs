@@ -0,0 +1,52 @@
+use super::*;
+use crate::srs::tokenizer;
+
+/// Grammar points found automatically, by the forms they take in sentences. Other grammar points
+/// can be added to sentences by hand.
+const PATTERNS: &[(&str, &[&str])] = &[
+    ("〜ておく", &["ておく", "ておい", "ておき", "でおく", "でおい", "でおき", "といた", "どいた"]),
+    ("〜てしまう", &["てしまう", "てしまっ", "でしまう", "でしまっ", "ちゃう", "ちゃっ", "じゃう", "じゃっ"]),
+    ("〜ている", &["ている", "ていた", "ていま", "でいる", "でいた", "でいま"]),
+    ("〜てみる", &["てみる", "てみた", "てみま", "てみよう", "でみる", "でみた", "でみま"]),
+    ("〜たい", &["たい", "たかった", "たくない"]),
+    ("〜なければならない", &["なければならない", "なければなりません", "なくてはいけない", "なきゃ", "なくちゃ"]),
+    ("〜てもいい", &["てもいい", "てもいいです", "でもいい", "てもよい"]),
+    ("〜たことがある", &["たことがある", "たことがあり", "たことがない", "だことがある", "だことがない"]),
+    ("〜ようにする", &["ようにする", "ようにして", "ようにしま"]),
+    ("〜そうだ", &["そうだ", "そうです", "そうな", "そうに"]),
+    ("〜ば", &["れば", "えば", "けば", "せば", "てば", "ねば", "べば", "めば", "げば"]),
+    ("〜たら", &["たら", "だら"]),
+    ("〜ながら", &["ながら"]),
+    ("〜から (reason)", &["から、", "からです", "だから"]),
+    ("〜のに", &["のに"]),
+];
+
+/// Find the grammar points in `PATTERNS` that a sentence uses. Forms only count when they line up
+/// with the sentence's words, so e.g. たい isn't found in たいてい.
+pub fn detect(text: &str) -> Vec<&'static str> {
+    let tokens: Vec<(usize, usize)> = tokenizer::tokenize_with_offsets(text)
+        .into_iter()
+        .map(|token| (token.byte_start, token.byte_end))
+        .collect();
+
+    PATTERNS.iter()
+        .filter(|(_, forms)| forms.iter().any(|form| contains_form(text, form, &tokens)))
+        .map(|(grammar_point, _)| *grammar_point)
+        .collect()
+}
+
+/// Whether a form appears in the text lined up with its tokens (given as byte ranges). The form
+/// has to end where a token does, and either start where one does or carry on past the end of the
+/// token it starts in, so conjugation endings like the れ in 食べれば still count.
+pub(super) fn contains_form(text: &str, form: &str, tokens: &[(usize, usize)]) -> bool {
+    let inside = |pos: usize| tokens.iter().find(|(start, end)| *start < pos && pos < *end);
+
+    text.match_indices(form).any(|(start, matched)| {
+        let end = start + matched.len();
+
+        inside(end).is_none() && match inside(start) {
+            Some((_, token_end)) => end > *token_end,
+            None => true,
+        }
+    })
+}
@@ -0,0 +1,111 @@
+use std::str::FromStr;
+
+use mysql::{PooledConn, prelude::Queryable, params};
+use uuid::Uuid;
+
+use super::*;
+use crate::srs::{SrsResult, Sentence};
+
+/// The most grammar points to insert with one query when detecting them
+const INSERT_CHUNK_SIZE: usize = 500;
+
+/// Create the grammar points table, shared by all the algorithms
+pub(crate) fn create_table(conn: &mut PooledConn) -> SrsResult<()> {
+    conn.query_drop(r"
+        CREATE TABLE IF NOT EXISTS grammar_points (
+            sentence_id CHAR(36) NOT NULL,
+            grammar_point VARCHAR(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci NOT NULL,
+            exposures INT NOT NULL DEFAULT 0,
+            PRIMARY KEY (sentence_id, grammar_point)
+        )
+    ")?;
+
+    Ok(())
+}
+
+/// Add a grammar point to sentences, sentences that already have it are left alone
+pub(crate) fn add(conn: &mut PooledConn, ids: &[Uuid], grammar_point: &str) -> SrsResult<()> {
+    conn.exec_batch(r"INSERT IGNORE INTO grammar_points (sentence_id, grammar_point)
+                      VALUES (:sentence_id, :grammar_point)",
+        ids.iter().map(|id| params! {
+            "sentence_id" => id.to_string(),
+            "grammar_point" => grammar_point,
+        }))?;
+
+    Ok(())
+}
+
+/// Remove a grammar point from a sentence
+pub(crate) fn remove(conn: &mut PooledConn, sentence_id: &Uuid, grammar_point: &str) -> SrsResult<()> {
+    conn.exec_drop("DELETE FROM grammar_points WHERE sentence_id = :sentence_id && grammar_point = :grammar_point",
+        params! {
+            "sentence_id" => sentence_id.to_string(),
+            "grammar_point" => grammar_point,
+        })?;
+
+    Ok(())
+}
+
+/// Load a sentence's grammar points, in alphabetical order
+pub(crate) fn load(conn: &mut PooledConn, sentence_id: &Uuid) -> SrsResult<Vec<String>> {
+    Ok(conn.exec("SELECT grammar_point FROM grammar_points WHERE sentence_id = :sentence_id ORDER BY grammar_point",
+        params! { "sentence_id" => sentence_id.to_string() })?)
+}
+
+/// Add the grammar points the matcher finds to sentences, returns the number of grammar points
+/// added, including ones the sentences already had
+pub(crate) fn add_detected(conn: &mut PooledConn, sentences: &[Sentence]) -> SrsResult<usize> {
+    let detected: Vec<(String, &str)> = sentences.iter()
+        .flat_map(|sentence| detect(&sentence.text)
+            .into_iter()
+            .map(|grammar_point| (sentence.id.to_string(), grammar_point)))
+        .collect();
+
+    for chunk in detected.chunks(INSERT_CHUNK_SIZE) {
+        let placeholders = vec!["(?, ?)"; chunk.len()].join(",");
+        let values: Vec<mysql::Value> = chunk.iter()
+            .flat_map(|(id, grammar_point)| [id.into(), (*grammar_point).into()])
+            .collect();
+
+        conn.exec_drop(format!("INSERT IGNORE INTO grammar_points (sentence_id, grammar_point) VALUES {placeholders}"),
+                       values)?;
+    }
+
+    Ok(detected.len())
+}
+
+/// Detect the grammar points in every sentence in the collection, e.g. after the patterns have
+/// changed, returns the number found
+pub(crate) fn detect_all(conn: &mut PooledConn) -> SrsResult<usize> {
+    let sentences = conn.query_map("SELECT id, text FROM sentences WHERE deleted IS NULL",
+        |(id, text): (String, String)| Sentence {
+            id: Uuid::from_str(&id).unwrap(),
+            text,
+        })?;
+
+    log::info!("Detecting grammar points in {} sentences", sentences.len());
+
+    add_detected(conn, &sentences)
+}
+
+/// Count a review of a sentence towards its grammar points
+pub(crate) fn record_exposure(conn: &mut PooledConn, sentence_id: &Uuid) -> SrsResult<()> {
+    conn.exec_drop("UPDATE grammar_points SET exposures = exposures + 1 WHERE sentence_id = :sentence_id",
+        params! { "sentence_id" => sentence_id.to_string() })?;
+
+    Ok(())
+}
+
+/// Get how much each grammar point has been studied, most exposed first
+pub(crate) fn stats(conn: &mut PooledConn) -> SrsResult<Vec<GrammarStats>> {
+    Ok(conn.query_map(
+        r"SELECT grammar_points.grammar_point,
+                 CAST(COUNT(*) AS SIGNED),
+                 CAST(COALESCE(SUM(grammar_points.exposures), 0) AS SIGNED)
+          FROM grammar_points
+          INNER JOIN sentences ON sentences.id = grammar_points.sentence_id
+          WHERE sentences.deleted IS NULL
+          GROUP BY grammar_points.grammar_point
+          ORDER BY SUM(grammar_points.exposures) DESC, grammar_points.grammar_point",
+        |(grammar_point, sentences, exposures)| GrammarStats { grammar_point, sentences, exposures })?)
+}
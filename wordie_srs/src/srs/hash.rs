@@ -0,0 +1,5 @@
+/// FNV-1a, for hashes that have to be the same between runs and rust versions, unlike the std hasher,
+/// e.g. ones that are saved or exported
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3))
+}
@@ -0,0 +1,245 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::BufRead;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use uuid::Uuid;
+
+use super::{Sentence, SrsAlgorithm, SrsResult};
+use super::sentence_splitter::SentenceSplitter;
+
+/// How many sentences a streamed import adds to the collection at once
+pub const IMPORT_CHUNK_SIZE: usize = 500;
+
+/// Options for filtering sentences before they're imported
+#[derive(Debug, Clone, Default)]
+pub struct ImportOptions {
+    /// Drop sentences shorter than this many chars, e.g. single word fragments like "はい。"
+    pub min_length: usize,
+    /// Drop sentences longer than this many chars, e.g. whole paragraphs that didn't get split
+    pub max_length: Option<usize>,
+}
+
+/// The sentences left after filtering, along with counts of what was dropped
+#[derive(Debug, Clone, Default)]
+pub struct FilteredSentences {
+    pub sentences: Vec<Sentence>,
+    pub too_short: usize,
+    pub too_long: usize,
+}
+
+impl ImportOptions {
+    /// Filter sentences according to the import options
+    pub fn filter(&self, sentences: Vec<Sentence>) -> FilteredSentences {
+        let mut result = FilteredSentences::default();
+
+        for sentence in sentences {
+            let length = sentence.text.trim().chars().count();
+
+            if length < self.min_length {
+                result.too_short += 1;
+            }
+            else if self.max_length.is_some_and(|max| length > max) {
+                result.too_long += 1;
+            }
+            else {
+                result.sentences.push(sentence);
+            }
+        }
+
+        if result.too_short > 0 || result.too_long > 0 {
+            log::info!("Filtered out {} sentences that were too short and {} that were too long",
+                       result.too_short, result.too_long);
+        }
+
+        result
+    }
+}
+
+/// A report of what an import added to the collection
+#[derive(Debug, Clone, Default)]
+pub struct ImportReport {
+    /// Words that got new cards, in the order they were added
+    pub new_words: Vec<String>,
+    /// The number of sentences in the collection containing each word in the imported sentences
+    pub sentences_per_word: HashMap<String, i32>,
+    /// Imported sentences that didn't introduce any new words, so are pure review material
+    pub review_only_sentences: Vec<Sentence>,
+    /// Words that were flagged as likely proper names, and won't be reviewed unless rescued
+    pub likely_names: Vec<String>,
+    /// Sentences that weren't added because they had no words left after the blacklist
+    pub skipped_sentences: Vec<Sentence>,
+}
+
+impl ImportReport {
+    /// Merge the report from a later import into this one
+    pub fn merge(&mut self, other: ImportReport) {
+        self.new_words.extend(other.new_words);
+        // The later import's counts are more up to date
+        self.sentences_per_word.extend(other.sentences_per_word);
+        self.review_only_sentences.extend(other.review_only_sentences);
+        self.skipped_sentences.extend(other.skipped_sentences);
+
+        for name in other.likely_names {
+            if !self.likely_names.contains(&name) {
+                self.likely_names.push(name);
+            }
+        }
+    }
+}
+
+/// The sentences in a text file, read a line at a time so the whole file is never in memory at
+/// once. Lines are split with a `SentenceSplitter`, which always ends sentences at line breaks, so
+/// this gives the same sentences as splitting the whole file.
+pub struct SentenceStream<R> {
+    reader: R,
+    splitter: SentenceSplitter,
+    /// Sentences split from the last line read that haven't been taken yet
+    pending: VecDeque<String>,
+    bytes_read: Arc<AtomicU64>,
+    finished: bool,
+}
+
+impl<R: BufRead> SentenceStream<R> {
+    pub fn new(reader: R, splitter: SentenceSplitter) -> Self {
+        Self {
+            reader,
+            splitter,
+            pending: VecDeque::new(),
+            bytes_read: Arc::new(AtomicU64::new(0)),
+            finished: false,
+        }
+    }
+
+    /// The number of bytes read so far, which can be checked from another thread while the
+    /// stream is being imported, e.g. against the file's size for a progress bar
+    pub fn bytes_read(&self) -> Arc<AtomicU64> {
+        self.bytes_read.clone()
+    }
+}
+
+impl<R: BufRead> Iterator for SentenceStream<R> {
+    type Item = SrsResult<Sentence>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pending.is_empty() && !self.finished {
+            let mut line = String::new();
+            match self.reader.read_line(&mut line) {
+                Ok(0) => self.finished = true,
+                Ok(read) => {
+                    self.bytes_read.fetch_add(read as u64, Ordering::Relaxed);
+                    self.pending.extend(self.splitter.split(&line));
+                },
+                Err(err) => {
+                    self.finished = true;
+                    return Some(Err(err.into()));
+                }
+            }
+        }
+
+        self.pending.pop_front().map(|text| Ok(Sentence { id: Uuid::new_v4(), text }))
+    }
+}
+
+/// How far a streamed import has got
+#[derive(Debug, Clone, Default)]
+pub struct ImportProgress {
+    /// Sentences taken from the stream so far, including ones that were dropped or failed
+    pub read: usize,
+    pub added: usize,
+    /// Sentences dropped by the import options
+    pub too_short: usize,
+    pub too_long: usize,
+    /// Sentences with no words, which there's nothing to learn from
+    pub no_words: usize,
+    pub failed: usize,
+    /// Words that got new cards, sentences that didn't introduce any and words flagged as likely
+    /// names, counted rather than kept, as a stream can be any size
+    pub new_words: usize,
+    pub review_only: usize,
+    pub likely_names: usize,
+    /// The first sentence that failed to be added, with why
+    pub first_failure: Option<(String, String)>,
+}
+
+/// Import sentences as they come in from `sentences`, e.g. a `SentenceStream`, adding them to the
+/// collection `IMPORT_CHUNK_SIZE` at a time. `on_chunk` is called after each chunk with the
+/// sentences that were added, the chunk's report and the progress so far, e.g. to set their source
+/// or update a progress bar, and the import stops after the current chunk once `cancel` is set.
+/// Only counts are kept across chunks, so anything else wanted from the reports has to be taken
+/// from them as they come.
+///
+/// Adding sentences is all or nothing, so if a chunk fails to be added, none of it was, and its
+/// sentences are retried one at a time so one bad sentence doesn't fail the rest. Reading from
+/// the stream failing ends the import with the error.
+pub fn import_stream<A, I, F>(srs_algorithm: &mut A, sentences: I, options: &ImportOptions, cancel: &AtomicBool,
+                              mut on_chunk: F) -> SrsResult<ImportProgress>
+    where A: SrsAlgorithm + ?Sized,
+          I: Iterator<Item = SrsResult<Sentence>>,
+          F: FnMut(&mut A, &[Sentence], &ImportReport, &ImportProgress) -> SrsResult<()>
+{
+    let mut progress = ImportProgress::default();
+    let mut sentences = sentences.peekable();
+
+    while sentences.peek().is_some() {
+        if cancel.load(Ordering::Relaxed) {
+            log::info!("Import cancelled after {} sentences", progress.read);
+            break;
+        }
+
+        let chunk = sentences.by_ref().take(IMPORT_CHUNK_SIZE).collect::<SrsResult<Vec<Sentence>>>()?;
+        progress.read += chunk.len();
+
+        let filtered = options.filter(chunk);
+        progress.too_short += filtered.too_short;
+        progress.too_long += filtered.too_long;
+
+        if filtered.sentences.is_empty() {
+            continue;
+        }
+
+        let mut report = ImportReport::default();
+        let mut added = match srs_algorithm.add_sentences(&filtered.sentences) {
+            Ok(chunk_report) => {
+                report = chunk_report;
+                filtered.sentences
+            },
+            Err(err) => {
+                log::warn!("Failed to add chunk of {} sentences, adding them one at a time: {err}", filtered.sentences.len());
+
+                let mut added = Vec::new();
+                for sentence in filtered.sentences {
+                    match srs_algorithm.add_sentences(std::slice::from_ref(&sentence)) {
+                        Ok(sentence_report) => {
+                            report.merge(sentence_report);
+                            added.push(sentence);
+                        },
+                        Err(err) => {
+                            log::warn!("Failed to add sentence {}: {err}", sentence.text);
+                            progress.failed += 1;
+                            if progress.first_failure.is_none() {
+                                progress.first_failure = Some((sentence.text, err.to_string()));
+                            }
+                        }
+                    }
+                }
+                added
+            }
+        };
+
+        // Sentences skipped for having no words weren't added, so they don't get a source etc
+        let skipped: HashSet<Uuid> = report.skipped_sentences.drain(..).map(|sentence| sentence.id).collect();
+        added.retain(|sentence| !skipped.contains(&sentence.id));
+        progress.no_words += skipped.len();
+
+        progress.added += added.len();
+        progress.new_words += report.new_words.len();
+        progress.review_only += report.review_only_sentences.len();
+        progress.likely_names += report.likely_names.len();
+        on_chunk(srs_algorithm, &added, &report, &progress)?;
+    }
+
+    log::info!("Imported {} of {} sentences with {} new words", progress.added, progress.read, progress.new_words);
+
+    Ok(progress)
+}
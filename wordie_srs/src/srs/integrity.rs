@@ -0,0 +1,62 @@
+#[cfg(feature = "mysql")]
+pub(crate) use self::storage::*;
+
+/// The problems found by an integrity check. If the check was run in repair mode, these are the
+/// problems that were fixed.
+#[derive(Debug, Clone, Default)]
+pub struct IntegrityReport {
+    /// Rows linking sentences and words where the sentence or word no longer exists
+    pub orphaned_sentence_words: usize,
+    /// Cards whose word (or sentence, for sentence cards) no longer exists
+    pub cards_without_words: usize,
+    /// Sentences that aren't split into any words, so they can never be reviewed
+    pub sentences_without_words: usize,
+    /// Cards that are scheduled but have no interval to schedule the next review from
+    pub due_cards_without_interval: usize,
+    /// Words that are the same once unicode normalized, as (duplicate, word it duplicates)
+    pub duplicate_words: Vec<(String, String)>,
+    /// Whether the problems were repaired
+    pub repaired: bool,
+}
+
+impl IntegrityReport {
+    /// The total number of problems found
+    pub fn problem_count(&self) -> usize {
+        self.orphaned_sentence_words
+            + self.cards_without_words
+            + self.sentences_without_words
+            + self.due_cards_without_interval
+            + self.duplicate_words.len()
+    }
+
+    /// Whether no problems were found
+    pub fn is_ok(&self) -> bool {
+        self.problem_count() == 0
+    }
+
+    /// A line describing each kind of problem found, for showing to the user
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        let counts = [
+            (self.orphaned_sentence_words, "orphaned sentence words"),
+            (self.cards_without_words, "cards without words"),
+            (self.sentences_without_words, "sentences without words"),
+            (self.due_cards_without_interval, "scheduled cards without an interval"),
+        ];
+
+        for (count, description) in counts.iter().filter(|(count, _)| *count > 0) {
+            problems.push(format!("{count} {description}"));
+        }
+
+        for (word, into) in self.duplicate_words.iter() {
+            problems.push(format!("{word} is a duplicate of {into}"));
+        }
+
+        problems
+    }
+}
+
+/// Checks that run against the database, and finding what to merge
+#[cfg(feature = "mysql")]
+mod storage;
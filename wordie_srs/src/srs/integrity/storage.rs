@@ -0,0 +1,47 @@
+use std::collections::HashMap;
+
+use mysql::{PooledConn, prelude::Queryable};
+
+use super::*;
+use crate::srs::SrsResult;
+use crate::srs::normalize;
+
+/// Count the rows in a table matching a condition, deleting them if `repair` is set
+pub(crate) fn check_rows(conn: &mut PooledConn, table: &str, condition: &str, repair: bool) -> SrsResult<usize> {
+    let count: Option<i64> = conn.query_first(format!("SELECT count(*) FROM {table} WHERE {condition}"))?;
+    let count = count.unwrap_or(0) as usize;
+
+    if count > 0 {
+        log::warn!("Found {count} rows in {table} where {condition}");
+
+        if repair {
+            conn.query_drop(format!("DELETE FROM {table} WHERE {condition}"))?;
+        }
+    }
+
+    Ok(count)
+}
+
+/// Find words that are the same as another word once normalized (see `normalize::word`, so that
+/// e.g. half width katakana matches full width), paired with the word they should be merged into.
+/// The normalized spelling is preferred if it's in the collection.
+pub(crate) fn find_duplicate_words(words: &[String]) -> Vec<(String, String)> {
+    let mut groups: HashMap<String, Vec<&String>> = HashMap::new();
+    for word in words.iter() {
+        groups.entry(normalize::word(word)).or_default().push(word);
+    }
+
+    let mut duplicates = Vec::new();
+    for (normalized, group) in groups.iter().filter(|(_, group)| group.len() > 1) {
+        let into = group.iter()
+            .find(|word| **word == normalized)
+            .unwrap_or(&group[0]);
+
+        for word in group.iter().filter(|word| *word != into) {
+            duplicates.push((word.to_string(), into.to_string()));
+        }
+    }
+
+    duplicates.sort();
+    duplicates
+}
@@ -0,0 +1,48 @@
+#[cfg(feature = "mysql")]
+pub(crate) use self::storage::*;
+
+/// How far along the words containing a kanji are
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KanjiStats {
+    pub kanji: char,
+    /// The number of words (with cards) containing the kanji
+    pub words: i32,
+    /// The number of those words that have been learned
+    pub learned_words: i32,
+    /// The number of those words that are mature
+    pub mature_words: i32,
+}
+
+impl KanjiStats {
+    /// Whether the kanji is known, i.e. it's in at least one mature word
+    pub fn is_known(&self) -> bool {
+        self.mature_words > 0
+    }
+
+    /// Whether the kanji has been seen, i.e. it's in at least one learned word
+    pub fn is_seen(&self) -> bool {
+        self.learned_words > 0
+    }
+}
+
+/// Whether a character is a kanji (a CJK unified ideograph)
+pub fn is_kanji(c: char) -> bool {
+    matches!(c, '\u{4E00}'..='\u{9FFF}' | '\u{3400}'..='\u{4DBF}' | '\u{F900}'..='\u{FAFF}')
+}
+
+/// Get the distinct kanji in some words
+pub fn kanji_in<'a>(words: impl IntoIterator<Item = &'a String>) -> Vec<char> {
+    let mut kanji = Vec::new();
+
+    for c in words.into_iter().flat_map(|word| word.chars()) {
+        if is_kanji(c) && !kanji.contains(&c) {
+            kanji.push(c);
+        }
+    }
+
+    kanji
+}
+
+/// Working out kanji stats from the words in the database
+#[cfg(feature = "mysql")]
+mod storage;
@@ -0,0 +1,65 @@
+use mysql::{PooledConn, prelude::Queryable, params};
+
+use super::*;
+use crate::srs::SrsResult;
+use crate::srs::blacklist::NOT_EXCLUDED;
+
+/// Create the kanji table. It's derived entirely from the words and cards tables, and kept up to
+/// date as words are added and reviewed.
+pub(crate) fn create_table(conn: &mut PooledConn) -> SrsResult<()> {
+    conn.query_drop(r"
+        CREATE TABLE IF NOT EXISTS kanji (
+            kanji VARCHAR(4) CHARACTER SET utf8mb4 COLLATE utf8mb4_bin NOT NULL,
+            words INT NOT NULL,
+            learned_words INT NOT NULL,
+            mature_words INT NOT NULL,
+            PRIMARY KEY (kanji)
+        )
+    ")?;
+
+    Ok(())
+}
+
+/// Recalculate the stats for some kanji from their words' cards
+pub(crate) fn update(conn: &mut PooledConn, kanji: &[char], mature_secs: u64) -> SrsResult<()> {
+    conn.exec_batch(format!(r"
+            REPLACE INTO kanji (kanji, words, learned_words, mature_words)
+            SELECT :kanji,
+                   count(*),
+                   COALESCE(SUM(cards.due IS NOT NULL), 0),
+                   COALESCE(SUM(cards.interval_secs IS NOT NULL && cards.interval_secs >= :mature_secs), 0)
+            FROM words
+            INNER JOIN cards ON cards.word_id = words.id && cards.card_type = 'recognition'
+            WHERE INSTR(words.word, :kanji) > 0
+               && {NOT_EXCLUDED}
+        "),
+        kanji.iter().map(|kanji| params! {
+            "kanji" => kanji.to_string(),
+            "mature_secs" => mature_secs,
+        }))?;
+
+    Ok(())
+}
+
+/// Recalculate the stats for every kanji, for collections that were created before kanji were
+/// tracked
+pub(crate) fn rebuild(conn: &mut PooledConn, mature_secs: u64) -> SrsResult<()> {
+    let words: Vec<String> = conn.query("SELECT word FROM words")?;
+    let kanji = kanji_in(words.iter());
+
+    log::info!("Rebuilding stats for {} kanji", kanji.len());
+
+    update(conn, &kanji, mature_secs)
+}
+
+/// Load the stats for every kanji, most common first
+pub(crate) fn load(conn: &mut PooledConn) -> SrsResult<Vec<KanjiStats>> {
+    Ok(conn.query_map(
+        "SELECT kanji, words, learned_words, mature_words FROM kanji WHERE words > 0 ORDER BY words DESC, kanji",
+        |(kanji, words, learned_words, mature_words): (String, i32, i32, i32)| KanjiStats {
+            kanji: kanji.chars().next().unwrap_or_default(),
+            words,
+            learned_words,
+            mature_words,
+        })?)
+}
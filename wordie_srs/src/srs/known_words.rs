@@ -0,0 +1,131 @@
+use std::path::Path;
+
+use super::SrsResult;
+
+/// The interval known words are given by default, the same as a mature card
+pub const DEFAULT_KNOWN_INTERVAL_DAYS: u32 = 21;
+
+/// LWT statuses for learned and well known terms. 1-4 are still being learned and 98 is ignored.
+const LWT_KNOWN_STATUSES: &[&str] = &["5", "99"];
+
+/// The lowest LingQ status that counts as known, 4 being known and 3 familiar
+const LINGQ_KNOWN_STATUS: i32 = 3;
+
+/// A tool whose word exports can be imported as known words
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KnownWordSource {
+    /// Learning With Texts term exports, tab separated with the status in the fifth column
+    Lwt,
+    /// LingQ vocabulary or known word exports, csv with a header
+    Lingq,
+}
+
+impl KnownWordSource {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "lwt" => Some(Self::Lwt),
+            "lingq" => Some(Self::Lingq),
+            _ => None,
+        }
+    }
+}
+
+/// Read the known words from an export file
+pub fn load(path: &Path, source: KnownWordSource) -> SrsResult<Vec<String>> {
+    let contents = std::fs::read_to_string(path)?;
+
+    let words = match source {
+        KnownWordSource::Lwt => lwt_words(&contents),
+        KnownWordSource::Lingq => lingq_words(&contents)?,
+    };
+
+    log::info!("Read {} known words from {path:?}", words.len());
+
+    Ok(words)
+}
+
+/// Get the learned and well known terms from an LWT export. Terms are the first column and
+/// statuses the fifth, after the translation, sentence and romanization.
+fn lwt_words(contents: &str) -> Vec<String> {
+    let words = contents.lines()
+        .map(|line| line.split('\t').collect::<Vec<&str>>())
+        .filter(|columns| columns.get(4).is_some_and(|status| LWT_KNOWN_STATUSES.contains(&status.trim())))
+        .map(|columns| columns[0].to_string())
+        .collect::<Vec<String>>();
+
+    clean(words)
+}
+
+/// Get the known words from a LingQ export. Vocabulary exports have `term` and `status` columns,
+/// while known word exports are just a list of words, which are all known.
+fn lingq_words(contents: &str) -> SrsResult<Vec<String>> {
+    let mut lines = contents.lines();
+    let header = lines.next().map(split_csv_line).unwrap_or_default();
+
+    let column = |name: &str| header.iter().position(|column| column.trim().eq_ignore_ascii_case(name));
+
+    let words = match (column("term").or_else(|| column("word")), column("status")) {
+        (Some(term), Some(status)) => lines
+            .map(split_csv_line)
+            .filter(|columns| columns.get(status).is_some_and(|status| lingq_status_known(status)))
+            .filter_map(|columns| columns.get(term).cloned())
+            .collect(),
+        (Some(term), None) => lines
+            .map(split_csv_line)
+            .filter_map(|columns| columns.get(term).cloned())
+            .collect(),
+        // Known word exports don't always have a header
+        (None, None) => contents.lines()
+            .filter_map(|line| split_csv_line(line).into_iter().next())
+            .collect(),
+        (None, Some(_)) => return Err("LingQ export has a status column but no term column".into()),
+    };
+
+    Ok(clean(words))
+}
+
+/// Whether a LingQ status counts as known, either a number or a name
+fn lingq_status_known(status: &str) -> bool {
+    match status.trim().parse::<i32>() {
+        Ok(status) => status >= LINGQ_KNOWN_STATUS,
+        Err(_) => matches!(status.trim().to_ascii_lowercase().as_str(), "known" | "learned" | "familiar"),
+    }
+}
+
+/// Split a csv line into columns, allowing for quoted columns with commas in
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut columns = Vec::new();
+    let mut column = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                column.push('"');
+                chars.next();
+            },
+            '"' => quoted = !quoted,
+            ',' if !quoted => columns.push(std::mem::take(&mut column)),
+            _ => column.push(c),
+        }
+    }
+
+    columns.push(column);
+
+    columns
+}
+
+/// Trim words and remove empty ones and duplicates, along with any with quotes in, which can't be
+/// words anyway
+fn clean(words: Vec<String>) -> Vec<String> {
+    let mut words = words.into_iter()
+        .map(|word| word.trim().to_string())
+        .filter(|word| !word.is_empty() && !word.contains(|c| c == '"' || c == '\\'))
+        .collect::<Vec<String>>();
+
+    words.sort();
+    words.dedup();
+
+    words
+}
@@ -0,0 +1,28 @@
+#[cfg(feature = "mysql")]
+pub(crate) use self::storage::*;
+
+/// Progress through the words in a level list, e.g. JLPT N4 or CEFR B1
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LevelProgress {
+    pub level: String,
+    /// The number of words in the level list, whether they're in the collection or not
+    pub words: i32,
+    pub learned_words: i32,
+    pub mature_words: i32,
+}
+
+impl LevelProgress {
+    /// The percentage of the level's words that are mature
+    pub fn mature_percent(&self) -> f32 {
+        if self.words == 0 {
+            0.0
+        }
+        else {
+            self.mature_words as f32 * 100.0 / self.words as f32
+        }
+    }
+}
+
+/// Counting progress through the levels from the database
+#[cfg(feature = "mysql")]
+mod storage;
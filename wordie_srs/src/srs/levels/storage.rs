@@ -0,0 +1,51 @@
+use mysql::{PooledConn, prelude::Queryable, params};
+
+use super::*;
+use crate::srs::SrsResult;
+
+/// Create the table of word levels
+pub(crate) fn create_table(conn: &mut PooledConn) -> SrsResult<()> {
+    conn.query_drop(r"
+        CREATE TABLE IF NOT EXISTS word_levels (
+            word VARCHAR(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci NOT NULL,
+            level VARCHAR(16) NOT NULL,
+            PRIMARY KEY (word)
+        )
+    ")?;
+
+    Ok(())
+}
+
+/// Tag words with a level. Words that were already tagged move to the new level.
+pub(crate) fn import(conn: &mut PooledConn, level: &str, words: &[String]) -> SrsResult<()> {
+    conn.exec_batch("REPLACE INTO word_levels (word, level) VALUES (:word, :level)",
+        words.iter().map(|word| params! {
+            "word" => word.as_str(),
+            "level" => level,
+        }))?;
+
+    log::info!("Tagged {} words as {level}", words.len());
+
+    Ok(())
+}
+
+/// Load the progress through each level
+pub(crate) fn load_progress(conn: &mut PooledConn, mature_secs: u64) -> SrsResult<Vec<LevelProgress>> {
+    Ok(conn.exec_map(
+        r"SELECT word_levels.level,
+                 CAST(count(*) AS SIGNED),
+                 CAST(COALESCE(SUM(cards.due IS NOT NULL), 0) AS SIGNED),
+                 CAST(COALESCE(SUM(cards.interval_secs IS NOT NULL && cards.interval_secs >= :mature_secs), 0) AS SIGNED)
+          FROM word_levels
+          LEFT JOIN words ON words.word = word_levels.word
+          LEFT JOIN cards ON cards.word_id = words.id && cards.card_type = 'recognition'
+          GROUP BY word_levels.level
+          ORDER BY word_levels.level",
+        params! { "mature_secs" => mature_secs },
+        |(level, words, learned_words, mature_words): (String, i32, i32, i32)| LevelProgress {
+            level,
+            words,
+            learned_words,
+            mature_words,
+        })?)
+}
@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+
+use chrono::{NaiveDateTime, Utc};
+use mysql::{Pool, PooledConn, prelude::Queryable};
+use uuid::Uuid;
+
+use super::{SrsResult, Sentence};
+use super::sync::{SyncStore, SyncChange};
+use super::anki::AnkiSrsAlgorithm;
+use super::scheduler::Schedule;
+use super::db;
+
+/// The schema of a database being migrated from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceSchema {
+    /// Cards are words (`WordieSrsAlgorithm`)
+    Wordie,
+    /// Cards are sentences (`AnkiSrsAlgorithm`)
+    Anki,
+}
+
+/// The number of rows brought over by a migration
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MigrationSummary {
+    pub sentences: usize,
+    pub cards: usize,
+}
+
+/// A card's scheduling as stored in either schema
+type CardRow = (i32, f32, Option<u64>, NaiveDateTime);
+
+/// Copy the sentences and scheduling from an existing database into `target`, so switching
+/// backends or schema versions doesn't reset progress. Migrated cards win over any reviews
+/// already in the target.
+pub fn migrate(source_url: &str, schema: SourceSchema, target: &mut dyn SyncStore) -> SrsResult<MigrationSummary> {
+    log::info!("Migrating {schema:?} collection from {source_url}");
+
+    let changes = read_collection(source_url, schema)?;
+
+    let summary = MigrationSummary {
+        sentences: changes.iter().filter(|change| matches!(change, SyncChange::Sentence(_))).count(),
+        cards: changes.iter().filter(|change| matches!(change, SyncChange::Card { .. })).count(),
+    };
+
+    target.apply_changes(&changes)?;
+
+    log::info!("Migrated {} sentences and {} cards", summary.sentences, summary.cards);
+
+    Ok(summary)
+}
+
+/// Copy the sentences and scheduling from an existing database into an Anki schema collection,
+/// the other way to `migrate`, so a Wordie collection can be switched over to sentence cards.
+/// Migrated schedules win over any reviews already in the target.
+pub fn migrate_to_anki(source_url: &str, schema: SourceSchema, target: &mut AnkiSrsAlgorithm) -> SrsResult<MigrationSummary> {
+    log::info!("Migrating {schema:?} collection from {source_url} to the Anki schema");
+
+    let mut conn = Pool::new(source_url)?.get_conn()?;
+    let sentences = read_sentences(&mut conn)?;
+
+    let cards = match schema {
+        SourceSchema::Wordie => {
+            let sentence_words = read_sentence_words(&mut conn, schema)?;
+            word_cards_to_sentence_cards(&sentences, &sentence_words, read_word_cards(&mut conn)?)
+        },
+        SourceSchema::Anki => {
+            let cards = read_sentence_cards(&mut conn)?.into_iter().collect::<HashMap<Uuid, CardRow>>();
+            sentences.iter().map(|sentence| cards.get(&sentence.id).copied()).collect()
+        },
+    };
+
+    let scheduled = sentences.into_iter()
+        .zip(cards)
+        .map(|(sentence, card)| (sentence, card.map(|(review_count, ease, interval, due)| Schedule {
+            due: Some(due),
+            interval: interval.map(Duration::from_secs),
+            review_count,
+            ease,
+        })))
+        .collect::<Vec<(Sentence, Option<Schedule>)>>();
+
+    let summary = MigrationSummary {
+        sentences: scheduled.len(),
+        cards: scheduled.iter().filter(|(_, schedule)| schedule.is_some()).count(),
+    };
+
+    target.import_schedules(&scheduled)?;
+
+    log::info!("Migrated {} sentences and {} cards", summary.sentences, summary.cards);
+
+    Ok(summary)
+}
+
+/// Read every sentence and reviewed card from a database as changes, sentences first so their
+/// words exist by the time the cards are applied
+pub fn read_collection(source_url: &str, schema: SourceSchema) -> SrsResult<Vec<SyncChange>> {
+    let mut conn = Pool::new(source_url)?.get_conn()?;
+
+    let mut changes = read_sentences(&mut conn)?
+        .into_iter()
+        .map(SyncChange::Sentence)
+        .collect::<Vec<SyncChange>>();
+
+    // Unreviewed cards are left out, the target creates new cards for every word anyway
+    let cards: Vec<(String, CardRow)> = match schema {
+        SourceSchema::Wordie => read_word_cards(&mut conn)?,
+        SourceSchema::Anki => {
+            let sentence_words = read_sentence_words(&mut conn, schema)?;
+            sentence_cards_to_word_cards(read_sentence_cards(&mut conn)?, &sentence_words)
+        },
+    };
+
+    // Everything migrated counts as modified now, so it wins over the target's own reviews
+    let modified = Utc::now().timestamp();
+
+    changes.extend(cards.into_iter().map(|(word, (review_count, ease, interval, due))| SyncChange::Card {
+        word,
+        review_count,
+        ease,
+        interval_secs: interval,
+        due: Some(due.timestamp()),
+        modified,
+    }));
+
+    Ok(changes)
+}
+
+/// Read the sentences from a database, leaving out any in the trash
+fn read_sentences(conn: &mut PooledConn) -> SrsResult<Vec<Sentence>> {
+    let not_trashed = not_trashed_filter(conn)?;
+
+    Ok(conn.query_map(format!("SELECT id, text FROM sentences WHERE {not_trashed}"),
+        |(id, text): (String, String)| Sentence {
+            id: Uuid::from_str(&id).unwrap(),
+            text,
+        })?)
+}
+
+/// A filter for sentences that aren't in the trash, for databases from before there was a trash too
+fn not_trashed_filter(conn: &mut PooledConn) -> SrsResult<&'static str> {
+    if db::column_exists(conn, "sentences", "deleted")? {
+        Ok("sentences.deleted IS NULL")
+    }
+    else {
+        Ok("TRUE")
+    }
+}
+
+/// Read the words in each sentence as they were stored when the sentence was added, so the
+/// migration doesn't depend on the tokenizer still splitting them the same way
+fn read_sentence_words(conn: &mut PooledConn, schema: SourceSchema) -> SrsResult<HashMap<Uuid, Vec<String>>> {
+    let query = match schema {
+        SourceSchema::Wordie => r"SELECT sentence_words.sentence_id, words.word
+            FROM sentence_words
+            INNER JOIN words ON words.id = sentence_words.word_id",
+        SourceSchema::Anki => "SELECT sentence_id, word FROM sentence_words",
+    };
+
+    let mut sentence_words: HashMap<Uuid, Vec<String>> = HashMap::new();
+    for (id, word) in conn.query::<(String, String), _>(query)? {
+        sentence_words.entry(Uuid::from_str(&id).unwrap()).or_default().push(word);
+    }
+
+    Ok(sentence_words)
+}
+
+/// Read the reviewed cards from a Wordie schema database, by word
+fn read_word_cards(conn: &mut PooledConn) -> SrsResult<Vec<(String, CardRow)>> {
+    // Databases that haven't been opened since intervals moved to seconds still have the old column
+    let interval_secs = db::interval_secs_column(conn)?;
+
+    // Only recognition cards are migrated, databases from before production cards only have those
+    // anyway
+    let card_type_filter = if db::column_exists(conn, "cards", "card_type")? {
+        "&& cards.card_type = 'recognition'"
+    }
+    else {
+        ""
+    };
+
+    Ok(conn.query_map(
+        format!(r"SELECT words.word, cards.review_count, cards.ease, {interval_secs}, cards.due
+          FROM cards
+          INNER JOIN words ON words.id = cards.word_id
+          WHERE cards.due IS NOT NULL {card_type_filter}"),
+        |(word, review_count, ease, interval, due): (String, i32, f32, Option<u64>, NaiveDateTime)| {
+            (word, (review_count, ease, interval, due))
+        })?)
+}
+
+/// Read the reviewed cards from an Anki schema database, by sentence id, leaving out sentences in
+/// the trash
+fn read_sentence_cards(conn: &mut PooledConn) -> SrsResult<Vec<(Uuid, CardRow)>> {
+    let interval_secs = db::interval_secs_column(conn)?;
+    let not_trashed = not_trashed_filter(conn)?;
+
+    Ok(conn.query_map(
+        format!(r"SELECT sentences.id, cards.review_count, cards.ease, {interval_secs}, cards.due
+          FROM cards
+          INNER JOIN sentences ON sentences.id = cards.sentence_id
+          WHERE cards.due IS NOT NULL && {not_trashed}"),
+        |(id, review_count, ease, interval, due): (String, i32, f32, Option<u64>, NaiveDateTime)| {
+            (Uuid::from_str(&id).unwrap(), (review_count, ease, interval, due))
+        })?)
+}
+
+/// Convert word cards to sentence cards, one for each of `sentences` in order. A sentence is only
+/// scheduled once every one of its words has been reviewed, and then takes the schedule of
+/// whichever word is due first, as a sentence is only as well known as its weakest word. Sentences
+/// with any unreviewed words stay new, so they still get learned.
+fn word_cards_to_sentence_cards(sentences: &[Sentence], sentence_words: &HashMap<Uuid, Vec<String>>,
+                                word_cards: Vec<(String, CardRow)>) -> Vec<Option<CardRow>> {
+    let word_cards = word_cards.into_iter().collect::<HashMap<String, CardRow>>();
+
+    sentences.iter()
+        .map(|sentence| {
+            let words = sentence_words.get(&sentence.id).filter(|words| !words.is_empty())?;
+            words.iter()
+                .map(|word| word_cards.get(word).copied())
+                .collect::<Option<Vec<CardRow>>>()?
+                .into_iter()
+                .min_by_key(|card| card.3)
+        })
+        .collect()
+}
+
+/// Convert sentence cards to word cards. Each word takes the schedule of the sentence it's in,
+/// and words in more than one sentence take whichever is due first, so nothing gets pushed back
+/// further than it was.
+fn sentence_cards_to_word_cards(sentence_cards: Vec<(Uuid, CardRow)>, sentence_words: &HashMap<Uuid, Vec<String>>)
+    -> Vec<(String, CardRow)>
+{
+    let mut word_cards: HashMap<String, CardRow> = HashMap::new();
+
+    for (id, card) in sentence_cards.into_iter() {
+        for word in sentence_words.get(&id).into_iter().flatten() {
+            let due = card.3;
+            word_cards.entry(word.clone())
+                .and_modify(|existing| if due < existing.3 { *existing = card })
+                .or_insert(card);
+        }
+    }
+
+    word_cards.into_iter().collect()
+}
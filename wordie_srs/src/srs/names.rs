@@ -0,0 +1,39 @@
+use mysql::{PooledConn, prelude::Queryable, params};
+
+use super::SrsResult;
+
+/// Create the table of words flagged as likely proper names. Flagged words get cards like any
+/// other word, but they're excluded from reviews (see `blacklist::NOT_EXCLUDED`) until the user
+/// rescues them.
+pub(crate) fn create_table(conn: &mut PooledConn) -> SrsResult<()> {
+    conn.query_drop(r"
+        CREATE TABLE IF NOT EXISTS name_flags (
+            word VARCHAR(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci NOT NULL,
+            rescued BOOL NOT NULL DEFAULT FALSE,
+            PRIMARY KEY (word)
+        )
+    ")?;
+
+    Ok(())
+}
+
+/// Flag words as likely names. Words that were already rescued stay rescued.
+pub(crate) fn flag(conn: &mut PooledConn, words: &[String]) -> SrsResult<()> {
+    conn.exec_batch("INSERT IGNORE INTO name_flags (word) VALUES (:word)",
+        words.iter().map(|word| params! { "word" => word.as_str() }))?;
+
+    Ok(())
+}
+
+/// Mark a flagged word as not actually a name, so it gets reviewed like any other word
+pub(crate) fn rescue(conn: &mut PooledConn, word: &str) -> SrsResult<()> {
+    conn.exec_drop("UPDATE name_flags SET rescued = TRUE WHERE word = :word",
+        params! { "word" => word })?;
+
+    Ok(())
+}
+
+/// Load the words that are flagged as names and haven't been rescued, in alphabetical order
+pub(crate) fn load_flagged(conn: &mut PooledConn) -> SrsResult<Vec<String>> {
+    Ok(conn.query("SELECT word FROM name_flags WHERE NOT rescued ORDER BY word")?)
+}
@@ -0,0 +1,32 @@
+use unicode_normalization::UnicodeNormalization;
+
+use super::tokenizer;
+
+/// Normalize a word before it becomes a card, so spellings that only differ in width or case
+/// share one. NFKC maps full width latin (ＡＢＣ) and half width katakana (ｶﾀｶﾅ) to their usual
+/// forms, and case is folded so sentence-initial capitals don't make a separate card.
+pub fn word(word: &str) -> String {
+    word.nfkc().collect::<String>().to_lowercase()
+}
+
+/// Normalize a reading like `word`, optionally converting katakana to hiragana so readings match
+/// whichever script the dictionary gives them in
+pub fn reading(reading: &str, to_hiragana: bool) -> String {
+    let reading = word(reading);
+
+    if to_hiragana {
+        tokenizer::katakana_to_hiragana(&reading)
+    }
+    else {
+        reading
+    }
+}
+
+/// Pair every word that isn't normalized yet with its normalized spelling, for bringing words
+/// stored before normalization in line
+pub fn unnormalized_words(words: &[String]) -> Vec<(String, String)> {
+    words.iter()
+        .map(|stored| (stored.clone(), word(stored)))
+        .filter(|(stored, normalized)| stored != normalized)
+        .collect()
+}
@@ -0,0 +1,102 @@
+use std::path::Path;
+
+use rusty_tesseract::{Args, Image};
+
+use super::SrsResult;
+use super::kanji;
+
+/// Extensions of the images that can be read with `recognize`
+pub const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "bmp", "gif", "tif", "tiff", "webp"];
+
+/// Whether a file looks like an image we can read text from
+pub fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// Read the text in an image, e.g. a manga panel or game screenshot, with tesseract. `language`
+/// is a tesseract language code, e.g. "jpn", or "jpn_vert" for vertical text. This takes a few
+/// seconds for a big screenshot, so it shouldn't be called from the ui thread.
+pub fn recognize(path: &Path, language: &str) -> SrsResult<String> {
+    log::info!("Reading text from {path:?} ({language})");
+
+    let image = Image::from_path(path)
+        .map_err(|e| format!("Failed to open {path:?}: {e}"))?;
+
+    let args = Args {
+        lang: language.to_string(),
+        ..Default::default()
+    };
+
+    let text = rusty_tesseract::image_to_string(&image, &args)
+        .map_err(|e| format!("Tesseract failed, is it installed? {e}"))?;
+
+    Ok(remove_cjk_spaces(&text))
+}
+
+/// Remove the spaces tesseract puts between Japanese and Chinese characters, keeping the ones
+/// between words in languages that use them
+fn remove_cjk_spaces(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+
+    // Spaces are held back until we know what comes after them
+    let mut last: Option<char> = None;
+    let mut spaces = 0;
+
+    for c in text.chars() {
+        if c == ' ' {
+            spaces += 1;
+            continue;
+        }
+
+        let between_cjk = is_cjk(c) && last.is_some_and(is_cjk);
+        if !between_cjk {
+            result.extend(std::iter::repeat_n(' ', spaces));
+        }
+
+        result.push(c);
+        last = Some(c);
+        spaces = 0;
+    }
+
+    result.extend(std::iter::repeat_n(' ', spaces));
+    result
+}
+
+/// Whether a character is written without spaces between words: kanji, kana, and CJK and
+/// fullwidth punctuation. Hangul isn't, as Korean puts spaces between words.
+fn is_cjk(c: char) -> bool {
+    kanji::is_kanji(c)
+        || matches!(c, '\u{3000}'..='\u{30FF}' | '\u{31F0}'..='\u{31FF}' | '\u{FF00}'..='\u{FFEF}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spaces_between_cjk_characters_are_removed() {
+        assert_eq!(remove_cjk_spaces("猫 が  好き です"), "猫が好きです");
+    }
+
+    #[test]
+    fn other_spaces_are_kept() {
+        assert_eq!(remove_cjk_spaces(" I like cats  "), " I like cats  ");
+        assert_eq!(remove_cjk_spaces("猫 cat 犬"), "猫 cat 犬");
+        assert_eq!(remove_cjk_spaces(" 猫 "), " 猫 ");
+    }
+
+    #[test]
+    fn spaces_between_other_non_ascii_words_are_kept() {
+        assert_eq!(remove_cjk_spaces("café été"), "café été");
+        assert_eq!(remove_cjk_spaces("Привет мир"), "Привет мир");
+        assert_eq!(remove_cjk_spaces("안녕 세상"), "안녕 세상");
+    }
+
+    #[test]
+    fn spaces_around_cjk_punctuation_are_removed() {
+        assert_eq!(remove_cjk_spaces("猫 、 犬 。"), "猫、犬。");
+        assert_eq!(remove_cjk_spaces("ネコ ！"), "ネコ！");
+    }
+}
@@ -0,0 +1,63 @@
+use serde::{Serialize, Deserialize};
+
+#[cfg(feature = "mysql")]
+pub(crate) use self::storage::*;
+
+/// The number of items in a page when none is given
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+
+/// A request for a page of results. Get the first page with `PageRequest::first`, then follow
+/// each page's `next` until it's None.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageRequest {
+    /// Where the page starts, frontends should pass back the cursor from `Page::next` as it is
+    pub cursor: usize,
+    /// The most items to return in the page
+    pub limit: usize,
+}
+
+impl PageRequest {
+    /// Request the first page of `limit` items
+    pub fn first(limit: usize) -> Self {
+        Self {
+            cursor: 0,
+            limit,
+        }
+    }
+}
+
+impl Default for PageRequest {
+    fn default() -> Self {
+        Self::first(DEFAULT_PAGE_SIZE)
+    }
+}
+
+/// A page of results from a listing API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// The request for the page after this one, or None if this is the last page
+    pub next: Option<PageRequest>,
+}
+
+impl<T> Page<T> {
+    /// A page with nothing in it
+    pub fn empty() -> Self {
+        Self {
+            items: Vec::new(),
+            next: None,
+        }
+    }
+
+    /// Convert the items in the page
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            next: self.next,
+        }
+    }
+}
+
+/// Adding paging to the algorithms' queries
+#[cfg(feature = "mysql")]
+mod storage;
@@ -0,0 +1,70 @@
+use std::str::FromStr;
+
+use mysql::{PooledConn, prelude::Queryable, params};
+use uuid::Uuid;
+
+use super::*;
+use crate::srs::{SrsResult, Sentence};
+use crate::srs::db;
+use crate::srs::sources::{self, SentenceSource, FROM_SOURCE};
+use crate::srs::flags::Flag;
+
+impl PageRequest {
+    /// The LIMIT and OFFSET for a query. One row more than the page holds is fetched so we can
+    /// tell whether there's another page after it, see `Page::from_rows`.
+    pub(crate) fn sql(&self) -> String {
+        format!("LIMIT {} OFFSET {}", self.limit + 1, self.cursor)
+    }
+}
+
+impl<T> Page<T> {
+    /// Make a page from rows queried with `PageRequest::sql`
+    pub(crate) fn from_rows(mut rows: Vec<T>, request: PageRequest) -> Self {
+        let next = if rows.len() > request.limit {
+            rows.truncate(request.limit);
+            Some(PageRequest {
+                cursor: request.cursor + request.limit,
+                limit: request.limit,
+            })
+        }
+        else {
+            None
+        };
+
+        Self {
+            items: rows,
+            next,
+        }
+    }
+}
+
+/// Get a page of the sentences not in the trash, optionally only the ones containing `query`,
+/// from `source` and with `flag`, in alphabetical order. Shared by all the algorithms.
+pub(crate) fn sentences(conn: &mut PooledConn, query: Option<&str>, source: Option<&SentenceSource>, flag: Option<Flag>,
+                        request: PageRequest)
+    -> SrsResult<Page<Sentence>>
+{
+    let (source_kind, source_name) = sources::source_params(source);
+
+    let rows = conn.exec_map(
+        format!(r"SELECT sentences.id, sentences.text
+          FROM sentences
+          WHERE sentences.deleted IS NULL
+             && (:pattern IS NULL || sentences.text LIKE :pattern)
+             && {FROM_SOURCE}
+             && (:flag IS NULL || sentences.id IN (SELECT sentence_id FROM sentence_flags WHERE flag = :flag))
+          ORDER BY sentences.text, sentences.id
+          {}", request.sql()),
+        params! {
+            "pattern" => query.map(db::contains_pattern),
+            "source_kind" => source_kind,
+            "source_name" => source_name,
+            "flag" => flag.map(|flag| flag.as_str()),
+        },
+        |(id, text): (String, String)| Sentence {
+            id: Uuid::from_str(&id).unwrap(),
+            text,
+        })?;
+
+    Ok(Page::from_rows(rows, request))
+}
@@ -0,0 +1,48 @@
+use std::path::Path;
+use std::process::Command;
+
+use super::SrsResult;
+
+/// Whether a file looks like a pdf
+pub fn is_pdf(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("pdf"))
+}
+
+/// Extract the text of each page in a pdf, as (page number, text), with pdftotext from poppler.
+/// Pages are numbered from 1, and `first_page` and `last_page` limit which pages are extracted.
+pub fn extract_pages(path: &Path, first_page: u32, last_page: Option<u32>) -> SrsResult<Vec<(u32, String)>> {
+    log::info!("Extracting text from {path:?} from page {first_page} to {last_page:?}");
+
+    let mut command = Command::new("pdftotext");
+    command.args(["-enc", "UTF-8", "-f", &first_page.to_string()]);
+    if let Some(last_page) = last_page {
+        command.args(["-l", &last_page.to_string()]);
+    }
+
+    let output = command
+        .arg(path)
+        .arg("-")
+        .output()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => "Importing pdfs needs pdftotext, which wasn't found. Install poppler \
+                                             (poppler-utils on most linux distributions) and make sure it's on the PATH.".to_string(),
+            _ => format!("Failed to run pdftotext: {e}"),
+        })?;
+
+    if !output.status.success() {
+        return Err(format!("pdftotext failed: {}", String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+
+    // Pages are separated by form feeds, with one after the last page too
+    let text = String::from_utf8_lossy(&output.stdout);
+    let mut pages = text.split('\x0c')
+        .enumerate()
+        .map(|(i, page)| (first_page + i as u32, page.to_string()))
+        .collect::<Vec<(u32, String)>>();
+
+    if pages.last().is_some_and(|(_, page)| page.trim().is_empty()) {
+        pages.pop();
+    }
+
+    Ok(pages)
+}
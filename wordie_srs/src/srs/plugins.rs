@@ -0,0 +1,92 @@
+use crate::srs::{Difficulty, Review, Sentence, SrsResult};
+use crate::srs::daily_stats::DailyStats;
+
+/// An extension that hooks into wordie to do something extra with sentences and reviews, e.g.
+/// sending answers somewhere else or showing extra information on cards. Every hook does nothing
+/// by default, so plugins only need to implement the ones they care about.
+pub trait Plugin {
+    /// The name of the plugin, for logging
+    fn name(&self) -> &str;
+
+    /// Called after sentences have been imported
+    fn on_sentences_imported(&mut self, _sentences: &[Sentence]) -> SrsResult<()> {
+        Ok(())
+    }
+
+    /// Called after a review has been answered
+    fn on_review_answered(&mut self, _review: &Review, _difficulty: Difficulty) -> SrsResult<()> {
+        Ok(())
+    }
+
+    /// Called the first time the daily goals are met each day, with the day's stats
+    fn on_daily_goals_met(&mut self, _stats: &DailyStats) -> SrsResult<()> {
+        Ok(())
+    }
+
+    /// Extra content to show under a review's card, if there is any
+    fn card_content(&self, _review: &Review) -> SrsResult<Option<String>> {
+        Ok(None)
+    }
+}
+
+/// The registered plugins. A failing plugin is logged and skipped, so one broken plugin can't
+/// stop imports or reviews.
+#[derive(Default)]
+pub struct Plugins {
+    plugins: Vec<Box<dyn Plugin>>,
+}
+
+impl Plugins {
+    pub fn register(&mut self, plugin: Box<dyn Plugin>) {
+        log::info!("Registered plugin {}", plugin.name());
+        self.plugins.push(plugin);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.plugins.is_empty()
+    }
+
+    /// Run every plugin's import hook
+    pub fn sentences_imported(&mut self, sentences: &[Sentence]) {
+        if sentences.is_empty() {
+            return;
+        }
+
+        for plugin in self.plugins.iter_mut() {
+            if let Err(err) = plugin.on_sentences_imported(sentences) {
+                log::error!("Plugin {} failed to handle imported sentences: {err}", plugin.name());
+            }
+        }
+    }
+
+    /// Run every plugin's review hook
+    pub fn review_answered(&mut self, review: &Review, difficulty: Difficulty) {
+        for plugin in self.plugins.iter_mut() {
+            if let Err(err) = plugin.on_review_answered(review, difficulty) {
+                log::error!("Plugin {} failed to handle answered review: {err}", plugin.name());
+            }
+        }
+    }
+
+    /// Run every plugin's daily goals hook
+    pub fn daily_goals_met(&mut self, stats: &DailyStats) {
+        for plugin in self.plugins.iter_mut() {
+            if let Err(err) = plugin.on_daily_goals_met(stats) {
+                log::error!("Plugin {} failed to handle met daily goals: {err}", plugin.name());
+            }
+        }
+    }
+
+    /// Get the extra card content from every plugin that has some for `review`
+    pub fn card_content(&self, review: &Review) -> Vec<String> {
+        self.plugins.iter()
+            .filter_map(|plugin| match plugin.card_content(review) {
+                Ok(content) => content,
+                Err(err) => {
+                    log::error!("Plugin {} failed to get card content: {err}", plugin.name());
+                    None
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,26 @@
+#[cfg(feature = "mysql")]
+pub(crate) use self::storage::*;
+
+/// What changed when the cards were rebuilt from the sentences
+#[derive(Debug, Clone, Default)]
+pub struct RebuildReport {
+    /// Sentences that were split into different words than before
+    pub sentences_changed: usize,
+    /// Cards created for words or sentences that were missing one
+    pub cards_added: usize,
+    /// Words that aren't in any sentence anymore and had never been studied
+    pub words_removed: usize,
+    /// Words whose reading changed, e.g. after a dictionary upgrade
+    pub readings_updated: usize,
+}
+
+impl RebuildReport {
+    /// Whether the rebuild changed anything
+    pub fn is_unchanged(&self) -> bool {
+        self.sentences_changed == 0 && self.cards_added == 0 && self.words_removed == 0 && self.readings_updated == 0
+    }
+}
+
+/// Rebuilding the tables the algorithms keep
+#[cfg(feature = "mysql")]
+mod storage;
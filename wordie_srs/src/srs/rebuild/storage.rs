@@ -0,0 +1,26 @@
+use mysql::{PooledConn, prelude::Queryable, params};
+
+use super::*;
+use crate::srs::SrsResult;
+use crate::srs::tokenizer;
+
+/// Look up the reading of every word again with the current tokenizer, returning how many changed,
+/// see `tokenizer::reading`
+pub(crate) fn update_readings(conn: &mut PooledConn, to_hiragana: bool) -> SrsResult<usize> {
+    let words: Vec<(String, String, Option<String>)> = conn.query("SELECT id, word, reading FROM words")?;
+
+    let changed = words.into_iter()
+        .filter_map(|(id, word, reading)| {
+            let new_reading = tokenizer::reading(&word, to_hiragana);
+            (new_reading != reading).then_some((id, new_reading))
+        })
+        .collect::<Vec<(String, Option<String>)>>();
+
+    conn.exec_batch("UPDATE words SET reading = :reading WHERE id = :id",
+        changed.iter().map(|(id, reading)| params! {
+            "id" => id,
+            "reading" => reading,
+        }))?;
+
+    Ok(changed.len())
+}
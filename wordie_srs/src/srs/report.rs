@@ -0,0 +1,214 @@
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use chrono::{NaiveDate, Utc};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use super::SrsResult;
+use super::retention::RetentionStats;
+use super::stats_export::{StatsExport, ForecastDay};
+
+/// The number of days a weekly report covers, looking back for the study stats and ahead for
+/// the upcoming reviews
+pub const REPORT_DAYS: usize = 7;
+
+/// An smtp server to email reports through, set in the config file, e.g.
+/// `"report_email": { "smtp_url": "smtps://smtp.example.com:465", "username": "me@example.com",
+/// "password": "...", "from": "me@example.com", "to": "me@example.com" }`
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReportEmail {
+    pub smtp_url: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    pub from: String,
+    pub to: String,
+}
+
+/// The formats a report can be written in
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ReportFormat {
+    Markdown,
+    Html,
+}
+
+impl ReportFormat {
+    /// Pick the format from a file's extension, or None if it isn't one we write
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "md" | "markdown" => Some(ReportFormat::Markdown),
+            "html" | "htm" => Some(ReportFormat::Html),
+            _ => None,
+        }
+    }
+}
+
+/// A summary of the last week of study and the week ahead
+#[derive(Debug, Clone)]
+pub struct WeeklyReport {
+    /// The first and last days the report covers
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub cards_reviewed: i32,
+    /// New cards learned, i.e. new words
+    pub cards_learned: i32,
+    /// The days there were any reviews on
+    pub days_studied: usize,
+    pub retention: RetentionStats,
+    /// The current streak of days meeting the daily goals
+    pub streak: i32,
+    /// The cards due on each of the coming days, starting today
+    pub upcoming: Vec<ForecastDay>,
+}
+
+impl WeeklyReport {
+    /// Summarize the last `REPORT_DAYS` days of a stats export, which should end today
+    pub fn new(export: &StatsExport, streak: i32, today: NaiveDate) -> Self {
+        let days = &export.days[export.days.len().saturating_sub(REPORT_DAYS)..];
+
+        Self {
+            start: days.first().map_or(today, |day| day.day),
+            end: today,
+            cards_reviewed: days.iter().map(|day| day.cards_reviewed).sum(),
+            cards_learned: days.iter().map(|day| day.cards_learned).sum(),
+            days_studied: days.iter().filter(|day| day.cards_reviewed > 0).count(),
+            retention: RetentionStats {
+                reviews: days.iter().map(|day| day.retention_reviews).sum(),
+                passed: days.iter().map(|day| day.retention_passed).sum(),
+            },
+            streak,
+            upcoming: export.forecast.iter().take(REPORT_DAYS).cloned().collect(),
+        }
+    }
+
+    pub fn title(&self) -> String {
+        format!("Wordie weekly report, {} to {}", self.start.format("%d %b"), self.end.format("%d %b %Y"))
+    }
+
+    fn retention_text(&self) -> String {
+        match self.retention.rate() {
+            Some(rate) => format!("{:.1}% over {} reviews", rate * 100.0, self.retention.reviews),
+            None => "no reviews of mature cards".to_string(),
+        }
+    }
+
+    /// The summary lines shared by both formats
+    fn summary(&self) -> Vec<String> {
+        vec![
+            format!("Reviews: {}", self.cards_reviewed),
+            format!("New words: {}", self.cards_learned),
+            format!("Days studied: {}/{REPORT_DAYS}", self.days_studied),
+            format!("Retention: {}", self.retention_text()),
+            format!("Streak: {} days", self.streak),
+        ]
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("# {}\n\n", self.title());
+
+        for line in self.summary() {
+            out.push_str(&format!("- {line}\n"));
+        }
+
+        out.push_str("\n## Upcoming reviews\n\n| Day | Due cards |\n| --- | --- |\n");
+        for day in self.upcoming.iter() {
+            out.push_str(&format!("| {} | {} |\n", day.day.format("%a %d %b"), day.due_cards));
+        }
+
+        out
+    }
+
+    pub fn to_html(&self) -> String {
+        let mut out = format!("<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>{0}</title></head>\n<body>\n<h1>{0}</h1>\n<ul>\n",
+                              self.title());
+
+        for line in self.summary() {
+            out.push_str(&format!("<li>{line}</li>\n"));
+        }
+
+        out.push_str("</ul>\n<h2>Upcoming reviews</h2>\n<table>\n<tr><th>Day</th><th>Due cards</th></tr>\n");
+        for day in self.upcoming.iter() {
+            out.push_str(&format!("<tr><td>{}</td><td>{}</td></tr>\n", day.day.format("%a %d %b"), day.due_cards));
+        }
+
+        out.push_str("</table>\n</body>\n</html>\n");
+
+        out
+    }
+
+    /// Write the report to a file in `format`
+    pub fn write(&self, path: &Path, format: ReportFormat) -> SrsResult<()> {
+        log::info!("Writing weekly report to {path:?}");
+
+        let text = match format {
+            ReportFormat::Markdown => self.to_markdown(),
+            ReportFormat::Html => self.to_html(),
+        };
+
+        std::fs::write(path, text)?;
+
+        Ok(())
+    }
+
+    /// Email the report as html. Mail is sent with curl, so we don't need an smtp client with tls
+    /// support. The login is passed to curl as a config on stdin rather than as an argument, so it
+    /// doesn't show up in the process list, which means the message goes through a temp file.
+    pub fn send(&self, email: &ReportEmail) -> SrsResult<()> {
+        log::info!("Emailing weekly report to {}", email.to);
+
+        let message_path = std::env::temp_dir().join(format!("wordie_report_{}.eml", Uuid::new_v4()));
+        std::fs::write(&message_path, self.to_email(email))?;
+
+        let result = Self::run_curl(email, &message_path);
+        std::fs::remove_file(&message_path).ok();
+
+        result
+    }
+
+    /// The report as an email message with headers, ready to send
+    fn to_email(&self, email: &ReportEmail) -> String {
+        // The message id needs a domain, the sender's is as good as any
+        let domain = email.from.rsplit('@').next().unwrap_or("localhost").trim_end_matches('>');
+
+        format!("From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nMessage-ID: <{}@{domain}>\r\nMIME-Version: 1.0\r\n\
+                 Content-Type: text/html; charset=utf-8\r\n\r\n{}",
+                email.from, email.to, self.title(), Utc::now().to_rfc2822(), Uuid::new_v4(), self.to_html())
+    }
+
+    /// Send the message in `message_path` with curl
+    fn run_curl(email: &ReportEmail, message_path: &Path) -> SrsResult<()> {
+        let mut command = Command::new("curl");
+        command.args(["--silent", "--show-error", "--ssl-reqd", "--url", &email.smtp_url,
+                      "--mail-from", &email.from, "--mail-rcpt", &email.to, "--config", "-"])
+            .arg("--upload-file")
+            .arg(message_path);
+
+        // Quoted and escaped as curl config strings are
+        let config = match email.username.as_ref() {
+            Some(username) => {
+                let user = format!("{username}:{}", email.password.as_deref().unwrap_or(""));
+                format!("user = \"{}\"\n", user.replace('\\', "\\\\").replace('"', "\\\""))
+            },
+            None => String::new(),
+        };
+
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to run curl, is it installed? {e}"))?;
+
+        child.stdin.take().ok_or("Failed to open curl's stdin")?.write_all(config.as_bytes())?;
+
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(format!("Failed to send report email: {}", String::from_utf8_lossy(&output.stderr).trim()).into());
+        }
+
+        Ok(())
+    }
+}
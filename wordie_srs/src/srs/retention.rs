@@ -0,0 +1,52 @@
+use serde::Deserialize;
+
+#[cfg(feature = "mysql")]
+pub(crate) use self::storage::*;
+
+/// The range the interval modifier is kept in
+const MIN_INTERVAL_MODIFIER: f64 = 0.5;
+const MAX_INTERVAL_MODIFIER: f64 = 2.0;
+
+/// How many reviews of graduated cards were remembered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetentionStats {
+    pub reviews: i32,
+    /// Reviews answered anything but Again
+    pub passed: i32,
+}
+
+impl RetentionStats {
+    /// The fraction of reviews that were remembered, or None if there weren't any
+    pub fn rate(&self) -> Option<f64> {
+        if self.reviews > 0 {
+            Some(self.passed as f64 / self.reviews as f64)
+        }
+        else {
+            None
+        }
+    }
+}
+
+/// A target retention for the sentences from one deck (or any other source, e.g. a subtitle file),
+/// e.g. `{ "deck": "core6k.wordiedeck", "target_retention": 0.85 }`. The deck's cards get their own
+/// interval modifier, adjusted from just their reviews.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct DeckRetention {
+    /// The name of the source the sentences came from, usually the file they were imported from
+    pub deck: String,
+    /// The fraction of the deck's reviews to aim to remember
+    pub target_retention: f64,
+}
+
+/// The interval modifier that would bring `measured` retention to `target`, given the current
+/// one. This is the guidance from the anki manual: intervals scale with log(target) / log(measured).
+pub fn adjusted_modifier(current: f64, measured: f64, target: f64) -> f64 {
+    let measured = measured.clamp(0.01, 0.999);
+    let target = target.clamp(0.01, 0.999);
+
+    (current * target.ln() / measured.ln()).clamp(MIN_INTERVAL_MODIFIER, MAX_INTERVAL_MODIFIER)
+}
+
+/// Reading the review history to measure retention
+#[cfg(feature = "mysql")]
+mod storage;
@@ -0,0 +1,141 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+use mysql::{PooledConn, prelude::Queryable, params};
+
+use super::*;
+use crate::srs::SrsResult;
+
+/// How often the interval modifier is adjusted in auto ease mode
+const ADJUST_INTERVAL_DAYS: i64 = 7;
+
+/// The fewest reviews to adjust the interval modifier from, so a few bad days don't swing it
+const MIN_REVIEWS: i32 = 100;
+
+/// Reviews of cards shorter than this are learning steps, which don't say much about retention
+const MIN_REVIEW_INTERVAL_SECS: i64 = 24 * 60 * 60;
+
+/// Create the scheduler settings tables. The main one only ever has one row, with the interval
+/// modifier applied to graduated cards and when it was last adjusted, and the deck one has the same
+/// for each deck with its own target retention.
+pub(crate) fn create_table(conn: &mut PooledConn) -> SrsResult<()> {
+    conn.query_drop(r"
+        CREATE TABLE IF NOT EXISTS scheduler_settings (
+            id INT NOT NULL,
+            interval_modifier DOUBLE NOT NULL,
+            adjusted DATETIME,
+            PRIMARY KEY (id)
+        )
+    ")?;
+
+    conn.query_drop("INSERT IGNORE INTO scheduler_settings (id, interval_modifier) VALUES (1, 1.0)")?;
+
+    conn.query_drop(r"
+        CREATE TABLE IF NOT EXISTS deck_scheduler_settings (
+            deck VARCHAR(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci NOT NULL,
+            interval_modifier DOUBLE NOT NULL,
+            adjusted DATETIME,
+            PRIMARY KEY (deck)
+        )
+    ")?;
+
+    Ok(())
+}
+
+/// Measure retention from the reviews of graduated cards since `since`, only counting reviews of
+/// sentences from `deck` if it's given. Reviews from before they were linked to their sentences
+/// aren't in any deck.
+pub(crate) fn measure(conn: &mut PooledConn, since: NaiveDateTime, deck: Option<&str>) -> SrsResult<RetentionStats> {
+    let stats = conn.exec_first(
+        r"SELECT CAST(COUNT(*) AS SIGNED), CAST(COALESCE(SUM(reviews.difficulty != 0), 0) AS SIGNED)
+          FROM reviews
+          LEFT JOIN sentence_sources ON sentence_sources.sentence_id = reviews.sentence_id
+          WHERE reviews.difficulty IS NOT NULL
+             && reviews.last_interval_secs >= :min_interval_secs
+             && reviews.review_date >= :since
+             && (:deck IS NULL || sentence_sources.name = :deck)",
+        params! {
+            "min_interval_secs" => MIN_REVIEW_INTERVAL_SECS,
+            "since" => since,
+            "deck" => deck,
+        })?
+        .map(|(reviews, passed)| RetentionStats { reviews, passed });
+
+    Ok(stats.unwrap_or_default())
+}
+
+/// Measure retention for each local day from the reviews of graduated cards since `since`, oldest
+/// first. Days without any of those reviews are left out.
+pub(crate) fn measure_daily(conn: &mut PooledConn, since: NaiveDateTime) -> SrsResult<Vec<(NaiveDate, RetentionStats)>> {
+    let mut days: BTreeMap<NaiveDate, RetentionStats> = BTreeMap::new();
+
+    // Reviews are stored in utc, so they're split into days here rather than in the query
+    conn.exec_map(
+        r"SELECT review_date, difficulty != 0
+          FROM reviews
+          WHERE difficulty IS NOT NULL
+             && last_interval_secs >= :min_interval_secs
+             && review_date >= :since",
+        params! {
+            "min_interval_secs" => MIN_REVIEW_INTERVAL_SECS,
+            "since" => since,
+        },
+        |(review_date, passed): (NaiveDateTime, bool)| {
+            let day = Local.from_utc_datetime(&review_date).naive_local().date();
+            let stats = days.entry(day).or_default();
+            stats.reviews += 1;
+            stats.passed += passed as i32;
+        })?;
+
+    Ok(days.into_iter().collect())
+}
+
+/// Get the interval modifier for `deck`, or the whole collection if None, first adjusting it
+/// towards `target_retention` if it hasn't been for `ADJUST_INTERVAL_DAYS` and there have been
+/// enough reviews since
+pub(crate) fn interval_modifier(conn: &mut PooledConn, deck: Option<&str>, target_retention: f64, now: DateTime<Local>)
+    -> SrsResult<f64>
+{
+    let settings: Option<(f64, Option<NaiveDateTime>)> = match deck {
+        Some(deck) => conn.exec_first("SELECT interval_modifier, adjusted FROM deck_scheduler_settings WHERE deck = :deck",
+            params! { "deck" => deck })?,
+        None => conn.query_first("SELECT interval_modifier, adjusted FROM scheduler_settings WHERE id = 1")?,
+    };
+    let (modifier, adjusted) = settings.unwrap_or((1.0, None));
+
+    let now = now.naive_utc();
+    let since = now - chrono::Duration::days(ADJUST_INTERVAL_DAYS);
+
+    if adjusted.is_some_and(|adjusted| adjusted > since) {
+        return Ok(modifier);
+    }
+
+    let stats = measure(conn, adjusted.unwrap_or(since), deck)?;
+    let measured = match stats.rate() {
+        Some(measured) if stats.reviews >= MIN_REVIEWS => measured,
+        _ => return Ok(modifier),
+    };
+
+    let new_modifier = adjusted_modifier(modifier, measured, target_retention);
+    log::info!("Retention{} was {:.1}% over {} reviews, adjusting interval modifier from {modifier:.2} to {new_modifier:.2}",
+               deck.map(|deck| format!(" for {deck}")).unwrap_or_default(), measured * 100.0, stats.reviews);
+
+    match deck {
+        Some(deck) => conn.exec_drop(
+            r"INSERT INTO deck_scheduler_settings (deck, interval_modifier, adjusted)
+              VALUES (:deck, :modifier, :adjusted)
+              ON DUPLICATE KEY UPDATE interval_modifier = :modifier, adjusted = :adjusted",
+            params! {
+                "deck" => deck,
+                "modifier" => new_modifier,
+                "adjusted" => now,
+            })?,
+        None => conn.exec_drop("UPDATE scheduler_settings SET interval_modifier = :modifier, adjusted = :adjusted WHERE id = 1",
+            params! {
+                "modifier" => new_modifier,
+                "adjusted" => now,
+            })?,
+    }
+
+    Ok(new_modifier)
+}
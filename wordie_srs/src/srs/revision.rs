@@ -0,0 +1,21 @@
+use chrono::{NaiveDate, NaiveDateTime};
+
+#[cfg(feature = "mysql")]
+pub(crate) use self::storage::*;
+
+/// A point in the history of a collection, for telling whether its counts might have changed
+/// since they were last fetched
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CountsRevision {
+    /// Bumped by everything that changes the cards, e.g. adding sentences or reviewing
+    pub revision: i64,
+    /// The day the counts were for, as cards become due when the day changes without any writes
+    pub day: NaiveDate,
+    /// When the next card in the learning queue becomes due, in utc, as that changes the counts
+    /// without any writes too
+    pub next_learning_due: Option<NaiveDateTime>,
+}
+
+/// The revision table, kept in the mysql database
+#[cfg(feature = "mysql")]
+mod storage;
@@ -0,0 +1,67 @@
+use chrono::{DateTime, Local};
+use mysql::{PooledConn, prelude::Queryable, params};
+
+use super::*;
+use crate::srs::{SrsResult, CollectionStats};
+use crate::srs::scheduler::{IN_LEARNING_QUEUE, LEARNING_QUEUE_INTERVAL};
+
+/// Create the revision table, shared by all the algorithms. It only ever has one row.
+pub(crate) fn create_table(conn: &mut PooledConn) -> SrsResult<()> {
+    conn.query_drop(r"
+        CREATE TABLE IF NOT EXISTS collection_revision (
+            id INT NOT NULL,
+            revision BIGINT NOT NULL,
+            PRIMARY KEY (id)
+        )
+    ")?;
+
+    conn.query_drop("INSERT IGNORE INTO collection_revision (id, revision) VALUES (1, 0)")?;
+
+    Ok(())
+}
+
+/// Record that the collection has changed. Call this after the change, so anything polling in
+/// between sees the old revision and polls again.
+pub(crate) fn bump(conn: &mut PooledConn) -> SrsResult<()> {
+    conn.query_drop("UPDATE collection_revision SET revision = revision + 1 WHERE id = 1")?;
+
+    Ok(())
+}
+
+/// Get the current revision of the collection
+pub(crate) fn current(conn: &mut PooledConn) -> SrsResult<i64> {
+    Ok(conn.query_first("SELECT revision FROM collection_revision WHERE id = 1")?
+        .unwrap_or(0))
+}
+
+/// Get when the next card in the learning queue after `now` becomes due, in utc
+fn next_learning_due(conn: &mut PooledConn, now: DateTime<Local>) -> SrsResult<Option<NaiveDateTime>> {
+    let due: Option<Option<NaiveDateTime>> = conn.exec_first(
+        format!("SELECT MIN(cards.due) FROM cards WHERE cards.due IS NOT NULL && {IN_LEARNING_QUEUE} && cards.due > :now"),
+        params! {
+            "now" => now.naive_utc(),
+            "learning_queue_secs" => LEARNING_QUEUE_INTERVAL.as_secs(),
+        })?;
+
+    Ok(due.flatten())
+}
+
+/// Get the collection stats from `stats` if the collection has changed since `since`, or None if
+/// it hasn't so the caller can keep the counts it already has. Besides writes, the counts change
+/// when the day changes and when a card in the learning queue becomes due, both as of `now`.
+pub(crate) fn counts_if_changed<F>(conn: &mut PooledConn, since: Option<CountsRevision>, now: DateTime<Local>, stats: F)
+    -> SrsResult<Option<(CollectionStats, CountsRevision)>>
+    where F: FnOnce() -> SrsResult<CollectionStats>
+{
+    let latest = CountsRevision {
+        revision: current(conn)?,
+        day: crate::srs::end_of_day(now).naive_local().date(),
+        next_learning_due: next_learning_due(conn, now)?,
+    };
+
+    if since == Some(latest) {
+        return Ok(None);
+    }
+
+    Ok(Some((stats()?, latest)))
+}
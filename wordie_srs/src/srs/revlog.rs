@@ -0,0 +1,58 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use super::SrsResult;
+
+#[cfg(feature = "mysql")]
+pub(crate) use self::storage::*;
+
+/// The default file name for review log exports
+pub const REVLOG_FILE: &str = "revlog.csv";
+
+/// The columns of anki's revlog table, in order
+const REVLOG_HEADER: &str = "id,cid,usn,ease,ivl,lastIvl,factor,time,type";
+
+/// A review in the form of a row of anki's revlog table, so tools made for anki's review
+/// history, e.g. the fsrs optimizer, can read ours
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RevlogEntry {
+    /// When the review happened in epoch milliseconds, unique across the log
+    pub id: i64,
+    /// A number standing in for the card, the same for every review of a word
+    pub card_id: i64,
+    /// The answer, from 1 for Again to 4 for Easy
+    pub ease: i32,
+    /// The interval after the review, in days if positive or seconds if negative
+    pub interval: i64,
+    /// The interval before the review, in the same units
+    pub last_interval: i64,
+    /// The card's ease in permille. Only the current ease is kept, so this is 0 for learning.
+    pub factor: i32,
+    /// How long the review took in milliseconds, which we don't record
+    pub time: i64,
+    pub review_type: i32,
+}
+
+/// Write revlog entries to a csv file with the columns of anki's revlog table, which can also be
+/// loaded into sqlite with `.import`
+pub fn write_csv(path: &Path, entries: &[RevlogEntry]) -> SrsResult<()> {
+    log::info!("Writing {} reviews to {path:?}", entries.len());
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "{REVLOG_HEADER}")?;
+
+    for entry in entries.iter() {
+        // The update sequence number is -1 for changes that haven't been synced
+        writeln!(writer, "{},{},-1,{},{},{},{},{},{}", entry.id, entry.card_id, entry.ease, entry.interval,
+                 entry.last_interval, entry.factor, entry.time, entry.review_type)?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Reading and writing the review log table
+#[cfg(feature = "mysql")]
+mod storage;
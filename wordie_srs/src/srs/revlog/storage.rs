@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDateTime;
+use mysql::{PooledConn, prelude::Queryable};
+
+use super::*;
+use crate::srs::hash;
+
+/// Intervals shorter than this are learning steps, which anki stores in seconds rather than days
+const DAY_SECS: i64 = 24 * 60 * 60;
+
+/// Anki's review types
+const TYPE_LEARN: i32 = 0;
+
+const TYPE_REVIEW: i32 = 1;
+
+const TYPE_RELEARN: i32 = 2;
+
+/// Load the review history as revlog entries, oldest first. Reviews from before answers were
+/// recorded are left out, as they'd be no use without them.
+pub(crate) fn load(conn: &mut PooledConn) -> SrsResult<Vec<RevlogEntry>> {
+    let reviews: Vec<(String, NaiveDateTime, i32, Option<i64>)> = conn.query(
+        r"SELECT word_id, review_date, difficulty, last_interval_secs
+          FROM reviews
+          WHERE difficulty IS NOT NULL
+          ORDER BY word_id, review_date")?;
+
+    // The interval and ease each word's cards have now, for the interval after its latest review
+    let mut cards: HashMap<String, (Option<i64>, f32)> = HashMap::new();
+    conn.query_map("SELECT word_id, interval_secs, ease FROM cards",
+        |(word_id, interval, ease): (String, Option<u64>, f32)| {
+            let card = cards.entry(word_id).or_insert((None, 0.0));
+            card.0 = card.0.max(interval.map(|interval| interval as i64));
+            card.1 = card.1.max(ease);
+        })?;
+
+    let mut entries = Vec::new();
+    let mut graduated = false;
+
+    for (i, (word_id, review_date, difficulty, last_interval)) in reviews.iter().enumerate() {
+        let first_review = i == 0 || reviews[i - 1].0 != *word_id;
+        if first_review {
+            graduated = false;
+        }
+
+        let (current_interval, ease) = cards.get(word_id).copied().unwrap_or((None, 0.0));
+        let next_interval = match reviews.get(i + 1) {
+            Some((next_word_id, _, _, next_last_interval)) if next_word_id == word_id => *next_last_interval,
+            _ => current_interval,
+        };
+
+        let review_type = match last_interval {
+            Some(secs) if *secs >= DAY_SECS => TYPE_REVIEW,
+            _ if graduated => TYPE_RELEARN,
+            _ => TYPE_LEARN,
+        };
+        graduated |= last_interval.is_some_and(|secs| secs >= DAY_SECS);
+
+        entries.push(RevlogEntry {
+            id: review_date.timestamp_millis(),
+            card_id: card_id(word_id),
+            ease: difficulty + 1,
+            interval: anki_interval(next_interval),
+            last_interval: anki_interval(*last_interval),
+            factor: if review_type == TYPE_REVIEW { (ease * 1000.0).round() as i32 } else { 0 },
+            time: 0,
+            review_type,
+        });
+    }
+
+    // Reviewing a sentence reviews all its words at once, so nudge ids apart to keep them unique
+    entries.sort_by_key(|entry| (entry.id, entry.card_id));
+    for i in 1..entries.len() {
+        if entries[i].id <= entries[i - 1].id {
+            entries[i].id = entries[i - 1].id + 1;
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Convert an interval in seconds to anki's form, days if it's at least a day or negative
+/// seconds if not
+fn anki_interval(secs: Option<i64>) -> i64 {
+    match secs {
+        Some(secs) if secs >= DAY_SECS => secs / DAY_SECS,
+        Some(secs) => -secs,
+        None => 0,
+    }
+}
+
+/// A stable positive number for a word id, as anki's card ids are numbers
+fn card_id(word_id: &str) -> i64 {
+    let hash = hash::fnv1a(word_id.as_bytes());
+
+    // Keep it within the integers a double can hold, for tools that read it as a float
+    (hash >> 11) as i64
+}
@@ -0,0 +1,557 @@
+use std::time::Duration;
+#[cfg(feature = "mysql")]
+use std::collections::VecDeque;
+
+use chrono::{DateTime, Local, NaiveDateTime, Utc};
+use lazy_static::lazy_static;
+#[cfg(feature = "mysql")]
+use uuid::Uuid;
+
+use super::{SrsResult, Difficulty};
+use super::retention::DeckRetention;
+#[cfg(feature = "mysql")]
+use super::hash;
+
+#[cfg(feature = "mysql")]
+pub(crate) use self::storage::*;
+
+lazy_static! {
+    /// The initial intervals for new cards
+    pub(crate) static ref INITIAL_INTERVALS: [Duration; 3] = [
+        Duration::from_secs(1 * 60),
+        Duration::from_secs(10 * 60),
+        Duration::from_secs(24 * 60 * 60),
+    ];
+}
+
+/// The default ease
+pub(crate) const DEFAULT_EASE: f32 = 2.5;
+
+/// The minimum ease
+pub(crate) const MINIMUM_EASE: f32 = 1.3;
+
+/// Cards with an interval shorter than this are in the intra-day learning queue. They're only due
+/// once their due time has actually passed, rather than any time on the day they're due, and then
+/// they're reviewed ahead of new cards, so a card answered Again comes back in the same session.
+pub(crate) const LEARNING_QUEUE_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The answer buttons shown for reviews. Smaller sets are mapped onto the same difficulties, so
+/// cards are scheduled the same way whichever set they were answered with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AnswerButtons {
+    /// Fail and Pass, answered as Again and Good
+    PassFail,
+    /// Again, Good and Easy, like older versions of anki
+    Three,
+    /// Again, Hard, Good and Easy
+    #[default]
+    Four,
+}
+
+impl AnswerButtons {
+    /// Get a button set from its number of buttons
+    pub fn from_count(count: usize) -> Option<Self> {
+        match count {
+            2 => Some(AnswerButtons::PassFail),
+            3 => Some(AnswerButtons::Three),
+            4 => Some(AnswerButtons::Four),
+            _ => None,
+        }
+    }
+
+    /// The number of buttons in the set
+    pub fn count(&self) -> usize {
+        self.difficulties().len()
+    }
+
+    /// The difficulty each button answers with, from left to right
+    pub fn difficulties(&self) -> &'static [Difficulty] {
+        match self {
+            AnswerButtons::PassFail => &[Difficulty::Again, Difficulty::Good],
+            AnswerButtons::Three => &[Difficulty::Again, Difficulty::Good, Difficulty::Easy],
+            AnswerButtons::Four => &[Difficulty::Again, Difficulty::Hard, Difficulty::Good, Difficulty::Easy],
+        }
+    }
+
+    /// The button in the set that's closest to a difficulty, e.g. for a suggested answer. Hard
+    /// and Easy are both passes, so they fall back to Good.
+    pub fn nearest(&self, difficulty: Difficulty) -> Difficulty {
+        if self.difficulties().contains(&difficulty) {
+            difficulty
+        }
+        else {
+            Difficulty::Good
+        }
+    }
+
+    /// The label for the button that answers with a difficulty
+    pub fn label(&self, difficulty: Difficulty) -> &'static str {
+        match (self, difficulty) {
+            (AnswerButtons::PassFail, Difficulty::Again) => "Fail",
+            (AnswerButtons::PassFail, _) => "Pass",
+            (_, Difficulty::Again) => "Again",
+            (_, Difficulty::Hard) => "Hard",
+            (_, Difficulty::Good) => "Good",
+            (_, Difficulty::Easy) => "Easy",
+        }
+    }
+}
+
+/// Shuffle cards due today by a hash of the seed and each one's id. The same seed always gives the
+/// same order, and answering a card doesn't change the order of the rest, so a session's order
+/// doesn't jump around as cards are answered.
+#[cfg(feature = "mysql")]
+pub(crate) fn shuffle_by_seed<T>(items: &mut [T], seed: u32, id: impl Fn(&T) -> Uuid) {
+    items.sort_by_cached_key(|item| shuffle_key(seed, &id(item)));
+}
+
+/// Hash a seed and an id with FNV-1a, which unlike the std hasher is the same in every build, so a
+/// restored session keeps its order
+#[cfg(feature = "mysql")]
+fn shuffle_key(seed: u32, id: &Uuid) -> u64 {
+    let mut bytes = seed.to_le_bytes().to_vec();
+    bytes.extend_from_slice(id.as_bytes());
+    let hash = hash::fnv1a(&bytes);
+
+    // FNV barely mixes the last bytes into the high bits, so finish with splitmix64's mixer
+    let hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    let hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d049bb133111eb);
+    hash ^ (hash >> 31)
+}
+
+/// Reorder new sentences so short and long ones take turns, starting with the opposite of
+/// `last_long`, whether the last new sentence learned was long. Candidates are given in order with
+/// their number of unknown words and whether they're long, and only swap places with candidates
+/// with as many unknown words, so the most i+1 sentences still come first.
+#[cfg(feature = "mysql")]
+pub(crate) fn alternate_lengths<T>(candidates: Vec<(T, i32, bool)>, mut last_long: Option<bool>) -> Vec<T> {
+    let mut result = Vec::with_capacity(candidates.len());
+    let mut candidates = candidates.into_iter().peekable();
+
+    while let Some((first, unknown_words, first_long)) = candidates.next() {
+        // Split the candidates with this many unknown words by length, keeping their order
+        let mut short = VecDeque::new();
+        let mut long = VecDeque::new();
+        if first_long { long.push_back(first) } else { short.push_back(first) }
+
+        while let Some((candidate, _, is_long)) = candidates.next_if(|(_, words, _)| *words == unknown_words) {
+            if is_long { long.push_back(candidate) } else { short.push_back(candidate) }
+        }
+
+        while !short.is_empty() || !long.is_empty() {
+            let want_long = last_long.map_or(first_long, |last_long| !last_long);
+            let take_long = (want_long && !long.is_empty()) || short.is_empty();
+
+            result.extend(if take_long { long.pop_front() } else { short.pop_front() });
+            last_long = Some(take_long);
+        }
+    }
+
+    result
+}
+
+/// The default max number of cards in learning state at once, see `SchedulerConfig`
+pub const DEFAULT_MAX_LEARNING_CARDS: i32 = 10;
+
+/// The default max number of new cards to introduce each day, the same as Anki's
+pub const DEFAULT_NEW_CARD_LIMIT: i32 = 20;
+
+/// Options for how cards are scheduled and introduced
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchedulerConfig {
+    /// How many cards can be in learning at once before new cards stop being introduced, so the
+    /// learning cards get reviewed first
+    pub max_learning_cards: i32,
+    /// Stop introducing new sentences with more than this many new words, or introduce any
+    /// sentence if None
+    pub max_new_words_per_sentence: Option<i32>,
+    /// Whether words still in the learning steps count as known when suggesting sentences, or
+    /// only words that have graduated
+    pub learning_counts_as_known: bool,
+    /// The fraction of reviews to aim to remember in auto ease mode, which adjusts the interval
+    /// modifier every so often from the review log, or None to turn it off
+    pub target_retention: Option<f64>,
+    /// Target retentions for particular decks, which override `target_retention` for their
+    /// sentences and adjust their own interval modifiers
+    pub deck_target_retention: Vec<DeckRetention>,
+    /// Alternate new sentences between short and long ones, counting sentences with more than this
+    /// many characters as long, or None to leave them in the usual order
+    pub long_sentence_chars: Option<i32>,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            max_learning_cards: DEFAULT_MAX_LEARNING_CARDS,
+            max_new_words_per_sentence: None,
+            learning_counts_as_known: true,
+            target_retention: None,
+            deck_target_retention: Vec::new(),
+            long_sentence_chars: None,
+        }
+    }
+}
+
+/// The easy bonus
+const EASY_BONUS: f64 = 1.3;
+
+/// The hard interval
+const HARD_INTERVAL: f64 = 1.2;
+
+/// The scheduling state of a card, shared by all the algorithms. Reviewing a schedule keeps these
+/// invariants:
+/// * The ease is never below `MINIMUM_EASE`
+/// * Good never shrinks the interval
+/// * Cards in learning go through each of the initial intervals in turn when answered Good, and
+///   graduate after the last one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Schedule {
+    pub due: Option<NaiveDateTime>,
+    pub interval: Option<Duration>,
+    pub review_count: i32,
+    pub ease: f32,
+}
+
+impl Default for Schedule {
+    /// A new card that hasn't been learned yet
+    fn default() -> Self {
+        Self {
+            due: None,
+            interval: None,
+            review_count: 0,
+            ease: DEFAULT_EASE,
+        }
+    }
+}
+
+impl Schedule {
+    /// Whether the card is still in the initial learning steps
+    pub fn is_learning(&self) -> bool {
+        self.review_count < INITIAL_INTERVALS.len() as i32
+    }
+
+    /// Whether the card is in the intra-day learning queue, see `LEARNING_QUEUE_INTERVAL`
+    pub fn in_learning_queue(&self) -> bool {
+        self.interval.is_some_and(|interval| interval < LEARNING_QUEUE_INTERVAL)
+    }
+
+    /// Review the card at `time_now`, scheduling its next review
+    pub fn review(&mut self, time_now: DateTime<Local>, score: Difficulty) -> SrsResult<()> {
+        self.review_with_modifier(time_now, score, 1.0)
+    }
+
+    /// Review the card at `time_now`, multiplying the intervals of graduated cards answered Hard,
+    /// Good or Easy by `interval_modifier`, like anki's interval modifier
+    pub fn review_with_modifier(&mut self, time_now: DateTime<Local>, score: Difficulty, interval_modifier: f64) -> SrsResult<()> {
+        // https://faqs.ankiweb.net/what-spaced-repetition-algorithm.html
+        // For learning/relearning the algorithm is a bit different. We track if a card is
+        // currently in the learning stage by its review count, if there's a corresponding entry in
+        // INITIAL_INTERVALS that's one of the initial learning stages, once it passes out of there
+        // it graduates to no longer being a new card.
+        if self.is_learning() {
+            // For cards in learning/relearning:
+            // * Again moves the card back to the first stage of the new card intervals
+            // * Hard repeats the current step
+            // * Good moves the card to the next step, if the card was on the final step, it is
+            //   converted into a review card
+            // * Easy immediately converts the card into a review card
+            // There are no ease adjustments for new cards.
+            self.review_count = match score {
+                Difficulty::Again => 0,
+                Difficulty::Hard => self.review_count,
+                Difficulty::Good => self.review_count + 1,
+                Difficulty::Easy => INITIAL_INTERVALS.len() as i32,
+            };
+
+            let interval_index = i32::clamp(self.review_count, 0, INITIAL_INTERVALS.len() as i32 - 1);
+            let new_interval = INITIAL_INTERVALS[interval_index as usize];
+            let new_due = time_now + chrono::Duration::from_std(new_interval)?;
+
+            self.interval = Some(new_interval);
+            self.due = Some(new_due.naive_utc());
+        }
+        else {
+            // For cards that have graduated learning:
+            // * Again puts the card back into learning mode, and decreases the ease by 20%
+            // * Hard multiplies the current interval by the hard interval (1.2 by default) and
+            //   decreases the ease by 15%
+            // * Good multiplies the current interval by the ease
+            // * Easy multiplies the current interval by the ease times the easy bonus (1.3 by
+            //   default) and increases the ease by 15%
+            let interval = self.interval.ok_or("Graduated card has no interval")?;
+            let (new_interval, new_ease, new_review_count) = match score {
+                Difficulty::Again => {
+                    (INITIAL_INTERVALS[0], self.ease - 0.2, 0)
+                },
+                Difficulty::Hard => {
+                    let new_interval = Self::mul_duration(interval, HARD_INTERVAL * interval_modifier);
+                    (new_interval, self.ease - 0.15, self.review_count + 1)
+                },
+                Difficulty::Good => {
+                    // A low interval modifier mustn't make Good shrink the interval
+                    let new_interval = Self::mul_duration(interval, self.ease as f64 * interval_modifier).max(interval);
+                    (new_interval, self.ease, self.review_count + 1)
+                },
+                Difficulty::Easy => {
+                    let new_interval = Self::mul_duration(interval, self.ease as f64 * EASY_BONUS * interval_modifier);
+                    (new_interval, self.ease + 0.15, self.review_count + 1)
+                },
+            };
+
+            let new_due = time_now + chrono::Duration::from_std(new_interval)?;
+
+            self.interval = Some(new_interval);
+            self.due = Some(new_due.naive_utc());
+            self.ease = f32::max(MINIMUM_EASE, new_ease);
+            self.review_count = new_review_count;
+        }
+
+        Ok(())
+    }
+
+    fn mul_duration(duration: Duration, multiplier: f64) -> Duration {
+        let new_interval_secs = duration.as_secs() as f64 * multiplier;
+        Duration::from_secs(new_interval_secs as u64)
+    }
+
+    /// The days after being learned that a new card gets reviewed on (within the next `days`
+    /// days), assuming it's answered Good every time
+    pub fn good_review_days(days: usize) -> SrsResult<Vec<usize>> {
+        let learned_time = Local::now();
+        let mut schedule = Schedule::default();
+        let mut review_days = Vec::new();
+        let mut time = learned_time;
+
+        loop {
+            schedule.review(time, Difficulty::Good)?;
+            time = DateTime::<Utc>::from_utc(schedule.due.unwrap(), Utc).with_timezone(&Local);
+
+            let day = (time - learned_time).num_days() as usize;
+            if day >= days {
+                break;
+            }
+
+            // Learning steps on the same day all count as one review of the sentence
+            if day > 0 && review_days.last() != Some(&day) {
+                review_days.push(day);
+            }
+        }
+
+        Ok(review_days)
+    }
+}
+
+/// The parts of the scheduler that work on cards in the database
+#[cfg(feature = "mysql")]
+mod storage;
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn difficulty() -> impl Strategy<Value = Difficulty> {
+        prop_oneof![
+            Just(Difficulty::Again),
+            Just(Difficulty::Hard),
+            Just(Difficulty::Good),
+            Just(Difficulty::Easy),
+        ]
+    }
+
+    /// Sequences of answers short enough that answering Easy every time, with the biggest interval
+    /// modifier, doesn't run past the dates chrono can represent
+    fn difficulties() -> impl Strategy<Value = Vec<Difficulty>> {
+        prop::collection::vec(difficulty(), 1..8)
+    }
+
+    /// Review a new card with each of `scores` in turn, each time it's due, returning the time of
+    /// each review and the schedule before and after it
+    fn review_all(scores: &[Difficulty], interval_modifier: f64) -> Vec<(Schedule, DateTime<Local>, Schedule)> {
+        let mut schedule = Schedule::default();
+        let mut time = Local::now();
+        let mut steps = Vec::new();
+
+        for &score in scores {
+            let before = schedule;
+            schedule.review_with_modifier(time, score, interval_modifier).unwrap();
+            steps.push((before, time, schedule));
+
+            time = DateTime::<Utc>::from_utc(schedule.due.unwrap(), Utc).with_timezone(&Local);
+        }
+
+        steps
+    }
+
+    #[test]
+    fn learning_steps_are_in_the_learning_queue() {
+        let steps = review_all(&[Difficulty::Again, Difficulty::Good, Difficulty::Good], 1.0);
+
+        // Again puts the card on the minute step and each Good moves it on a step, to ten minutes
+        // and then a day. The minute and ten minute steps come back the same session, the day
+        // step doesn't.
+        let in_queue = steps.iter().map(|(_, _, after)| after.in_learning_queue()).collect::<Vec<_>>();
+        assert_eq!(in_queue, vec![true, true, false]);
+    }
+
+    #[test]
+    fn again_puts_a_graduated_card_back_in_the_learning_queue() {
+        let (_, _, graduated) = *review_all(&[Difficulty::Easy, Difficulty::Good], 1.0).last().unwrap();
+        assert!(!graduated.is_learning());
+        assert!(!graduated.in_learning_queue());
+
+        let (_, _, lapsed) = *review_all(&[Difficulty::Easy, Difficulty::Good, Difficulty::Again], 1.0).last().unwrap();
+        assert!(lapsed.in_learning_queue());
+        assert_eq!(lapsed.interval, Some(INITIAL_INTERVALS[0]));
+    }
+
+    #[test]
+    fn new_cards_are_not_in_the_learning_queue() {
+        assert!(!Schedule::default().in_learning_queue());
+    }
+
+    proptest! {
+        #[test]
+        fn again_comes_back_the_same_session(mut scores in difficulties()) {
+            scores.push(Difficulty::Again);
+
+            let (_, time, after) = *review_all(&scores, 1.0).last().unwrap();
+            prop_assert!(after.in_learning_queue());
+            prop_assert!(after.due.unwrap() - time.naive_utc() < chrono::Duration::from_std(LEARNING_QUEUE_INTERVAL).unwrap());
+        }
+
+        #[test]
+        fn ease_never_below_minimum(scores in difficulties(), modifier in 0.5..2.0f64) {
+            for (_, _, after) in review_all(&scores, modifier) {
+                prop_assert!(after.ease >= MINIMUM_EASE);
+            }
+        }
+
+        #[test]
+        fn next_review_never_in_the_past(scores in difficulties(), modifier in 0.5..2.0f64) {
+            for (_, time, after) in review_all(&scores, modifier) {
+                prop_assert!(after.interval.is_some());
+                prop_assert!(after.due.unwrap() >= time.naive_utc());
+            }
+        }
+
+        #[test]
+        fn good_never_shrinks_graduated_interval(scores in difficulties(), modifier in 0.5..2.0f64) {
+            for (score, (before, _, after)) in scores.iter().zip(review_all(&scores, modifier)) {
+                if *score == Difficulty::Good && !before.is_learning() {
+                    prop_assert!(after.interval >= before.interval);
+                }
+            }
+        }
+
+        #[test]
+        fn learning_goes_through_each_step(scores in difficulties()) {
+            for (score, (before, _, after)) in scores.iter().zip(review_all(&scores, 1.0)) {
+                if !before.is_learning() {
+                    continue;
+                }
+
+                let expected_count = match score {
+                    Difficulty::Again => 0,
+                    Difficulty::Hard => before.review_count,
+                    Difficulty::Good => before.review_count + 1,
+                    Difficulty::Easy => INITIAL_INTERVALS.len() as i32,
+                };
+                let step = expected_count.min(INITIAL_INTERVALS.len() as i32 - 1) as usize;
+
+                prop_assert_eq!(after.review_count, expected_count);
+                prop_assert_eq!(after.interval, Some(INITIAL_INTERVALS[step]));
+                prop_assert_eq!(after.ease, before.ease);
+            }
+        }
+
+        #[test]
+        fn graduates_after_the_last_step(extra_goods in 0..5usize) {
+            let scores = vec![Difficulty::Good; INITIAL_INTERVALS.len() + extra_goods];
+
+            for (i, (_, _, after)) in review_all(&scores, 1.0).into_iter().enumerate() {
+                prop_assert_eq!(after.is_learning(), i + 1 < INITIAL_INTERVALS.len());
+            }
+        }
+
+        #[test]
+        fn again_goes_back_to_the_first_step(mut scores in difficulties()) {
+            scores.push(Difficulty::Again);
+
+            let (_, _, after) = *review_all(&scores, 1.0).last().unwrap();
+            prop_assert!(after.is_learning());
+            prop_assert_eq!(after.review_count, 0);
+            prop_assert_eq!(after.interval, Some(INITIAL_INTERVALS[0]));
+        }
+
+        #[test]
+        fn intervals_round_trip_through_seconds(scores in difficulties(), interval_modifier in 0.5..2.0f64) {
+            // Intervals are stored as whole seconds in `cards.interval_secs`
+            for (_, _, after) in review_all(&scores, interval_modifier) {
+                let interval = after.interval.unwrap();
+                prop_assert_eq!(Duration::from_secs(interval.as_secs()), interval);
+            }
+        }
+
+        #[test]
+        #[cfg(feature = "mysql")]
+        fn same_seed_gives_the_same_order(seed in any::<u32>(), ids in prop::collection::vec(any::<u128>(), 0..50)) {
+            let ids: Vec<Uuid> = ids.into_iter().map(Uuid::from_u128).collect();
+            let mut reversed: Vec<Uuid> = ids.iter().rev().cloned().collect();
+            let mut ids = ids;
+
+            shuffle_by_seed(&mut ids, seed, |id| *id);
+            shuffle_by_seed(&mut reversed, seed, |id| *id);
+            prop_assert_eq!(ids, reversed);
+        }
+
+        #[test]
+        #[cfg(feature = "mysql")]
+        fn answering_keeps_the_order_of_the_rest(seed in any::<u32>(), ids in prop::collection::vec(any::<u128>(), 1..50),
+                                                 answered in any::<prop::sample::Index>()) {
+            let mut ids: Vec<Uuid> = ids.into_iter().map(Uuid::from_u128).collect();
+            shuffle_by_seed(&mut ids, seed, |id| *id);
+
+            ids.remove(answered.index(ids.len()));
+            let mut rest = ids.clone();
+            rest.reverse();
+            shuffle_by_seed(&mut rest, seed, |id| *id);
+
+            prop_assert_eq!(rest, ids);
+        }
+    }
+
+    /// The longest interval the old `interval` TIME column could hold, see `db::MAX_TIME_INTERVAL_SECS`
+    const MAX_TIME_INTERVAL: Duration = Duration::from_secs(838 * 60 * 60 + 59 * 60 + 59);
+
+    #[test]
+    fn intervals_grow_past_the_old_time_column_limit() {
+        let mut schedule = Schedule {
+            due: Some(Local::now().naive_utc()),
+            interval: Some(MAX_TIME_INTERVAL),
+            review_count: INITIAL_INTERVALS.len() as i32,
+            ease: DEFAULT_EASE,
+        };
+
+        schedule.review(Local::now(), Difficulty::Good).unwrap();
+
+        let interval = schedule.interval.unwrap();
+        assert!(interval > MAX_TIME_INTERVAL * 2);
+        assert_eq!(Duration::from_secs(interval.as_secs()), interval);
+    }
+
+    #[test]
+    #[cfg(feature = "mysql")]
+    fn different_seeds_give_different_orders() {
+        let ids: Vec<Uuid> = (0..20).map(Uuid::from_u128).collect();
+
+        let mut first = ids.clone();
+        shuffle_by_seed(&mut first, 1, |id| *id);
+        let mut second = ids.clone();
+        shuffle_by_seed(&mut second, 2, |id| *id);
+
+        assert_ne!(first, ids);
+        assert_ne!(first, second);
+    }
+}
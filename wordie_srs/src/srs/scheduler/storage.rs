@@ -0,0 +1,47 @@
+use std::collections::HashMap;
+
+use super::*;
+
+/// A condition for cards that should be reviewed now, e.g. `WHERE {DUE_NOW}`: cards in the
+/// learning queue once their due time has passed, and other cards due before `:latest_time`, the
+/// end of the day. Queries using it need the `:now`, `:latest_time` and `:learning_queue_secs`
+/// parameters.
+pub(crate) const DUE_NOW: &str = r"(cards.due IS NOT NULL && IF(cards.interval_secs < :learning_queue_secs,
+    cards.due <= :now,
+    cards.due < :latest_time))";
+
+/// A condition for cards in the intra-day learning queue, see `LEARNING_QUEUE_INTERVAL`
+pub(crate) const IN_LEARNING_QUEUE: &str = "cards.interval_secs < :learning_queue_secs";
+
+/// Sentences pushed back for a while with `SrsAlgorithm::snooze`. Snoozes are only kept in memory
+/// and leave the cards' schedules alone, so a snoozed sentence keeps its interval and ease, and
+/// comes back when the app restarts.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Snoozes {
+    until: HashMap<Uuid, NaiveDateTime>,
+}
+
+impl Snoozes {
+    /// Keep a sentence out of the queue until `until`, forgetting snoozes that are already over
+    /// as of `now`
+    pub(crate) fn snooze(&mut self, sentence_id: Uuid, until: NaiveDateTime, now: NaiveDateTime) {
+        self.until.retain(|_, until| *until > now);
+        self.until.insert(sentence_id, until);
+    }
+
+    /// A condition leaving out the sentences still snoozed at `now`, e.g.
+    /// `WHERE {snoozes.not_snoozed("sentences.id", now)}`
+    pub(crate) fn not_snoozed(&self, sentence_id_column: &str, now: NaiveDateTime) -> String {
+        let snoozed = self.until.iter()
+            .filter(|(_, until)| **until > now)
+            .map(|(sentence_id, _)| format!("'{sentence_id}'"))
+            .collect::<Vec<String>>();
+
+        if snoozed.is_empty() {
+            "TRUE".to_string()
+        }
+        else {
+            format!("{sentence_id_column} NOT IN ({})", snoozed.join(","))
+        }
+    }
+}
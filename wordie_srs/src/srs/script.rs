@@ -0,0 +1,45 @@
+use serde::{Serialize, Deserialize};
+use strum_macros::EnumIter;
+
+/// The writing system some text is in, for choosing a font for it. Only the scripts that need a
+/// font of their own are told apart, other text is left in the UI font.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone, EnumIter, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Script {
+    /// Kana and kanji. Chinese text is counted as Japanese too, as there's no telling them apart
+    /// without kana.
+    Japanese,
+    Korean,
+    Cyrillic,
+    Latin,
+}
+
+impl Script {
+    /// The name of the script, as it's written in the config file
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Script::Japanese => "japanese",
+            Script::Korean => "korean",
+            Script::Cyrillic => "cyrillic",
+            Script::Latin => "latin",
+        }
+    }
+
+    /// Find the script some text is in. Any character from the earlier scripts wins, so e.g. a
+    /// Japanese sentence with a romaji word in it is still Japanese.
+    pub fn detect(text: &str) -> Option<Self> {
+        [Script::Japanese, Script::Korean, Script::Cyrillic, Script::Latin].into_iter()
+            .find(|script| text.chars().any(|c| script.contains(c)))
+    }
+
+    /// Whether a character is written in the script
+    fn contains(&self, c: char) -> bool {
+        match self {
+            Script::Japanese => matches!(c,
+                '\u{3040}'..='\u{30FF}' | '\u{3400}'..='\u{4DBF}' | '\u{4E00}'..='\u{9FFF}' | '\u{FF66}'..='\u{FF9F}'),
+            Script::Korean => matches!(c, '\u{1100}'..='\u{11FF}' | '\u{3130}'..='\u{318F}' | '\u{AC00}'..='\u{D7AF}'),
+            Script::Cyrillic => matches!(c, '\u{0400}'..='\u{04FF}'),
+            Script::Latin => c.is_ascii_alphabetic() || matches!(c, '\u{00C0}'..='\u{024F}'),
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use std::collections::HashSet;
+use strum_macros::EnumIter;
+
+/// Languages with built in splitting rules
+#[derive(Debug, PartialEq, Eq, Copy, Clone, EnumIter)]
+pub enum SplitterPreset {
+    Japanese,
+    English,
+}
+
+impl SplitterPreset {
+    /// Get the splitter for this preset
+    pub fn splitter(&self) -> SentenceSplitter {
+        match self {
+            SplitterPreset::Japanese => SentenceSplitter::japanese(),
+            SplitterPreset::English => SentenceSplitter::english(),
+        }
+    }
+}
+
+/// Rules for splitting a block of text into sentences
+#[derive(Debug, Clone)]
+pub struct SentenceSplitter {
+    /// Characters that end a sentence when they're not inside a quote
+    terminators: HashSet<char>,
+    /// Pairs of (open, close) quote characters. Pairs where both are the same character, like
+    /// ('"', '"'), are treated as ambiguous and toggle in and out of the quote.
+    quote_pairs: Vec<(char, char)>,
+    /// Words ending in a '.' that don't end a sentence, e.g. "Mr."
+    abbreviations: HashSet<String>,
+    /// Sentences longer than this (in chars) are force split
+    max_length: Option<usize>,
+}
+
+impl SentenceSplitter {
+    /// Create a splitter with the given terminators and quote pairs
+    pub fn new(terminators: &[char], quote_pairs: &[(char, char)]) -> Self {
+        Self {
+            terminators: terminators.iter().cloned().collect(),
+            quote_pairs: quote_pairs.to_vec(),
+            abbreviations: HashSet::new(),
+            max_length: None,
+        }
+    }
+
+    /// Preset for Japanese text
+    pub fn japanese() -> Self {
+        Self::new(&['。', '！', '？', '!', '?', '…'],
+                  &[('「', '」'), ('『', '』'), ('（', '）'), ('"', '"')])
+    }
+
+    /// Preset for English (and most other western) text
+    pub fn english() -> Self {
+        Self::new(&['.', '!', '?', '…'], &[('"', '"'), ('“', '”')])
+            .with_abbreviations(&["Mr", "Mrs", "Ms", "Dr", "Prof", "St", "vs", "etc", "e.g", "i.e"])
+    }
+
+    /// Add words that shouldn't end a sentence when followed by a '.'
+    pub fn with_abbreviations(mut self, abbreviations: &[&str]) -> Self {
+        self.abbreviations.extend(abbreviations.iter().map(|a| a.to_lowercase()));
+        self
+    }
+
+    /// Force split sentences longer than `max_length` chars
+    pub fn with_max_length(mut self, max_length: usize) -> Self {
+        self.max_length = Some(max_length);
+        self
+    }
+
+    /// Split text into trimmed, non-empty sentences
+    pub fn split(&self, text: &str) -> Vec<String> {
+        let mut result = Vec::new();
+
+        // The close quotes we're waiting for, innermost last
+        let mut quote_stack: Vec<char> = Vec::new();
+        let mut cur_string = String::new();
+        let mut cur_length = 0;
+
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            // Line breaks always end a sentence, and also get us out of any unbalanced quotes
+            if c == '\n' {
+                Self::push_sentence(&mut result, &mut cur_string, &mut cur_length);
+                quote_stack.clear();
+                continue;
+            }
+
+            cur_string.push(c);
+            cur_length += 1;
+
+            if quote_stack.last() == Some(&c) {
+                quote_stack.pop();
+            }
+            else if let Some((_, close)) = self.quote_pairs.iter().find(|(open, _)| *open == c) {
+                quote_stack.push(*close);
+            }
+            else if quote_stack.is_empty() && self.terminators.contains(&c) && self.is_sentence_end(c, &cur_string, chars.peek()) {
+                Self::push_sentence(&mut result, &mut cur_string, &mut cur_length);
+                continue;
+            }
+
+            if self.max_length.is_some_and(|max| cur_length >= max) {
+                Self::push_sentence(&mut result, &mut cur_string, &mut cur_length);
+                quote_stack.clear();
+            }
+        }
+
+        Self::push_sentence(&mut result, &mut cur_string, &mut cur_length);
+
+        result
+    }
+
+    /// Check whether a terminator actually ends the sentence, given the sentence so far
+    /// (including the terminator) and the next character
+    fn is_sentence_end(&self, c: char, cur_string: &str, next: Option<&char>) -> bool {
+        // Keep runs of terminators together, e.g. "？！" or "..."
+        if next.is_some_and(|next| self.terminators.contains(next)) {
+            return false;
+        }
+
+        if c == '.' {
+            // Western full stops need a space after them, otherwise it's probably a decimal
+            // point, url etc
+            if next.is_some_and(|next| !next.is_whitespace()) {
+                return false;
+            }
+
+            let word = cur_string[..cur_string.len() - 1]
+                .rsplit(char::is_whitespace)
+                .next()
+                .unwrap_or("")
+                .to_lowercase();
+
+            if self.abbreviations.contains(&word) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn push_sentence(result: &mut Vec<String>, cur_string: &mut String, cur_length: &mut usize) {
+        let sentence = cur_string.trim();
+
+        if !sentence.is_empty() {
+            result.push(sentence.to_string());
+        }
+
+        cur_string.clear();
+        *cur_length = 0;
+    }
+}
+
+impl Default for SentenceSplitter {
+    fn default() -> Self {
+        Self::japanese()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn japanese_splits_on_japanese_and_western_terminators() {
+        let splitter = SentenceSplitter::japanese();
+        assert_eq!(splitter.split("猫が好きです。犬は？ 鳥だ！魚!"), vec!["猫が好きです。", "犬は？", "鳥だ！", "魚!"]);
+    }
+
+    #[test]
+    fn japanese_quotes_keep_their_sentences_together() {
+        let splitter = SentenceSplitter::japanese();
+        assert_eq!(splitter.split("「行こう。早く！」と言った。次。"), vec!["「行こう。早く！」と言った。", "次。"]);
+        assert_eq!(splitter.split("『本。』と（注。）だ。"), vec!["『本。』と（注。）だ。"]);
+    }
+
+    #[test]
+    fn apostrophes_dont_open_quotes() {
+        let splitter = SentenceSplitter::japanese();
+        assert_eq!(splitter.split("don't stop！次です。"), vec!["don't stop！", "次です。"]);
+    }
+
+    #[test]
+    fn english_splits_after_full_stops_followed_by_a_space() {
+        let splitter = SentenceSplitter::english();
+        assert_eq!(splitter.split("It costs 3.50 now. See example.com! Why?"),
+                   vec!["It costs 3.50 now.", "See example.com!", "Why?"]);
+    }
+
+    #[test]
+    fn english_abbreviations_dont_end_sentences() {
+        let splitter = SentenceSplitter::english();
+        assert_eq!(splitter.split("Mr. Smith met Dr. Jones. They talked."), vec!["Mr. Smith met Dr. Jones.", "They talked."]);
+    }
+
+    #[test]
+    fn english_quotes_keep_their_sentences_together() {
+        let splitter = SentenceSplitter::english();
+        assert_eq!(splitter.split("\"Stop. Now,\" he said. Then he left."), vec!["\"Stop. Now,\" he said.", "Then he left."]);
+        assert_eq!(splitter.split("“Wait! Please.” She left."), vec!["“Wait! Please.” She left."]);
+    }
+
+    #[test]
+    fn ellipses_stay_together() {
+        assert_eq!(SentenceSplitter::english().split("Wait... what? Well… fine."), vec!["Wait...", "what?", "Well…", "fine."]);
+        assert_eq!(SentenceSplitter::japanese().split("待って……何？"), vec!["待って……", "何？"]);
+    }
+
+    #[test]
+    fn newlines_end_sentences_and_unbalanced_quotes() {
+        let splitter = SentenceSplitter::japanese();
+        assert_eq!(splitter.split("一行目\n\n二行目"), vec!["一行目", "二行目"]);
+        assert_eq!(splitter.split("「閉じない\n次。後。"), vec!["「閉じない", "次。", "後。"]);
+    }
+
+    #[test]
+    fn long_sentences_are_force_split() {
+        let splitter = SentenceSplitter::japanese().with_max_length(3);
+        assert_eq!(splitter.split("あいうえお。"), vec!["あいう", "えお。"]);
+    }
+}
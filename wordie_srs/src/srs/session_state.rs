@@ -0,0 +1,86 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{Review, Difficulty, SrsResult};
+
+/// The file the app saves the session it was in to when it's closed
+pub const SESSION_STATE_FILE: &str = "wordie_session.json";
+
+/// The kind of session that was going on, with its counters so far
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SavedSession {
+    /// A timed session, with the time used so far and the answers given
+    Timed { limit_secs: u64, elapsed_secs: u64, answers: Vec<(Difficulty, i32)> },
+    /// A speed review of due cards
+    Speed { passed: i32, failed: i32 },
+}
+
+/// What the app was in the middle of when it was closed, so it can carry on from there next time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionState {
+    /// The day it was saved, as the queue will have moved on by another day
+    #[serde(serialize_with = "serialize_day", deserialize_with = "deserialize_day")]
+    pub day: NaiveDate,
+    /// The review that was showing and hadn't been answered yet
+    pub review: Option<Review>,
+    pub session: Option<SavedSession>,
+}
+
+impl SessionState {
+    /// Save the state, replacing any saved before
+    pub fn save(&self, path: &Path) -> SrsResult<()> {
+        log::info!("Saving session state to {path:?}");
+
+        let file = File::create(path)
+            .map_err(|err| format!("Failed to create session state file {path:?}: {err}"))?;
+        serde_json::to_writer_pretty(BufWriter::new(file), self)?;
+
+        Ok(())
+    }
+
+    /// Load the saved state and delete the file so it's only restored once. State saved on an
+    /// earlier day than `today` is thrown away.
+    pub fn take(path: &Path, today: NaiveDate) -> SrsResult<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let file = File::open(path)
+            .map_err(|err| format!("Failed to open session state file {path:?}: {err}"))?;
+        let state: Self = serde_json::from_reader(BufReader::new(file))
+            .map_err(|err| format!("Failed to parse session state file {path:?}: {err}"))?;
+
+        Self::clear(path)?;
+
+        if state.day != today {
+            log::info!("Ignoring session state from {}", state.day);
+            return Ok(None);
+        }
+
+        Ok(Some(state))
+    }
+
+    /// Delete the saved state, e.g. when the app is closed outside of a session
+    pub fn clear(path: &Path) -> SrsResult<()> {
+        match std::fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Write days as e.g. 2022-10-31, as chrono's serde support isn't enabled
+fn serialize_day<S: Serializer>(day: &NaiveDate, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(day)
+}
+
+fn deserialize_day<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveDate, D::Error> {
+    String::deserialize(deserializer)?
+        .parse()
+        .map_err(serde::de::Error::custom)
+}
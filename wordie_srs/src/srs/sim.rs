@@ -0,0 +1,221 @@
+use std::io::Write;
+
+use chrono::{DateTime, Local, NaiveDateTime};
+
+use super::{SrsResult, Difficulty};
+#[cfg(feature = "simulation")]
+use super::{SrsAlgorithm, Sentence};
+use super::scheduler::Schedule;
+use super::import::ImportReport;
+
+/// The most times a card is reviewed in a simulated day, in case a learner model keeps answering
+/// Again and the card never leaves the learning steps
+const MAX_REVIEWS_PER_CARD_PER_DAY: usize = 20;
+
+/// How a simulated learner answers reviews
+#[derive(Debug, Clone)]
+pub struct LearnerModel {
+    /// How often each difficulty is answered, relative to the others
+    weights: Vec<(Difficulty, i32)>,
+    /// Running credit for each difficulty, so answers follow the weights without any randomness
+    /// (smooth weighted round robin)
+    credits: Vec<i32>,
+}
+
+impl LearnerModel {
+    /// Create a learner who answers with each difficulty in proportion to its weight
+    pub fn new(weights: &[(Difficulty, i32)]) -> Self {
+        Self {
+            weights: weights.to_vec(),
+            credits: vec![0; weights.len()],
+        }
+    }
+
+    /// Get the learner's next answer
+    pub fn answer(&mut self) -> Difficulty {
+        let total: i32 = self.weights.iter().map(|(_, weight)| weight).sum();
+
+        for (credit, (_, weight)) in self.credits.iter_mut().zip(self.weights.iter()) {
+            *credit += weight;
+        }
+
+        let best = (0..self.credits.len())
+            .max_by_key(|i| self.credits[*i])
+            .expect("Learner model has no weights");
+
+        self.credits[best] -= total;
+        self.weights[best].0
+    }
+}
+
+impl Default for LearnerModel {
+    /// A learner who mostly answers Good
+    fn default() -> Self {
+        Self::new(&[
+            (Difficulty::Again, 5),
+            (Difficulty::Hard, 10),
+            (Difficulty::Good, 80),
+            (Difficulty::Easy, 5),
+        ])
+    }
+}
+
+/// A day of a simulation
+#[derive(Debug, Clone, Copy)]
+pub struct SimulatedDay {
+    pub day: usize,
+    /// Cards learned that day
+    pub learned: i32,
+    /// Sentences reviewed that day, including new ones
+    pub reviewed: i32,
+}
+
+/// The result of simulating an algorithm
+#[derive(Debug, Clone)]
+pub struct SimulationResult {
+    /// The report from adding the corpus
+    pub report: ImportReport,
+    pub days: Vec<SimulatedDay>,
+}
+
+impl SimulationResult {
+    /// Write the days as csv, with a header row
+    pub fn write_csv(&self, writer: &mut impl Write) -> SrsResult<()> {
+        writeln!(writer, "day,learned,reviewed")?;
+
+        for day in self.days.iter() {
+            writeln!(writer, "{},{},{}", day.day, day.learned, day.reviewed)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Simulate studying a corpus with an algorithm for `days` days starting from now, doing every
+/// review the algorithm gives each day and answering them with `learner`. The algorithm's db is
+/// reinitialized first, so only use this with a db that's just for simulations.
+#[cfg(feature = "simulation")]
+pub fn simulate(algorithm: &mut dyn SrsAlgorithm, corpus: &[Sentence], learner: &mut LearnerModel,
+                days: usize) -> SrsResult<SimulationResult> {
+    log::info!("Simulating srs algorithm");
+
+    algorithm.reinitialize_db(true)?;
+
+    let report = algorithm.add_sentences(corpus)?;
+    log::info!("Added {} sentences, {} new words, {} review-only sentences", corpus.len(),
+               report.new_words.len(), report.review_only_sentences.len());
+
+    let start = Local::now();
+    let mut result = SimulationResult {
+        report,
+        days: Vec::with_capacity(days),
+    };
+
+    for day in 0..days {
+        log::info!("Starting day {day}");
+        algorithm.set_time_now(start + chrono::Duration::days(day as i64));
+
+        // Do all the day's reviews
+        let mut reviewed = 0;
+        while let Some(review) = algorithm.get_next_card()?.review() {
+            log::info!("Reviewing: {}", review.sentence().text);
+            algorithm.review(review, learner.answer())?;
+            reviewed += 1;
+        }
+
+        log::info!("No more sentences to review");
+
+        result.days.push(SimulatedDay {
+            day,
+            learned: algorithm.cards_learned_today(),
+            reviewed,
+        });
+
+        // Reset daily limits and move on to the next day
+        algorithm.reset_daily_limits();
+    }
+
+    log::info!("Done simulating");
+
+    Ok(result)
+}
+
+/// A day of a projected workload
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProjectedDay {
+    /// Card reviews, including the first review of new cards and repeated learning steps
+    pub reviews: i32,
+    pub new_cards: i32,
+    /// Cards that have graduated from learning by the end of the day
+    pub known_cards: i32,
+}
+
+/// Project the daily workload over the next `days` days (starting today) by simulating the
+/// collection's cards in memory, learning up to `new_cards_per_day` of the unlearned cards each
+/// day. Every card due each day is reviewed, and reviews are answered by `learner`.
+pub fn project(schedules: &[Schedule], new_cards_per_day: usize, days: usize, start: DateTime<Local>,
+               learner: &mut LearnerModel) -> SrsResult<Vec<ProjectedDay>> {
+    let (mut learned, unlearned): (Vec<Schedule>, Vec<Schedule>) = schedules.iter()
+        .partition(|schedule| schedule.due.is_some());
+    let mut unlearned = unlearned.len();
+
+    let mut projection = Vec::with_capacity(days);
+    for day in 0..days {
+        let day_start = if day == 0 {
+            start
+        }
+        else {
+            super::end_of_day(start + chrono::Duration::days(day as i64 - 1))
+        };
+        let day_end = super::end_of_day(day_start).naive_utc();
+
+        let mut projected = ProjectedDay::default();
+
+        for schedule in learned.iter_mut() {
+            projected.reviews += review_day(schedule, day_start, day_end, learner)?;
+        }
+
+        let new_cards = new_cards_per_day.min(unlearned);
+        unlearned -= new_cards;
+        projected.new_cards = new_cards as i32;
+
+        for _ in 0..new_cards {
+            let mut schedule = Schedule {
+                due: Some(day_start.naive_utc()),
+                ..Schedule::default()
+            };
+            projected.reviews += review_day(&mut schedule, day_start, day_end, learner)?;
+            learned.push(schedule);
+        }
+
+        projected.known_cards = learned.iter().filter(|schedule| !schedule.is_learning()).count() as i32;
+        projection.push(projected);
+    }
+
+    Ok(projection)
+}
+
+/// Review a card every time it comes due during a day, returning the number of reviews
+fn review_day(schedule: &mut Schedule, day_start: DateTime<Local>, day_end: NaiveDateTime,
+              learner: &mut LearnerModel) -> SrsResult<i32> {
+    let mut reviews = 0;
+
+    while let Some(due) = schedule.due.filter(|due| *due < day_end) {
+        if reviews as usize >= MAX_REVIEWS_PER_CARD_PER_DAY {
+            break;
+        }
+
+        // Overdue cards get reviewed at the start of the day, and learning steps when they're due
+        let time = if due > day_start.naive_utc() {
+            DateTime::<chrono::Utc>::from_utc(due, chrono::Utc).with_timezone(&Local)
+        }
+        else {
+            day_start
+        };
+
+        schedule.review(time, learner.answer())?;
+        reviews += 1;
+    }
+
+    Ok(reviews)
+}
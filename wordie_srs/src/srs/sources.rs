@@ -0,0 +1,87 @@
+#[cfg(feature = "mysql")]
+pub(crate) use self::storage::*;
+
+/// How a sentence got into the collection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SourceKind {
+    /// Imported from a file, e.g. subtitles, a pdf or a deck
+    File,
+    /// Added in a batch that didn't come from a file, e.g. the core 6k import
+    Batch,
+    /// Typed or pasted into the add screen
+    Manual,
+    /// Picked up from the clipboard or a texthooker
+    Clipboard,
+    /// Sent from the browser extension
+    Browser,
+}
+
+impl SourceKind {
+    /// The name of the source kind in the db
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SourceKind::File => "file",
+            SourceKind::Batch => "batch",
+            SourceKind::Manual => "manual",
+            SourceKind::Clipboard => "clipboard",
+            SourceKind::Browser => "browser",
+        }
+    }
+
+    /// Get a source kind from its name in the db
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "file" => Some(SourceKind::File),
+            "batch" => Some(SourceKind::Batch),
+            "manual" => Some(SourceKind::Manual),
+            "clipboard" => Some(SourceKind::Clipboard),
+            "browser" => Some(SourceKind::Browser),
+            _ => None,
+        }
+    }
+}
+
+/// Where a sentence came from, e.g. the file it was imported from
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SentenceSource {
+    pub kind: SourceKind,
+    /// The file name or batch name, empty for sources without one
+    pub name: String,
+}
+
+impl SentenceSource {
+    pub fn new(kind: SourceKind, name: &str) -> Self {
+        Self {
+            kind,
+            name: name.to_string(),
+        }
+    }
+
+    /// A source for sentences that were typed in
+    pub fn manual() -> Self {
+        Self::new(SourceKind::Manual, "")
+    }
+
+    /// A name for showing the source, e.g. "file: episode1.srt"
+    pub fn label(&self) -> String {
+        if self.name.is_empty() {
+            self.kind.as_str().to_string()
+        }
+        else {
+            format!("{}: {}", self.kind.as_str(), self.name)
+        }
+    }
+}
+
+/// How much a source has been studied
+#[derive(Debug, Clone)]
+pub struct SourceStats {
+    pub source: SentenceSource,
+    pub sentences: i32,
+    /// The new words learned while reviewing the source's sentences
+    pub words_taught: i32,
+}
+
+/// Storing where sentences came from and what they taught
+#[cfg(feature = "mysql")]
+mod storage;
@@ -0,0 +1,94 @@
+use chrono::NaiveDateTime;
+use mysql::{PooledConn, prelude::Queryable, params};
+use uuid::Uuid;
+
+use super::*;
+use crate::srs::SrsResult;
+
+/// A condition for sentences from the source in the `:source_kind` and `:source_name` parameters,
+/// or any sentence if `:source_kind` is NULL
+pub(crate) const FROM_SOURCE: &str = r"(:source_kind IS NULL || sentences.id IN (
+    SELECT sentence_sources.sentence_id
+    FROM sentence_sources
+    WHERE sentence_sources.kind = :source_kind && sentence_sources.name = :source_name))";
+
+/// The parameters for `FROM_SOURCE`
+pub(crate) fn source_params(source: Option<&SentenceSource>) -> (Option<&'static str>, Option<String>) {
+    (source.map(|source| source.kind.as_str()), source.map(|source| source.name.clone()))
+}
+
+/// Create the sentence sources table, shared by all the algorithms
+pub(crate) fn create_table(conn: &mut PooledConn) -> SrsResult<()> {
+    conn.query_drop(r"
+        CREATE TABLE IF NOT EXISTS sentence_sources (
+            sentence_id CHAR(36) NOT NULL,
+            kind VARCHAR(16) NOT NULL,
+            name VARCHAR(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci NOT NULL,
+            added DATETIME NOT NULL,
+            words_taught INT NOT NULL DEFAULT 0,
+            PRIMARY KEY (sentence_id)
+        )
+    ")?;
+
+    Ok(())
+}
+
+/// Set where sentences came from, sentences that already have a source keep it
+pub(crate) fn set(conn: &mut PooledConn, ids: &[Uuid], source: &SentenceSource, now: NaiveDateTime) -> SrsResult<()> {
+    conn.exec_batch(
+        r"INSERT IGNORE INTO sentence_sources (sentence_id, kind, name, added)
+          VALUES (:sentence_id, :kind, :name, :added)",
+        ids.iter().map(|id| params! {
+            "sentence_id" => id.to_string(),
+            "kind" => source.kind.as_str(),
+            "name" => source.name.as_str(),
+            "added" => now,
+        }))?;
+
+    Ok(())
+}
+
+/// Load where a sentence came from, if it's known
+pub(crate) fn load(conn: &mut PooledConn, sentence_id: &Uuid) -> SrsResult<Option<SentenceSource>> {
+    let source: Option<(String, String)> = conn.exec_first(
+        "SELECT kind, name FROM sentence_sources WHERE sentence_id = :sentence_id",
+        params! { "sentence_id" => sentence_id.to_string() })?;
+
+    Ok(source.and_then(|(kind, name)| Some(SentenceSource::new(SourceKind::from_name(&kind)?, &name))))
+}
+
+/// Count new words learned from a sentence towards its source
+pub(crate) fn record_words_taught(conn: &mut PooledConn, sentence_id: &Uuid, words: usize) -> SrsResult<()> {
+    if words == 0 {
+        return Ok(());
+    }
+
+    conn.exec_drop("UPDATE sentence_sources SET words_taught = words_taught + :words WHERE sentence_id = :sentence_id",
+        params! {
+            "sentence_id" => sentence_id.to_string(),
+            "words" => words,
+        })?;
+
+    Ok(())
+}
+
+/// Get the stats for each source of the sentences not in the trash, most words taught first
+pub(crate) fn stats(conn: &mut PooledConn) -> SrsResult<Vec<SourceStats>> {
+    let stats = conn.query_map(
+        r"SELECT sentence_sources.kind, sentence_sources.name,
+                 CAST(COUNT(*) AS SIGNED), CAST(SUM(sentence_sources.words_taught) AS SIGNED)
+          FROM sentence_sources
+          INNER JOIN sentences ON sentences.id = sentence_sources.sentence_id
+          WHERE sentences.deleted IS NULL
+          GROUP BY sentence_sources.kind, sentence_sources.name
+          ORDER BY SUM(sentence_sources.words_taught) DESC, sentence_sources.name",
+        |(kind, name, sentences, words_taught): (String, String, i32, i32)| (kind, name, sentences, words_taught))?;
+
+    Ok(stats.into_iter()
+        .filter_map(|(kind, name, sentences, words_taught)| Some(SourceStats {
+            source: SentenceSource::new(SourceKind::from_name(&kind)?, &name),
+            sentences,
+            words_taught,
+        }))
+        .collect())
+}
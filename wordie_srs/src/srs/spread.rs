@@ -0,0 +1,88 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+
+/// How much later than it's due a card can be moved, as a fraction of its interval, so spreading
+/// dues out never stretches an interval by much
+pub const MAX_DELAY_FRACTION: f64 = 0.25;
+
+/// The shortest interval a card can have to be spread out. Cards in learning are left alone, as
+/// their steps are too short to move them by days.
+pub const MIN_SPREAD_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// What spreading dues out changed
+#[derive(Debug, Clone, Default)]
+pub struct SpreadReport {
+    /// The number of cards given a new due date
+    pub cards_moved: usize,
+    /// The most cards due on one day in the spread window, before and after
+    pub busiest_day_before: usize,
+    pub busiest_day_after: usize,
+}
+
+/// Spread cards due in the `days` days starting today out evenly over the days, e.g. after a large
+/// import made thousands due on the same day. Cards are only moved later, never earlier, and by
+/// no more than `MAX_DELAY_FRACTION` of their interval. Overdue cards count as due today.
+///
+/// Cards are given as their key, due date in UTC and interval, and the cards that move are
+/// returned with their new due date. Cards are placed earliest deadline first, on the day with
+/// the fewest cards they can go on, so the same cards are always spread the same way.
+pub fn spread<K: Clone + Ord>(cards: &[(K, NaiveDateTime, Duration)], now: DateTime<Local>, days: u32)
+    -> (Vec<(K, NaiveDateTime)>, SpreadReport)
+{
+    let today = now.naive_local().date();
+    let last_day = days.max(1) as i64 - 1;
+
+    // The days each card can go on, counting from today
+    let mut placements: Vec<(i64, i64, &K, NaiveDateTime)> = cards.iter()
+        .filter(|(_, _, interval)| *interval >= MIN_SPREAD_INTERVAL)
+        .filter_map(|(key, due, interval)| {
+            let due_day = Local.from_utc_datetime(due).naive_local().date();
+            let earliest = (due_day - today).num_days().max(0);
+            if earliest > last_day {
+                return None;
+            }
+
+            let max_delay = (interval.as_secs_f64() * MAX_DELAY_FRACTION / (24.0 * 60.0 * 60.0)) as i64;
+            let latest = (earliest + max_delay).min(last_day);
+
+            Some((earliest, latest, key, *due))
+        })
+        .collect();
+
+    let mut before = vec![0; days.max(1) as usize];
+    for (earliest, _, _, _) in placements.iter() {
+        before[*earliest as usize] += 1;
+    }
+
+    placements.sort_by(|(a_earliest, a_latest, a_key, _), (b_earliest, b_latest, b_key, _)| {
+        a_latest.cmp(b_latest).then(a_earliest.cmp(b_earliest)).then(a_key.cmp(b_key))
+    });
+
+    let mut after = vec![0; days.max(1) as usize];
+    let mut moved = Vec::new();
+
+    for (earliest, latest, key, due) in placements {
+        // The first of the least busy days, so cards stay as early as they can
+        let day = (earliest..=latest)
+            .min_by_key(|day| (after[*day as usize], *day))
+            .unwrap_or(earliest);
+        after[day as usize] += 1;
+
+        // Cards left on their first day keep their due date, so overdue cards stay overdue. Moved
+        // cards keep the time of day they were due at.
+        if day != earliest {
+            let due_day = Local.from_utc_datetime(&due).naive_local().date();
+            let shift = today + chrono::Duration::days(day) - due_day;
+            moved.push((key.clone(), due + shift));
+        }
+    }
+
+    let report = SpreadReport {
+        cards_moved: moved.len(),
+        busiest_day_before: before.iter().cloned().max().unwrap_or(0),
+        busiest_day_after: after.iter().cloned().max().unwrap_or(0),
+    };
+
+    (moved, report)
+}
@@ -0,0 +1,146 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use chrono::{Duration, NaiveDate};
+use serde::{Serialize, Serializer};
+
+use super::SrsResult;
+use super::daily_stats::DailyStats;
+use super::retention::RetentionStats;
+
+/// The default file name for stats exports
+pub const STATS_EXPORT_FILE: &str = "stats.csv";
+
+/// The columns of a csv stats export. Past days have the study columns, and today onwards has the
+/// forecast column.
+const CSV_HEADER: &str = "day,cards_reviewed,cards_learned,retention_reviews,retention_passed,retention,forecast_due_cards";
+
+/// The formats stats can be exported in
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    /// Pick the format from a file's extension, or None if it isn't one we write
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "csv" => Some(ExportFormat::Csv),
+            "json" => Some(ExportFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// A day of study in a stats export
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExportedDay {
+    #[serde(serialize_with = "serialize_day")]
+    pub day: NaiveDate,
+    pub cards_reviewed: i32,
+    /// New cards learned, i.e. words learned
+    pub cards_learned: i32,
+    /// Reviews of graduated cards, and how many of them were remembered, see `RetentionStats`
+    pub retention_reviews: i32,
+    pub retention_passed: i32,
+    pub retention: Option<f64>,
+}
+
+/// The cards due on a day in the forecast
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ForecastDay {
+    #[serde(serialize_with = "serialize_day")]
+    pub day: NaiveDate,
+    pub due_cards: i32,
+}
+
+/// Study statistics for exporting to other tools, see `SrsAlgorithm::get_stats_export`
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StatsExport {
+    /// The past days, oldest first and ending today
+    pub days: Vec<ExportedDay>,
+    /// The cards due each day, starting today
+    pub forecast: Vec<ForecastDay>,
+}
+
+impl StatsExport {
+    /// Put an export together from daily stats ordered oldest first, retention by day, and due
+    /// card counts for each day starting `today`
+    pub fn new(daily_stats: &[DailyStats], retention: &[(NaiveDate, RetentionStats)], forecast: &[i32], today: NaiveDate) -> Self {
+        let retention = retention.iter().copied().collect::<BTreeMap<NaiveDate, RetentionStats>>();
+
+        let days = daily_stats.iter()
+            .map(|stats| {
+                let retention = retention.get(&stats.day).copied().unwrap_or_default();
+                ExportedDay {
+                    day: stats.day,
+                    cards_reviewed: stats.cards_reviewed,
+                    cards_learned: stats.cards_learned,
+                    retention_reviews: retention.reviews,
+                    retention_passed: retention.passed,
+                    retention: retention.rate(),
+                }
+            })
+            .collect();
+
+        let forecast = forecast.iter()
+            .enumerate()
+            .map(|(i, &due_cards)| ForecastDay { day: today + Duration::days(i as i64), due_cards })
+            .collect();
+
+        Self {
+            days,
+            forecast,
+        }
+    }
+
+    /// Write the export to a file in `format`
+    pub fn write(&self, path: &Path, format: ExportFormat) -> SrsResult<()> {
+        log::info!("Writing stats for {} days to {path:?}", self.days.len());
+
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        match format {
+            ExportFormat::Csv => self.write_csv(&mut writer)?,
+            ExportFormat::Json => serde_json::to_writer_pretty(&mut writer, self)?,
+        }
+
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Write the export as csv, with a row per day so it can be charted in a spreadsheet
+    fn write_csv(&self, writer: &mut impl Write) -> SrsResult<()> {
+        let mut rows: BTreeMap<NaiveDate, (Option<&ExportedDay>, Option<i32>)> = BTreeMap::new();
+        for day in self.days.iter() {
+            rows.entry(day.day).or_default().0 = Some(day);
+        }
+        for day in self.forecast.iter() {
+            rows.entry(day.day).or_default().1 = Some(day.due_cards);
+        }
+
+        writeln!(writer, "{CSV_HEADER}")?;
+
+        for (day, (stats, due_cards)) in rows {
+            let stats = match stats {
+                Some(stats) => format!("{},{},{},{},{}", stats.cards_reviewed, stats.cards_learned, stats.retention_reviews,
+                                       stats.retention_passed, stats.retention.map_or(String::new(), |rate| format!("{rate:.4}"))),
+                None => ",,,,".to_string(),
+            };
+            let due_cards = due_cards.map_or(String::new(), |due_cards| due_cards.to_string());
+
+            writeln!(writer, "{day},{stats},{due_cards}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Write days as e.g. 2022-10-31, as chrono's serde support isn't enabled
+fn serialize_day<S: Serializer>(day: &NaiveDate, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(day)
+}
@@ -0,0 +1,274 @@
+use std::collections::{HashMap, HashSet};
+
+use super::Sentence;
+
+#[cfg(feature = "mysql")]
+pub(crate) use self::storage::*;
+
+/// How much the number of sentences a sentence's unknown words are in counts towards its score
+const FREQUENCY_WEIGHT: f64 = 1.0;
+
+/// How much the number of other sentences learning a sentence would bring within the i+N limit
+/// counts towards its score
+const UNLOCK_WEIGHT: f64 = 2.0;
+
+/// How much seeing a sentence's unknown words alongside known words counts towards its score
+const CONTEXT_WEIGHT: f64 = 1.0;
+
+/// An unknown word and the sentences learning it would open up, see `WordGraph::unlocking_words`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnlockingWord {
+    pub word: String,
+    /// Sentences that are one unknown word over the new word limit now and would be within it
+    pub unlocked: i32,
+    /// Sentences that are i+1 now and would become fully known
+    pub completed: i32,
+}
+
+/// A sentence's words, split into the known and unknown ones
+#[derive(Debug, Clone)]
+pub struct SentenceWords {
+    pub sentence: Sentence,
+    pub known: Vec<String>,
+    pub unknown: Vec<String>,
+}
+
+/// The sentences in the collection linked by the words they share, for ranking suggested sentences
+/// by how useful their unknown words are to learn rather than just by how many there are
+#[derive(Debug, Default)]
+pub struct WordGraph {
+    sentences: Vec<SentenceWords>,
+    /// The sentences each word is in, by index into `sentences`
+    word_sentences: HashMap<String, Vec<usize>>,
+}
+
+impl WordGraph {
+    pub fn new(sentences: Vec<SentenceWords>) -> Self {
+        let mut word_sentences: HashMap<String, Vec<usize>> = HashMap::new();
+
+        for (index, sentence) in sentences.iter().enumerate() {
+            for word in sentence.known.iter().chain(sentence.unknown.iter()) {
+                word_sentences.entry(word.clone()).or_default().push(index);
+            }
+        }
+
+        Self { sentences, word_sentences }
+    }
+
+    /// The sentences with between 1 and `new_word_limit` unknown words, along with their unknown
+    /// words. Sentences with fewer unknown words come first, then the ones with the highest score.
+    pub fn suggest(&self, new_word_limit: usize) -> Vec<(Sentence, Vec<String>)> {
+        let mut candidates: Vec<(usize, f64)> = self.sentences.iter()
+            .enumerate()
+            .filter(|(_, sentence)| !sentence.unknown.is_empty() && sentence.unknown.len() <= new_word_limit)
+            .map(|(index, _)| (index, self.score(index, new_word_limit)))
+            .collect();
+
+        candidates.sort_by(|(a, a_score), (b, b_score)| {
+            self.sentences[*a].unknown.len().cmp(&self.sentences[*b].unknown.len())
+                .then(b_score.total_cmp(a_score))
+        });
+
+        candidates.into_iter()
+            .map(|(index, _)| {
+                let sentence = &self.sentences[index];
+                (sentence.sentence.clone(), sentence.unknown.clone())
+            })
+            .collect()
+    }
+
+    /// The unknown words that would bring the most sentences within `new_word_limit` or down to
+    /// i+0 if they were learned, most sentences unlocked first
+    pub fn unlocking_words(&self, new_word_limit: usize) -> Vec<UnlockingWord> {
+        let mut words: Vec<UnlockingWord> = self.word_sentences.iter()
+            .filter_map(|(word, sentences)| {
+                let mut unlocking = UnlockingWord { word: word.clone(), unlocked: 0, completed: 0 };
+
+                for sentence in sentences.iter().map(|index| &self.sentences[*index]) {
+                    if !sentence.unknown.contains(word) {
+                        continue;
+                    }
+
+                    let unknown = sentence.unknown.len();
+                    if unknown == 1 {
+                        unlocking.completed += 1;
+                    }
+                    else if unknown == new_word_limit + 1 {
+                        unlocking.unlocked += 1;
+                    }
+                }
+
+                (unlocking.unlocked + unlocking.completed > 0).then_some(unlocking)
+            })
+            .collect();
+
+        words.sort_by(|a, b| b.unlocked.cmp(&a.unlocked)
+            .then(b.completed.cmp(&a.completed))
+            .then(a.word.cmp(&b.word)));
+
+        words
+    }
+
+    /// How useful learning a sentence's unknown words would be: how common they are, how many other
+    /// sentences it would bring within the limit, and how often the words turn up among known ones
+    fn score(&self, index: usize, new_word_limit: usize) -> f64 {
+        let unknown = &self.sentences[index].unknown;
+
+        let frequency = unknown.iter()
+            .map(|word| (1.0 + self.frequency(word) as f64).ln())
+            .sum::<f64>() / unknown.len() as f64;
+
+        let context = unknown.iter()
+            .map(|word| self.known_context(word))
+            .sum::<f64>() / unknown.len() as f64;
+
+        let unlocks = self.unlocks(index, new_word_limit) as f64;
+
+        FREQUENCY_WEIGHT * frequency + UNLOCK_WEIGHT * (1.0 + unlocks).ln() + CONTEXT_WEIGHT * context
+    }
+
+    /// The number of sentences a word is in
+    fn frequency(&self, word: &str) -> usize {
+        self.word_sentences.get(word).map_or(0, |sentences| sentences.len())
+    }
+
+    /// The number of other sentences that are over the limit now, but wouldn't be once the unknown
+    /// words in the sentence at `index` are learned, e.g. i+2 sentences that would become i+1
+    fn unlocks(&self, index: usize, new_word_limit: usize) -> usize {
+        let learned: HashSet<&str> = self.sentences[index].unknown.iter().map(String::as_str).collect();
+
+        let neighbours: HashSet<usize> = learned.iter()
+            .filter_map(|word| self.word_sentences.get(*word))
+            .flatten()
+            .cloned()
+            .filter(|other| *other != index)
+            .collect();
+
+        neighbours.into_iter()
+            .map(|other| &self.sentences[other].unknown)
+            .filter(|unknown| unknown.len() > new_word_limit)
+            .filter(|unknown| unknown.iter().filter(|word| !learned.contains(word.as_str())).count() <= new_word_limit)
+            .count()
+    }
+
+    /// How much of the company a word keeps is already known, as the average fraction of known
+    /// words in the sentences it's in. Words seen among known words are easier to pick up.
+    fn known_context(&self, word: &str) -> f64 {
+        let sentences = match self.word_sentences.get(word) {
+            Some(sentences) if !sentences.is_empty() => sentences,
+            _ => return 0.0,
+        };
+
+        sentences.iter()
+            .map(|index| {
+                let sentence = &self.sentences[*index];
+                sentence.known.len() as f64 / (sentence.known.len() + sentence.unknown.len()) as f64
+            })
+            .sum::<f64>() / sentences.len() as f64
+    }
+}
+
+/// Loading the word graph from the database
+#[cfg(feature = "mysql")]
+mod storage;
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn sentence(text: &str, known: &[&str], unknown: &[&str]) -> SentenceWords {
+        SentenceWords {
+            sentence: Sentence { id: Uuid::new_v4(), text: text.to_string() },
+            known: known.iter().map(|word| word.to_string()).collect(),
+            unknown: unknown.iter().map(|word| word.to_string()).collect(),
+        }
+    }
+
+    fn suggested_texts(graph: &WordGraph, new_word_limit: usize) -> Vec<String> {
+        graph.suggest(new_word_limit).into_iter().map(|(sentence, _)| sentence.text).collect()
+    }
+
+    #[test]
+    fn suggest_leaves_out_known_and_over_limit_sentences() {
+        let graph = WordGraph::new(vec![
+            sentence("known", &["a", "b"], &[]),
+            sentence("i+1", &["a"], &["c"]),
+            sentence("i+3", &["a"], &["d", "e", "f"]),
+        ]);
+
+        assert_eq!(suggested_texts(&graph, 2), vec!["i+1"]);
+    }
+
+    #[test]
+    fn suggest_puts_fewer_unknown_words_first() {
+        // The i+2 sentence's words are far more common, but it still comes after the i+1 one
+        let graph = WordGraph::new(vec![
+            sentence("i+2", &["a"], &["b", "c"]),
+            sentence("i+1", &["a"], &["d"]),
+            sentence("b", &["a"], &["b", "x", "y"]),
+            sentence("c", &["a"], &["c", "x", "y"]),
+        ]);
+
+        assert_eq!(suggested_texts(&graph, 2), vec!["i+1", "i+2"]);
+    }
+
+    #[test]
+    fn common_words_score_higher() {
+        let graph = WordGraph::new(vec![
+            sentence("rare", &["a"], &["rare"]),
+            sentence("common", &["a"], &["common"]),
+            sentence("common 2", &["a"], &["common", "x", "y"]),
+            sentence("common 3", &["a"], &["common", "x", "z"]),
+        ]);
+
+        assert_eq!(graph.frequency("common"), 3);
+        assert!(graph.score(1, 1) > graph.score(0, 1));
+        assert_eq!(suggested_texts(&graph, 1), vec!["common", "rare"]);
+    }
+
+    #[test]
+    fn sentences_that_unlock_others_score_higher() {
+        // Both words are in two sentences, but only learning "unlocks" brings another within i+1
+        let graph = WordGraph::new(vec![
+            sentence("no unlocks", &["a"], &["stuck"]),
+            sentence("unlocks", &["a"], &["unlocks"]),
+            sentence("i+2", &["a"], &["unlocks", "b"]),
+            sentence("i+3", &["a"], &["stuck", "c", "d"]),
+        ]);
+
+        assert_eq!(graph.unlocks(1, 1), 1);
+        assert_eq!(graph.unlocks(0, 1), 0);
+        assert_eq!(suggested_texts(&graph, 1), vec!["unlocks", "no unlocks"]);
+    }
+
+    #[test]
+    fn unlocking_words_use_the_new_word_limit() {
+        let graph = WordGraph::new(vec![
+            sentence("i+1", &["a"], &["word"]),
+            sentence("i+2", &["a"], &["word", "b"]),
+            sentence("i+3", &["a"], &["word", "c", "d"]),
+        ]);
+
+        let unlocking = |new_word_limit| graph.unlocking_words(new_word_limit).into_iter()
+            .find(|unlocking| unlocking.word == "word")
+            .unwrap();
+
+        assert_eq!(unlocking(1), UnlockingWord { word: "word".to_string(), unlocked: 1, completed: 1 });
+        assert_eq!(unlocking(2), UnlockingWord { word: "word".to_string(), unlocked: 1, completed: 1 });
+        assert_eq!(graph.unlocking_words(2).iter().find(|unlocking| unlocking.word == "b"), None);
+        assert_eq!(graph.unlocking_words(1).iter().find(|unlocking| unlocking.word == "b").map(|b| b.unlocked), Some(1));
+    }
+
+    #[test]
+    fn known_context_is_the_average_known_fraction() {
+        let graph = WordGraph::new(vec![
+            sentence("half", &["a"], &["word"]),
+            sentence("quarter", &["a"], &["word", "b", "c"]),
+        ]);
+
+        assert_eq!(graph.known_context("word"), (0.5 + 0.25) / 2.0);
+        assert_eq!(graph.known_context("missing"), 0.0);
+    }
+}
@@ -0,0 +1,47 @@
+use std::str::FromStr;
+
+use mysql::{PooledConn, prelude::Queryable};
+use uuid::Uuid;
+
+use super::*;
+use crate::srs::SrsResult;
+use crate::srs::blacklist::NOT_EXCLUDED;
+
+/// Load the sentences that can be suggested along with their words, split by `unknown`, a
+/// condition on a word's recognition card that's true if the word counts as unknown
+pub(crate) fn load(conn: &mut PooledConn, unknown: &str) -> SrsResult<WordGraph> {
+    let rows: Vec<(String, String, String, bool)> = conn.query(format!(r"
+        SELECT sentences.id, sentences.text, words.word, {unknown}
+        FROM sentences
+        INNER JOIN sentence_words ON sentence_words.sentence_id = sentences.id
+        INNER JOIN words ON words.id = sentence_words.word_id
+        INNER JOIN cards ON cards.word_id = sentence_words.word_id && cards.card_type = 'recognition'
+        WHERE NOT sentences.retired && sentences.deleted IS NULL
+           && {NOT_EXCLUDED}
+        ORDER BY sentences.id
+    "))?;
+
+    let mut sentences: Vec<SentenceWords> = Vec::new();
+
+    for (sentence_id, text, word, is_unknown) in rows {
+        let id = Uuid::from_str(&sentence_id).unwrap();
+
+        if sentences.last().is_none_or(|last| last.sentence.id != id) {
+            sentences.push(SentenceWords {
+                sentence: Sentence { id, text },
+                known: Vec::new(),
+                unknown: Vec::new(),
+            });
+        }
+
+        let last = sentences.last_mut().unwrap();
+        if is_unknown {
+            last.unknown.push(word);
+        }
+        else {
+            last.known.push(word);
+        }
+    }
+
+    Ok(WordGraph::new(sentences))
+}
@@ -0,0 +1,261 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
+use uuid::Uuid;
+
+use super::{SrsResult, Sentence};
+
+#[cfg(feature = "mysql")]
+pub(crate) use self::storage::*;
+
+#[cfg(feature = "sqlite")]
+pub use self::sqlite::SqliteStore;
+
+#[cfg(feature = "postgres")]
+pub use self::postgresql::PostgresStore;
+
+/// The address the sync server listens on unless another is configured, which only accepts
+/// connections from the same machine, e.g. through an ssh tunnel or reverse proxy
+pub const DEFAULT_LISTEN_ADDR: &str = "127.0.0.1:7878";
+
+/// How long either end of a sync waits for the other before giving up, so a client that stops
+/// responding can't hold up the server, which handles one client at a time
+pub const SYNC_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Sync settings, set in the config file, e.g.
+/// `"sync": { "server_addr": "myserver:7878", "token": "..." }`. The server and its clients all
+/// need the same token.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SyncConfig {
+    /// The secret clients send before syncing, so only our devices can read or change the collection
+    pub token: String,
+    /// The server to sync with, for clients
+    #[serde(default)]
+    pub server_addr: Option<String>,
+    /// The address to listen on, for the server
+    #[serde(default = "default_listen_addr")]
+    pub listen_addr: String,
+}
+
+fn default_listen_addr() -> String {
+    DEFAULT_LISTEN_ADDR.to_string()
+}
+
+/// The kind of row stored in the sync log for sentences
+#[cfg(any(feature = "mysql", feature = "sqlite", feature = "postgres"))]
+pub(crate) const SENTENCE_ROW: &str = "sentence";
+
+/// The kind of row stored in the sync log for cards
+#[cfg(any(feature = "mysql", feature = "sqlite", feature = "postgres"))]
+pub(crate) const CARD_ROW: &str = "card";
+
+/// The kind of row stored in the sync log for reviews
+#[cfg(any(feature = "mysql", feature = "sqlite", feature = "postgres"))]
+pub(crate) const REVIEW_ROW: &str = "review";
+
+/// The kind of row stored in the sync log for sentences moved to or from the trash. These stay
+/// in the log after the sentence is purged, as a tombstone.
+#[cfg(any(feature = "mysql", feature = "sqlite", feature = "postgres"))]
+pub(crate) const TRASH_ROW: &str = "trash";
+
+/// A change to a single row of a collection, sent between devices when syncing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SyncChange {
+    /// A sentence was added or its text was edited. Sentences keep the same id on every device.
+    Sentence(Sentence),
+    /// A card was reviewed. Cards are identified by their word, as word ids are generated
+    /// separately in each collection. Times are unix timestamps.
+    Card {
+        word: String,
+        review_count: i32,
+        ease: f32,
+        interval_secs: Option<u64>,
+        due: Option<i64>,
+        modified: i64,
+    },
+    /// A card was reviewed, sent as well as the card so every device keeps the whole review
+    /// history. Reviews never change once they're written, so histories are merged by adding the
+    /// ones a device doesn't have yet.
+    Review {
+        id: Uuid,
+        word: String,
+        sentence_id: Option<Uuid>,
+        review_date: i64,
+        difficulty: Option<i32>,
+        last_interval_secs: Option<u64>,
+    },
+    /// A sentence was moved to the trash at `deleted`, or taken back out if it's None. This is
+    /// still sent once the sentence has been purged, so devices that haven't synced since don't
+    /// send it back.
+    SentenceTrashed {
+        id: Uuid,
+        deleted: Option<i64>,
+    },
+}
+
+/// A request from a sync client to the server
+#[derive(Debug, Serialize, Deserialize)]
+pub enum SyncRequest {
+    /// Log in with the shared token, which has to be the first request
+    Auth { token: String },
+    /// Get all the changes after a server revision
+    Pull { since: i64 },
+    /// Upload changes made on a device
+    Push { changes: Vec<SyncChange> },
+}
+
+/// The server's response to a `SyncRequest`
+#[derive(Debug, Serialize, Deserialize)]
+pub enum SyncResponse {
+    Authenticated,
+    Changes { changes: Vec<SyncChange>, revision: i64 },
+    Pushed,
+    Error { message: String },
+}
+
+/// A collection that can be synced, either the mysql backed wordie algorithm, a `SqliteStore` or
+/// a `PostgresStore`
+pub trait SyncStore {
+    /// Get the rows changed after `revision`, oldest first, along with the latest revision
+    fn changes_since(&self, revision: i64) -> SrsResult<(Vec<SyncChange>, i64)>;
+
+    /// Apply changes from another device. Cards are merged last-write-wins, reviews by keeping
+    /// every one either device has, and changes that don't win are ignored. Sentences that have
+    /// been purged from the trash here aren't added back.
+    fn apply_changes(&mut self, changes: &[SyncChange]) -> SrsResult<()>;
+
+    /// Get a revision we've synced up to, or 0 if we've never synced
+    fn sync_revision(&self, name: &str) -> SrsResult<i64>;
+
+    /// Store a revision we've synced up to
+    fn set_sync_revision(&mut self, name: &str, revision: i64) -> SrsResult<()>;
+}
+
+/// The result of a sync
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SyncSummary {
+    pub pushed: usize,
+    pub pulled: usize,
+}
+
+/// Client for syncing a collection with a wordie server
+pub struct SyncClient {
+    server_addr: String,
+    token: String,
+}
+
+impl SyncClient {
+    /// Create a client for the server at `server_addr`, e.g. "localhost:7878", logging in with `token`
+    pub fn new(server_addr: &str, token: &str) -> Self {
+        Self {
+            server_addr: server_addr.to_string(),
+            token: token.to_string(),
+        }
+    }
+
+    /// Create a client for the server in the sync settings
+    pub fn from_config(config: &SyncConfig) -> SrsResult<Self> {
+        let server_addr = config.server_addr.as_ref()
+            .ok_or("No sync server configured, add a server_addr to the sync settings")?;
+
+        Ok(Self::new(server_addr, &config.token))
+    }
+
+    /// Push our local changes to the server, then pull everyone else's
+    pub fn sync(&self, store: &mut dyn SyncStore) -> SrsResult<SyncSummary> {
+        log::info!("Syncing with {}", self.server_addr);
+
+        let pushed_name = format!("pushed:{}", self.server_addr);
+        let pulled_name = format!("pulled:{}", self.server_addr);
+
+        let stream = TcpStream::connect(&self.server_addr)?;
+        stream.set_read_timeout(Some(SYNC_TIMEOUT))?;
+        stream.set_write_timeout(Some(SYNC_TIMEOUT))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut writer = stream;
+
+        write_message(&mut writer, &SyncRequest::Auth { token: self.token.clone() })?;
+        match read_message(&mut reader)? {
+            Some(SyncResponse::Authenticated) => {},
+            response => return Err(Self::unexpected_response(response)),
+        }
+
+        // Push first, so that changes we pull don't get sent straight back. Changes we pull that
+        // win locally do get logged and pushed next time, but the server ignores them as they
+        // don't win there.
+        let (changes, local_revision) = store.changes_since(store.sync_revision(&pushed_name)?)?;
+        let pushed = changes.len();
+
+        if !changes.is_empty() {
+            write_message(&mut writer, &SyncRequest::Push { changes })?;
+            match read_message(&mut reader)? {
+                Some(SyncResponse::Pushed) => {},
+                response => return Err(Self::unexpected_response(response)),
+            }
+        }
+
+        store.set_sync_revision(&pushed_name, local_revision)?;
+
+        write_message(&mut writer, &SyncRequest::Pull { since: store.sync_revision(&pulled_name)? })?;
+        let pulled = match read_message(&mut reader)? {
+            Some(SyncResponse::Changes { changes, revision }) => {
+                store.apply_changes(&changes)?;
+                store.set_sync_revision(&pulled_name, revision)?;
+                changes.len()
+            },
+            response => return Err(Self::unexpected_response(response)),
+        };
+
+        log::info!("Synced with {}, pushed {pushed} changes and pulled {pulled}", self.server_addr);
+
+        Ok(SyncSummary { pushed, pulled })
+    }
+
+    fn unexpected_response(response: Option<SyncResponse>) -> Box<dyn std::error::Error> {
+        match response {
+            Some(SyncResponse::Error { message }) => format!("Sync server error: {message}").into(),
+            Some(response) => format!("Unexpected response from sync server: {response:?}").into(),
+            None => "Sync server closed the connection".into(),
+        }
+    }
+}
+
+/// Check a token a client sent against ours, taking the same time however much of it matches,
+/// so the token can't be guessed a character at a time
+pub fn token_matches(expected: &str, given: &str) -> bool {
+    expected.len() == given.len() &&
+        expected.bytes().zip(given.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// Write a message as a single line of json
+pub fn write_message<T: Serialize>(writer: &mut impl Write, message: &T) -> SrsResult<()> {
+    let mut line = serde_json::to_string(message)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes())?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Read a single line json message, returns None if the connection was closed
+pub fn read_message<T: DeserializeOwned>(reader: &mut impl BufRead) -> SrsResult<Option<T>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+
+    Ok(Some(serde_json::from_str(&line)?))
+}
+
+/// Reading and writing the sync tables
+#[cfg(feature = "mysql")]
+mod storage;
+
+/// Keeping a synced collection in a sqlite file
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+/// Keeping a synced collection in postgres
+#[cfg(feature = "postgres")]
+mod postgresql;
@@ -0,0 +1,340 @@
+use std::path::Path;
+use std::str::FromStr;
+
+use rusqlite::{Connection, OptionalExtension, Transaction, params};
+use uuid::Uuid;
+
+use super::*;
+
+/// A synced collection kept in a single sqlite file, for devices and servers without a database
+/// server. It holds everything that's synced: sentences, the trash, card schedules and the review
+/// history. It only stores and merges them, studying still needs one of the algorithms.
+pub struct SqliteStore {
+    conn: Connection,
+}
+
+impl SqliteStore {
+    /// Open the store at `path`, creating it if it doesn't exist yet
+    pub fn open(path: &Path) -> SrsResult<Self> {
+        let conn = Connection::open(path)?;
+        create_tables(&conn)?;
+
+        Ok(Self { conn })
+    }
+
+    /// Get the current state of a logged row, or None if it's gone or can't be sent
+    fn change(&self, kind: &str, row_id: &str) -> SrsResult<Option<SyncChange>> {
+        let change = match kind {
+            SENTENCE_ROW => match Uuid::from_str(row_id) {
+                Ok(id) => self.conn.query_row("SELECT text FROM sentences WHERE id = ?1", params![row_id],
+                        |row| row.get(0))
+                    .optional()?
+                    .map(|text| SyncChange::Sentence(Sentence { id, text })),
+                Err(_) => None,
+            },
+            CARD_ROW => self.conn.query_row(
+                    "SELECT word, review_count, ease, interval_secs, due, modified FROM cards WHERE word = ?1",
+                    params![row_id],
+                    |row| Ok(SyncChange::Card {
+                        word: row.get(0)?,
+                        review_count: row.get(1)?,
+                        ease: row.get(2)?,
+                        interval_secs: row.get::<_, Option<i64>>(3)?.map(|interval| interval as u64),
+                        due: row.get(4)?,
+                        modified: row.get(5)?,
+                    }))
+                .optional()?,
+            REVIEW_ROW => self.conn.query_row(
+                    "SELECT id, word, sentence_id, review_date, difficulty, last_interval_secs FROM reviews WHERE id = ?1",
+                    params![row_id],
+                    |row| Ok((row.get::<_, String>(0)?, row.get(1)?, row.get::<_, Option<String>>(2)?, row.get(3)?,
+                              row.get(4)?, row.get::<_, Option<i64>>(5)?)))
+                .optional()?
+                .and_then(|(id, word, sentence_id, review_date, difficulty, last_interval_secs)| Some(SyncChange::Review {
+                    id: Uuid::from_str(&id).ok()?,
+                    word,
+                    sentence_id: sentence_id.and_then(|sentence_id| Uuid::from_str(&sentence_id).ok()),
+                    review_date,
+                    difficulty,
+                    last_interval_secs: last_interval_secs.map(|interval| interval as u64),
+                })),
+            TRASH_ROW => match Uuid::from_str(row_id) {
+                Ok(id) => {
+                    let deleted: Option<Option<i64>> = self.conn.query_row("SELECT deleted FROM sentences WHERE id = ?1",
+                            params![row_id], |row| row.get(0))
+                        .optional()?;
+
+                    // Trashed sentences we never had are only kept as a tombstone in the log
+                    let deleted = deleted.unwrap_or_else(|| Some(chrono::Utc::now().timestamp()));
+
+                    Some(SyncChange::SentenceTrashed { id, deleted })
+                },
+                Err(_) => None,
+            },
+            _ => None,
+        };
+
+        Ok(change)
+    }
+}
+
+impl SyncStore for SqliteStore {
+    fn changes_since(&self, revision: i64) -> SrsResult<(Vec<SyncChange>, i64)> {
+        let rows = self.conn
+            .prepare("SELECT revision, kind, row_id FROM sync_log WHERE revision > ?1 ORDER BY revision")?
+            .query_map(params![revision], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<Result<Vec<(i64, String, String)>, _>>()?;
+
+        let latest_revision = rows.last().map_or(revision, |(revision, _, _)| *revision);
+
+        let mut changes = Vec::new();
+        for (_, kind, row_id) in rows.iter() {
+            match self.change(kind, row_id)? {
+                Some(change) => changes.push(change),
+                None => log::warn!("Skipping unknown or invalid row in sync log: {kind} {row_id}"),
+            }
+        }
+
+        Ok((changes, latest_revision))
+    }
+
+    fn apply_changes(&mut self, changes: &[SyncChange]) -> SrsResult<()> {
+        log::info!("Applying {} synced changes", changes.len());
+
+        // Dropping the transaction without committing it rolls it back
+        let tx = self.conn.transaction()?;
+
+        for change in changes.iter() {
+            apply_change(&tx, change)?;
+        }
+
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    fn sync_revision(&self, name: &str) -> SrsResult<i64> {
+        Ok(self.conn.query_row("SELECT revision FROM sync_state WHERE name = ?1", params![name], |row| row.get(0))
+            .optional()?
+            .unwrap_or(0))
+    }
+
+    fn set_sync_revision(&mut self, name: &str, revision: i64) -> SrsResult<()> {
+        self.conn.execute("REPLACE INTO sync_state (name, revision) VALUES (?1, ?2)", params![name, revision])?;
+
+        Ok(())
+    }
+}
+
+/// Create the tables, times are stored as unix timestamps like they're synced
+fn create_tables(conn: &Connection) -> SrsResult<()> {
+    conn.execute_batch(r"
+        CREATE TABLE IF NOT EXISTS sentences (
+            id TEXT NOT NULL PRIMARY KEY,
+            text TEXT NOT NULL,
+            deleted INTEGER
+        );
+
+        CREATE TABLE IF NOT EXISTS cards (
+            word TEXT NOT NULL PRIMARY KEY,
+            review_count INTEGER NOT NULL,
+            ease REAL NOT NULL,
+            interval_secs INTEGER,
+            due INTEGER,
+            modified INTEGER NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS reviews (
+            id TEXT NOT NULL PRIMARY KEY,
+            word TEXT NOT NULL,
+            sentence_id TEXT,
+            review_date INTEGER NOT NULL,
+            difficulty INTEGER,
+            last_interval_secs INTEGER
+        );
+
+        -- Each changed row appears once, with the revision it was last changed in
+        CREATE TABLE IF NOT EXISTS sync_log (
+            revision INTEGER PRIMARY KEY AUTOINCREMENT,
+            kind TEXT NOT NULL,
+            row_id TEXT NOT NULL,
+            UNIQUE (kind, row_id)
+        );
+
+        CREATE TABLE IF NOT EXISTS sync_state (
+            name TEXT NOT NULL PRIMARY KEY,
+            revision INTEGER NOT NULL
+        );
+    ")?;
+
+    Ok(())
+}
+
+/// Merge a single change into the store, logging it if it changed anything so it's passed on to
+/// the other devices. Changes that don't aren't logged, so they don't get sent back and forth.
+fn apply_change(tx: &Transaction, change: &SyncChange) -> SrsResult<()> {
+    match change {
+        SyncChange::Sentence(sentence) => {
+            let id = sentence.id.to_string();
+
+            let existing: Option<String> = tx.query_row("SELECT text FROM sentences WHERE id = ?1", params![id],
+                    |row| row.get(0))
+                .optional()?;
+
+            match existing {
+                // A tombstone for a sentence that was purged before it got here
+                None if is_logged(tx, TRASH_ROW, &id)? => return Ok(()),
+                None => { tx.execute("INSERT INTO sentences (id, text) VALUES (?1, ?2)", params![id, sentence.text])?; },
+                Some(text) if text != sentence.text => {
+                    tx.execute("UPDATE sentences SET text = ?2 WHERE id = ?1", params![id, sentence.text])?;
+                },
+                Some(_) => return Ok(()),
+            }
+
+            record_change(tx, SENTENCE_ROW, &id)
+        },
+        SyncChange::Card { word, review_count, ease, interval_secs, due, modified } => {
+            // Last write wins, so only take the card if it was reviewed after ours
+            let changed = tx.execute(
+                r"INSERT INTO cards (word, review_count, ease, interval_secs, due, modified)
+                  VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                  ON CONFLICT (word) DO UPDATE
+                  SET review_count = excluded.review_count,
+                      ease = excluded.ease,
+                      interval_secs = excluded.interval_secs,
+                      due = excluded.due,
+                      modified = excluded.modified
+                  WHERE cards.modified < excluded.modified",
+                params![word, review_count, ease, interval_secs.map(|interval| interval as i64), due, modified])?;
+
+            if changed > 0 {
+                record_change(tx, CARD_ROW, word)?;
+            }
+
+            Ok(())
+        },
+        SyncChange::Review { id, word, sentence_id, review_date, difficulty, last_interval_secs } => {
+            let id = id.to_string();
+
+            // Reviews never change, so one we already have is skipped
+            let added = tx.execute(
+                r"INSERT OR IGNORE INTO reviews (id, word, sentence_id, review_date, difficulty, last_interval_secs)
+                  VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![id, word, sentence_id.map(|sentence_id| sentence_id.to_string()), review_date, difficulty,
+                        last_interval_secs.map(|interval| interval as i64)])?;
+
+            if added > 0 {
+                record_change(tx, REVIEW_ROW, &id)?;
+            }
+
+            Ok(())
+        },
+        SyncChange::SentenceTrashed { id, deleted } => {
+            let id = id.to_string();
+
+            let current: Option<Option<i64>> = tx.query_row("SELECT deleted FROM sentences WHERE id = ?1", params![id],
+                    |row| row.get(0))
+                .optional()?;
+
+            match (current, deleted) {
+                (Some(None), Some(_)) | (Some(Some(_)), None) => {
+                    tx.execute("UPDATE sentences SET deleted = ?2 WHERE id = ?1", params![id, deleted])?;
+                },
+                // Kept as a tombstone, so the sentence isn't added if it turns up later
+                (None, Some(_)) if !is_logged(tx, TRASH_ROW, &id)? => {},
+                _ => return Ok(()),
+            }
+
+            record_change(tx, TRASH_ROW, &id)
+        },
+    }
+}
+
+/// Record that a row has changed, moving it to a new revision
+fn record_change(tx: &Transaction, kind: &str, row_id: &str) -> SrsResult<()> {
+    // REPLACE deletes the old entry for the row, so it gets a new revision
+    tx.execute("REPLACE INTO sync_log (kind, row_id) VALUES (?1, ?2)", params![kind, row_id])?;
+
+    Ok(())
+}
+
+/// Check whether a row has ever been logged
+fn is_logged(tx: &Transaction, kind: &str, row_id: &str) -> SrsResult<bool> {
+    Ok(tx.query_row("SELECT revision FROM sync_log WHERE kind = ?1 AND row_id = ?2", params![kind, row_id],
+            |row| row.get::<_, i64>(0))
+        .optional()?
+        .is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_store() -> SqliteStore {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+        SqliteStore { conn }
+    }
+
+    fn card(word: &str, review_count: i32, modified: i64) -> SyncChange {
+        SyncChange::Card {
+            word: word.to_string(),
+            review_count,
+            ease: 2.5,
+            interval_secs: Some(86400),
+            due: Some(modified + 86400),
+            modified,
+        }
+    }
+
+    fn review_count(store: &SqliteStore, word: &str) -> i32 {
+        store.conn.query_row("SELECT review_count FROM cards WHERE word = ?1", params![word], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn cards_are_last_write_wins() {
+        let mut store = test_store();
+
+        store.apply_changes(&[card("猫", 3, 200)]).unwrap();
+        store.apply_changes(&[card("猫", 1, 100)]).unwrap();
+        assert_eq!(review_count(&store, "猫"), 3);
+
+        store.apply_changes(&[card("猫", 4, 300)]).unwrap();
+        assert_eq!(review_count(&store, "猫"), 4);
+    }
+
+    #[test]
+    fn reviews_are_merged_and_only_logged_once() {
+        let mut store = test_store();
+
+        let review = SyncChange::Review {
+            id: Uuid::new_v4(),
+            word: "猫".to_string(),
+            sentence_id: Some(Uuid::new_v4()),
+            review_date: 100,
+            difficulty: Some(2),
+            last_interval_secs: None,
+        };
+
+        store.apply_changes(std::slice::from_ref(&review)).unwrap();
+        let (changes, revision) = store.changes_since(0).unwrap();
+        assert_eq!(changes.len(), 1);
+
+        // Getting the same review back from another device doesn't log it again
+        store.apply_changes(&[review]).unwrap();
+        assert!(store.changes_since(revision).unwrap().0.is_empty());
+    }
+
+    #[test]
+    fn purged_sentences_stay_deleted() {
+        let mut store = test_store();
+        let sentence = Sentence { id: Uuid::new_v4(), text: "猫がいる".to_string() };
+
+        // Trashed and purged on another device before this store ever saw it
+        store.apply_changes(&[SyncChange::SentenceTrashed { id: sentence.id, deleted: Some(100) }]).unwrap();
+        store.apply_changes(&[SyncChange::Sentence(sentence.clone())]).unwrap();
+
+        let (changes, _) = store.changes_since(0).unwrap();
+        assert!(matches!(changes.as_slice(), [SyncChange::SentenceTrashed { deleted: Some(_), .. }]));
+    }
+}
@@ -0,0 +1,104 @@
+use mysql::{PooledConn, prelude::Queryable, params};
+
+use super::*;
+
+/// Create the sync tables
+pub(crate) fn create_tables(conn: &mut PooledConn) -> SrsResult<()> {
+    // Each changed row appears once, with the revision it was last changed in
+    conn.query_drop(r"
+        CREATE TABLE IF NOT EXISTS sync_log (
+            revision BIGINT NOT NULL AUTO_INCREMENT,
+            kind VARCHAR(16) NOT NULL,
+            row_id CHAR(36) NOT NULL,
+            PRIMARY KEY (revision),
+            UNIQUE (kind, row_id)
+        )
+    ")?;
+
+    conn.query_drop(r"
+        CREATE TABLE IF NOT EXISTS sync_state (
+            name VARCHAR(255) NOT NULL,
+            revision BIGINT NOT NULL,
+            PRIMARY KEY (name)
+        )
+    ")?;
+
+    Ok(())
+}
+
+/// Record that a row has changed, moving it to a new revision
+pub(crate) fn record_change(conn: &mut PooledConn, kind: &str, row_id: &str) -> SrsResult<()> {
+    // REPLACE deletes the old entry for the row, so it gets a new auto increment revision
+    conn.exec_drop("REPLACE INTO sync_log (kind, row_id) VALUES (:kind, :row_id)",
+        params! {
+            "kind" => kind,
+            "row_id" => row_id,
+        })?;
+
+    Ok(())
+}
+
+/// Record that every card matching `condition` has changed in a single statement, e.g. after
+/// updating them all at once. Cards are synced by word, so this is only for wordie's cards table.
+pub(crate) fn record_card_changes(conn: &mut PooledConn, condition: &str) -> SrsResult<()> {
+    conn.exec_drop(
+        format!("REPLACE INTO sync_log (kind, row_id) SELECT :kind, cards.word_id FROM cards WHERE {condition}"),
+        params! { "kind" => CARD_ROW })?;
+
+    Ok(())
+}
+
+/// Record every review as changed, e.g. after giving the existing reviews ids to sync them by
+pub(crate) fn record_review_changes(conn: &mut PooledConn) -> SrsResult<()> {
+    conn.exec_drop("REPLACE INTO sync_log (kind, row_id) SELECT :kind, reviews.id FROM reviews",
+        params! { "kind" => REVIEW_ROW })?;
+
+    Ok(())
+}
+
+/// Remove the log entries of reviews that have been deleted, so they aren't looked up on every sync
+pub(crate) fn forget_deleted_reviews(conn: &mut PooledConn) -> SrsResult<()> {
+    conn.exec_drop(
+        r"DELETE sync_log FROM sync_log
+          LEFT JOIN reviews ON reviews.id = sync_log.row_id
+          WHERE sync_log.kind = :kind && reviews.id IS NULL",
+        params! { "kind" => REVIEW_ROW })?;
+
+    Ok(())
+}
+
+/// Check whether a row has ever been logged, e.g. to find the tombstone of a purged sentence
+pub(crate) fn is_logged(conn: &mut PooledConn, kind: &str, row_id: &str) -> SrsResult<bool> {
+    let revision: Option<i64> = conn.exec_first("SELECT revision FROM sync_log WHERE kind = :kind && row_id = :row_id",
+        params! {
+            "kind" => kind,
+            "row_id" => row_id,
+        })?;
+
+    Ok(revision.is_some())
+}
+
+/// Get the (revision, kind, row_id) of the rows changed after `revision`, oldest first
+pub(crate) fn changed_rows(conn: &mut PooledConn, revision: i64) -> SrsResult<Vec<(i64, String, String)>> {
+    Ok(conn.exec(
+        "SELECT revision, kind, row_id FROM sync_log WHERE revision > :revision ORDER BY revision",
+        params! { "revision" => revision })?)
+}
+
+/// Get a stored sync revision, or 0 if there isn't one
+pub(crate) fn get_revision(conn: &mut PooledConn, name: &str) -> SrsResult<i64> {
+    Ok(conn.exec_first("SELECT revision FROM sync_state WHERE name = :name",
+        params! { "name" => name })?
+        .unwrap_or(0))
+}
+
+/// Store a sync revision
+pub(crate) fn set_revision(conn: &mut PooledConn, name: &str, revision: i64) -> SrsResult<()> {
+    conn.exec_drop("REPLACE INTO sync_state (name, revision) VALUES (:name, :revision)",
+        params! {
+            "name" => name,
+            "revision" => revision,
+        })?;
+
+    Ok(())
+}
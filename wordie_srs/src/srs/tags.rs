@@ -0,0 +1,35 @@
+use mysql::{PooledConn, prelude::Queryable, params};
+use uuid::Uuid;
+
+use super::SrsResult;
+
+/// Create the sentence tags table, shared by all the algorithms. Tags say where a sentence came
+/// from, e.g. the page of a pdf it was imported from.
+pub(crate) fn create_table(conn: &mut PooledConn) -> SrsResult<()> {
+    conn.query_drop(r"
+        CREATE TABLE IF NOT EXISTS sentence_tags (
+            sentence_id CHAR(36) NOT NULL,
+            tag VARCHAR(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci NOT NULL,
+            PRIMARY KEY (sentence_id, tag)
+        )
+    ")?;
+
+    Ok(())
+}
+
+/// Add a tag to sentences, sentences that already have it are left alone
+pub(crate) fn add(conn: &mut PooledConn, ids: &[Uuid], tag: &str) -> SrsResult<()> {
+    conn.exec_batch("INSERT IGNORE INTO sentence_tags (sentence_id, tag) VALUES (:sentence_id, :tag)",
+        ids.iter().map(|id| params! {
+            "sentence_id" => id.to_string(),
+            "tag" => tag,
+        }))?;
+
+    Ok(())
+}
+
+/// Load a sentence's tags, in alphabetical order
+pub(crate) fn load(conn: &mut PooledConn, sentence_id: &Uuid) -> SrsResult<Vec<String>> {
+    Ok(conn.exec("SELECT tag FROM sentence_tags WHERE sentence_id = :sentence_id ORDER BY tag",
+        params! { "sentence_id" => sentence_id.to_string() })?)
+}
@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "mysql")]
+pub(crate) use self::storage::*;
+
+/// The word a sentence was written to teach, e.g. from a Core6k card, with its authored reading
+/// and meaning so they can be shown instead of relying on tokenization
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TargetWord {
+    pub word: String,
+    pub reading: String,
+    pub meaning: String,
+}
+
+/// Storing target words
+#[cfg(feature = "mysql")]
+mod storage;
@@ -0,0 +1,41 @@
+use mysql::{PooledConn, prelude::Queryable, params};
+use uuid::Uuid;
+
+use super::*;
+use crate::srs::SrsResult;
+
+/// Create the sentence target words table, shared by all the algorithms
+pub(crate) fn create_table(conn: &mut PooledConn) -> SrsResult<()> {
+    conn.query_drop(r"
+        CREATE TABLE IF NOT EXISTS sentence_targets (
+            sentence_id CHAR(36) NOT NULL PRIMARY KEY,
+            word VARCHAR(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci NOT NULL,
+            reading VARCHAR(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci NOT NULL,
+            meaning TEXT CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci NOT NULL
+        )
+    ")?;
+
+    Ok(())
+}
+
+/// Set the target words of sentences, replacing any they already had
+pub(crate) fn set(conn: &mut PooledConn, targets: &[(Uuid, TargetWord)]) -> SrsResult<()> {
+    conn.exec_batch(r"REPLACE INTO sentence_targets (sentence_id, word, reading, meaning)
+                      VALUES (:sentence_id, :word, :reading, :meaning)",
+        targets.iter().map(|(sentence_id, target)| params! {
+            "sentence_id" => sentence_id.to_string(),
+            "word" => &target.word,
+            "reading" => &target.reading,
+            "meaning" => &target.meaning,
+        }))?;
+
+    Ok(())
+}
+
+/// Load a sentence's target word, if it has one
+pub(crate) fn load(conn: &mut PooledConn, sentence_id: &Uuid) -> SrsResult<Option<TargetWord>> {
+    let target = conn.exec_first("SELECT word, reading, meaning FROM sentence_targets WHERE sentence_id = :sentence_id",
+        params! { "sentence_id" => sentence_id.to_string() })?;
+
+    Ok(target.map(|(word, reading, meaning)| TargetWord { word, reading, meaning }))
+}
@@ -0,0 +1,233 @@
+use std::collections::{HashSet, VecDeque};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+use super::SrsResult;
+
+/// Fields that texthookers put the hooked line in, when they write json
+const TEXT_FIELDS: &[&str] = &["sentence", "text", "line"];
+
+/// How many of the latest lines to remember for leaving out repeats, so a long session doesn't
+/// keep every line in memory
+const MAX_SEEN_LINES: usize = 1000;
+
+/// How long to wait before connecting to a texthooker's websocket again after losing it
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Where hooked lines come from, see `TexthookerLog` and `TexthookerSocket`
+pub enum Texthooker {
+    Log(TexthookerLog),
+    Socket(TexthookerSocket),
+}
+
+impl Texthooker {
+    /// Get the lines hooked since the last poll, leaving out ones we've seen recently
+    pub fn poll(&mut self) -> SrsResult<Vec<String>> {
+        match self {
+            Texthooker::Log(log) => log.poll(),
+            Texthooker::Socket(socket) => socket.poll(),
+        }
+    }
+}
+
+/// The latest lines a texthooker has sent, as games often repeat lines, e.g. when going back
+#[derive(Default)]
+struct SeenLines {
+    lines: HashSet<String>,
+    order: VecDeque<String>,
+}
+
+impl SeenLines {
+    /// Remember a line, returns whether it's new
+    fn insert(&mut self, line: &str) -> bool {
+        if self.lines.contains(line) {
+            return false;
+        }
+
+        if self.order.len() >= MAX_SEEN_LINES {
+            if let Some(oldest) = self.order.pop_front() {
+                self.lines.remove(&oldest);
+            }
+        }
+
+        self.lines.insert(line.to_string());
+        self.order.push_back(line.to_string());
+
+        true
+    }
+}
+
+/// Follows the log file a texthooker writes hooked lines to, e.g. from a visual novel, one line
+/// per entry. Lines can be plain text or json objects with the text in one of `TEXT_FIELDS`.
+pub struct TexthookerLog {
+    path: PathBuf,
+    position: u64,
+    seen: SeenLines,
+}
+
+impl TexthookerLog {
+    /// Start following a log file from its current end, so lines from earlier sessions aren't
+    /// picked up again
+    pub fn follow(path: &Path) -> SrsResult<Self> {
+        let position = match std::fs::metadata(path) {
+            Ok(metadata) => metadata.len(),
+            // The texthooker might not have started writing yet
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => 0,
+            Err(e) => return Err(e.into()),
+        };
+
+        log::info!("Following texthooker log {path:?}");
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            position,
+            seen: SeenLines::default(),
+        })
+    }
+
+    /// Get the lines written since the last poll, leaving out ones we've already seen
+    pub fn poll(&mut self) -> SrsResult<Vec<String>> {
+        let mut file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        // Start again from the beginning if the log was cleared
+        if file.metadata()?.len() < self.position {
+            self.position = 0;
+        }
+
+        file.seek(SeekFrom::Start(self.position))?;
+
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+
+        // Leave any partly written line for next time
+        let complete = match bytes.iter().rposition(|byte| *byte == b'\n') {
+            Some(end) => end + 1,
+            None => return Ok(Vec::new()),
+        };
+        self.position += complete as u64;
+
+        let lines = String::from_utf8_lossy(&bytes[..complete])
+            .lines()
+            .filter_map(parse_line)
+            .filter(|line| self.seen.insert(line))
+            .collect::<Vec<String>>();
+
+        if !lines.is_empty() {
+            log::info!("Got {} new lines from texthooker", lines.len());
+        }
+
+        Ok(lines)
+    }
+}
+
+/// Takes hooked lines from a texthooker's websocket, e.g. Textractor's websocket extension at
+/// `ws://localhost:6677`. Messages are read like the lines of a `TexthookerLog`.
+pub struct TexthookerSocket {
+    receiver: Receiver<String>,
+    seen: SeenLines,
+    /// Tells the thread to stop trying to reconnect once we're dropped
+    stopped: Arc<AtomicBool>,
+}
+
+impl TexthookerSocket {
+    /// Start listening to the websocket at `url` on a background thread, which reconnects if the
+    /// texthooker isn't running yet or goes away
+    pub fn connect(url: &str) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let url = url.to_string();
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        let thread_stopped = stopped.clone();
+        thread::spawn(move || {
+            while !thread_stopped.load(Ordering::Relaxed) {
+                match tungstenite::connect(url.as_str()) {
+                    Ok((mut socket, _)) => {
+                        log::info!("Connected to texthooker at {url}");
+
+                        loop {
+                            let text = match socket.read_message() {
+                                Ok(tungstenite::Message::Text(text)) => text,
+                                Ok(_) => continue,
+                                Err(err) => {
+                                    log::error!("Lost texthooker connection: {err}");
+                                    break;
+                                }
+                            };
+
+                            // The screen following the texthooker has gone, so stop
+                            if parse_line(&text).map_or(Ok(()), |line| sender.send(line)).is_err() {
+                                return;
+                            }
+                        }
+                    },
+                    Err(err) => log::error!("Failed to connect to texthooker at {url}: {err}"),
+                }
+
+                thread::sleep(RECONNECT_DELAY);
+            }
+        });
+
+        Self {
+            receiver,
+            seen: SeenLines::default(),
+            stopped,
+        }
+    }
+
+    /// Get the lines received since the last poll, leaving out ones we've seen recently
+    pub fn poll(&mut self) -> SrsResult<Vec<String>> {
+        let mut lines = Vec::new();
+
+        loop {
+            match self.receiver.try_recv() {
+                Ok(line) if self.seen.insert(&line) => lines.push(line),
+                Ok(_) => {},
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return Err("The texthooker thread stopped unexpectedly".into()),
+            }
+        }
+
+        if !lines.is_empty() {
+            log::info!("Got {} new lines from texthooker", lines.len());
+        }
+
+        Ok(lines)
+    }
+}
+
+impl Drop for TexthookerSocket {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Get the text from a line of a texthooker log, or None if there isn't any
+pub fn parse_line(line: &str) -> Option<String> {
+    let line = line.trim();
+
+    let text = match serde_json::from_str::<serde_json::Value>(line) {
+        Ok(serde_json::Value::Object(object)) => TEXT_FIELDS.iter()
+            .find_map(|field| object.get(*field).and_then(|value| value.as_str()))?
+            .trim()
+            .to_string(),
+        Ok(serde_json::Value::String(text)) => text.trim().to_string(),
+        _ => line.to_string(),
+    };
+
+    if text.is_empty() {
+        None
+    }
+    else {
+        Some(text)
+    }
+}
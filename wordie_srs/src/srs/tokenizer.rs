@@ -0,0 +1,35 @@
+#[cfg(feature = "tokenizer")]
+pub use self::default_tokenizer::*;
+
+/// A word from a sentence, along with where it's written in the sentence
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    /// The word as it becomes a card, e.g. in dictionary form
+    pub word: String,
+    /// The byte range of the word as it's written in the sentence
+    pub byte_start: usize,
+    pub byte_end: usize,
+}
+
+/// Splits sentences into the words that will become cards, so the algorithms can be built with a
+/// different tokenizer than the default one, e.g. for a language it doesn't handle
+pub trait Tokenizer: std::fmt::Debug + Send + Sync {
+    /// Split a sentence into words, along with where each word is written in the sentence
+    fn tokenize(&self, text: &str) -> Vec<Token>;
+}
+
+/// Convert katakana to hiragana, leaving everything else as it is
+pub fn katakana_to_hiragana(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            // The hiragana block is laid out the same as katakana, 0x60 earlier
+            'ァ'..='ヶ' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+            _ => c,
+        })
+        .collect()
+}
+
+/// The tokenizer used by default, charabia with lindera to find Japanese dictionary forms and
+/// readings
+#[cfg(feature = "tokenizer")]
+mod default_tokenizer;
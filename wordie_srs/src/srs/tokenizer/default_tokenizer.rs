@@ -0,0 +1,243 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use charabia::Tokenize;
+use lazy_static::lazy_static;
+use lindera::tokenizer::Tokenizer as LinderaTokenizer;
+
+use super::*;
+use crate::srs::normalize;
+
+lazy_static! {
+    /// Used to look up the dictionary forms of Japanese words, as charabia only gives us the
+    /// segmented text
+    static ref LINDERA: Option<LinderaTokenizer> = LinderaTokenizer::new()
+        .map_err(|err| log::error!("Failed to create lindera tokenizer, conjugated words won't be grouped: {err}"))
+        .ok();
+
+    /// Tokens already worked out this session, so previewing, editing and importing the same
+    /// text doesn't run the tokenizer again each time
+    static ref TOKEN_CACHE: Mutex<TokenCache> = Mutex::new(TokenCache::new(TOKEN_CACHE_CAPACITY));
+}
+
+/// The most texts to keep the tokens of, the least recently used are dropped past this
+const TOKEN_CACHE_CAPACITY: usize = 10_000;
+
+/// The index of the dictionary form in an ipadic token's details
+const IPADIC_BASE_FORM: usize = 6;
+
+/// The index of the reading (in katakana) in an ipadic token's details
+const IPADIC_READING: usize = 7;
+
+/// What one run of the tokenizer works out from a text
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Tokenized {
+    tokens: Vec<Token>,
+    /// See `likely_names`
+    likely_names: Vec<String>,
+}
+
+/// The tokenized texts used most recently, up to a capacity
+#[derive(Debug)]
+struct TokenCache {
+    capacity: usize,
+    /// Each text's tokens, and when they were last used
+    entries: HashMap<String, (u64, Tokenized)>,
+    /// The texts by when they were last used, oldest first
+    last_used: BTreeMap<u64, String>,
+    /// Counts up each time the cache is used, to order the texts by
+    clock: u64,
+}
+
+impl TokenCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            last_used: BTreeMap::new(),
+            clock: 0,
+        }
+    }
+
+    /// Get a text's tokens if they're cached, making it the most recently used
+    fn get(&mut self, text: &str) -> Option<Tokenized> {
+        self.clock += 1;
+        let (used, tokenized) = self.entries.get_mut(text)?;
+
+        let text = self.last_used.remove(&*used).unwrap_or_else(|| text.to_string());
+        *used = self.clock;
+        self.last_used.insert(self.clock, text);
+
+        Some(tokenized.clone())
+    }
+
+    /// Cache a text's tokens, dropping the least recently used text if the cache is full
+    fn insert(&mut self, text: &str, tokenized: Tokenized) {
+        self.clock += 1;
+
+        if let Some((used, _)) = self.entries.remove(text) {
+            self.last_used.remove(&used);
+        }
+        else if self.entries.len() >= self.capacity {
+            if let Some((_, oldest)) = self.last_used.pop_first() {
+                self.entries.remove(&oldest);
+            }
+        }
+
+        self.entries.insert(text.to_string(), (self.clock, tokenized));
+        self.last_used.insert(self.clock, text.to_string());
+    }
+}
+
+/// The tokenizer the algorithms use unless they're given another one, see `tokenize_with_offsets`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultTokenizer;
+
+impl Tokenizer for DefaultTokenizer {
+    fn tokenize(&self, text: &str) -> Vec<Token> {
+        tokenize_with_offsets(text)
+    }
+}
+
+/// Tokenize a sentence into the words that will become cards. Conjugated Japanese words are
+/// mapped to their dictionary form, so e.g. 食べた and 食べません both give 食べる.
+pub fn tokenize(text: &str) -> Vec<String> {
+    tokenize_with_offsets(text)
+        .into_iter()
+        .map(|token| token.word)
+        .collect()
+}
+
+/// Tokenize a sentence like `tokenize`, keeping where each word is written in the sentence
+pub fn tokenize_with_offsets(text: &str) -> Vec<Token> {
+    tokenize_cached(text).tokens
+}
+
+/// Find the words in a sentence that look like proper names, as they'd be returned by `tokenize`.
+/// This is only a heuristic: words written entirely in katakana (which also catches loanwords),
+/// and capitalized western words that aren't at the start of the sentence.
+pub fn likely_names(text: &str) -> Vec<String> {
+    tokenize_cached(text).likely_names
+}
+
+/// Get a sentence's tokens from the cache, or run the tokenizer and cache them
+fn tokenize_cached(text: &str) -> Tokenized {
+    if let Some(tokenized) = TOKEN_CACHE.lock().unwrap().get(text) {
+        return tokenized;
+    }
+
+    let tokenized = tokenize_uncached(text);
+    TOKEN_CACHE.lock().unwrap().insert(text, tokenized.clone());
+
+    tokenized
+}
+
+/// Run the tokenizer on a sentence, see `tokenize_with_offsets` and `likely_names`
+fn tokenize_uncached(text: &str) -> Tokenized {
+    let base_forms = japanese_base_forms(text);
+    let mut tokenized = Tokenized::default();
+
+    for (i, token) in text.tokenize().filter(|token| token.is_word()).enumerate() {
+        let lemma = token.lemma.to_string();
+        tokenized.tokens.push(Token {
+            word: normalize::word(base_forms.get(&lemma).unwrap_or(&lemma)),
+            byte_start: token.byte_start,
+            byte_end: token.byte_end,
+        });
+
+        let original = &text[token.byte_start..token.byte_end];
+        let katakana = original.chars().count() > 1 && original.chars().all(is_katakana);
+        let capitalized = i > 0 && original.chars().next().is_some_and(char::is_uppercase);
+
+        // Normalized the same as the tokenized words, so the names match their cards
+        let name = normalize::word(&lemma);
+        if (katakana || capitalized) && !tokenized.likely_names.contains(&name) {
+            tokenized.likely_names.push(name);
+        }
+    }
+
+    tokenized
+}
+
+/// Map the conjugated Japanese words in a sentence to their dictionary forms, e.g. 食べ -> 食べる
+fn japanese_base_forms(text: &str) -> HashMap<String, String> {
+    let tokens = match LINDERA.as_ref().map(|lindera| lindera.tokenize(text)) {
+        Some(Ok(tokens)) => tokens,
+        _ => return HashMap::new(),
+    };
+
+    tokens.into_iter()
+        .filter_map(|token| {
+            let base_form = token.detail.get(IPADIC_BASE_FORM)?;
+
+            // Words that aren't in the dictionary have a base form of "*"
+            if base_form == "*" || base_form == token.text {
+                None
+            }
+            else {
+                Some((token.text.to_string(), base_form.clone()))
+            }
+        })
+        .collect()
+}
+
+/// Look up how a Japanese word is read, in hiragana, or in katakana as the dictionary gives it if
+/// `to_hiragana` is false. Returns None if any part of the word isn't in the dictionary, e.g. for
+/// words in other languages.
+pub fn reading(word: &str, to_hiragana: bool) -> Option<String> {
+    let tokens = LINDERA.as_ref()?.tokenize(word).ok()?;
+    if tokens.is_empty() {
+        return None;
+    }
+
+    tokens.iter()
+        .map(|token| token.detail.get(IPADIC_READING)
+            .filter(|reading| *reading != "*")
+            .map(|reading| normalize::reading(reading, to_hiragana)))
+        .collect()
+}
+
+/// Whether a character is katakana, including the long vowel mark and middle dot used in names
+fn is_katakana(c: char) -> bool {
+    matches!(c, '\u{30A0}'..='\u{30FF}' | '\u{31F0}'..='\u{31FF}' | '\u{FF66}'..='\u{FF9F}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokenized(word: &str) -> Tokenized {
+        Tokenized {
+            tokens: vec![Token { word: word.to_string(), byte_start: 0, byte_end: word.len() }],
+            likely_names: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn the_least_recently_used_text_is_dropped() {
+        let mut cache = TokenCache::new(2);
+        cache.insert("a", tokenized("a"));
+        cache.insert("b", tokenized("b"));
+
+        // Using a makes b the oldest
+        assert_eq!(cache.get("a"), Some(tokenized("a")));
+        cache.insert("c", tokenized("c"));
+
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(cache.get("a"), Some(tokenized("a")));
+        assert_eq!(cache.get("c"), Some(tokenized("c")));
+    }
+
+    #[test]
+    fn inserting_a_cached_text_replaces_it() {
+        let mut cache = TokenCache::new(2);
+        cache.insert("a", tokenized("a"));
+        cache.insert("b", tokenized("b"));
+        cache.insert("a", tokenized("x"));
+
+        assert_eq!(cache.entries.len(), 2);
+        assert_eq!(cache.last_used.len(), 2);
+        assert_eq!(cache.get("a"), Some(tokenized("x")));
+        assert_eq!(cache.get("b"), Some(tokenized("b")));
+    }
+}
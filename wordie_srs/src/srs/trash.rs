@@ -0,0 +1,28 @@
+use chrono::NaiveDateTime;
+
+use super::Sentence;
+
+#[cfg(feature = "mysql")]
+pub(crate) use self::storage::*;
+
+/// The number of days sentences stay in the trash before they're deleted for good
+pub const TRASH_RETENTION_DAYS: i64 = 30;
+
+/// A sentence in the trash
+#[derive(Debug, Clone)]
+pub struct TrashedSentence {
+    pub sentence: Sentence,
+    /// When the sentence was moved to the trash, in utc
+    pub deleted: NaiveDateTime,
+}
+
+impl TrashedSentence {
+    /// When the sentence will be deleted for good, in utc
+    pub fn expires(&self) -> NaiveDateTime {
+        self.deleted + chrono::Duration::days(TRASH_RETENTION_DAYS)
+    }
+}
+
+/// The trash table and purging old sentences from it
+#[cfg(feature = "mysql")]
+mod storage;
@@ -0,0 +1,44 @@
+use std::str::FromStr;
+
+use mysql::{PooledConn, prelude::Queryable, params};
+use uuid::Uuid;
+
+use super::*;
+use crate::srs::SrsResult;
+
+/// Move sentences to the trash. Sentences that are already in the trash keep their deletion time.
+pub(crate) fn trash(conn: &mut PooledConn, ids: &[Uuid], now: NaiveDateTime) -> SrsResult<()> {
+    conn.exec_batch("UPDATE sentences SET deleted = :now WHERE id = :id && deleted IS NULL",
+        ids.iter().map(|id| params! {
+            "id" => id.to_string(),
+            "now" => now,
+        }))?;
+
+    Ok(())
+}
+
+/// Take sentences back out of the trash
+pub(crate) fn restore(conn: &mut PooledConn, ids: &[Uuid]) -> SrsResult<()> {
+    conn.exec_batch("UPDATE sentences SET deleted = NULL WHERE id = :id",
+        ids.iter().map(|id| params! { "id" => id.to_string() }))?;
+
+    Ok(())
+}
+
+/// Load the sentences in the trash, most recently deleted first
+pub(crate) fn load(conn: &mut PooledConn) -> SrsResult<Vec<TrashedSentence>> {
+    Ok(conn.query_map("SELECT id, text, deleted FROM sentences WHERE deleted IS NOT NULL ORDER BY deleted DESC",
+        |(id, text, deleted): (String, String, NaiveDateTime)| TrashedSentence {
+            sentence: Sentence {
+                id: Uuid::from_str(&id).unwrap(),
+                text,
+            },
+            deleted,
+        })?)
+}
+
+/// Get the ids of the sentences that have been in the trash for longer than the retention period
+pub(crate) fn expired(conn: &mut PooledConn, now: NaiveDateTime) -> SrsResult<Vec<String>> {
+    Ok(conn.exec("SELECT id FROM sentences WHERE deleted < :cutoff",
+        params! { "cutoff" => now - chrono::Duration::days(TRASH_RETENTION_DAYS) })?)
+}
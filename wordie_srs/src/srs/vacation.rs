@@ -0,0 +1,20 @@
+use chrono::NaiveDateTime;
+
+#[cfg(feature = "mysql")]
+pub(crate) use self::storage::*;
+
+/// A break from reviewing. When it ends, every due date is moved forward by the number of whole
+/// days it lasted, so the cards that came due in the meantime don't all pile up at once.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vacation {
+    /// When the vacation started and ended, in UTC
+    pub started: NaiveDateTime,
+    pub ended: Option<NaiveDateTime>,
+    /// The number of days the due dates were moved forward by when it ended
+    pub days_shifted: i32,
+    pub cards_shifted: i32,
+}
+
+/// Storing vacations and shifting cards around them
+#[cfg(feature = "mysql")]
+mod storage;
@@ -0,0 +1,116 @@
+use mysql::{PooledConn, prelude::Queryable, params};
+
+use super::*;
+use crate::srs::{SrsResult, CollectionStats};
+use crate::srs::{revision, sync};
+
+/// Create the vacations table, shared by all the algorithms. Ended vacations are kept, as a record
+/// of every time the due dates were shifted.
+pub(crate) fn create_table(conn: &mut PooledConn) -> SrsResult<()> {
+    conn.query_drop(r"
+        CREATE TABLE IF NOT EXISTS vacations (
+            started DATETIME NOT NULL,
+            ended DATETIME,
+            days_shifted INT NOT NULL DEFAULT 0,
+            cards_shifted INT NOT NULL DEFAULT 0,
+            PRIMARY KEY (started)
+        )
+    ")?;
+
+    Ok(())
+}
+
+/// Get the vacation that's going on, if there is one
+pub(crate) fn current(conn: &mut PooledConn) -> SrsResult<Option<Vacation>> {
+    Ok(conn.query_first("SELECT started FROM vacations WHERE ended IS NULL ORDER BY started DESC")?
+        .map(|started| Vacation { started, ended: None, days_shifted: 0, cards_shifted: 0 }))
+}
+
+/// Clear the due counts in `stats` while on vacation, as nothing gets reviewed until it ends, so
+/// reminders and due badges don't go off for cards that won't be shown
+pub(crate) fn pause_counts(conn: &mut PooledConn, stats: CollectionStats) -> SrsResult<CollectionStats> {
+    if current(conn)?.is_none() {
+        return Ok(stats);
+    }
+
+    Ok(CollectionStats {
+        due_cards: 0,
+        learning_due_later: 0,
+        ..stats
+    })
+}
+
+/// Start a vacation at `now`
+pub(crate) fn start(conn: &mut PooledConn, now: NaiveDateTime) -> SrsResult<()> {
+    if current(conn)?.is_some() {
+        return Err("Already on vacation".into());
+    }
+
+    conn.exec_drop("INSERT INTO vacations (started) VALUES (:started)", params! { "started" => now })?;
+
+    // Nothing's due any more, so anything polling the counts needs to fetch them again
+    revision::bump(conn)?;
+
+    Ok(())
+}
+
+/// End the vacation at `now`, moving the due date of every card that has one forward by the whole
+/// days the vacation lasted. The shift, the record of it and the sync log entries for it are made in
+/// one transaction, so the cards are never shifted without it being recorded or the other way
+/// around. Wordie's cards, which have a `modified` column and are synced by word, should set
+/// `synced`, so the shift gets synced.
+pub(crate) fn end(conn: &mut PooledConn, now: NaiveDateTime, synced: bool) -> SrsResult<Vacation> {
+    let mut vacation = current(conn)?.ok_or("Not on vacation")?;
+    let days = (now - vacation.started).num_days().max(0) as i32;
+
+    // Roll back if either half fails, so it's as if the vacation hadn't ended
+    conn.query_drop("START TRANSACTION")?;
+
+    let cards = match shift_cards(conn, &vacation, now, days, synced) {
+        Ok(cards) => cards,
+        Err(err) => {
+            conn.query_drop("ROLLBACK")?;
+            return Err(err);
+        }
+    };
+
+    conn.query_drop("COMMIT")?;
+
+    log::info!("Vacation from {} ended, moved {cards} cards forward by {days} days", vacation.started);
+
+    vacation.ended = Some(now);
+    vacation.days_shifted = days;
+    vacation.cards_shifted = cards;
+
+    Ok(vacation)
+}
+
+/// Move the due dates forward and record it against the vacation, returns the number of cards moved
+fn shift_cards(conn: &mut PooledConn, vacation: &Vacation, now: NaiveDateTime, days: i32, synced: bool) -> SrsResult<i32> {
+    let set_modified = if synced { ", modified = :now" } else { "" };
+
+    conn.exec_drop(
+        format!("UPDATE cards SET due = DATE_ADD(due, INTERVAL :days DAY){set_modified} WHERE due IS NOT NULL"),
+        params! { "days" => days, "now" => now })?;
+    let cards = conn.affected_rows() as i32;
+
+    conn.exec_drop(
+        r"UPDATE vacations SET ended = :ended, days_shifted = :days, cards_shifted = :cards
+          WHERE started = :started",
+        params! {
+            "ended" => now,
+            "days" => days,
+            "cards" => cards,
+            "started" => vacation.started,
+        })?;
+
+    if cards > 0 {
+        if synced {
+            sync::record_card_changes(conn, "cards.card_type = 'recognition' && cards.due IS NOT NULL")?;
+        }
+
+        revision::bump(conn)?;
+    }
+
+    Ok(cards)
+}
@@ -0,0 +1,104 @@
+use std::collections::HashSet;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use uuid::Uuid;
+
+use super::{SrsAlgorithm, SrsResult};
+use super::audio_source;
+use super::page::PageRequest;
+
+#[cfg(feature = "mysql")]
+pub(crate) use self::storage::*;
+
+/// What was cleaned up by a vacuum
+#[derive(Debug, Clone, Default)]
+pub struct VacuumReport {
+    /// Words that weren't in any sentence and had never been studied
+    pub unused_words: usize,
+    /// Review log entries older than the retention window
+    pub old_reviews: usize,
+    /// The approximate space freed on disk, in bytes
+    pub reclaimed_bytes: i64,
+    /// Media files deleted for sentences that are gone, see `remove_unreferenced_media`. The
+    /// algorithms don't know where the media is kept, so this is filled in by whoever does.
+    pub unreferenced_media: usize,
+}
+
+/// Where the media for sentences is kept
+#[derive(Debug, Clone)]
+pub struct MediaDirs {
+    /// Audio fetched for sentences, named by their text, see `audio_source::cache_path`
+    pub sentence_audio: PathBuf,
+    /// Recordings of reviews, in a folder for each sentence named by its id
+    pub recordings: PathBuf,
+}
+
+/// Delete the media for sentences that aren't in the collection or its trash anymore: audio cached
+/// for texts no sentence has, e.g. after a sentence was edited, and the recordings of sentences
+/// that were deleted for good. Audio for single words is kept, as it's shared between sentences.
+/// Returns how many files were deleted.
+pub fn remove_unreferenced_media<A>(srs_algorithm: &A, dirs: &MediaDirs) -> SrsResult<usize>
+    where A: SrsAlgorithm + ?Sized
+{
+    let mut texts = HashSet::new();
+    let mut ids = HashSet::new();
+
+    let mut page = Some(PageRequest::default());
+    while let Some(request) = page {
+        let sentences = srs_algorithm.list_sentences(request)?;
+        for sentence in sentences.items {
+            ids.insert(sentence.id);
+            texts.insert(sentence.text);
+        }
+        page = sentences.next;
+    }
+
+    for trashed in srs_algorithm.get_trash()? {
+        ids.insert(trashed.sentence.id);
+        texts.insert(trashed.sentence.text);
+    }
+
+    let audio_files: HashSet<PathBuf> = texts.iter()
+        .map(|text| audio_source::cache_path(&dirs.sentence_audio, text).with_extension(""))
+        .collect();
+
+    let mut removed = 0;
+
+    // Audio is cached as an mp3, or a marker if there wasn't any for the text
+    for path in files_in(&dirs.sentence_audio)? {
+        let is_audio = path.extension().is_some_and(|ext| ext == "mp3" || ext == "missing");
+        if path.is_file() && is_audio && !audio_files.contains(&path.with_extension("")) {
+            log::info!("Removing unreferenced audio {path:?}");
+            std::fs::remove_file(&path)?;
+            removed += 1;
+        }
+    }
+
+    for path in files_in(&dirs.recordings)? {
+        let sentence_id = path.file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| Uuid::parse_str(name).ok());
+
+        if path.is_dir() && sentence_id.is_some_and(|id| !ids.contains(&id)) {
+            log::info!("Removing recordings of deleted sentence {path:?}");
+            removed += files_in(&path)?.len();
+            std::fs::remove_dir_all(&path)?;
+        }
+    }
+
+    Ok(removed)
+}
+
+/// The paths of everything in a folder, or nothing if it doesn't exist
+fn files_in(dir: &Path) -> SrsResult<Vec<PathBuf>> {
+    match std::fs::read_dir(dir) {
+        Ok(entries) => Ok(entries.map(|entry| entry.map(|entry| entry.path())).collect::<Result<_, _>>()?),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Cleaning up the database
+#[cfg(feature = "mysql")]
+mod storage;
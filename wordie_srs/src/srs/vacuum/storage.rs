@@ -0,0 +1,22 @@
+use mysql::{PooledConn, prelude::Queryable};
+
+use super::*;
+use crate::srs::SrsResult;
+
+/// Get the approximate size of the current database on disk, in bytes
+pub(crate) fn database_size(conn: &mut PooledConn) -> SrsResult<i64> {
+    let size: Option<i64> = conn.query_first(
+        r"SELECT CAST(COALESCE(SUM(data_length + index_length), 0) AS SIGNED)
+          FROM information_schema.tables
+          WHERE table_schema = DATABASE()")?;
+
+    Ok(size.unwrap_or(0))
+}
+
+/// Rebuild tables so the space from deleted rows is given back, this also updates the table
+/// sizes in information_schema
+pub(crate) fn compact(conn: &mut PooledConn, tables: &[&str]) -> SrsResult<()> {
+    log::info!("Compacting tables {tables:?}");
+    conn.query_drop(format!("OPTIMIZE TABLE {}", tables.join(", ")))?;
+    Ok(())
+}
@@ -0,0 +1,128 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::SrsResult;
+use super::sentence_splitter::SentenceSplitter;
+
+/// Extensions of the files picked up from a watch folder
+pub const WATCH_EXTENSIONS: &[&str] = &["txt", "srt", "vtt", "ass", "ssa"];
+
+/// The folder inside the watch folder that files are moved to once they've been imported
+const IMPORTED_DIR: &str = "imported";
+
+/// How long a file has to go without changing before it's picked up, so a file that's still being
+/// written by another tool isn't imported half finished
+const SETTLE_TIME: Duration = Duration::from_secs(5);
+
+/// A folder that other tools can drop text and subtitle files into, to have them imported
+pub struct WatchFolder {
+    dir: PathBuf,
+}
+
+impl WatchFolder {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    /// Get the files waiting to be imported, in name order. Files changed in the last few seconds
+    /// are left for next time, as they may not have been written in full yet.
+    pub fn pending(&self) -> SrsResult<Vec<PathBuf>> {
+        let mut files = Vec::new();
+
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            let extension = path.extension().and_then(|ext| ext.to_str()).map(|ext| ext.to_ascii_lowercase());
+
+            if path.is_file() && extension.is_some_and(|ext| WATCH_EXTENSIONS.contains(&ext.as_str())) && is_settled(&path) {
+                files.push(path);
+            }
+        }
+
+        files.sort();
+
+        Ok(files)
+    }
+
+    /// Read the sentences from a file, taking just the dialogue from subtitles
+    pub fn read(&self, path: &Path, splitter: &SentenceSplitter) -> SrsResult<Vec<String>> {
+        let contents = std::fs::read_to_string(path)?;
+
+        let text = match path.extension().and_then(|ext| ext.to_str()).map(|ext| ext.to_ascii_lowercase()).as_deref() {
+            Some("srt") | Some("vtt") => srt_text(&contents),
+            Some("ass") | Some("ssa") => ass_text(&contents),
+            _ => contents,
+        };
+
+        Ok(splitter.split(&text))
+    }
+
+    /// Move a file out of the way once it's been imported, so it isn't imported again
+    pub fn mark_imported(&self, path: &Path) -> SrsResult<()> {
+        let imported_dir = self.dir.join(IMPORTED_DIR);
+        std::fs::create_dir_all(&imported_dir)?;
+
+        let file_name = path.file_name().ok_or_else(|| format!("{path:?} isn't a file"))?;
+        std::fs::rename(path, imported_dir.join(file_name))?;
+
+        Ok(())
+    }
+}
+
+/// Whether a file hasn't changed for `SETTLE_TIME`. Files whose modified time can't be read are
+/// taken as settled, so they're still picked up on platforms without one.
+fn is_settled(path: &Path) -> bool {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_none_or(|age| age >= SETTLE_TIME)
+}
+
+/// The tag for sentences imported from a file, its file name
+pub fn file_tag(path: &Path) -> String {
+    path.file_name().map_or(String::new(), |name| name.to_string_lossy().to_string())
+}
+
+/// Get the dialogue from srt or vtt subtitles, leaving out cue numbers, timings and formatting
+fn srt_text(contents: &str) -> String {
+    contents.lines()
+        .map(|line| strip_tags(line.trim(), '<', '>'))
+        .filter(|line| !line.is_empty()
+                    && !line.contains("-->")
+                    && !line.starts_with("WEBVTT")
+                    && !line.chars().all(|c| c.is_ascii_digit()))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Get the dialogue from ass or ssa subtitles, which is the last field of each Dialogue line
+fn ass_text(contents: &str) -> String {
+    contents.lines()
+        .filter_map(|line| line.strip_prefix("Dialogue:"))
+        .filter_map(|line| line.splitn(10, ',').nth(9))
+        .map(|text| strip_tags(&text.replace("\\N", "\n").replace("\\n", "\n"), '{', '}'))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Remove anything between `open` and `close`, e.g. html tags or ass override codes
+fn strip_tags(text: &str, open: char, close: char) -> String {
+    let mut result = String::new();
+    let mut in_tag = false;
+
+    for c in text.chars() {
+        if c == open {
+            in_tag = true;
+        }
+        else if c == close && in_tag {
+            in_tag = false;
+        }
+        else if !in_tag {
+            result.push(c);
+        }
+    }
+
+    result
+}
@@ -0,0 +1,133 @@
+use std::process::Command;
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{Difficulty, Review, Sentence, SrsResult};
+use super::daily_stats::DailyStats;
+use super::plugins::Plugin;
+
+/// The longest to wait for a webhook request, so a dead server doesn't back the queue up
+const REQUEST_TIMEOUT_SECS: u32 = 10;
+
+/// A url to POST json events to, set in the config file, e.g.
+/// `"webhooks": [{ "url": "http://localhost:8080/wordie", "events": ["review_completed"] }]`
+#[derive(Debug, Clone, Deserialize)]
+pub struct Webhook {
+    pub url: String,
+    /// The events to send, or every event if empty
+    #[serde(default)]
+    pub events: Vec<WebhookEvent>,
+}
+
+/// The kinds of events that can be sent to a webhook
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    ReviewCompleted,
+    DailyGoalReached,
+    ImportFinished,
+}
+
+impl WebhookEvent {
+    /// The name of the event, as it's written in the config file and the event's json
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::ReviewCompleted => "review_completed",
+            WebhookEvent::DailyGoalReached => "daily_goal_reached",
+            WebhookEvent::ImportFinished => "import_finished",
+        }
+    }
+}
+
+impl Webhook {
+    fn wants(&self, event: WebhookEvent) -> bool {
+        self.events.is_empty() || self.events.contains(&event)
+    }
+}
+
+/// A plugin that sends events to webhooks. Requests are made with curl on a background thread, so
+/// a slow server doesn't hold up reviews, and failed requests are logged and dropped.
+pub struct WebhookPlugin {
+    webhooks: Vec<Webhook>,
+    sender: Sender<(String, String)>,
+}
+
+impl WebhookPlugin {
+    pub fn new(webhooks: Vec<Webhook>) -> Self {
+        let (sender, receiver) = mpsc::channel::<(String, String)>();
+
+        thread::spawn(move || {
+            for (url, body) in receiver {
+                if let Err(err) = post(&url, &body) {
+                    log::error!("Failed to send webhook to {url}: {err}");
+                }
+            }
+        });
+
+        Self {
+            webhooks,
+            sender,
+        }
+    }
+
+    /// Queue an event to be sent to every webhook that wants it
+    fn send(&self, event: WebhookEvent, mut body: serde_json::Value) -> SrsResult<()> {
+        body["event"] = json!(event.as_str());
+        body["time"] = json!(chrono::Utc::now().to_rfc3339());
+        let body = body.to_string();
+
+        for webhook in self.webhooks.iter().filter(|webhook| webhook.wants(event)) {
+            self.sender.send((webhook.url.clone(), body.clone()))
+                .map_err(|_| "The webhook thread has stopped")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Plugin for WebhookPlugin {
+    fn name(&self) -> &str {
+        "webhooks"
+    }
+
+    fn on_sentences_imported(&mut self, sentences: &[Sentence]) -> SrsResult<()> {
+        self.send(WebhookEvent::ImportFinished, json!({
+            "sentences": sentences.len(),
+        }))
+    }
+
+    fn on_review_answered(&mut self, review: &Review, difficulty: Difficulty) -> SrsResult<()> {
+        self.send(WebhookEvent::ReviewCompleted, json!({
+            "sentence_id": review.sentence().id.to_string(),
+            "sentence": review.sentence().text,
+            "card_type": format!("{:?}", review.card_type()).to_lowercase(),
+            "difficulty": format!("{difficulty:?}").to_lowercase(),
+        }))
+    }
+
+    fn on_daily_goals_met(&mut self, stats: &DailyStats) -> SrsResult<()> {
+        self.send(WebhookEvent::DailyGoalReached, json!({
+            "day": stats.day.to_string(),
+            "cards_learned": stats.cards_learned,
+            "cards_reviewed": stats.cards_reviewed,
+        }))
+    }
+}
+
+/// POST a json body to a url
+fn post(url: &str, body: &str) -> SrsResult<()> {
+    let output = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--max-time", &REQUEST_TIMEOUT_SECS.to_string(),
+               "--header", "Content-Type: application/json", "--data-binary", body, url])
+        .output()
+        .map_err(|e| format!("Failed to run curl, is it installed? {e}"))?;
+
+    if !output.status.success() {
+        return Err(format!("Webhook request failed: {}", String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+
+    Ok(())
+}
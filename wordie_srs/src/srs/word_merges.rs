@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+
+use mysql::{PooledConn, prelude::Queryable, params};
+
+use super::SrsResult;
+use super::normalize;
+use super::tokenizer::Token;
+
+/// Create the table of user-defined merge rules, for conjugated forms etc that the tokenizer
+/// doesn't map to the same word
+pub(crate) fn create_table(conn: &mut PooledConn) -> SrsResult<()> {
+    conn.query_drop(r"
+        CREATE TABLE IF NOT EXISTS word_merges (
+            word VARCHAR(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci NOT NULL,
+            into_word VARCHAR(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci NOT NULL,
+            PRIMARY KEY (word)
+        )
+    ")?;
+
+    Ok(())
+}
+
+/// Add a rule merging `word` into `into`
+pub(crate) fn add(conn: &mut PooledConn, word: &str, into: &str) -> SrsResult<()> {
+    // Rules are matched against tokenized words, which are normalized
+    let (word, into) = (normalize::word(word), normalize::word(into));
+
+    conn.exec_drop("REPLACE INTO word_merges (word, into_word) VALUES (:word, :into)",
+        params! {
+            "word" => word.as_str(),
+            "into" => into.as_str(),
+        })?;
+
+    // Anything that was merged into `word` now ends up in `into` too, so rules never chain
+    conn.exec_drop("UPDATE word_merges SET into_word = :into WHERE into_word = :word",
+        params! {
+            "word" => word.as_str(),
+            "into" => into.as_str(),
+        })?;
+
+    Ok(())
+}
+
+/// Bring merge rules stored before words were normalized in line, see `normalize::word`
+pub(crate) fn normalize_stored(conn: &mut PooledConn) -> SrsResult<()> {
+    let merges: Vec<(String, String)> = conn.query("SELECT word, into_word FROM word_merges")?;
+
+    for (word, into) in merges {
+        let (normalized_word, normalized_into) = (normalize::word(&word), normalize::word(&into));
+        if normalized_word == word && normalized_into == into {
+            continue;
+        }
+
+        conn.exec_drop("DELETE FROM word_merges WHERE word = BINARY :word",
+            params! { "word" => word.as_str() })?;
+
+        // A rule between two spellings of the same word isn't needed anymore
+        if normalized_word != normalized_into {
+            add(conn, &normalized_word, &normalized_into)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Load the merge rules, as a map from each word to the word it's merged into
+pub(crate) fn load(conn: &mut PooledConn) -> SrsResult<HashMap<String, String>> {
+    Ok(conn.query::<(String, String), _>("SELECT word, into_word FROM word_merges")?
+        .into_iter()
+        .collect())
+}
+
+/// Apply merge rules to tokenized words
+pub(crate) fn apply(merges: &HashMap<String, String>, tokens: Vec<Token>) -> Vec<Token> {
+    tokens.into_iter()
+        .map(|token| match merges.get(&token.word) {
+            Some(into) => Token { word: into.clone(), ..token },
+            None => token,
+        })
+        .collect()
+}
@@ -1,393 +1,555 @@
-use std::{str::FromStr, time::Duration};
-use chrono::{DateTime, Local, Timelike, NaiveDateTime};
-use lazy_static::lazy_static;
-use mysql::{prelude::*, Pool, params};
-use charabia::Tokenize;
+use std::{str::FromStr, time::Duration, cell::RefCell, collections::{HashSet, HashMap}, sync::Arc};
+use chrono::{Datelike, NaiveDateTime};
+use mysql::{prelude::*, params, PooledConn, Value};
 use uuid::Uuid;
 
 use crate::srs::Sentence;
 
-use super::{SrsAlgorithm, SrsResult, Review, Difficulty};
-
-lazy_static! {
-    /// The initial intervals for new cards
-    static ref INITIAL_INTERVALS: [Duration; 3] = [
-        Duration::from_secs(1 * 60),
-        Duration::from_secs(10 * 60),
-        Duration::from_secs(24 * 60 * 60),
-    ];
-}
-
-/// The default ease
-const DEFAULT_EASE: f32 = 2.5;
-
-/// The minimum ease
-const MINIMUM_EASE: f32 = 1.3;
-
-/// The easy bonus
-const EASY_BONUS: f64 = 1.3;
-
-/// The hard interval
-const HARD_INTERVAL: f64 = 1.2;
-
-/// The max number of cards in learning state at once
-const MAX_LEARNING_CARDS: i32 = 10;
+use super::{SrsAlgorithm, SrsResult, Review, Difficulty, SentencePreview, CollectionStats, CardType, ReviewRecord, KnowledgeState, LearningCard,
+    QueueState, SelectionReason, NewWord, WordInfo};
+use super::tokenizer::{self, Token, Tokenizer, DefaultTokenizer};
+use super::normalize;
+use super::forecast;
+use super::connection::{ConnectionPool, ConnectionState, ConnectionOptions};
+use super::daily_stats::{self, DailyStats};
+use super::import::ImportReport;
+use super::sync::{self, SyncStore, SyncChange};
+use super::db;
+use super::blacklist::{self, NOT_EXCLUDED};
+use super::names;
+use super::word_merges;
+use super::expressions::{self, Expression};
+use super::kanji::{self, KanjiStats};
+use super::levels::{self, LevelProgress};
+use super::integrity::{self, IntegrityReport};
+use super::vacuum::{self, VacuumReport};
+use super::rebuild::{self, RebuildReport};
+use super::trash::{self, TrashedSentence};
+use super::cloze::Cloze;
+use super::page::{self, Page, PageRequest};
+use super::scheduler::{self, Schedule, SchedulerConfig, Snoozes, INITIAL_INTERVALS, DEFAULT_EASE, DEFAULT_NEW_CARD_LIMIT, DUE_NOW, IN_LEARNING_QUEUE, LEARNING_QUEUE_INTERVAL};
+use super::revision::{self, CountsRevision};
+use super::tags;
+use super::targets::{self, TargetWord};
+use super::grammar::{self, GrammarStats};
+use super::flags::{self, Flag};
+use super::retention;
+use super::revlog::{self, RevlogEntry};
+use super::sources::{self, SentenceSource, SourceStats, FROM_SOURCE};
+use super::stats_export::StatsExport;
+use super::clock::Clock;
+use super::config::WeekdayLimits;
+use super::suggestions::{self, UnlockingWord, WordGraph};
+use super::spread::{self, SpreadReport, MIN_SPREAD_INTERVAL};
+use super::vacation::{self, Vacation};
+
+/// The interval after which a card is considered mature
+const MATURE_INTERVAL_SECS: u64 = 21 * 24 * 60 * 60;
+
+/// All the tables, in an order they can be dropped in without breaking foreign keys
+const TABLES: &[&str] = &["sentence_words", "cards", "sentences", "words", "reviews", "daily_stats", "source_daily_stats", "sync_log",
+    "sync_state", "word_blacklist", "word_suspensions", "name_flags", "word_merges", "expressions", "kanji", "word_levels",
+    "collection_revision", "sentence_tags", "sentence_targets", "grammar_points", "scheduler_settings", "deck_scheduler_settings",
+    "sentence_sources", "sentence_flags", "vacations"];
 
 /// A card
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Card {
     word_id: String,
-    due: Option<NaiveDateTime>,
-    interval: Option<Duration>,
-    review_count: i32,
-    ease: f32,
+    card_type: CardType,
+    schedule: Schedule,
 }
 
-impl Card {
-    fn review(&mut self, time_now: DateTime<Local>, score: Difficulty) -> SrsResult<()> {
-        // https://faqs.ankiweb.net/what-spaced-repetition-algorithm.html
-        // For learning/relearning the algorithm is a bit different. We track if a card is
-        // currently in the learning stage by its review count, if there's a corresponding entry in
-        // INITIAL_INTERVALS that's one of the initial learning stages, once it passes out of there
-        // it graduates to no longer being a new card.
-        if self.review_count < INITIAL_INTERVALS.len() as i32 {
-            // For cards in learning/relearning:
-            // * Again moves the card back to the first stage of the new card intervals
-            // * Hard repeats the current step
-            // * Good moves the card to the next step, if the card was on the final step, it is
-            //   converted into a review card
-            // * Easy immediately converts the card into a review card
-            // There are no ease adjustments for new cards.
-            self.review_count = match score {
-                Difficulty::Again => 0,
-                Difficulty::Hard => self.review_count,
-                Difficulty::Good => self.review_count + 1,
-                Difficulty::Easy => INITIAL_INTERVALS.len() as i32,
-            };
-
-            let interval_index = i32::clamp(self.review_count, 0, INITIAL_INTERVALS.len() as i32 - 1);
-            let new_interval = INITIAL_INTERVALS[interval_index as usize];
-            let new_due = time_now + chrono::Duration::from_std(new_interval)?;
+/// Write-through cache of cards, so reviewing sentences with words we've already seen this session
+/// doesn't have to load their cards from the db again. Only cards we've loaded ourselves are
+/// cached, and the db is always updated at the same time as the cache, so it only goes stale if
+/// another process reviews the same collection.
+#[derive(Default)]
+struct CardCache {
+    /// Cards by word id and type
+    cards: HashMap<(String, CardType), Card>,
+    /// The word ids with cards of each type in each sentence
+    sentence_words: HashMap<(Uuid, CardType), Vec<String>>,
+}
 
-            self.interval = Some(new_interval);
-            self.due = Some(new_due.naive_utc());
-        }
-        else {
-            // For cards that have graduated learning:
-            // * Again puts the card back into learning mode, and decreases the ease by 20%
-            // * Hard multiplies the current interval by the hard interval (1.2 by default) and
-            //   decreases the ease by 15%
-            // * Good multiplies the current interval by the ease
-            // * Easy multiplies the current interval by the ease times the easy bonus (1.3 by
-            //   default) and increases the ease by 15%
-            let (new_interval, new_ease, new_review_count) = match score {
-                Difficulty::Again => {
-                    (INITIAL_INTERVALS[0], self.ease - 0.2, 0)
-                },
-                Difficulty::Hard => {
-                    let new_interval = Self::mul_duration(self.interval.unwrap(), HARD_INTERVAL);
-                    (new_interval, self.ease - 0.15, self.review_count + 1)
-                },
-                Difficulty::Good => {
-                    let new_interval = Self::mul_duration(self.interval.unwrap(), self.ease as f64);
-                    (new_interval, self.ease, self.review_count + 1)
-                },
-                Difficulty::Easy => {
-                    let new_interval = Self::mul_duration(self.interval.unwrap(), self.ease as f64 * EASY_BONUS);
-                    (new_interval, self.ease + 0.15, self.review_count + 1)
-                },
-            };
+impl CardCache {
+    /// Get the cached cards of a type for a sentence, if they're all in the cache
+    fn sentence_cards(&self, sentence_id: &Uuid, card_type: CardType) -> Option<Vec<Card>> {
+        self.sentence_words
+            .get(&(*sentence_id, card_type))?
+            .iter()
+            .map(|word_id| self.cards.get(&(word_id.clone(), card_type)).cloned())
+            .collect()
+    }
 
-            let new_due = time_now + chrono::Duration::from_std(new_interval)?;
+    /// Add the cards of a type for a sentence to the cache
+    fn insert_sentence_cards(&mut self, sentence_id: Uuid, card_type: CardType, cards: &[Card]) {
+        self.sentence_words.insert((sentence_id, card_type), cards.iter().map(|card| card.word_id.clone()).collect());
+        self.update_cards(cards);
+    }
 
-            self.interval = Some(new_interval);
-            self.due = Some(new_due.naive_utc());
-            self.ease = f32::max(MINIMUM_EASE, new_ease);
-            self.review_count = new_review_count;
+    /// Update cached cards
+    fn update_cards(&mut self, cards: &[Card]) {
+        for card in cards.iter() {
+            self.cards.insert((card.word_id.clone(), card.card_type), card.clone());
         }
-
-        Ok(())
     }
 
-    fn mul_duration(duration: Duration, multiplier: f64) -> Duration {
-        let new_interval_secs = duration.as_secs() as f64 * multiplier;
-        Duration::from_secs(new_interval_secs as u64)
+    fn clear(&mut self) {
+        self.cards.clear();
+        self.sentence_words.clear();
     }
 }
 
+/// Parse a new word from the new sentence query's reason, written as the word, how many sentences
+/// it's in, and its level if it has one, separated by tabs
+fn parse_new_word(line: &str) -> Option<NewWord> {
+    let mut fields = line.split('\t');
+
+    Some(NewWord {
+        word: fields.next()?.to_string(),
+        frequency: fields.next()?.parse().ok()?,
+        level: fields.next().map(str::to_string),
+    })
+}
+
 /// Wordie srs algorithm, version 1
 pub struct WordieSrsAlgorithm {
-    pool: Pool,
+    pool: ConnectionPool,
+    tokenizer: Arc<dyn Tokenizer>,
     new_card_limit: i32,
-    // TODO: should store this in db, or it doesn't persist app restarts
+    weekday_new_card_limits: WeekdayLimits,
     cards_learned_today: i32,
     cards_reviewed_today: i32,
-    local_time: DateTime<Local>,
+    clock: Clock,
+    auto_retire_redundant_sentences: bool,
+    exclude_likely_names: bool,
+    katakana_readings: bool,
+    new_card_levels: Option<Vec<String>>,
+    production_cards: bool,
+    scheduler_config: SchedulerConfig,
+    source_filter: Option<SentenceSource>,
+    due_shuffle_seed: Option<u32>,
+    snoozes: Snoozes,
+    card_cache: CardCache,
+    /// Whether the last new sentence learned was long, see `SchedulerConfig::long_sentence_chars`
+    last_new_sentence_long: Option<bool>,
+    /// The word graph for suggestions, along with the collection revision and unknown condition it
+    /// was loaded with, as loading it reads the whole collection
+    word_graph: RefCell<Option<(i64, String, WordGraph)>>,
 }
 
-impl WordieSrsAlgorithm {
-    /// Connect to a database and create a new WordieSrsAlgorithm
-    pub fn new(db_url: &str, new_card_limit: i32) -> SrsResult<Self> {
-        let pool = Pool::new(db_url)?;
+/// Builds a `WordieSrsAlgorithm`, e.g.
+/// `WordieSrsAlgorithm::builder().db_url(url).new_card_limit(20).build()`. Anything not set is
+/// left at its default.
+#[derive(Debug, Clone)]
+pub struct WordieSrsAlgorithmBuilder {
+    db_url: Option<String>,
+    tokenizer: Arc<dyn Tokenizer>,
+    new_card_limit: i32,
+    weekday_new_card_limits: WeekdayLimits,
+    connection_options: ConnectionOptions,
+    scheduler_config: SchedulerConfig,
+    auto_retire_redundant_sentences: bool,
+    exclude_likely_names: bool,
+    katakana_readings: bool,
+    new_card_levels: Option<Vec<String>>,
+    production_cards: bool,
+}
+
+impl WordieSrsAlgorithmBuilder {
+    /// The url of the database to connect to, which has to be set
+    pub fn db_url(mut self, db_url: &str) -> Self {
+        self.db_url = Some(db_url.to_string());
+        self
+    }
+
+    /// The tokenizer to split sentences into words with, `DefaultTokenizer` if not set
+    pub fn tokenizer(mut self, tokenizer: Arc<dyn Tokenizer>) -> Self {
+        self.tokenizer = tokenizer;
+        self
+    }
+
+    /// The max number of new cards to introduce each day, `DEFAULT_NEW_CARD_LIMIT` if not set
+    pub fn new_card_limit(mut self, new_card_limit: i32) -> Self {
+        self.new_card_limit = new_card_limit;
+        self
+    }
+
+    /// Different new card limits for some days of the week, the rest use `new_card_limit`
+    pub fn weekday_new_card_limits(mut self, limits: WeekdayLimits) -> Self {
+        self.weekday_new_card_limits = limits;
+        self
+    }
+
+    pub fn connection_options(mut self, connection_options: ConnectionOptions) -> Self {
+        self.connection_options = connection_options;
+        self
+    }
+
+    pub fn scheduler_config(mut self, scheduler_config: SchedulerConfig) -> Self {
+        self.scheduler_config = scheduler_config;
+        self
+    }
+
+    /// See `WordieSrsAlgorithm::set_auto_retire_redundant_sentences`
+    pub fn auto_retire_redundant_sentences(mut self, auto_retire: bool) -> Self {
+        self.auto_retire_redundant_sentences = auto_retire;
+        self
+    }
+
+    /// See `WordieSrsAlgorithm::set_exclude_likely_names`
+    pub fn exclude_likely_names(mut self, exclude: bool) -> Self {
+        self.exclude_likely_names = exclude;
+        self
+    }
+
+    /// See `WordieSrsAlgorithm::set_katakana_readings`
+    pub fn katakana_readings(mut self, katakana_readings: bool) -> Self {
+        self.katakana_readings = katakana_readings;
+        self
+    }
+
+    /// See `WordieSrsAlgorithm::set_new_card_levels`
+    pub fn new_card_levels(mut self, levels: Option<Vec<String>>) -> Self {
+        self.new_card_levels = levels;
+        self
+    }
+
+    /// See `WordieSrsAlgorithm::set_production_cards`
+    pub fn production_cards(mut self, production_cards: bool) -> Self {
+        self.production_cards = production_cards;
+        self
+    }
+
+    /// Connect to the database and create the WordieSrsAlgorithm
+    pub fn build(self) -> SrsResult<WordieSrsAlgorithm> {
+        let db_url = self.db_url.ok_or("No database url set for WordieSrsAlgorithm")?;
+        let pool = ConnectionPool::new(&db_url, &self.connection_options)?;
 
         Ok(WordieSrsAlgorithm {
             pool,
-            new_card_limit,
+            tokenizer: self.tokenizer,
+            new_card_limit: self.new_card_limit,
+            weekday_new_card_limits: self.weekday_new_card_limits,
             cards_learned_today: 0,
             cards_reviewed_today: 0,
-            local_time: Local::now(),
+            clock: Clock::system(),
+            auto_retire_redundant_sentences: self.auto_retire_redundant_sentences,
+            exclude_likely_names: self.exclude_likely_names,
+            katakana_readings: self.katakana_readings,
+            new_card_levels: self.new_card_levels,
+            production_cards: self.production_cards,
+            scheduler_config: self.scheduler_config,
+            source_filter: None,
+            due_shuffle_seed: None,
+            snoozes: Snoozes::default(),
+            card_cache: CardCache::default(),
+            last_new_sentence_long: None,
+            word_graph: RefCell::new(None),
         })
     }
+}
 
-    fn get_next_due(&self) -> SrsResult<Option<Review>> {
-        let mut conn = self.pool.get_conn()?;
-
-        let midnight = (self.local_time + chrono::Duration::days(1))
-            .with_hour(0).unwrap()
-            .with_minute(0).unwrap()
-            .with_second(0).unwrap()
-            .with_nanosecond(0).unwrap();
+impl WordieSrsAlgorithm {
+    /// Start building a WordieSrsAlgorithm, for setting options before connecting
+    pub fn builder() -> WordieSrsAlgorithmBuilder {
+        WordieSrsAlgorithmBuilder {
+            db_url: None,
+            tokenizer: Arc::new(DefaultTokenizer),
+            new_card_limit: DEFAULT_NEW_CARD_LIMIT,
+            weekday_new_card_limits: WeekdayLimits::default(),
+            connection_options: ConnectionOptions::default(),
+            scheduler_config: SchedulerConfig::default(),
+            auto_retire_redundant_sentences: false,
+            exclude_likely_names: false,
+            katakana_readings: false,
+            new_card_levels: None,
+            production_cards: false,
+        }
+    }
 
-        let result = conn.exec_map(
-            r"
-                -- Find a sentence to review: Get all the sentences with words due today, and order them
-                -- by how many words in each one are due today to find the one most worth reviewing
-                SELECT sentence_words.sentence_id, sentences.text, count(cards.word_id) as words_due
-                FROM cards
-                INNER JOIN sentence_words ON sentence_words.word_id = cards.word_id
-                LEFT JOIN (
-                    -- Get all the sentences with unlearned words
-                    SELECT DISTINCT sentence_words.sentence_id
-                    FROM sentence_words
-                    INNER JOIN cards ON sentence_words.word_id = cards.word_id
-                    WHERE cards.due IS NULL
-                ) sentences_with_unlearned_words ON sentences_with_unlearned_words.sentence_id = sentence_words.sentence_id
-                INNER JOIN sentences ON sentences.id = sentence_words.sentence_id
-                WHERE sentences_with_unlearned_words.sentence_id IS NULL
-                   && cards.due IS NOT NULL
-                   && cards.due < :latest_time
-                GROUP BY sentence_words.sentence_id
-                ORDER BY words_due DESC
-                LIMIT 1
-            ",
-            params! {
-                "latest_time" => midnight.naive_utc()
-            },
-            |(sentence_id, text, words_due) : (String, String, i32)| {
-                Review::Due {
-                    sentence: Sentence {
-                        id: Uuid::from_str(sentence_id.as_str()).unwrap(),
-                        text,
-                    },
-                    words_due,
-                }
-            })?;
+    /// Connect to a database and create a new WordieSrsAlgorithm
+    pub fn new(db_url: &str, new_card_limit: i32) -> SrsResult<Self> {
+        Self::builder().db_url(db_url).new_card_limit(new_card_limit).build()
+    }
 
-        Ok(result.into_iter().next())
+    /// Connect to a database with the given connection pool options and create a new WordieSrsAlgorithm
+    pub fn with_connection_options(db_url: &str, new_card_limit: i32, options: &ConnectionOptions) -> SrsResult<Self> {
+        Self::builder()
+            .db_url(db_url)
+            .new_card_limit(new_card_limit)
+            .connection_options(options.clone())
+            .build()
     }
 
-    fn get_next_new(&self) -> SrsResult<Option<Review>> {
-        // If there are too many cards in learning, let user do some reviews first
-        let learning_count = self.cards_in_learning_count()?;
-        if learning_count >= MAX_LEARNING_CARDS {
-            log::info!("Too many cards in learning ({learning_count}) to get a new card");
-            return Ok(None);
-        }
-        else {
-            log::info!("Only ({learning_count}) cards in learning, getting a new card");
-        }
+    /// Set whether sentences should be retired automatically after a review once they become
+    /// redundant (see `get_redundant_sentences`)
+    pub fn set_auto_retire_redundant_sentences(&mut self, auto_retire: bool) {
+        self.auto_retire_redundant_sentences = auto_retire;
+    }
 
-        if self.cards_learned_today >= self.new_card_limit {
-            log::info!("at new word limit, cards learned: {}, limit: {}", self.cards_learned_today, self.new_card_limit);
-            return Ok(None);
-        }
+    /// Set whether words that look like proper names are flagged when importing, so they aren't
+    /// reviewed unless they're rescued (see `get_flagged_names`). Off by default, as the heuristic
+    /// flags katakana loanwords too.
+    pub fn set_exclude_likely_names(&mut self, exclude: bool) {
+        self.exclude_likely_names = exclude;
+    }
 
-        let mut conn = self.pool.get_conn()?;
+    /// Set whether readings are kept in katakana as the dictionary gives them, rather than being
+    /// converted to hiragana. Only readings looked up from then on are affected, rebuilding the
+    /// collection looks up the rest again.
+    pub fn set_katakana_readings(&mut self, katakana_readings: bool) {
+        self.katakana_readings = katakana_readings;
+    }
 
-        let result = conn.query_map(
-            r"
-                -- Find a new sentence to learn: First we get all pairs of (sentence_id, word_id) where word_id
-                -- is an unlearned word. Then we group by the sentence id and count the unknown words in each one
-                -- to find the most i+1 sentence to learn.
-                SELECT sentences_with_unlearned.sentence_id, sentences.text, count(sentences_with_unlearned.word_id)
-                FROM (
-                    -- Get all sentences with unlearned words, along with the unlearned words in them
-                    SELECT sentence_words.sentence_id, cards.word_id
-                    FROM cards
-                    INNER JOIN sentence_words ON sentence_words.word_id = cards.word_id
-                    WHERE cards.due IS NULL
-                    ORDER BY cards.added_order ASC
-                ) sentences_with_unlearned
-                INNER JOIN sentences ON sentences.id = sentences_with_unlearned.sentence_id
-                GROUP BY sentences_with_unlearned.sentence_id
-                ORDER BY count(sentences_with_unlearned.word_id)
-                LIMIT 1
-            ",
-            |(sentence_id, text, unknown_words) : (String, String, i32)| {
-                Review::New {
-                    sentence: Sentence {
-                        id: Uuid::from_str(sentence_id.as_str()).unwrap(),
-                        text,
-                    },
-                    unknown_words,
-                }
-            })?;
+    /// Only introduce new sentences whose new words are all in one of `levels` (see
+    /// `import_word_levels`), or any sentence if None
+    pub fn set_new_card_levels(&mut self, levels: Option<Vec<String>>) {
+        self.new_card_levels = levels;
+    }
 
-        Ok(result.into_iter().next())
+    /// Set whether words get production cards as well as recognition cards once they're learned.
+    /// Production cards are only reviewed in sentences with translations (see `set_translation`).
+    pub fn set_production_cards(&mut self, production_cards: bool) {
+        self.production_cards = production_cards;
     }
 
-    fn cards_in_learning_count(&self) -> SrsResult<i32> {
-        let mut conn = self.pool.get_conn()?;
+    /// Set how many cards can be in learning at once before new cards stop being introduced, so
+    /// the learning cards get reviewed first
+    pub fn set_max_learning_cards(&mut self, max_learning_cards: i32) {
+        self.scheduler_config.max_learning_cards = max_learning_cards;
+    }
 
-        let midnight = (self.local_time + chrono::Duration::days(1))
-            .with_hour(0).unwrap()
-            .with_minute(0).unwrap()
-            .with_second(0).unwrap()
-            .with_nanosecond(0).unwrap();
+    /// Stop introducing new sentences with more than this many new words, or introduce any
+    /// sentence if None. `get_next_card` reports `QueueState::NextIsIPlusN` once only those are left.
+    pub fn set_max_new_words_per_sentence(&mut self, max_new_words: Option<i32>) {
+        self.scheduler_config.max_new_words_per_sentence = max_new_words;
+    }
 
-        Ok(conn.exec_first(
-            r"SELECT count(*)
-              FROM cards
-              WHERE cards.review_count < :max_review_count
-                 && cards.due IS NOT NULL
-                 && cards.due < :latest_time",
-            params! {
-                "max_review_count" => INITIAL_INTERVALS.len(),
-                "latest_time" => midnight.naive_utc(),
-            })?
-            .unwrap_or(0))
+    /// Set whether words still in the learning steps count as known when suggesting sentences
+    /// (see `get_suggested_sentences`), or only words that have graduated
+    pub fn set_learning_counts_as_known(&mut self, learning_counts_as_known: bool) {
+        self.scheduler_config.learning_counts_as_known = learning_counts_as_known;
     }
-}
 
-impl SrsAlgorithm for WordieSrsAlgorithm {
-    fn reinitialize_db(&mut self) -> SrsResult<()> {
-        log::info!("Reinitializing database");
+    /// Turn on auto ease, which adjusts the interval modifier every so often from the review log
+    /// so that this fraction of reviews are remembered, e.g. 0.9. None turns it off.
+    pub fn set_target_retention(&mut self, target_retention: Option<f64>) {
+        self.scheduler_config.target_retention = target_retention;
+    }
 
-        // Drop all tables
-        self.pool.get_conn()?.query_drop("DROP TABLE IF EXISTS sentence_words, cards, sentences, words, reviews")?;
+    /// Alternate new sentences between short and long ones, counting sentences with more than this
+    /// many characters as long, or leave them in the usual order if None
+    pub fn set_long_sentence_chars(&mut self, long_sentence_chars: Option<i32>) {
+        self.scheduler_config.long_sentence_chars = long_sentence_chars;
+    }
 
-        // Initialise db
-        self.initialize_db()
+    /// Set all the scheduling options at once
+    pub fn set_scheduler_config(&mut self, scheduler_config: SchedulerConfig) {
+        self.scheduler_config = scheduler_config;
     }
 
-    fn initialize_db(&mut self) -> SrsResult<()> {
-        log::info!("Initializing database");
+    /// The words in a sentence to flag as names, if we're excluding them
+    fn likely_names(&self, text: &str) -> Vec<String> {
+        if self.exclude_likely_names {
+            tokenizer::likely_names(text)
+        }
+        else {
+            Vec::new()
+        }
+    }
 
+    /// Find redundant sentences, optionally limited to a single sentence
+    fn find_redundant_sentences(&self, sentence_id: Option<String>) -> SrsResult<Vec<Sentence>> {
         let mut conn = self.pool.get_conn()?;
 
-        // Recreate tables
-        conn.query_drop(r"
-            CREATE TABLE IF NOT EXISTS sentences (
-                id CHAR(36) NOT NULL,
-                text TEXT CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci NOT NULL,
-                PRIMARY KEY (id)
-            )
-        ")?;
+        Ok(conn.exec_map(
+            format!(r"
+                -- Find active sentences where every word is mature and also appears in at least
+                -- one other active sentence, so the sentence adds review load without adding value
+                SELECT sentences.id, sentences.text
+                FROM sentences
+                INNER JOIN sentence_words ON sentence_words.sentence_id = sentences.id
+                INNER JOIN cards ON cards.word_id = sentence_words.word_id
+                INNER JOIN (
+                    -- Count the active sentences each word appears in
+                    SELECT sentence_words.word_id, count(sentence_words.sentence_id) as sentence_count
+                    FROM sentence_words
+                    INNER JOIN sentences ON sentences.id = sentence_words.sentence_id
+                    WHERE NOT sentences.retired && sentences.deleted IS NULL
+                    GROUP BY sentence_words.word_id
+                ) word_sentences ON word_sentences.word_id = sentence_words.word_id
+                WHERE NOT sentences.retired && sentences.deleted IS NULL
+                   && (:sentence_id IS NULL || sentences.id = :sentence_id)
+                   && {NOT_EXCLUDED}
+                GROUP BY sentences.id, sentences.text
+                HAVING min(cards.interval_secs IS NOT NULL && cards.interval_secs >= :mature_secs) = 1
+                   && min(word_sentences.sentence_count) > 1
+            "),
+            params! {
+                "sentence_id" => sentence_id,
+                "mature_secs" => MATURE_INTERVAL_SECS,
+            },
+            |(id, text): (String, String)| Sentence {
+                id: Uuid::from_str(&id).unwrap(),
+                text,
+            })?)
+    }
 
-        conn.query_drop(r"
-            CREATE TABLE IF NOT EXISTS words (
-                id CHAR(36) NOT NULL,
-                word VARCHAR(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci NOT NULL UNIQUE,
-                PRIMARY KEY (id)
-            )
-        ")?;
+    /// Today's new card limit, which can be different on some days of the week
+    fn new_card_limit_today(&self) -> i32 {
+        self.weekday_new_card_limits.limit(self.clock.now().weekday(), self.new_card_limit)
+    }
 
-        conn.query_drop(r"
-            CREATE TABLE IF NOT EXISTS sentence_words (
-                sentence_id CHAR(36) NOT NULL,
-                word_id CHAR(36) NOT NULL,
-                FOREIGN KEY (sentence_id) REFERENCES sentences(id),
-                FOREIGN KEY (word_id) REFERENCES words(id),
-                PRIMARY KEY (word_id, sentence_id)
-            )
-        ")?;
+    /// A condition on a word's card that's true if the word counts as unknown when suggesting
+    /// sentences. Words that haven't been learned are always unknown, and words in learning can be too.
+    fn unknown_condition(&self) -> String {
+        if self.scheduler_config.learning_counts_as_known {
+            "cards.due IS NULL".to_string()
+        }
+        else {
+            format!("(cards.due IS NULL || cards.review_count < {})", INITIAL_INTERVALS.len())
+        }
+    }
 
-        conn.query_drop(r"
-            CREATE TABLE IF NOT EXISTS cards (
-                word_id CHAR(36) NOT NULL,
-                review_count INT NOT NULL,
-                ease FLOAT NOT NULL,
-                `interval` TIME,
-                due DATETIME,
-                added_order INT NOT NULL,
-                FOREIGN KEY (word_id) REFERENCES words(id),
-                PRIMARY KEY (word_id)
-            )
-        ")?;
+    /// Run `f` on the word graph, only loading it again if the collection has changed since it was
+    /// last loaded
+    fn with_word_graph<T>(&self, f: impl FnOnce(&WordGraph) -> T) -> SrsResult<T> {
+        let mut conn = self.pool.get_conn()?;
+        let revision = revision::current(&mut conn)?;
+        let unknown = self.unknown_condition();
+
+        let mut cached = self.word_graph.borrow_mut();
+        let stale = cached.as_ref().is_none_or(|(cached_revision, cached_unknown, _)| {
+            *cached_revision != revision || *cached_unknown != unknown
+        });
+
+        if stale {
+            log::info!("Loading word graph at revision {revision}");
+            let graph = suggestions::load(&mut conn, &unknown)?;
+            *cached = Some((revision, unknown, graph));
+        }
 
-        conn.query_drop(r"
-            CREATE TABLE IF NOT EXISTS reviews (
-                word_id CHAR(36) NOT NULL,
-                review_date DATETIME NOT NULL,
-                FOREIGN KEY (word_id) REFERENCES words(id)
-            )
-        ")?;
+        Ok(f(&cached.as_ref().unwrap().2))
+    }
 
-        Ok(())
+    /// Get the cards of a type for the words in a sentence, from the cache if we've seen them all
+    /// already
+    fn get_sentence_cards(&mut self, conn: &mut PooledConn, sentence_id: &Uuid, card_type: CardType) -> SrsResult<Vec<Card>> {
+        if let Some(cards) = self.card_cache.sentence_cards(sentence_id, card_type) {
+            return Ok(cards);
+        }
+
+        // Blacklisted words don't get reviewed along with the rest of the sentence
+        let cards = conn.exec_map(
+            format!(r"SELECT cards.word_id, cards.review_count, cards.ease, cards.interval_secs, cards.due
+              FROM sentence_words
+              INNER JOIN cards ON cards.word_id = sentence_words.word_id
+              WHERE sentence_words.sentence_id = :sentence_id
+                 && cards.card_type = :card_type
+                 && {NOT_EXCLUDED}"),
+            params! {
+                "sentence_id" => sentence_id.to_string(),
+                "card_type" => card_type.as_str(),
+            },
+            |(word_id, review_count, ease, interval, due) : (String, i32, f32, Option<u64>, Option<NaiveDateTime>)| Card {
+                word_id,
+                card_type,
+                schedule: Schedule { due, interval: interval.map(Duration::from_secs), review_count, ease },
+            })?;
+
+        self.card_cache.insert_sentence_cards(*sentence_id, card_type, &cards);
+
+        Ok(cards)
     }
 
-    fn set_time_now(&mut self, time: chrono::DateTime<chrono::Local>) {
-        log::info!("Setting current time to {time:?}");
-        self.local_time = time;
+    /// Split a sentence into words, joining expressions and applying merge rules
+    fn split_words(&self, text: &str, merges: &HashMap<String, String>, expressions: &[Expression]) -> Vec<String> {
+        self.split_tokens(text, merges, expressions)
+            .into_iter()
+            .map(|token| token.word)
+            .collect()
     }
 
-    fn reset_daily_limits(&mut self) {
-        log::info!("Resetting daily card limits");
-        self.cards_learned_today = 0;
+    /// Split a sentence into words like `split_words`, keeping where each word is in the sentence
+    fn split_tokens(&self, text: &str, merges: &HashMap<String, String>, expressions: &[Expression]) -> Vec<Token> {
+        word_merges::apply(merges, expressions::join(self.tokenizer.tokenize(text), expressions))
     }
 
-    fn add_sentences(&mut self, sentences: &[super::Sentence]) -> SrsResult<()> {
-        let mut conn = self.pool.get_conn()?;
+    /// Add sentences and their words' cards, see `add_sentences`
+    fn insert_sentences(&self, conn: &mut PooledConn, sentences: &[super::Sentence]) -> SrsResult<ImportReport> {
+        let mut report = ImportReport::default();
+        let mut all_words = HashSet::new();
+
+        let blacklist: HashSet<String> = blacklist::load(conn)?.into_iter().collect();
+        let merges = word_merges::load(conn)?;
+        let expressions = expressions::load(conn)?;
 
         // Tokenize sentences, and then add them to the db
         for sentence in sentences.iter() {
-            // Tokenize sentence into words
-            let words = sentence.text
-                .as_str()
-                .tokenize()
-                .filter(|token| token.is_word())
-                .map(|token| token.lemma.to_string())
-                .collect::<Vec<String>>();
-
-            // Add new words to database
-            conn.exec_batch("INSERT IGNORE INTO words (id, word) VALUES (:id, :word)",
-                words.iter().map(|word| params! {
-                    "id" => Uuid::new_v4().to_string(),
-                    "word" => word.as_str(),
-                }))?;
+            // Tokenize sentence into words, leaving out blacklisted words so they never become cards
+            let mut tokens = self.split_tokens(&sentence.text, &merges, &expressions);
+            tokens.retain(|token| !blacklist.contains(&token.word));
+            let words = tokens.iter().map(|token| token.word.clone()).collect::<Vec<String>>();
+
+            if words.is_empty() {
+                log::warn!("Sentence has no words, skipping: {}", sentence.text);
+                report.skipped_sentences.push(sentence.clone());
+                continue;
+            }
 
-            // Get words with proper ids (they might have existed in the db with an id already).
-            // TODO: Annoyingly, there's no way to parameterise the IN (?) part of the query, and
-            // you have to build the query with the words in it instead. This probably opens us up
-            // to SQL injection.
-            let query = {
-                let mut query = "SELECT id FROM words WHERE word in (".to_string();
+            // Find out which words are new before adding them, for the import report
+            let existing_words: HashSet<String> = conn.exec::<String, _, _>(
+                format!("SELECT word FROM words WHERE word IN ({})", db::placeholders(words.len())),
+                words.clone())?
+                .into_iter()
+                .collect();
+
+            // Names still get cards, so they can be rescued later, but they're flagged so they
+            // aren't reviewed. Words that already have cards are left alone, so a word the user is
+            // already learning doesn't disappear from reviews because it showed up in katakana.
+            let mut likely_names = self.likely_names(&sentence.text);
+            likely_names.retain(|name| !existing_words.contains(name));
+
+            if !likely_names.is_empty() {
+                names::flag(conn, &likely_names)?;
+            }
 
-                for (i, word) in words.iter().enumerate() {
-                    if i != 0 {
-                        query.push(',');
+            let mut new_words = Vec::new();
+            for word in words.iter() {
+                if likely_names.contains(word) {
+                    if !report.likely_names.contains(word) {
+                        report.likely_names.push(word.clone());
                     }
-
-                    query.push('"');
-                    query.push_str(word);
-                    query.push('"');
                 }
+                else if !existing_words.contains(word) && !new_words.contains(word) {
+                    new_words.push(word.clone());
+                }
+            }
 
-                query.push(')');
+            if new_words.is_empty() {
+                report.review_only_sentences.push(sentence.clone());
+            }
 
-                query
-            };
+            report.new_words.extend(new_words);
+            all_words.extend(words.iter().cloned());
 
-            let word_ids: Vec<String> = conn.query(query)?;
+            // Add new words and their cards to database
+            let word_ids = self.add_words(conn, &words)?;
 
             // Insert sentence
             let sentence_id = sentence.id.to_string();
-            conn.exec_drop("INSERT INTO sentences (id, text) VALUES (:id, :text)",
+            conn.exec_drop("INSERT INTO sentences (id, text, char_count) VALUES (:id, :text, :char_count)",
                 params! {
                     "id" => sentence_id.as_str(),
                     "text" => sentence.text.as_str(),
+                    "char_count" => sentence.text.chars().count(),
                 })?;
 
+            sync::record_change(conn, sync::SENTENCE_ROW, &sentence_id)?;
+            grammar::add_detected(conn, std::slice::from_ref(sentence))?;
+
             // Insert sentence words
             conn.exec_batch("INSERT INTO sentence_words (sentence_id, word_id) VALUES (:sentence_id, :word_id)",
                 word_ids.iter().map(|word| params! {
@@ -395,126 +557,2325 @@ impl SrsAlgorithm for WordieSrsAlgorithm {
                     "word_id" => word,
                 }))?;
 
-            // Insert cards
-            conn.exec_batch(
-                r"INSERT IGNORE INTO cards (word_id, review_count, ease, added_order)
-                  VALUES (:word_id, :review_count, :ease, :added_order)",
-                word_ids.iter().enumerate().map(|(i, w)| params! {
-                    "word_id" => w,
-                    "review_count" => 0,
-                    "ease" => DEFAULT_EASE,
-                    "added_order" => i,
-                })
-            )?;
+            Self::record_word_offsets(conn, &sentence_id, &tokens)?;
         }
-        Ok(())
-    }
 
-    fn get_next_card(&self) -> SrsResult<Option<super::Review>> {
-        let next_card = self.get_next_new()?
-            .or(self.get_next_due()?);
+        // Count how many candidate sentences there are now for each word we touched
+        if !all_words.is_empty() {
+            let all_words = all_words.into_iter().collect::<Vec<String>>();
+            report.sentences_per_word = conn.exec::<(String, i32), _, _>(format!(r"
+                SELECT words.word, count(sentence_words.sentence_id)
+                FROM words
+                INNER JOIN sentence_words ON sentence_words.word_id = words.id
+                WHERE words.word IN ({})
+                GROUP BY words.word
+            ", db::placeholders(all_words.len())), all_words.clone())?
+            .into_iter()
+            .collect();
+
+            kanji::update(conn, &kanji::kanji_in(all_words.iter()), MATURE_INTERVAL_SECS)?;
+        }
+
+        revision::bump(conn)?;
 
-        Ok(next_card)
+        Ok(report)
     }
 
-    fn review(&mut self, review: super::Review, score: super::Difficulty) -> SrsResult<()> {
-        let mut conn = self.pool.get_conn()?;
+    /// Store where each word is written in a sentence, for hiding it in cloze reviews. Words that
+    /// are in the sentence more than once use the first place they're written.
+    fn record_word_offsets(conn: &mut PooledConn, sentence_id: &str, tokens: &[Token]) -> SrsResult<()> {
+        let mut seen = HashSet::new();
+
+        conn.exec_batch(
+            r"UPDATE sentence_words
+              INNER JOIN words ON words.id = sentence_words.word_id
+              SET sentence_words.byte_start = :byte_start,
+                  sentence_words.byte_end = :byte_end
+              WHERE sentence_words.sentence_id = :sentence_id && words.word = :word",
+            tokens.iter()
+                .filter(|token| seen.insert(token.word.as_str()))
+                .map(|token| params! {
+                    "sentence_id" => sentence_id,
+                    "word" => token.word.as_str(),
+                    "byte_start" => token.byte_start,
+                    "byte_end" => token.byte_end,
+                }))?;
 
-        // Get cards for words in the sentence
-        let mut cards = conn.exec_map(
-            r"SELECT cards.word_id, cards.review_count, cards.ease, cards.interval, cards.due
-              FROM sentence_words
-              INNER JOIN cards ON cards.word_id = sentence_words.word_id
-              WHERE sentence_words.sentence_id = :sentence_id",
-            params! { "sentence_id" => review.sentence().id.to_string() },
-            |(word_id, review_count, ease, interval, due) : (String, i32, f32, Option<Duration>, Option<NaiveDateTime>)| Card {
-                word_id,
-                review_count,
-                ease,
-                interval,
-                due,
-            })?;
+        Ok(())
+    }
 
-        // Mark each word as reviewed
-        for card in cards.iter_mut() {
-            // Increment reviewed count
-            self.cards_reviewed_today += 1;
+    /// Replace the words in a sentence, adding cards for new words and cleaning up the cards for
+    /// words that were only in this sentence
+    fn replace_sentence_words(&self, conn: &mut PooledConn, sentence_id: &str, words: &[String]) -> SrsResult<()> {
+        let old_word_ids: Vec<String> = conn.exec("SELECT word_id FROM sentence_words WHERE sentence_id = :sentence_id",
+            params! { "sentence_id" => sentence_id })?;
 
-            // If this is a new card, increment new cards count
-            if card.due.is_none() {
-                log::info!("Learnt new card");
-                self.cards_learned_today += 1;
-            }
+        let word_ids = self.add_words(conn, words)?;
 
-            // Review card
-            card.review(self.local_time, score)?;
+        conn.exec_drop("DELETE FROM sentence_words WHERE sentence_id = :sentence_id",
+            params! { "sentence_id" => sentence_id })?;
 
-            // Update card in db
-            conn.exec_drop(
-                r"UPDATE cards
-                  SET cards.review_count = :review_count,
-                      cards.ease = :ease,
-                      cards.interval = :interval,
-                      cards.due = :due
-                  WHERE cards.word_id = :id",
-                params! {
-                    "id" => card.word_id.as_str(),
-                    "review_count" => card.review_count,
-                    "ease" => card.ease,
-                    "interval" => card.interval.unwrap(),
-                    "due" => card.due.unwrap(),
-                })?;
+        conn.exec_batch("INSERT INTO sentence_words (sentence_id, word_id) VALUES (:sentence_id, :word_id)",
+            word_ids.iter().map(|word| params! {
+                "sentence_id" => sentence_id,
+                "word_id" => word,
+            }))?;
+
+        for word_id in old_word_ids.iter().filter(|word_id| !word_ids.contains(*word_id)) {
+            Self::remove_word_if_unused(conn, word_id)?;
         }
 
         Ok(())
     }
 
-    fn cards_learned_today(&self) -> i32 {
-        self.cards_learned_today
-    }
+    /// Add words and new cards for them, if they don't exist already, and return their ids
+    fn add_words(&self, conn: &mut PooledConn, words: &[String]) -> SrsResult<Vec<String>> {
+        // Tokenized words are normalized already, but words typed in by hand might not be
+        let words = words.iter().map(|word| normalize::word(word)).collect::<Vec<String>>();
 
-    fn cards_reviewed_today(&self) -> i32 {
-        self.cards_reviewed_today
+        if words.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        conn.exec_batch("INSERT IGNORE INTO words (id, word, reading) VALUES (:id, :word, :reading)",
+            words.iter().map(|word| params! {
+                "id" => Uuid::new_v4().to_string(),
+                "word" => word.as_str(),
+                "reading" => tokenizer::reading(word, !self.katakana_readings),
+            }))?;
+
+        // Get words with proper ids (they might have existed in the db with an id already).
+        let word_ids: Vec<String> = conn.exec(
+            format!("SELECT id FROM words WHERE word IN ({})", db::placeholders(words.len())),
+            words.clone())?;
+
+        conn.exec_batch(
+            r"INSERT IGNORE INTO cards (word_id, review_count, ease, added_order)
+              VALUES (:word_id, :review_count, :ease, :added_order)",
+            word_ids.iter().enumerate().map(|(i, w)| params! {
+                "word_id" => w,
+                "review_count" => 0,
+                "ease" => DEFAULT_EASE,
+                "added_order" => i,
+            })
+        )?;
+
+        Ok(word_ids)
+    }
+
+    /// Remove a word and its card if it's no longer in any sentences and was never studied,
+    /// returning whether it was removed
+    fn remove_word_if_unused(conn: &mut PooledConn, word_id: &str) -> SrsResult<bool> {
+        let in_use: Option<i32> = conn.exec_first("SELECT 1 FROM sentence_words WHERE word_id = :word_id LIMIT 1",
+            params! { "word_id" => word_id })?;
+
+        if in_use.is_some() {
+            return Ok(false);
+        }
+
+        conn.exec_drop("DELETE FROM cards WHERE word_id = :word_id && due IS NULL",
+            params! { "word_id" => word_id })?;
+
+        if conn.affected_rows() == 0 {
+            return Ok(false);
+        }
+
+        conn.exec_drop("DELETE FROM words WHERE id = :word_id",
+            params! { "word_id" => word_id })?;
+
+        Ok(conn.affected_rows() > 0)
     }
 
-    fn get_suggested_sentences(&self, new_word_limit: i32) -> SrsResult<Vec<(Sentence, Vec<String>)>> {
+    /// Bring words stored before words were normalized in line, merging any that turn out to be
+    /// the same word into one card. Returns the number of words changed.
+    fn normalize_stored_words(&mut self) -> SrsResult<usize> {
         let mut conn = self.pool.get_conn()?;
 
-        log::info!("Getting recommended i+{new_word_limit} sentences");
+        let words: Vec<String> = conn.query("SELECT word FROM words")?;
+        let unnormalized = normalize::unnormalized_words(&words);
+
+        if unnormalized.is_empty() {
+            return Ok(0);
+        }
+
+        log::info!("Normalizing {} words", unnormalized.len());
+
+        for (word, normalized) in unnormalized.iter() {
+            // The collation ignores some of the differences, so compare exactly
+            let normalized_exists: Option<i32> = conn.exec_first("SELECT 1 FROM words WHERE word = BINARY :word",
+                params! { "word" => normalized })?;
+
+            if normalized_exists.is_some() {
+                self.merge_words(word, normalized)?;
+            }
+            else if let Some(word_id) = conn.exec_first::<String, _, _>("SELECT id FROM words WHERE word = BINARY :word",
+                params! { "word" => word })? {
+                conn.exec_drop("UPDATE words SET word = :normalized WHERE id = :id",
+                    params! {
+                        "id" => word_id.as_str(),
+                        "normalized" => normalized,
+                    })?;
+                sync::record_change(&mut conn, sync::CARD_ROW, &word_id)?;
+            }
+        }
+
+        self.card_cache.clear();
+        revision::bump(&mut conn)?;
+
+        Ok(unnormalized.len())
+    }
+
+    /// Merge a word into another one on a connection that's in a transaction, see `merge_words`
+    fn merge_words_in(conn: &mut PooledConn, word: &str, into: &str, now: NaiveDateTime) -> SrsResult<()> {
+        word_merges::add(conn, word, into)?;
+
+        let word_id: Option<String> = conn.exec_first("SELECT id FROM words WHERE word = :word",
+            params! { "word" => word })?;
+        let into_id: Option<String> = conn.exec_first("SELECT id FROM words WHERE word = :word",
+            params! { "word" => into })?;
+
+        let (word_id, into_id) = match (word_id, into_id) {
+            // Nothing to move over yet, the rule will be applied when the word is imported
+            (None, _) => return Ok(()),
+            // The target word doesn't exist yet, so just rename the word and keep its card
+            (Some(word_id), None) => {
+                conn.exec_drop("UPDATE words SET word = :into WHERE id = :id",
+                    params! {
+                        "id" => word_id.as_str(),
+                        "into" => into,
+                    })?;
+                sync::record_change(conn, sync::CARD_ROW, &word_id)?;
+                revision::bump(conn)?;
+                return Ok(());
+            },
+            (Some(word_id), Some(into_id)) => (word_id, into_id),
+        };
+
+        // Keep whichever card has been studied longest, so merging never loses progress
+        let card = Self::load_card(conn, &word_id)?;
+        let into_card = Self::load_card(conn, &into_id)?;
+
+        if let (Some(card), Some(into_card)) = (card, into_card) {
+            let card_is_older = match (card.schedule.interval, into_card.schedule.interval) {
+                (Some(interval), Some(into_interval)) => interval > into_interval,
+                (interval, into_interval) => interval.is_some() && into_interval.is_none(),
+            };
+
+            if card_is_older {
+                conn.exec_drop(
+                    r"UPDATE cards
+                      SET cards.review_count = :review_count,
+                          cards.ease = :ease,
+                          cards.interval_secs = :interval_secs,
+                          cards.due = :due,
+                          cards.modified = :modified
+                      WHERE cards.word_id = :id && cards.card_type = 'recognition'",
+                    params! {
+                        "id" => into_id.as_str(),
+                        "review_count" => card.schedule.review_count,
+                        "ease" => card.schedule.ease,
+                        "interval_secs" => card.schedule.interval.map(|interval| interval.as_secs()),
+                        "due" => card.schedule.due,
+                        "modified" => now,
+                    })?;
+            }
+        }
+
+        // Move the word's sentences and review history over, dropping the rows for sentences that
+        // have both words
+        for table in ["sentence_words", "reviews"] {
+            conn.exec_drop(format!("UPDATE IGNORE {table} SET word_id = :into_id WHERE word_id = :word_id"),
+                params! {
+                    "word_id" => word_id.as_str(),
+                    "into_id" => into_id.as_str(),
+                })?;
+        }
+
+        for table in ["sentence_words", "cards"] {
+            conn.exec_drop(format!("DELETE FROM {table} WHERE word_id = :word_id"),
+                params! { "word_id" => word_id.as_str() })?;
+        }
+
+        conn.exec_drop("DELETE FROM words WHERE id = :word_id",
+            params! { "word_id" => word_id.as_str() })?;
+
+        sync::record_change(conn, sync::CARD_ROW, &into_id)?;
+        revision::bump(conn)?;
+
+        Ok(())
+    }
+
+    /// Give learned words production cards if they're in a sentence with a translation but don't
+    /// have one yet, e.g. words learned before production cards were turned on or before their
+    /// sentence was translated. Only words in `sentence_id` are checked if it's set. The new cards
+    /// start in learning, first due alongside the word's next recognition review.
+    fn add_missing_production_cards(&mut self, conn: &mut PooledConn, sentence_id: Option<&Uuid>) -> SrsResult<()> {
+        if !self.production_cards {
+            return Ok(());
+        }
+
+        conn.exec_drop(
+            r"INSERT IGNORE INTO cards (word_id, card_type, review_count, ease, interval_secs, due, added_order)
+              SELECT cards.word_id, 'production', 0, :ease, :interval_secs, cards.due, 0
+              FROM cards
+              WHERE cards.card_type = 'recognition' && cards.due IS NOT NULL
+                 && EXISTS (SELECT 1
+                            FROM sentence_words
+                            INNER JOIN sentences ON sentences.id = sentence_words.sentence_id
+                            WHERE sentence_words.word_id = cards.word_id
+                               && sentences.translation IS NOT NULL
+                               && sentences.deleted IS NULL
+                               && (:sentence_id IS NULL || sentences.id = :sentence_id))",
+            params! {
+                "ease" => DEFAULT_EASE,
+                "interval_secs" => INITIAL_INTERVALS[0].as_secs(),
+                "sentence_id" => sentence_id.map(Uuid::to_string),
+            })?;
+
+        if conn.affected_rows() > 0 {
+            log::info!("Added {} missing production cards", conn.affected_rows());
+            self.card_cache.clear();
+            revision::bump(conn)?;
+        }
+
+        Ok(())
+    }
+
+    /// Load a single word's recognition card
+    fn load_card(conn: &mut PooledConn, word_id: &str) -> SrsResult<Option<Card>> {
+        Ok(conn.exec_first(
+            r"SELECT cards.review_count, cards.ease, cards.interval_secs, cards.due
+              FROM cards
+              WHERE cards.word_id = :word_id && cards.card_type = 'recognition'",
+            params! { "word_id" => word_id })?
+            .map(|(review_count, ease, interval, due): (i32, f32, Option<u64>, Option<NaiveDateTime>)| Card {
+                word_id: word_id.to_string(),
+                card_type: CardType::Recognition,
+                schedule: Schedule { due, interval: interval.map(Duration::from_secs), review_count, ease },
+            }))
+    }
+
+    /// Record a review of a sentence's `cards` and write their new schedules, returning the cards
+    /// learned by it. `review` runs this in a transaction, and only counts the review and caches
+    /// the cards once it's committed.
+    fn write_review(&self, conn: &mut PooledConn, review: &Review, cards: &mut [Card], score: Difficulty, interval_modifier: f64)
+        -> SrsResult<Vec<Card>>
+    {
+        let card_type = review.card_type();
+        let mut learned_cards = Vec::new();
+
+        grammar::record_exposure(conn, &review.sentence().id)?;
+
+        // Mark each word as reviewed
+        for card in cards.iter_mut() {
+            let is_new = card.schedule.due.is_none();
+            if is_new {
+                log::info!("Learnt new card");
+            }
+
+            // Record the review in the daily stats and the review history
+            daily_stats::record_review(conn, self.clock.now().naive_local().date(), &review.sentence().id, is_new)?;
+            let review_id = Uuid::new_v4().to_string();
+            conn.exec_drop(r"INSERT INTO reviews (id, word_id, sentence_id, review_date, difficulty, last_interval_secs)
+                             VALUES (:id, :word_id, :sentence_id, :review_date, :difficulty, :last_interval_secs)",
+                params! {
+                    "id" => review_id.as_str(),
+                    "word_id" => card.word_id.as_str(),
+                    "sentence_id" => review.sentence().id.to_string(),
+                    "review_date" => self.clock.now().naive_utc(),
+                    "difficulty" => score as i32,
+                    "last_interval_secs" => card.schedule.interval.map(|interval| interval.as_secs()),
+                })?;
+            sync::record_change(conn, sync::REVIEW_ROW, &review_id)?;
+
+            // Review card
+            card.schedule.review_with_modifier(self.clock.now(), score, interval_modifier)?;
+
+            // Update card in db
+            conn.exec_drop(
+                r"UPDATE cards
+                  SET cards.review_count = :review_count,
+                      cards.ease = :ease,
+                      cards.interval_secs = :interval_secs,
+                      cards.due = :due,
+                      cards.modified = :modified
+                  WHERE cards.word_id = :id && cards.card_type = :card_type",
+                params! {
+                    "id" => card.word_id.as_str(),
+                    "card_type" => card_type.as_str(),
+                    "review_count" => card.schedule.review_count,
+                    "ease" => card.schedule.ease,
+                    "interval_secs" => card.schedule.interval.unwrap().as_secs(),
+                    "due" => card.schedule.due.unwrap(),
+                    "modified" => self.clock.now().naive_utc(),
+                })?;
+
+            // Only recognition cards are synced, as the sync protocol has one card per word
+            if card_type == CardType::Recognition {
+                sync::record_change(conn, sync::CARD_ROW, &card.word_id)?;
+            }
+
+            if is_new {
+                learned_cards.push(card.clone());
+            }
+        }
+
+        sources::record_words_taught(conn, &review.sentence().id, learned_cards.len())?;
+
+        // Newly learned words start on production too, if there's a translation to produce them
+        // from. Their production cards are first due alongside the recognition card's next review.
+        if self.production_cards && !learned_cards.is_empty() && self.get_translation(&review.sentence().id)?.is_some() {
+            conn.exec_batch(
+                r"INSERT IGNORE INTO cards (word_id, card_type, review_count, ease, interval_secs, due, added_order)
+                  VALUES (:word_id, 'production', 0, :ease, :interval_secs, :due, 0)",
+                learned_cards.iter().map(|card| params! {
+                    "word_id" => card.word_id.as_str(),
+                    "ease" => DEFAULT_EASE,
+                    "interval_secs" => card.schedule.interval.map(|interval| interval.as_secs()),
+                    "due" => card.schedule.due,
+                }))?;
+        }
+
+        // Update the stats for the kanji in the reviewed words
+        if !cards.is_empty() {
+            let word_ids = cards.iter().map(|card| card.word_id.clone()).collect::<Vec<String>>();
+            let words: Vec<String> = conn.exec(
+                format!("SELECT word FROM words WHERE id IN ({})", db::placeholders(word_ids.len())), word_ids)?;
+            kanji::update(conn, &kanji::kanji_in(words.iter()), MATURE_INTERVAL_SECS)?;
+        }
+
+        Ok(learned_cards)
+    }
+
+    /// The interval modifier for reviewing a sentence in auto ease mode, from its deck's target
+    /// retention if it has one, otherwise the whole collection's. Without a target it's 1.0.
+    fn interval_modifier(&self, conn: &mut PooledConn, sentence_id: &Uuid) -> SrsResult<f64> {
+        let config = &self.scheduler_config;
+
+        if !config.deck_target_retention.is_empty() {
+            let deck = sources::load(conn, sentence_id)?
+                .and_then(|source| config.deck_target_retention.iter().find(|deck| deck.deck == source.name));
+
+            if let Some(deck) = deck {
+                return retention::interval_modifier(conn, Some(&deck.deck), deck.target_retention, self.clock.now());
+            }
+        }
+
+        match config.target_retention {
+            Some(target_retention) => retention::interval_modifier(conn, None, target_retention, self.clock.now()),
+            None => Ok(1.0),
+        }
+    }
+
+    /// Retire a sentence if it's (still) redundant, returns whether it was retired
+    fn retire_if_redundant(&mut self, sentence: &Sentence) -> SrsResult<bool> {
+        let sentence_id = sentence.id.to_string();
+
+        if self.find_redundant_sentences(Some(sentence_id.clone()))?.is_empty() {
+            return Ok(false);
+        }
+
+        log::info!("Retiring redundant sentence {}", sentence.text);
+
+        self.pool.get_conn()?.exec_drop("UPDATE sentences SET retired = TRUE WHERE id = :id",
+            params! { "id" => sentence_id })?;
+
+        Ok(true)
+    }
+
+    /// Whether there are any cards left to review today
+    fn has_dues(&self) -> SrsResult<bool> {
+        Ok(!self.get_due(1, false)?.is_empty())
+    }
+
+    /// Record whether today's dues are finished after a review, for the daily goals
+    fn record_dues_finished(&self) -> SrsResult<()> {
+        let finished = !self.has_dues()?;
+        daily_stats::set_dues_finished(&mut self.pool.get_conn()?, self.clock.now().naive_local().date(), finished)
+    }
+
+    /// Get up to `limit` sentences with words due, most due words first, optionally only counting
+    /// words from the learning queue
+    fn get_due(&self, limit: usize, learning_queue_only: bool) -> SrsResult<Vec<Review>> {
+        let mut conn = self.pool.get_conn()?;
+
+        let midnight = super::end_of_day(self.clock.now());
+
+        let learning_queue_filter = if learning_queue_only {
+            format!("&& {IN_LEARNING_QUEUE}")
+        }
+        else {
+            String::new()
+        };
+
+        // Production cards can only be reviewed in sentences with a translation to show
+        let card_type_filter = if self.production_cards {
+            "&& (cards.card_type = 'recognition' || sentences.translation IS NOT NULL)"
+        }
+        else {
+            "&& cards.card_type = 'recognition'"
+        };
+
+        let shuffle_seed = self.due_shuffle_seed.filter(|_| !learning_queue_only);
+        // Shuffled cards are all fetched, so they can be shuffled before taking the first `limit`
+        let query_limit = if shuffle_seed.is_some() { u64::MAX } else { limit as u64 };
+        let not_snoozed = self.snoozes.not_snoozed("sentences.id", self.clock.now().naive_utc());
+
+        let rows: Vec<(String, String, String, i32, String, NaiveDateTime, usize)> = conn.exec(
+            format!(r"
+                -- Find a sentence to review: Get all the sentences with words due today, and order them
+                -- by how many words in each one are due today to find the one most worth reviewing.
+                -- Each card type is reviewed separately, so they're interleaved by how many words are due.
+                -- The due words and how many others are mature are returned too, to explain the choice.
+                SELECT sentence_words.sentence_id, sentences.text, cards.card_type, count(cards.word_id) as words_due,
+                       GROUP_CONCAT(words.word ORDER BY LOCATE(words.word, sentences.text) SEPARATOR '\n'),
+                       MIN(cards.due), COALESCE(MAX(mature_words.count), 0)
+                FROM cards
+                INNER JOIN sentence_words ON sentence_words.word_id = cards.word_id
+                INNER JOIN words ON words.id = cards.word_id
+                LEFT JOIN (
+                    -- Count the mature words in each sentence, which aren't due today as they're out of
+                    -- the learning queue
+                    SELECT sentence_words.sentence_id, cards.card_type, count(cards.word_id) AS count
+                    FROM cards
+                    INNER JOIN sentence_words ON sentence_words.word_id = cards.word_id
+                    WHERE cards.interval_secs >= :mature_secs
+                       && cards.due >= :latest_time
+                       && {NOT_EXCLUDED}
+                    GROUP BY sentence_words.sentence_id, cards.card_type
+                ) mature_words ON mature_words.sentence_id = sentence_words.sentence_id && mature_words.card_type = cards.card_type
+                LEFT JOIN (
+                    -- Get all the sentences with unlearned words
+                    SELECT DISTINCT sentence_words.sentence_id
+                    FROM sentence_words
+                    INNER JOIN cards ON sentence_words.word_id = cards.word_id
+                    WHERE cards.due IS NULL
+                       && {NOT_EXCLUDED}
+                ) sentences_with_unlearned_words ON sentences_with_unlearned_words.sentence_id = sentence_words.sentence_id
+                INNER JOIN sentences ON sentences.id = sentence_words.sentence_id
+                WHERE sentences_with_unlearned_words.sentence_id IS NULL
+                   && NOT sentences.retired && sentences.deleted IS NULL
+                   && {not_snoozed}
+                   && {DUE_NOW}
+                   && {NOT_EXCLUDED}
+                   {card_type_filter}
+                   {learning_queue_filter}
+                GROUP BY sentence_words.sentence_id, cards.card_type
+                ORDER BY words_due DESC
+                LIMIT :limit
+            "),
+            params! {
+                "now" => self.clock.now().naive_utc(),
+                "latest_time" => midnight.naive_utc(),
+                "learning_queue_secs" => LEARNING_QUEUE_INTERVAL.as_secs(),
+                "mature_secs" => MATURE_INTERVAL_SECS,
+                "limit" => query_limit,
+            })?;
+
+        let mut result = rows.into_iter()
+            .map(|(sentence_id, text, card_type, words_due, due_words, due, mature_words)| {
+                Ok(Review::Due {
+                    sentence: Sentence {
+                        id: Uuid::from_str(sentence_id.as_str()).unwrap(),
+                        text,
+                    },
+                    words_due,
+                    card_type: CardType::from_name(&card_type).ok_or_else(|| format!("Unknown card type {card_type}"))?,
+                    reason: Some(SelectionReason {
+                        due_words: due_words.split('\n').map(str::to_string).collect(),
+                        mature_words,
+                        due: Some(due),
+                        ..Default::default()
+                    }),
+                })
+            })
+            .collect::<SrsResult<Vec<Review>>>()?;
+
+        if let Some(seed) = shuffle_seed {
+            scheduler::shuffle_by_seed(&mut result, seed, |review| review.sentence().id);
+            result.truncate(limit);
+        }
+
+        Ok(result)
+    }
+
+    /// Get up to `limit` new sentences to learn, most i+1 first, leaving out sentences with more
+    /// new words than `SchedulerConfig::max_new_words_per_sentence`
+    fn get_new(&self, limit: usize) -> SrsResult<Vec<Review>> {
+        self.find_new(limit, self.scheduler_config.max_new_words_per_sentence)
+    }
+
+    /// Get up to `limit` new sentences to learn with at most `max_new_words` new words, most i+1
+    /// first. Each one teaches at least one new word, so there are never more than the new card
+    /// limit has room for.
+    fn find_new(&self, limit: usize, max_new_words: Option<i32>) -> SrsResult<Vec<Review>> {
+        if self.new_cards_blocked()?.is_some() {
+            return Ok(Vec::new());
+        }
+
+        let limit = limit.min((self.new_card_limit_today() - self.cards_learned_today).max(0) as usize);
+
+        let mut conn = self.pool.get_conn()?;
+
+        let (source_kind, source_name) = sources::source_params(self.source_filter.as_ref());
+        let mut query_params: Vec<(String, Value)> = vec![
+            ("limit".to_string(), Value::from(limit)),
+            ("source_kind".to_string(), Value::from(source_kind)),
+            ("source_name".to_string(), Value::from(source_name)),
+        ];
+
+        // Only allow sentences where every unlearned word is in one of the allowed levels, and
+        // that don't have too many unlearned words
+        let mut conditions = Vec::new();
+        if let Some(levels) = self.new_card_levels.as_ref() {
+            let (level_list, level_params) = db::named_placeholders("level", levels);
+            conditions.push(format!("min(COALESCE(sentences_with_unlearned.level IN ({level_list}), FALSE)) = 1"));
+            query_params.extend(level_params);
+        }
+        if let Some(max_new_words) = max_new_words {
+            conditions.push(format!("count(sentences_with_unlearned.word_id) <= {max_new_words}"));
+        }
+        let having = if conditions.is_empty() { String::new() } else { format!("HAVING {}", conditions.join(" && ")) };
+
+        // Sentences of the other length to the last new one come first among those with as many
+        // unknown words
+        let long_chars = self.scheduler_config.long_sentence_chars.unwrap_or(i32::MAX);
+        let length_order = match self.last_new_sentence_long {
+            Some(last_long) if self.scheduler_config.long_sentence_chars.is_some() =>
+                format!(", (sentences.char_count > {long_chars}) = {last_long}"),
+            _ => String::new(),
+        };
 
-        let res: Vec<(String, String, String)> = conn.query(
+        let not_snoozed = self.snoozes.not_snoozed("sentences.id", self.clock.now().naive_utc());
+
+        let result = conn.exec_map(
             format!(r"
-                -- Get a list of sentences and unknown words for sentences that are up to i+n
-                SELECT sentences.id, sentences.text, words.word
+                -- Find a new sentence to learn: First we get all pairs of (sentence_id, word_id) where word_id
+                -- is an unlearned word. Then we group by the sentence id and count the unknown words in each one
+                -- to find the most i+1 sentence to learn. The unlearned words are returned too, to explain
+                -- the choice, one per line as the word, how many sentences it's in, and its level if it has one.
+                SELECT sentences_with_unlearned.sentence_id, sentences.text, count(sentences_with_unlearned.word_id),
+                       COALESCE(sentences.char_count, 0),
+                       GROUP_CONCAT(CONCAT_WS('\t', sentences_with_unlearned.word, sentences_with_unlearned.frequency,
+                                              sentences_with_unlearned.level)
+                                    ORDER BY LOCATE(sentences_with_unlearned.word, sentences.text) SEPARATOR '\n')
                 FROM (
-                    SELECT sentence_words.sentence_id, count(sentence_words.word_id) as unknown_words
+                    -- Get all sentences with unlearned words, along with the unlearned words in them
+                    SELECT sentence_words.sentence_id, cards.word_id, words.word, word_levels.level,
+                           (SELECT count(*) FROM sentence_words AS word_sentences
+                            WHERE word_sentences.word_id = cards.word_id) AS frequency
                     FROM cards
                     INNER JOIN sentence_words ON sentence_words.word_id = cards.word_id
+                    INNER JOIN words ON words.id = cards.word_id
+                    LEFT JOIN word_levels ON word_levels.word = words.word
                     WHERE cards.due IS NULL
-                    GROUP BY sentence_words.sentence_id
-                ) unlearned_sentences
-                INNER JOIN sentence_words ON sentence_words.sentence_id = unlearned_sentences.sentence_id
-                INNER JOIN sentences ON sentences.id = unlearned_sentences.sentence_id
-                INNER JOIN words ON words.id = sentence_words.word_id
-                INNER JOIN cards ON cards.word_id = sentence_words.word_id
-                WHERE unlearned_sentences.unknown_words <= {new_word_limit}
-                   && cards.due IS NULL
-                ORDER BY unlearned_sentences.unknown_words
-            "))?;
-
-        let mut ret = Vec::new();
-        let mut last_sentence_id: Option<String> = None;
-
-        for (sentence_id, sentence_text, word) in res.iter() {
-            if last_sentence_id.is_none() || last_sentence_id.as_ref().unwrap() != sentence_id {
-                let sentence = Sentence { id: Uuid::from_str(sentence_id.as_str()).unwrap(), text: sentence_text.clone() };
-                ret.push((sentence, Vec::new()));
-                last_sentence_id = Some(sentence_id.clone());
+                       && {NOT_EXCLUDED}
+                    ORDER BY cards.added_order ASC
+                ) sentences_with_unlearned
+                INNER JOIN sentences ON sentences.id = sentences_with_unlearned.sentence_id
+                WHERE NOT sentences.retired && sentences.deleted IS NULL && {FROM_SOURCE} && {not_snoozed}
+                GROUP BY sentences_with_unlearned.sentence_id
+                {having}
+                ORDER BY count(sentences_with_unlearned.word_id){length_order}
+                LIMIT :limit
+            "),
+            query_params,
+            |(sentence_id, text, unknown_words, char_count, new_words) : (String, String, i32, i32, String)| {
+                let review = Review::New {
+                    sentence: Sentence {
+                        id: Uuid::from_str(sentence_id.as_str()).unwrap(),
+                        text,
+                    },
+                    unknown_words,
+                    reason: Some(SelectionReason {
+                        new_words: new_words.split('\n').filter_map(parse_new_word).collect(),
+                        ..Default::default()
+                    }),
+                };
+                (review, unknown_words, char_count > long_chars)
+            })?;
+
+        if self.scheduler_config.long_sentence_chars.is_some() {
+            Ok(scheduler::alternate_lengths(result, self.last_new_sentence_long))
+        }
+        else {
+            Ok(result.into_iter().map(|(review, _, _)| review).collect())
+        }
+    }
+
+    /// Why new cards can't be introduced right now, if they can't
+    fn new_cards_blocked(&self) -> SrsResult<Option<QueueState>> {
+        // If there are too many cards in learning, let user do some reviews first
+        let learning_count = self.cards_in_learning_count()?;
+        if learning_count >= self.scheduler_config.max_learning_cards {
+            log::info!("Too many cards in learning ({learning_count}) to get a new card");
+            return Ok(Some(QueueState::TooManyLearning { learning: learning_count, limit: self.scheduler_config.max_learning_cards }));
+        }
+        else {
+            log::info!("Only ({learning_count}) cards in learning, getting a new card");
+        }
+
+        let new_card_limit = self.new_card_limit_today();
+        if self.cards_learned_today >= new_card_limit {
+            log::info!("at new word limit, cards learned: {}, limit: {}", self.cards_learned_today, new_card_limit);
+            return Ok(Some(QueueState::NewCardLimit { learned_today: self.cards_learned_today, limit: new_card_limit }));
+        }
+
+        Ok(None)
+    }
+
+    /// Why there's nothing to review, once there are no due or new cards
+    fn empty_queue_state(&self) -> SrsResult<QueueState> {
+        if !db::has_sentences(&mut self.pool.get_conn()?)? {
+            return Ok(QueueState::CollectionEmpty);
+        }
+
+        // Only say new cards are blocked if there are any left to introduce
+        if self.collection_stats()?.new_cards > 0 {
+            if let Some(state) = self.new_cards_blocked()? {
+                return Ok(state);
             }
+        }
 
-            ret.last_mut().unwrap().1.push(word.clone());
-        };
+        Ok(QueueState::NothingDue { reviewed_today: self.cards_reviewed_today })
+    }
+
+    fn cards_in_learning_count(&self) -> SrsResult<i32> {
+        let mut conn = self.pool.get_conn()?;
+
+        let midnight = super::end_of_day(self.clock.now());
+
+        Ok(conn.exec_first(
+            format!(r"SELECT count(*)
+              FROM cards
+              WHERE cards.review_count < :max_review_count
+                 && cards.due IS NOT NULL
+                 && cards.due < :latest_time
+                 && {NOT_EXCLUDED}"),
+            params! {
+                "max_review_count" => INITIAL_INTERVALS.len(),
+                "latest_time" => midnight.naive_utc(),
+            })?
+            .unwrap_or(0))
+    }
+}
+
+impl SrsAlgorithm for WordieSrsAlgorithm {
+    fn reinitialize_db(&mut self, force: bool) -> SrsResult<()> {
+        let mut conn = self.pool.get_conn()?;
+
+        if !force && db::has_reviews(&mut conn)? {
+            return Err("Refusing to reinitialize a database with reviews in it without force".into());
+        }
+
+        log::info!("Reinitializing database");
+
+        self.card_cache.clear();
+
+        db::backup_tables(&mut conn, TABLES)?;
+
+        // Drop all tables
+        conn.query_drop(format!("DROP TABLE IF EXISTS {}", TABLES.join(", ")))?;
+
+        // Initialise db
+        self.initialize_db()
+    }
+
+    fn initialize_db(&mut self) -> SrsResult<()> {
+        log::info!("Initializing database");
+
+        let mut conn = self.pool.get_conn()?;
+
+        // Recreate tables
+        conn.query_drop(r"
+            CREATE TABLE IF NOT EXISTS sentences (
+                id CHAR(36) NOT NULL,
+                text TEXT CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci NOT NULL,
+                PRIMARY KEY (id)
+            )
+        ")?;
+
+        conn.query_drop(r"
+            CREATE TABLE IF NOT EXISTS words (
+                id CHAR(36) NOT NULL,
+                word VARCHAR(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci NOT NULL UNIQUE,
+                -- How the word is read in hiragana, for checking typed answers
+                reading VARCHAR(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci,
+                PRIMARY KEY (id)
+            )
+        ")?;
+
+        conn.query_drop(r"
+            CREATE TABLE IF NOT EXISTS sentence_words (
+                sentence_id CHAR(36) NOT NULL,
+                word_id CHAR(36) NOT NULL,
+                -- Where the word is written in the sentence, for hiding it in cloze reviews
+                byte_start INT,
+                byte_end INT,
+                FOREIGN KEY (sentence_id) REFERENCES sentences(id),
+                FOREIGN KEY (word_id) REFERENCES words(id),
+                PRIMARY KEY (word_id, sentence_id)
+            )
+        ")?;
+
+        conn.query_drop(r"
+            CREATE TABLE IF NOT EXISTS cards (
+                word_id CHAR(36) NOT NULL,
+                card_type VARCHAR(16) NOT NULL DEFAULT 'recognition',
+                review_count INT NOT NULL,
+                ease FLOAT NOT NULL,
+                interval_secs BIGINT,
+                due DATETIME,
+                added_order INT NOT NULL,
+                FOREIGN KEY (word_id) REFERENCES words(id),
+                PRIMARY KEY (word_id, card_type)
+            )
+        ")?;
+
+        conn.query_drop(r"
+            CREATE TABLE IF NOT EXISTS reviews (
+                word_id CHAR(36) NOT NULL,
+                review_date DATETIME NOT NULL,
+                FOREIGN KEY (word_id) REFERENCES words(id)
+            )
+        ")?;
+
+        daily_stats::create_table(&mut conn)?;
+        sync::create_tables(&mut conn)?;
+        blacklist::create_table(&mut conn)?;
+        names::create_table(&mut conn)?;
+        word_merges::create_table(&mut conn)?;
+        expressions::create_table(&mut conn)?;
+        levels::create_table(&mut conn)?;
+        revision::create_table(&mut conn)?;
+        tags::create_table(&mut conn)?;
+        targets::create_table(&mut conn)?;
+        grammar::create_table(&mut conn)?;
+        flags::create_table(&mut conn)?;
+        retention::create_table(&mut conn)?;
+        sources::create_table(&mut conn)?;
+        vacation::create_table(&mut conn)?;
+
+        // Columns added since the tables were first created
+        db::add_column_if_missing(&mut conn, "sentences", "retired", "BOOL NOT NULL DEFAULT FALSE")?;
+        db::add_column_if_missing(&mut conn, "cards", "modified", "DATETIME")?;
+        db::add_column_if_missing(&mut conn, "sentences", "custom_words", "BOOL NOT NULL DEFAULT FALSE")?;
+        db::add_column_if_missing(&mut conn, "sentences", "deleted", "DATETIME")?;
+        db::add_column_if_missing(&mut conn, "sentences", "translation", "TEXT CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci")?;
+        db::add_column_if_missing(&mut conn, "sentences", "notes", "TEXT CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci")?;
+        db::add_column_if_missing(&mut conn, "sentence_words", "byte_start", "INT")?;
+        db::add_column_if_missing(&mut conn, "sentence_words", "byte_end", "INT")?;
+        db::add_column_if_missing(&mut conn, "reviews", "difficulty", "TINYINT")?;
+        db::add_column_if_missing(&mut conn, "reviews", "last_interval_secs", "INT")?;
+        db::add_column_if_missing(&mut conn, "reviews", "sentence_id", "CHAR(36)")?;
+
+        // Ids for merging review histories when syncing, as there's nothing else to tell reviews
+        // from different devices apart by
+        if db::add_column_if_missing(&mut conn, "reviews", "id", "CHAR(36)")? {
+            conn.query_drop("UPDATE reviews SET id = UUID() WHERE id IS NULL")?;
+            sync::record_review_changes(&mut conn)?;
+        }
+
+        // Character counts for balancing new sentence lengths, stored so they can be ordered by
+        if db::add_column_if_missing(&mut conn, "sentences", "char_count", "INT")? {
+            conn.query_drop("UPDATE sentences SET char_count = CHAR_LENGTH(text)")?;
+        }
+
+        // Intervals that hit the old TIME column's limit can be worked out again from when the
+        // card was last reviewed and when it's due
+        if db::migrate_interval_column(&mut conn)? {
+            conn.exec_drop(r"
+                UPDATE cards
+                INNER JOIN (
+                    SELECT word_id, MAX(review_date) AS last_review
+                    FROM reviews
+                    GROUP BY word_id
+                ) last_reviews ON last_reviews.word_id = cards.word_id
+                SET cards.interval_secs = GREATEST(cards.interval_secs, TIMESTAMPDIFF(SECOND, last_reviews.last_review, cards.due))
+                WHERE cards.interval_secs >= :max_time_secs && cards.due IS NOT NULL",
+                params! { "max_time_secs" => db::MAX_TIME_INTERVAL_SECS })?;
+        }
+
+        // Words from before readings were stored get them looked up now
+        if db::add_column_if_missing(&mut conn, "words", "reading", "VARCHAR(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci")? {
+            let words: Vec<(String, String)> = conn.query("SELECT id, word FROM words")?;
+            log::info!("Looking up readings for {} words", words.len());
+
+            conn.exec_batch("UPDATE words SET reading = :reading WHERE id = :id",
+                words.iter().filter_map(|(id, word)| tokenizer::reading(word, !self.katakana_readings).map(|reading| params! {
+                    "id" => id,
+                    "reading" => reading,
+                })))?;
+        }
+
+        // Existing cards are all recognition cards, and each word can now have one of each type
+        if db::add_column_if_missing(&mut conn, "cards", "card_type", "VARCHAR(16) NOT NULL DEFAULT 'recognition'")? {
+            conn.query_drop("ALTER TABLE cards DROP PRIMARY KEY, ADD PRIMARY KEY (word_id, card_type)")?;
+        }
+
+        // Fill in the kanji stats for collections from before they were tracked
+        kanji::create_table(&mut conn)?;
+        let kanji_count: Option<i64> = conn.query_first("SELECT count(*) FROM kanji")?;
+        if kanji_count == Some(0) {
+            kanji::rebuild(&mut conn, MATURE_INTERVAL_SECS)?;
+        }
+
+        // Words from before normalization might be duplicates of each other
+        self.normalize_stored_words()?;
+        blacklist::normalize_stored(&mut conn)?;
+        word_merges::normalize_stored(&mut conn)?;
+
+        self.add_missing_production_cards(&mut conn, None)?;
+
+        // Time can't go back before the latest review, even if the app was closed while the system
+        // clock was set back
+        let latest_review = conn.query_first::<Option<NaiveDateTime>, _>("SELECT MAX(review_date) FROM reviews")?.flatten();
+        if let Some(latest_review) = latest_review {
+            self.clock.catch_up(chrono::DateTime::<chrono::Utc>::from_utc(latest_review, chrono::Utc).with_timezone(&chrono::Local));
+        }
+
+        self.purge_trash()?;
+
+        // Pick up today's counts again, in case the app was closed partway through the day
+        let today = daily_stats::load(&mut self.pool.get_conn()?, self.clock.now().naive_local().date(), 1)?;
+        if let Some(today) = today.last() {
+            self.cards_learned_today = today.cards_learned;
+            self.cards_reviewed_today = today.cards_reviewed;
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "simulation")]
+    fn set_time_now(&mut self, time: chrono::DateTime<chrono::Local>) {
+        self.clock.set(time);
+    }
+
+    fn set_due_shuffle_seed(&mut self, seed: Option<u32>) {
+        self.due_shuffle_seed = seed;
+    }
+
+    fn snooze(&mut self, sentence_id: &Uuid, delay: Duration) {
+        let now = self.clock.now().naive_utc();
+        self.snoozes.snooze(*sentence_id, now + chrono::Duration::seconds(delay.as_secs() as i64), now);
+    }
+
+    fn reset_daily_limits(&mut self) {
+        log::info!("Resetting daily card limits");
+        self.cards_learned_today = 0;
+    }
+
+    fn add_sentences(&mut self, sentences: &[super::Sentence]) -> SrsResult<ImportReport> {
+        let mut conn = self.pool.get_conn()?;
+
+        // Importing doesn't change existing cards, but clear the cache anyway in case a sentence is
+        // being re-added with the same id
+        self.card_cache.clear();
+
+        // All or nothing, so a sentence that fails can't leave the ones before it half added
+        let report = db::in_transaction(&mut conn, |conn| self.insert_sentences(conn, sentences))?;
+
+        log::info!("Added {} sentences with {} new words", sentences.len(), report.new_words.len());
+
+        Ok(report)
+    }
+
+    fn preview_sentences(&self, sentences: &[Sentence]) -> SrsResult<Vec<SentencePreview>> {
+        let mut conn = self.pool.get_conn()?;
+
+        let blacklist = blacklist::load(&mut conn)?;
+        let merges = word_merges::load(&mut conn)?;
+        let expressions = expressions::load(&mut conn)?;
+
+        let mut previews = Vec::new();
+        for sentence in sentences.iter() {
+            let words = self.split_words(&sentence.text, &merges, &expressions);
+            let likely_names = self.likely_names(&sentence.text);
+
+            // Any word that isn't in the words table yet will get a new card, unless it's
+            // blacklisted or looks like a name
+            let mut new_words = Vec::new();
+            for word in words.iter().filter(|word| !blacklist.contains(*word) && !likely_names.contains(*word)) {
+                let existing: Option<i32> = conn.exec_first("SELECT 1 FROM words WHERE word = :word",
+                    params! { "word" => word.as_str() })?;
+
+                if existing.is_none() && !new_words.contains(word) {
+                    new_words.push(word.clone());
+                }
+            }
+
+            previews.push(SentencePreview {
+                text: sentence.text.clone(),
+                words,
+                new_words,
+            });
+        }
+
+        Ok(previews)
+    }
+
+    fn annotate(&self, text: &str) -> SrsResult<Vec<(Token, KnowledgeState)>> {
+        let mut conn = self.pool.get_conn()?;
+
+        let merges = word_merges::load(&mut conn)?;
+        let expressions = expressions::load(&mut conn)?;
+        let tokens = self.split_tokens(text, &merges, &expressions);
+
+        if tokens.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // The text can come from anywhere, so the words are bound rather than put in the queries
+        let words = tokens.iter().map(|token| token.word.clone()).collect::<Vec<String>>();
+        let (words, word_params) = db::named_placeholders("word", &words);
+
+        // Blacklisted words and names are never studied, whatever state their cards are in
+        let excluded: HashSet<String> = conn.exec::<String, _, _>(format!(
+            r"SELECT word FROM word_blacklist WHERE word IN ({words})
+              UNION
+              SELECT word FROM name_flags WHERE NOT name_flags.rescued && word IN ({words})"),
+            word_params.clone())?
+            .into_iter()
+            .collect();
+
+        let mut state_params = word_params;
+        state_params.push(("mature_secs".to_string(), Value::from(MATURE_INTERVAL_SECS)));
+
+        let states: HashMap<String, KnowledgeState> = conn.exec_map(
+            format!(r"SELECT words.word,
+                     cards.due IS NOT NULL,
+                     cards.interval_secs IS NOT NULL && cards.interval_secs >= :mature_secs
+              FROM words
+              INNER JOIN cards ON cards.word_id = words.id
+              WHERE words.word IN ({words}) && cards.card_type = 'recognition'"),
+            state_params,
+            |(word, learned, mature): (String, bool, bool)| {
+                let state = if mature {
+                    KnowledgeState::Mature
+                }
+                else if learned {
+                    KnowledgeState::Learning
+                }
+                else {
+                    KnowledgeState::New
+                };
+
+                (word, state)
+            })?
+            .into_iter()
+            .collect();
+
+        Ok(tokens.into_iter()
+            .map(|token| {
+                let state = if excluded.contains(&token.word) {
+                    KnowledgeState::Blacklisted
+                }
+                else {
+                    states.get(&token.word).cloned().unwrap_or(KnowledgeState::New)
+                };
+
+                (token, state)
+            })
+            .collect())
+    }
+
+    fn get_sentences(&self) -> SrsResult<Vec<Sentence>> {
+        Ok(self.pool.get_conn()?.query_map("SELECT id, text FROM sentences WHERE deleted IS NULL",
+            |(id, text): (String, String)| Sentence {
+                id: Uuid::from_str(&id).unwrap(),
+                text,
+            })?)
+    }
+
+    fn existing_sentences(&self, texts: &[String]) -> SrsResult<HashSet<String>> {
+        db::existing_sentences(&mut self.pool.get_conn()?, texts)
+    }
+
+    fn list_sentences(&self, page: PageRequest) -> SrsResult<Page<Sentence>> {
+        page::sentences(&mut self.pool.get_conn()?, None, None, None, page)
+    }
+
+    fn search_sentences(&self, query: &str, page: PageRequest) -> SrsResult<Page<Sentence>> {
+        page::sentences(&mut self.pool.get_conn()?, Some(query), None, None, page)
+    }
+
+    fn get_review_history(&self, page: PageRequest) -> SrsResult<Page<ReviewRecord>> {
+        let rows = self.pool.get_conn()?.query_map(
+            format!(r"SELECT words.word, reviews.review_date
+              FROM reviews
+              INNER JOIN words ON words.id = reviews.word_id
+              ORDER BY reviews.review_date DESC, words.word
+              {}", page.sql()),
+            |(word, review_date): (String, NaiveDateTime)| ReviewRecord { word, review_date })?;
+
+        Ok(Page::from_rows(rows, page))
+    }
+
+    fn get_cloze(&self, sentence: &Sentence) -> SrsResult<Option<Cloze>> {
+        // New words come first, then the most overdue
+        let word: Option<(String, Option<String>, Option<u32>, Option<u32>)> = self.pool.get_conn()?.exec_first(
+            format!(r"SELECT words.word, words.reading, sentence_words.byte_start, sentence_words.byte_end
+              FROM sentence_words
+              INNER JOIN words ON words.id = sentence_words.word_id
+              INNER JOIN cards ON cards.word_id = sentence_words.word_id && cards.card_type = 'recognition'
+              WHERE sentence_words.sentence_id = :sentence_id
+                 && {NOT_EXCLUDED}
+              ORDER BY cards.due IS NOT NULL, cards.due
+              LIMIT 1"),
+            params! { "sentence_id" => sentence.id.to_string() })?;
+
+        // Sentences with custom words don't know where their words are, so look for them instead
+        let cloze = word.and_then(|(word, reading, byte_start, byte_end)| {
+            let cloze = match (byte_start, byte_end) {
+                (Some(byte_start), Some(byte_end)) => Cloze::new(&sentence.text, byte_start as usize, byte_end as usize),
+                _ => Cloze::find(&sentence.text, &word),
+            }?;
+
+            // The stored reading is for the dictionary form, conjugated words need their own
+            let reading = reading
+                .filter(|_| cloze.answer() == word)
+                .or_else(|| tokenizer::reading(cloze.answer(), !self.katakana_readings));
+
+            Some(cloze.with_reading(reading))
+        });
+
+        Ok(cloze)
+    }
+
+    fn get_translation(&self, sentence_id: &Uuid) -> SrsResult<Option<String>> {
+        let translation: Option<Option<String>> = self.pool.get_conn()?.exec_first(
+            "SELECT translation FROM sentences WHERE id = :id",
+            params! { "id" => sentence_id.to_string() })?;
+
+        Ok(translation.flatten())
+    }
+
+    fn set_translation(&mut self, sentence_id: &Uuid, translation: Option<&str>) -> SrsResult<()> {
+        let mut conn = self.pool.get_conn()?;
+
+        conn.exec_drop("UPDATE sentences SET translation = :translation WHERE id = :id",
+            params! {
+                "id" => sentence_id.to_string(),
+                "translation" => translation,
+            })?;
+
+        // Words already learned from the sentence can be produced from the translation now
+        self.add_missing_production_cards(&mut conn, Some(sentence_id))
+    }
+
+    fn get_notes(&self, sentence_id: &Uuid) -> SrsResult<Option<String>> {
+        let notes: Option<Option<String>> = self.pool.get_conn()?.exec_first(
+            "SELECT notes FROM sentences WHERE id = :id",
+            params! { "id" => sentence_id.to_string() })?;
+
+        Ok(notes.flatten())
+    }
+
+    fn set_notes(&mut self, sentence_id: &Uuid, notes: Option<&str>) -> SrsResult<()> {
+        self.pool.get_conn()?.exec_drop("UPDATE sentences SET notes = :notes WHERE id = :id",
+            params! {
+                "id" => sentence_id.to_string(),
+                "notes" => notes,
+            })?;
+
+        Ok(())
+    }
+
+    fn set_sentence_text(&mut self, sentence_id: &Uuid, text: &str) -> SrsResult<()> {
+        log::info!("Changing the text of sentence {sentence_id} to {text}");
+
+        let mut conn = self.pool.get_conn()?;
+
+        let blacklist: HashSet<String> = blacklist::load(&mut conn)?.into_iter().collect();
+        let merges = word_merges::load(&mut conn)?;
+        let expressions = expressions::load(&mut conn)?;
+
+        let mut tokens = self.split_tokens(text, &merges, &expressions);
+        tokens.retain(|token| !blacklist.contains(&token.word));
+        let words = tokens.iter().map(|token| token.word.clone()).collect::<Vec<String>>();
+
+        if words.is_empty() {
+            return Err("A sentence needs at least one word".into());
+        }
+
+        let sentence = Sentence { id: *sentence_id, text: text.to_string() };
+        let sentence_id = sentence_id.to_string();
+
+        // The text and its words are changed together, so they can't end up out of sync
+        db::in_transaction(&mut conn, |conn| {
+            // The words were set for the old text, so they're tokenized again from the new one
+            conn.exec_drop("UPDATE sentences SET text = :text, char_count = :char_count, custom_words = FALSE WHERE id = :sentence_id",
+                params! {
+                    "sentence_id" => sentence_id.as_str(),
+                    "text" => text,
+                    "char_count" => text.chars().count(),
+                })?;
+
+            self.replace_sentence_words(conn, &sentence_id, &words)?;
+            Self::record_word_offsets(conn, &sentence_id, &tokens)?;
+            sync::record_change(conn, sync::SENTENCE_ROW, &sentence_id)?;
+            grammar::add_detected(conn, std::slice::from_ref(&sentence))?;
+            kanji::update(conn, &kanji::kanji_in(words.iter()), MATURE_INTERVAL_SECS)
+        })?;
+
+        self.card_cache.clear();
+        revision::bump(&mut conn)?;
+
+        Ok(())
+    }
+
+    fn get_sentence_tags(&self, sentence_id: &Uuid) -> SrsResult<Vec<String>> {
+        tags::load(&mut self.pool.get_conn()?, sentence_id)
+    }
+
+    fn tag_sentences(&mut self, ids: &[Uuid], tag: &str) -> SrsResult<()> {
+        log::info!("Tagging {} sentences with {tag}", ids.len());
+        tags::add(&mut self.pool.get_conn()?, ids, tag)
+    }
+
+    fn get_sentence_source(&self, sentence_id: &Uuid) -> SrsResult<Option<SentenceSource>> {
+        sources::load(&mut self.pool.get_conn()?, sentence_id)
+    }
+
+    fn set_sentence_source(&mut self, ids: &[Uuid], source: &SentenceSource) -> SrsResult<()> {
+        log::info!("Setting the source of {} sentences to {}", ids.len(), source.label());
+        sources::set(&mut self.pool.get_conn()?, ids, source, self.clock.now().naive_utc())
+    }
+
+    fn source_stats(&self) -> SrsResult<Vec<SourceStats>> {
+        sources::stats(&mut self.pool.get_conn()?)
+    }
+
+    fn find_sentences(&self, query: Option<&str>, source: Option<&SentenceSource>, flag: Option<Flag>, page: PageRequest)
+        -> SrsResult<Page<Sentence>>
+    {
+        page::sentences(&mut self.pool.get_conn()?, query, source, flag, page)
+    }
+
+    fn source_filter(&self) -> Option<&SentenceSource> {
+        self.source_filter.as_ref()
+    }
+
+    fn set_source_filter(&mut self, source: Option<SentenceSource>) {
+        log::info!("Only learning new sentences from {source:?}");
+        self.source_filter = source;
+    }
+
+    fn get_target_word(&self, sentence_id: &Uuid) -> SrsResult<Option<TargetWord>> {
+        targets::load(&mut self.pool.get_conn()?, sentence_id)
+    }
+
+    fn set_target_words(&mut self, targets: &[(Uuid, TargetWord)]) -> SrsResult<()> {
+        targets::set(&mut self.pool.get_conn()?, targets)
+    }
+
+    fn get_flag(&self, sentence_id: &Uuid) -> SrsResult<Option<Flag>> {
+        flags::load(&mut self.pool.get_conn()?, sentence_id)
+    }
+
+    fn set_flag(&mut self, sentence_id: &Uuid, flag: Option<Flag>) -> SrsResult<()> {
+        log::info!("Setting flag on sentence {sentence_id} to {flag:?}");
+        flags::set(&mut self.pool.get_conn()?, sentence_id, flag)
+    }
+
+    fn get_grammar_points(&self, sentence_id: &Uuid) -> SrsResult<Vec<String>> {
+        grammar::load(&mut self.pool.get_conn()?, sentence_id)
+    }
+
+    fn add_grammar_point(&mut self, ids: &[Uuid], grammar_point: &str) -> SrsResult<()> {
+        log::info!("Tagging {} sentences with grammar point {grammar_point}", ids.len());
+        grammar::add(&mut self.pool.get_conn()?, ids, grammar_point)
+    }
+
+    fn remove_grammar_point(&mut self, sentence_id: &Uuid, grammar_point: &str) -> SrsResult<()> {
+        grammar::remove(&mut self.pool.get_conn()?, sentence_id, grammar_point)
+    }
+
+    fn detect_grammar_points(&mut self) -> SrsResult<usize> {
+        grammar::detect_all(&mut self.pool.get_conn()?)
+    }
+
+    fn grammar_stats(&self) -> SrsResult<Vec<GrammarStats>> {
+        grammar::stats(&mut self.pool.get_conn()?)
+    }
+
+    fn get_review_log(&self) -> SrsResult<Vec<RevlogEntry>> {
+        revlog::load(&mut self.pool.get_conn()?)
+    }
+
+    fn delete_sentences(&mut self, ids: &[Uuid]) -> SrsResult<()> {
+        log::info!("Moving {} sentences to the trash", ids.len());
+        let mut conn = self.pool.get_conn()?;
+        trash::trash(&mut conn, ids, self.clock.now().naive_utc())?;
+        for id in ids.iter() {
+            sync::record_change(&mut conn, sync::TRASH_ROW, &id.to_string())?;
+        }
+        revision::bump(&mut conn)
+    }
+
+    fn get_trash(&self) -> SrsResult<Vec<TrashedSentence>> {
+        trash::load(&mut self.pool.get_conn()?)
+    }
+
+    fn restore_sentences(&mut self, ids: &[Uuid]) -> SrsResult<()> {
+        log::info!("Restoring {} sentences from the trash", ids.len());
+        let mut conn = self.pool.get_conn()?;
+        trash::restore(&mut conn, ids)?;
+        for id in ids.iter() {
+            sync::record_change(&mut conn, sync::TRASH_ROW, &id.to_string())?;
+        }
+        revision::bump(&mut conn)
+    }
+
+    fn purge_trash(&mut self) -> SrsResult<usize> {
+        let mut conn = self.pool.get_conn()?;
+
+        let expired = trash::expired(&mut conn, self.clock.now().naive_utc())?;
+
+        if expired.is_empty() {
+            return Ok(0);
+        }
+
+        log::info!("Deleting {} sentences from the trash", expired.len());
+
+        self.card_cache.clear();
+
+        for sentence_id in expired.iter() {
+            let word_ids: Vec<String> = conn.exec("SELECT word_id FROM sentence_words WHERE sentence_id = :sentence_id",
+                params! { "sentence_id" => sentence_id.as_str() })?;
+
+            conn.exec_drop("DELETE FROM sentence_words WHERE sentence_id = :sentence_id",
+                params! { "sentence_id" => sentence_id.as_str() })?;
+            conn.exec_drop("DELETE FROM sentences WHERE id = :sentence_id",
+                params! { "sentence_id" => sentence_id.as_str() })?;
+            conn.exec_drop("DELETE FROM sentence_tags WHERE sentence_id = :sentence_id",
+                params! { "sentence_id" => sentence_id.as_str() })?;
+            conn.exec_drop("DELETE FROM sentence_targets WHERE sentence_id = :sentence_id",
+                params! { "sentence_id" => sentence_id.as_str() })?;
+            conn.exec_drop("DELETE FROM grammar_points WHERE sentence_id = :sentence_id",
+                params! { "sentence_id" => sentence_id.as_str() })?;
+            conn.exec_drop("DELETE FROM sentence_sources WHERE sentence_id = :sentence_id",
+                params! { "sentence_id" => sentence_id.as_str() })?;
+            conn.exec_drop("DELETE FROM sentence_flags WHERE sentence_id = :sentence_id",
+                params! { "sentence_id" => sentence_id.as_str() })?;
+
+            // Cards for words that were only in this sentence go too, unless they were studied
+            for word_id in word_ids.iter() {
+                Self::remove_word_if_unused(&mut conn, word_id)?;
+            }
+        }
+
+        kanji::rebuild(&mut conn, MATURE_INTERVAL_SECS)?;
+        revision::bump(&mut conn)?;
+
+        Ok(expired.len())
+    }
+
+    fn get_next_card(&self) -> SrsResult<QueueState> {
+        // Nothing is served while reviews are paused
+        if let Some(vacation) = vacation::current(&mut self.pool.get_conn()?)? {
+            return Ok(QueueState::OnVacation { since: vacation.started });
+        }
+
+        // Cards from the learning queue that have come back round go first, so cards answered
+        // Again are seen again in the same session
+        if let Some(review) = self.get_due(1, true)?.into_iter().next() {
+            return Ok(QueueState::Review(review));
+        }
+
+        let next_due = self.get_due(1, false)?.into_iter().next();
+
+        let state = match (self.get_new(1)?.into_iter().next(), next_due) {
+            (Some(review), _) | (None, Some(review)) => QueueState::Review(review),
+            (None, None) => match self.scheduler_config.max_new_words_per_sentence {
+                // Once the dues are done, sentences with too many new words block the queue
+                Some(limit) => match self.find_new(1, None)?.into_iter().next() {
+                    Some(Review::New { unknown_words, .. }) => QueueState::NextIsIPlusN { unknown_words, limit },
+                    _ => self.empty_queue_state()?,
+                },
+                None => self.empty_queue_state()?,
+            },
+        };
+
+        Ok(state)
+    }
+
+    fn get_review_queue(&self, limit: usize) -> SrsResult<Vec<super::Review>> {
+        if vacation::current(&mut self.pool.get_conn()?)?.is_some() {
+            return Ok(Vec::new());
+        }
+
+        // Same order as get_next_card, learning queue cards that are due, then new sentences, then
+        // the other dues
+        let mut queue = self.get_due(limit, true)?;
+        let learning_queue = queue.iter().map(|review| review.sentence().id).collect::<HashSet<Uuid>>();
+        queue.extend(self.get_new(limit)?);
+        queue.extend(self.get_due(limit, false)?.into_iter()
+            .filter(|review| !learning_queue.contains(&review.sentence().id)));
+        queue.truncate(limit);
+
+        Ok(queue)
+    }
+
+    fn get_due_queue(&self, limit: usize) -> SrsResult<Vec<super::Review>> {
+        if vacation::current(&mut self.pool.get_conn()?)?.is_some() {
+            return Ok(Vec::new());
+        }
+
+        let mut queue = self.get_due(limit, true)?;
+        let learning_queue = queue.iter().map(|review| review.sentence().id).collect::<HashSet<Uuid>>();
+        queue.extend(self.get_due(limit, false)?.into_iter()
+            .filter(|review| !learning_queue.contains(&review.sentence().id)));
+        queue.truncate(limit);
+
+        Ok(queue)
+    }
+
+    fn get_learning_cards(&self) -> SrsResult<Vec<LearningCard>> {
+        let mut conn = self.pool.get_conn()?;
+
+        let cards = conn.exec_map(
+            format!(r"SELECT words.word, cards.due
+              FROM cards
+              INNER JOIN words ON words.id = cards.word_id
+              WHERE cards.due IS NOT NULL
+                 && cards.review_count < :graduated_count
+                 && {NOT_EXCLUDED}
+              ORDER BY cards.due"),
+            params! {
+                "graduated_count" => INITIAL_INTERVALS.len(),
+            },
+            |(text, due)| LearningCard { text, due })?;
+
+        Ok(cards)
+    }
+
+    fn review(&mut self, review: super::Review, score: Difficulty) -> SrsResult<()> {
+        let mut conn = self.pool.get_conn()?;
+
+        // Get cards for words in the sentence
+        let card_type = review.card_type();
+        let mut cards = self.get_sentence_cards(&mut conn, &review.sentence().id, card_type)?;
+
+        let interval_modifier = self.interval_modifier(&mut conn, &review.sentence().id)?;
+
+        // Either the whole review is written or none of it is, so retrying a failed answer doesn't
+        // review the cards that were written before the failure a second time
+        let learned_cards = db::in_transaction(&mut conn,
+            |conn| self.write_review(conn, &review, &mut cards, score, interval_modifier))?;
+
+        self.cards_reviewed_today += cards.len() as i32;
+        self.cards_learned_today += learned_cards.len() as i32;
+
+        // Write the new card states through to the cache
+        self.card_cache.update_cards(&cards);
+
+        if let (Review::New { sentence, .. }, Some(long_chars)) = (&review, self.scheduler_config.long_sentence_chars) {
+            self.last_new_sentence_long = Some(sentence.text.chars().count() as i32 > long_chars);
+        }
+
+        // The review's been committed, so failing now would get it retried and counted twice. None
+        // of these are worth that, so they're only logged.
+        if self.auto_retire_redundant_sentences {
+            if let Err(err) = self.retire_if_redundant(review.sentence()) {
+                log::error!("Failed to retire redundant sentence {}: {err}", review.sentence().text);
+            }
+        }
+
+        if let Err(err) = self.record_dues_finished() {
+            log::error!("Failed to record whether today's dues are finished: {err}");
+        }
+
+        if let Err(err) = revision::bump(&mut conn) {
+            log::error!("Failed to bump the counts revision: {err}");
+        }
+
+        Ok(())
+    }
+
+    fn forecast(&self, days: usize) -> SrsResult<Vec<i32>> {
+        let mut conn = self.pool.get_conn()?;
+
+        let dues: Vec<NaiveDateTime> = conn.exec(
+            format!("SELECT cards.due FROM cards WHERE cards.due IS NOT NULL && {NOT_EXCLUDED}"), ())?;
+
+        Ok(forecast::due_counts_by_day(self.clock.now(), &dues, days))
+    }
+
+    fn forecast_with_new_cards(&self, days: usize, new_cards_per_day: i32) -> SrsResult<Vec<i32>> {
+        let mut forecast = self.forecast(days)?;
+        forecast::add_new_card_reviews(&mut forecast, &Schedule::good_review_days(days)?, new_cards_per_day);
+        Ok(forecast)
+    }
+
+    fn connection_state(&self) -> ConnectionState {
+        self.pool.state()
+    }
+
+    fn clock_behind(&self) -> Option<chrono::DateTime<chrono::Local>> {
+        self.clock.behind()
+    }
+
+    fn collection_stats(&self) -> SrsResult<CollectionStats> {
+        let mut conn = self.pool.get_conn()?;
+
+        let stats = conn.exec_first(
+            format!(r"SELECT CAST(COALESCE(SUM({DUE_NOW}), 0) AS SIGNED),
+                     CAST(COALESCE(SUM(cards.due IS NOT NULL && {IN_LEARNING_QUEUE} && cards.due > :now && cards.due < :latest_time), 0) AS SIGNED),
+                     CAST(COALESCE(SUM(cards.due IS NULL), 0) AS SIGNED),
+                     CAST(COALESCE(SUM(cards.due IS NOT NULL && cards.review_count < :graduated_count), 0) AS SIGNED),
+                     CAST(COALESCE(SUM(cards.review_count >= :graduated_count && cards.card_type = 'recognition'), 0) AS SIGNED)
+              FROM cards
+              WHERE {NOT_EXCLUDED}"),
+            params! {
+                "now" => self.clock.now().naive_utc(),
+                "latest_time" => super::end_of_day(self.clock.now()).naive_utc(),
+                "learning_queue_secs" => LEARNING_QUEUE_INTERVAL.as_secs(),
+                "graduated_count" => INITIAL_INTERVALS.len(),
+            })?
+            .map(|(due_cards, learning_due_later, new_cards, learning_cards, known_cards)| CollectionStats {
+                due_cards,
+                learning_due_later,
+                new_cards,
+                learning_cards,
+                known_cards,
+            });
+
+        // Nothing's due while on vacation, as nothing gets reviewed until it ends
+        vacation::pause_counts(&mut conn, stats.unwrap_or_default())
+    }
+
+    fn counts_if_changed(&self, since: Option<CountsRevision>) -> SrsResult<Option<(CollectionStats, CountsRevision)>> {
+        revision::counts_if_changed(&mut self.pool.get_conn()?, since, self.clock.now(), || self.collection_stats())
+    }
+
+    fn daily_stats(&self, days: usize) -> SrsResult<Vec<DailyStats>> {
+        let mut stats = daily_stats::load(&mut self.pool.get_conn()?, self.clock.now().naive_local().date(), days)?;
+
+        // Cards can come due later in the day without a review to unset today's flag
+        if let Some(today) = stats.last_mut().filter(|today| today.dues_finished) {
+            today.dues_finished = !self.has_dues()?;
+        }
+
+        Ok(stats)
+    }
+
+    fn get_stats_export(&self, days: usize, forecast_days: usize) -> SrsResult<StatsExport> {
+        let daily_stats = self.daily_stats(days)?;
+        let since = self.clock.now().naive_utc() - chrono::Duration::days(days as i64);
+        let retention = retention::measure_daily(&mut self.pool.get_conn()?, since)?;
+        let forecast = self.forecast(forecast_days)?;
+
+        Ok(StatsExport::new(&daily_stats, &retention, &forecast, self.clock.now().naive_local().date()))
+    }
+
+    fn get_schedules(&self) -> SrsResult<Vec<Schedule>> {
+        Ok(self.pool.get_conn()?.query_map(
+            format!(r"SELECT cards.due, cards.interval_secs, cards.review_count, cards.ease
+              FROM cards
+              WHERE cards.card_type = 'recognition' && {NOT_EXCLUDED}"),
+            |(due, interval, review_count, ease): (Option<NaiveDateTime>, Option<u64>, i32, f32)| {
+                Schedule { due, interval: interval.map(Duration::from_secs), review_count, ease }
+            })?)
+    }
+
+    fn get_redundant_sentences(&self) -> SrsResult<Vec<Sentence>> {
+        self.find_redundant_sentences(None)
+    }
+
+    fn retire_redundant_sentences(&mut self) -> SrsResult<usize> {
+        // Check each sentence again before retiring it, as retiring one sentence can stop another
+        // one that shares its words from being redundant
+        let mut retired = 0;
+        for sentence in self.get_redundant_sentences()? {
+            if self.retire_if_redundant(&sentence)? {
+                retired += 1;
+            }
+        }
+
+        Ok(retired)
+    }
+
+    fn cards_learned_today(&self) -> i32 {
+        self.cards_learned_today
+    }
+
+    fn cards_reviewed_today(&self) -> i32 {
+        self.cards_reviewed_today
+    }
+
+    fn get_blacklist(&self) -> SrsResult<Vec<String>> {
+        blacklist::load(&mut self.pool.get_conn()?)
+    }
+
+    fn blacklist_word(&mut self, word: &str) -> SrsResult<()> {
+        log::info!("Blacklisting {word}");
+
+        // Cached sentences would still include the word's card
+        self.card_cache.clear();
+
+        let mut conn = self.pool.get_conn()?;
+        blacklist::add(&mut conn, word)?;
+        revision::bump(&mut conn)
+    }
+
+    fn unblacklist_word(&mut self, word: &str) -> SrsResult<()> {
+        log::info!("Removing {word} from the blacklist");
+
+        self.card_cache.clear();
+
+        let mut conn = self.pool.get_conn()?;
+        blacklist::remove(&mut conn, word)?;
+        revision::bump(&mut conn)
+    }
+
+    fn get_suspended_words(&self) -> SrsResult<Vec<String>> {
+        blacklist::load_suspended(&mut self.pool.get_conn()?)
+    }
+
+    fn suspend_word(&mut self, word: &str) -> SrsResult<()> {
+        log::info!("Suspending {word}");
+
+        self.card_cache.clear();
+
+        let mut conn = self.pool.get_conn()?;
+        blacklist::suspend(&mut conn, word)?;
+        revision::bump(&mut conn)
+    }
+
+    fn unsuspend_word(&mut self, word: &str) -> SrsResult<()> {
+        log::info!("Unsuspending {word}");
+
+        self.card_cache.clear();
+
+        let mut conn = self.pool.get_conn()?;
+        blacklist::unsuspend(&mut conn, word)?;
+        revision::bump(&mut conn)
+    }
+
+    fn get_word_info(&self, word: &str) -> SrsResult<Option<WordInfo>> {
+        let row: Option<(String, Option<String>, Option<String>, i32, i32, f32, Option<u64>, Option<NaiveDateTime>, bool, bool)> =
+            self.pool.get_conn()?.exec_first(
+                r"SELECT words.word, words.reading, word_levels.level,
+                         (SELECT count(*)
+                          FROM sentence_words
+                          INNER JOIN sentences ON sentences.id = sentence_words.sentence_id
+                          WHERE sentence_words.word_id = words.id && sentences.deleted IS NULL),
+                         cards.review_count, cards.ease, cards.interval_secs, cards.due,
+                         EXISTS(SELECT 1 FROM word_blacklist WHERE word_blacklist.word = words.word),
+                         EXISTS(SELECT 1 FROM word_suspensions WHERE word_suspensions.word = words.word)
+                  FROM words
+                  INNER JOIN cards ON cards.word_id = words.id && cards.card_type = 'recognition'
+                  LEFT JOIN word_levels ON word_levels.word = words.word
+                  WHERE words.word = :word",
+                params! { "word" => normalize::word(word) })?;
+
+        Ok(row.map(|(word, reading, level, sentences, review_count, ease, interval_secs, due, blacklisted, suspended)| WordInfo {
+            word,
+            reading,
+            level,
+            sentences,
+            review_count,
+            ease,
+            interval: interval_secs.map(Duration::from_secs),
+            due,
+            blacklisted,
+            suspended,
+        }))
+    }
+
+    fn get_flagged_names(&self) -> SrsResult<Vec<String>> {
+        names::load_flagged(&mut self.pool.get_conn()?)
+    }
+
+    fn rescue_name(&mut self, word: &str) -> SrsResult<()> {
+        log::info!("Rescuing {word}, it's not a name");
+
+        self.card_cache.clear();
+
+        let mut conn = self.pool.get_conn()?;
+        names::rescue(&mut conn, word)?;
+        revision::bump(&mut conn)
+    }
+
+    fn get_sentence_words(&self, sentence_id: &Uuid) -> SrsResult<Vec<String>> {
+        Ok(self.pool.get_conn()?.exec(
+            r"SELECT words.word
+              FROM sentence_words
+              INNER JOIN words ON words.id = sentence_words.word_id
+              INNER JOIN sentences ON sentences.id = sentence_words.sentence_id
+              WHERE sentence_words.sentence_id = :sentence_id
+              ORDER BY LOCATE(words.word, sentences.text)",
+            params! { "sentence_id" => sentence_id.to_string() })?)
+    }
+
+    fn set_sentence_words(&mut self, sentence_id: &Uuid, words: &[String]) -> SrsResult<()> {
+        log::info!("Overriding words for sentence {sentence_id}: {words:?}");
+
+        if words.is_empty() {
+            return Err("A sentence needs at least one word".into());
+        }
+
+        self.card_cache.clear();
+
+        let mut conn = self.pool.get_conn()?;
+        let sentence_id = sentence_id.to_string();
+
+        self.replace_sentence_words(&mut conn, &sentence_id, words)?;
+
+        // Mark the sentence so its words don't get replaced if sentences are retokenized
+        conn.exec_drop("UPDATE sentences SET custom_words = TRUE WHERE id = :sentence_id",
+            params! { "sentence_id" => sentence_id.as_str() })?;
+
+        revision::bump(&mut conn)?;
+
+        Ok(())
+    }
+
+    fn get_expressions(&self) -> SrsResult<Vec<String>> {
+        expressions::load_text(&mut self.pool.get_conn()?)
+    }
+
+    fn add_expression(&mut self, expression: &str) -> SrsResult<()> {
+        log::info!("Adding expression {expression}");
+        expressions::add(&mut self.pool.get_conn()?, expression)
+    }
+
+    fn remove_expression(&mut self, expression: &str) -> SrsResult<()> {
+        log::info!("Removing expression {expression}");
+        expressions::remove(&mut self.pool.get_conn()?, expression)
+    }
+
+    fn retokenize_sentences(&mut self) -> SrsResult<usize> {
+        log::info!("Retokenizing sentences");
+
+        self.card_cache.clear();
+
+        let mut conn = self.pool.get_conn()?;
+
+        let blacklist: HashSet<String> = blacklist::load(&mut conn)?.into_iter().collect();
+        let merges = word_merges::load(&mut conn)?;
+        let expressions = expressions::load(&mut conn)?;
+
+        let sentences: Vec<(String, String)> = conn.query("SELECT id, text FROM sentences WHERE NOT custom_words && deleted IS NULL")?;
+
+        let mut changed = 0;
+        for (sentence_id, text) in sentences.iter() {
+            let mut tokens = self.split_tokens(text, &merges, &expressions);
+            tokens.retain(|token| !blacklist.contains(&token.word));
+            let words = tokens.iter().map(|token| token.word.clone()).collect::<Vec<String>>();
+
+            if words.is_empty() {
+                continue;
+            }
+
+            let current_words: HashSet<String> = conn.exec::<String, _, _>(
+                r"SELECT words.word
+                  FROM sentence_words
+                  INNER JOIN words ON words.id = sentence_words.word_id
+                  WHERE sentence_words.sentence_id = :sentence_id",
+                params! { "sentence_id" => sentence_id.as_str() })?
+                .into_iter()
+                .collect();
+
+            if current_words != words.iter().cloned().collect::<HashSet<String>>() {
+                self.replace_sentence_words(&mut conn, sentence_id, &words)?;
+                changed += 1;
+            }
+
+            // This also fills in where the words are for sentences from before that was stored
+            Self::record_word_offsets(&mut conn, sentence_id, &tokens)?;
+        }
+
+        revision::bump(&mut conn)?;
+
+        log::info!("Retokenized {changed} sentences");
+
+        Ok(changed)
+    }
+
+    fn merge_words(&mut self, word: &str, into: &str) -> SrsResult<()> {
+        log::info!("Merging {word} into {into}");
+
+        self.card_cache.clear();
+
+        // Merging touches several tables, so it's done in a transaction to never leave a word
+        // half merged
+        let mut conn = self.pool.get_conn()?;
+        conn.query_drop("START TRANSACTION")?;
+
+        match Self::merge_words_in(&mut conn, word, into, self.clock.now().naive_utc()) {
+            Ok(()) => {
+                conn.query_drop("COMMIT")?;
+                Ok(())
+            },
+            Err(err) => {
+                conn.query_drop("ROLLBACK").ok();
+                Err(err)
+            }
+        }
+    }
+
+    fn get_kanji_stats(&self) -> SrsResult<Vec<KanjiStats>> {
+        kanji::load(&mut self.pool.get_conn()?)
+    }
+
+    fn import_word_levels(&mut self, level: &str, words: &[String]) -> SrsResult<()> {
+        levels::import(&mut self.pool.get_conn()?, level, words)
+    }
+
+    fn mark_words_known(&mut self, words: &[String], interval_days: u32) -> SrsResult<usize> {
+        if words.is_empty() {
+            return Ok(0);
+        }
+
+        log::info!("Marking {} words as known with an interval of {interval_days} days", words.len());
+
+        self.card_cache.clear();
+        let mut conn = self.pool.get_conn()?;
+
+        // Words that aren't in any sentences yet get cards too, so they're known when they turn up
+        let word_ids = self.add_words(&mut conn, words)?;
+        let new_word_ids: Vec<String> = conn.exec(
+            format!("SELECT word_id FROM cards WHERE card_type = 'recognition' && due IS NULL && word_id IN ({})",
+                    db::placeholders(word_ids.len())),
+            word_ids)?;
+
+        if new_word_ids.is_empty() {
+            return Ok(0);
+        }
+
+        // Graduate the cards, as if they'd been through the learning steps
+        let (word_id_list, mut update_params) = db::named_placeholders("word_id", &new_word_ids);
+        update_params.extend([
+            ("review_count".to_string(), Value::from(INITIAL_INTERVALS.len())),
+            ("interval_secs".to_string(), Value::from(interval_days as u64 * 24 * 60 * 60)),
+            ("due".to_string(), Value::from((self.clock.now() + chrono::Duration::days(interval_days as i64)).naive_utc())),
+            ("modified".to_string(), Value::from(self.clock.now().naive_utc())),
+        ]);
+        conn.exec_drop(
+            format!(r"UPDATE cards
+                      SET review_count = :review_count,
+                          interval_secs = :interval_secs,
+                          due = :due,
+                          modified = :modified
+                      WHERE card_type = 'recognition' && word_id IN ({word_id_list})"),
+            update_params)?;
+
+        for word_id in new_word_ids.iter() {
+            sync::record_change(&mut conn, sync::CARD_ROW, word_id)?;
+        }
+
+        kanji::update(&mut conn, &kanji::kanji_in(words.iter()), MATURE_INTERVAL_SECS)?;
+        revision::bump(&mut conn)?;
+
+        Ok(new_word_ids.len())
+    }
+
+    fn level_progress(&self) -> SrsResult<Vec<LevelProgress>> {
+        levels::load_progress(&mut self.pool.get_conn()?, MATURE_INTERVAL_SECS)
+    }
+
+    fn check_integrity(&mut self, repair: bool) -> SrsResult<IntegrityReport> {
+        log::info!("Checking database integrity (repair: {repair})");
+
+        if repair {
+            self.card_cache.clear();
+        }
+
+        let mut conn = self.pool.get_conn()?;
+
+        let mut report = IntegrityReport {
+            repaired: repair,
+            ..Default::default()
+        };
+
+        report.orphaned_sentence_words = integrity::check_rows(&mut conn, "sentence_words",
+            "sentence_id NOT IN (SELECT id FROM sentences) || word_id NOT IN (SELECT id FROM words)", repair)?;
+
+        report.cards_without_words = integrity::check_rows(&mut conn, "cards",
+            "word_id NOT IN (SELECT id FROM words)", repair)?;
+
+        let sentences_without_words: Vec<(String, String)> = conn.query(
+            "SELECT id, text FROM sentences WHERE id NOT IN (SELECT sentence_id FROM sentence_words)")?;
+        report.sentences_without_words = sentences_without_words.len();
+
+        if repair && !sentences_without_words.is_empty() {
+            // Split the sentences again, and delete any that still don't have any words
+            let blacklist: HashSet<String> = blacklist::load(&mut conn)?.into_iter().collect();
+            let merges = word_merges::load(&mut conn)?;
+            let expressions = expressions::load(&mut conn)?;
+
+            for (sentence_id, text) in sentences_without_words.iter() {
+                let mut tokens = self.split_tokens(text, &merges, &expressions);
+                tokens.retain(|token| !blacklist.contains(&token.word));
+                let words = tokens.iter().map(|token| token.word.clone()).collect::<Vec<String>>();
+
+                if words.is_empty() {
+                    log::warn!("Deleting sentence with no words: {text}");
+                    conn.exec_drop("DELETE FROM sentences WHERE id = :id",
+                        params! { "id" => sentence_id.as_str() })?;
+                }
+                else {
+                    self.replace_sentence_words(&mut conn, sentence_id, &words)?;
+                    Self::record_word_offsets(&mut conn, sentence_id, &tokens)?;
+                }
+            }
+        }
+
+        // Cards can't be reviewed without an interval once they've graduated, so they're put back
+        // into learning, which has fixed intervals
+        let no_interval = "cards.due IS NOT NULL && cards.interval_secs IS NULL";
+        let due_without_interval: Option<i64> = conn.query_first(format!("SELECT count(*) FROM cards WHERE {no_interval}"))?;
+        report.due_cards_without_interval = due_without_interval.unwrap_or(0) as usize;
+
+        if repair && report.due_cards_without_interval > 0 {
+            conn.exec_drop(format!("UPDATE cards SET cards.review_count = 0, cards.interval_secs = :interval_secs WHERE {no_interval}"),
+                params! { "interval_secs" => INITIAL_INTERVALS[0].as_secs() })?;
+        }
+
+        let words: Vec<String> = conn.query("SELECT word FROM words")?;
+        report.duplicate_words = integrity::find_duplicate_words(&words);
+
+        if repair {
+            for (word, into) in report.duplicate_words.iter() {
+                self.merge_words(word, into)?;
+            }
+
+            revision::bump(&mut conn)?;
+        }
+
+        log::info!("Integrity check found {} problems", report.problem_count());
+
+        Ok(report)
+    }
+
+    fn vacuum(&mut self, review_retention_days: u32) -> SrsResult<VacuumReport> {
+        log::info!("Vacuuming database, keeping {review_retention_days} days of review history");
+
+        self.card_cache.clear();
+
+        let mut conn = self.pool.get_conn()?;
+        let size_before = vacuum::database_size(&mut conn)?;
+
+        let mut report = VacuumReport::default();
+
+        // Words left behind by sentences being edited or deleted, studied words are kept so their
+        // progress isn't lost if they come back
+        let unused_words: Vec<String> = conn.query(
+            r"SELECT words.id
+              FROM words
+              INNER JOIN cards ON cards.word_id = words.id
+              WHERE cards.due IS NULL
+                 && words.id NOT IN (SELECT word_id FROM sentence_words)")?;
+
+        for word_id in unused_words.iter() {
+            Self::remove_word_if_unused(&mut conn, word_id)?;
+        }
+
+        report.unused_words = unused_words.len();
+
+        if report.unused_words > 0 {
+            kanji::rebuild(&mut conn, MATURE_INTERVAL_SECS)?;
+        }
+
+        let cutoff = self.clock.now() - chrono::Duration::days(review_retention_days as i64);
+        conn.exec_drop("DELETE FROM reviews WHERE review_date < :cutoff",
+            params! { "cutoff" => cutoff.naive_utc() })?;
+        report.old_reviews = conn.affected_rows() as usize;
+        sync::forget_deleted_reviews(&mut conn)?;
+
+        vacuum::compact(&mut conn, &["sentences", "words", "sentence_words", "cards", "reviews", "kanji"])?;
+
+        report.reclaimed_bytes = size_before - vacuum::database_size(&mut conn)?;
+        revision::bump(&mut conn)?;
+
+        log::info!("Vacuum removed {} unused words and {} old reviews, reclaiming {} bytes",
+                   report.unused_words, report.old_reviews, report.reclaimed_bytes);
+
+        Ok(report)
+    }
+
+    fn clear_cache(&mut self) {
+        self.card_cache.clear();
+    }
+
+    fn rebuild_cards(&mut self) -> SrsResult<RebuildReport> {
+        log::info!("Rebuilding cards from sentences");
+
+        let mut report = RebuildReport {
+            sentences_changed: self.retokenize_sentences()?,
+            ..Default::default()
+        };
+
+        self.card_cache.clear();
+
+        let mut conn = self.pool.get_conn()?;
+
+        // Words can be left without a card by older versions or a failed import
+        conn.exec_drop(
+            r"INSERT IGNORE INTO cards (word_id, review_count, ease, added_order)
+              SELECT DISTINCT sentence_words.word_id, 0, :ease, 0
+              FROM sentence_words
+              LEFT JOIN cards ON cards.word_id = sentence_words.word_id && cards.card_type = 'recognition'
+              WHERE cards.word_id IS NULL",
+            params! { "ease" => DEFAULT_EASE })?;
+        report.cards_added = conn.affected_rows() as usize;
+
+        report.readings_updated = rebuild::update_readings(&mut conn, !self.katakana_readings)?;
+
+        // Words the sentences aren't split into anymore. Studied ones are kept, like in vacuum.
+        let unused_words: Vec<String> = conn.query(
+            r"SELECT DISTINCT words.id
+              FROM words
+              INNER JOIN cards ON cards.word_id = words.id
+              WHERE cards.due IS NULL
+                 && words.id NOT IN (SELECT word_id FROM sentence_words)")?;
+
+        // Counted by what was actually deleted, as a word can have more than one unlearned card
+        for word_id in unused_words.iter() {
+            if Self::remove_word_if_unused(&mut conn, word_id)? {
+                report.words_removed += 1;
+            }
+        }
+
+        if !report.is_unchanged() {
+            kanji::rebuild(&mut conn, MATURE_INTERVAL_SECS)?;
+            revision::bump(&mut conn)?;
+        }
+
+        log::info!("Rebuild changed {} sentences, added {} cards, removed {} words and updated {} readings",
+                   report.sentences_changed, report.cards_added, report.words_removed, report.readings_updated);
+
+        Ok(report)
+    }
+
+    fn spread_dues(&mut self, days: u32) -> SrsResult<SpreadReport> {
+        log::info!("Spreading dues over {days} days");
+
+        self.card_cache.clear();
+        let mut conn = self.pool.get_conn()?;
+
+        let window_end = super::end_of_day(self.clock.now()) + chrono::Duration::days(days.max(1) as i64 - 1);
+
+        let cards = conn.exec_map(
+            r"SELECT word_id, card_type, due, interval_secs
+              FROM cards
+              WHERE due IS NOT NULL && due < :window_end && interval_secs >= :min_interval_secs",
+            params! {
+                "window_end" => window_end.naive_utc(),
+                "min_interval_secs" => MIN_SPREAD_INTERVAL.as_secs(),
+            },
+            |(word_id, card_type, due, interval_secs): (String, String, NaiveDateTime, u64)|
+                ((word_id, card_type), due, Duration::from_secs(interval_secs)))?;
+
+        let (moved, report) = spread::spread(&cards, self.clock.now(), days);
+
+        conn.exec_batch(
+            r"UPDATE cards SET due = :due, modified = :modified WHERE word_id = :word_id && card_type = :card_type",
+            moved.iter().map(|((word_id, card_type), due)| params! {
+                "due" => due,
+                "modified" => self.clock.now().naive_utc(),
+                "word_id" => word_id.as_str(),
+                "card_type" => card_type.as_str(),
+            }))?;
+
+        // Only recognition cards are synced
+        for ((word_id, _), _) in moved.iter().filter(|((_, card_type), _)| card_type.as_str() == "recognition") {
+            sync::record_change(&mut conn, sync::CARD_ROW, word_id)?;
+        }
+
+        if report.cards_moved > 0 {
+            revision::bump(&mut conn)?;
+        }
+
+        log::info!("Moved {} cards, the busiest day went from {} to {} cards",
+            report.cards_moved, report.busiest_day_before, report.busiest_day_after);
+
+        Ok(report)
+    }
+
+    fn get_vacation(&self) -> SrsResult<Option<Vacation>> {
+        vacation::current(&mut self.pool.get_conn()?)
+    }
+
+    fn start_vacation(&mut self) -> SrsResult<()> {
+        log::info!("Starting vacation");
+        vacation::start(&mut self.pool.get_conn()?, self.clock.now().naive_utc())
+    }
+
+    fn end_vacation(&mut self) -> SrsResult<Vacation> {
+        self.card_cache.clear();
+        vacation::end(&mut self.pool.get_conn()?, self.clock.now().naive_utc(), true)
+    }
+
+    fn get_suggested_sentences(&self, new_word_limit: i32, page: PageRequest) -> SrsResult<Page<(Sentence, Vec<String>)>> {
+        log::info!("Getting recommended i+{new_word_limit} sentences");
+
+        // The whole collection is ranked, as a sentence's score depends on the sentences around it,
+        // so the page is taken from the ranked sentences
+        let ranked = self.with_word_graph(|graph| graph.suggest(new_word_limit.max(0) as usize))?;
+
+        let rows = ranked.into_iter()
+            .skip(page.cursor)
+            .take(page.limit + 1)
+            .collect();
+
+        Ok(Page::from_rows(rows, page))
+    }
+
+    fn get_unlocking_words(&self, limit: usize) -> SrsResult<Vec<UnlockingWord>> {
+        let new_word_limit = self.scheduler_config.max_new_words_per_sentence.map_or(1, |max| max.max(1) as usize);
+
+        let mut words = self.with_word_graph(|graph| graph.unlocking_words(new_word_limit))?;
+        words.truncate(limit);
+
+        Ok(words)
+    }
+}
+
+impl SyncStore for WordieSrsAlgorithm {
+    fn changes_since(&self, revision: i64) -> SrsResult<(Vec<SyncChange>, i64)> {
+        let mut conn = self.pool.get_conn()?;
+
+        let rows = sync::changed_rows(&mut conn, revision)?;
+        let latest_revision = rows.last().map_or(revision, |(revision, _, _)| *revision);
+
+        // The log only stores which rows changed, so send their current state
+        let mut changes = Vec::new();
+        for (_, kind, row_id) in rows.iter() {
+            let change = match kind.as_str() {
+                // A row with a bad id is skipped like any other row we can't send
+                sync::SENTENCE_ROW => match Uuid::from_str(row_id) {
+                    Ok(id) => conn.exec_first("SELECT text FROM sentences WHERE id = :id",
+                            params! { "id" => row_id })?
+                        .map(|text| SyncChange::Sentence(Sentence { id, text })),
+                    Err(_) => None,
+                },
+                sync::CARD_ROW => conn.exec_first(
+                        r"SELECT words.word, cards.review_count, cards.ease, cards.interval_secs, cards.due, cards.modified
+                          FROM cards
+                          INNER JOIN words ON words.id = cards.word_id
+                          WHERE cards.word_id = :id && cards.card_type = 'recognition'",
+                        params! { "id" => row_id })?
+                    .map(|(word, review_count, ease, interval, due, modified)
+                          : (String, i32, f32, Option<u64>, Option<NaiveDateTime>, Option<NaiveDateTime>)| SyncChange::Card {
+                        word,
+                        review_count,
+                        ease,
+                        interval_secs: interval,
+                        due: due.map(|due| due.timestamp()),
+                        modified: modified.map_or(0, |modified| modified.timestamp()),
+                    }),
+                sync::REVIEW_ROW => conn.exec_first(
+                        r"SELECT reviews.id, words.word, reviews.sentence_id, reviews.review_date, reviews.difficulty, reviews.last_interval_secs
+                          FROM reviews
+                          INNER JOIN words ON words.id = reviews.word_id
+                          WHERE reviews.id = :id",
+                        params! { "id" => row_id })?
+                    .and_then(|(id, word, sentence_id, review_date, difficulty, last_interval_secs)
+                               : (String, String, Option<String>, NaiveDateTime, Option<i32>, Option<u64>)| Some(SyncChange::Review {
+                        id: Uuid::from_str(&id).ok()?,
+                        word,
+                        sentence_id: sentence_id.and_then(|sentence_id| Uuid::from_str(&sentence_id).ok()),
+                        review_date: review_date.timestamp(),
+                        difficulty,
+                        last_interval_secs,
+                    })),
+                sync::TRASH_ROW => match Uuid::from_str(row_id) {
+                    Ok(id) => {
+                        let deleted: Option<Option<NaiveDateTime>> = conn.exec_first("SELECT deleted FROM sentences WHERE id = :id",
+                            params! { "id" => row_id })?;
+
+                        // A sentence that's gone has been purged from the trash, which is still
+                        // sent so other devices delete it too
+                        let deleted = deleted.unwrap_or_else(|| Some(self.clock.now().naive_utc()));
+
+                        Some(SyncChange::SentenceTrashed { id, deleted: deleted.map(|deleted| deleted.timestamp()) })
+                    },
+                    Err(_) => None,
+                },
+                _ => None,
+            };
+
+            match change {
+                Some(change) => changes.push(change),
+                None => log::warn!("Skipping unknown or invalid row in sync log: {kind} {row_id}"),
+            }
+        }
+
+        Ok((changes, latest_revision))
+    }
+
+    fn apply_changes(&mut self, changes: &[SyncChange]) -> SrsResult<()> {
+        log::info!("Applying {} synced changes", changes.len());
+
+        // Synced reviews change cards underneath the cache
+        self.card_cache.clear();
+
+        for change in changes.iter() {
+            match change {
+                SyncChange::Sentence(sentence) => {
+                    let existing: Option<String> = self.pool.get_conn()?.exec_first("SELECT text FROM sentences WHERE id = :id",
+                        params! { "id" => sentence.id.to_string() })?;
+
+                    match existing {
+                        // Purged from the trash here, so it stays deleted
+                        None if sync::is_logged(&mut self.pool.get_conn()?, sync::TRASH_ROW, &sentence.id.to_string())? => {},
+                        None => { self.add_sentences(std::slice::from_ref(sentence))?; },
+                        // Edited on the other device, this is logged again so it reaches the rest
+                        Some(text) if text != sentence.text => self.set_sentence_text(&sentence.id, &sentence.text)?,
+                        Some(_) => {},
+                    }
+                },
+                SyncChange::Card { word, review_count, ease, interval_secs, due, modified } => {
+                    let mut conn = self.pool.get_conn()?;
+
+                    let word_id: Option<String> = conn.exec_first("SELECT id FROM words WHERE word = :word",
+                        params! { "word" => word.as_str() })?;
+
+                    let word_id = match word_id {
+                        Some(word_id) => word_id,
+                        None => {
+                            log::warn!("Skipping synced card for unknown word {word}");
+                            continue;
+                        },
+                    };
+
+                    // Last write wins, so only take the card if it was reviewed after ours
+                    let modified = NaiveDateTime::from_timestamp(*modified, 0);
+                    conn.exec_drop(
+                        r"UPDATE cards
+                          SET cards.review_count = :review_count,
+                              cards.ease = :ease,
+                              cards.interval_secs = :interval_secs,
+                              cards.due = :due,
+                              cards.modified = :modified
+                          WHERE cards.word_id = :id
+                             && cards.card_type = 'recognition'
+                             && (cards.modified IS NULL || cards.modified < :modified)",
+                        params! {
+                            "id" => word_id.as_str(),
+                            "review_count" => *review_count,
+                            "ease" => *ease,
+                            "interval_secs" => interval_secs,
+                            "due" => due.map(|due| NaiveDateTime::from_timestamp(due, 0)),
+                            "modified" => modified,
+                        })?;
+
+                    if conn.affected_rows() > 0 {
+                        sync::record_change(&mut conn, sync::CARD_ROW, &word_id)?;
+                    }
+                },
+                SyncChange::Review { id, word, sentence_id, review_date, difficulty, last_interval_secs } => {
+                    let mut conn = self.pool.get_conn()?;
+                    let id = id.to_string();
+
+                    let existing: Option<String> = conn.exec_first("SELECT id FROM reviews WHERE id = :id",
+                        params! { "id" => id.as_str() })?;
+                    if existing.is_some() {
+                        continue;
+                    }
+
+                    let word_id: Option<String> = conn.exec_first("SELECT id FROM words WHERE word = :word",
+                        params! { "word" => word.as_str() })?;
+
+                    let word_id = match word_id {
+                        Some(word_id) => word_id,
+                        None => {
+                            log::warn!("Skipping synced review for unknown word {word}");
+                            continue;
+                        },
+                    };
+
+                    conn.exec_drop(r"INSERT INTO reviews (id, word_id, sentence_id, review_date, difficulty, last_interval_secs)
+                                     VALUES (:id, :word_id, :sentence_id, :review_date, :difficulty, :last_interval_secs)",
+                        params! {
+                            "id" => id.as_str(),
+                            "word_id" => word_id.as_str(),
+                            "sentence_id" => sentence_id.map(|sentence_id| sentence_id.to_string()),
+                            "review_date" => NaiveDateTime::from_timestamp(*review_date, 0),
+                            "difficulty" => difficulty,
+                            "last_interval_secs" => last_interval_secs,
+                        })?;
+
+                    sync::record_change(&mut conn, sync::REVIEW_ROW, &id)?;
+                },
+                SyncChange::SentenceTrashed { id, deleted } => {
+                    let mut conn = self.pool.get_conn()?;
+
+                    let current: Option<Option<NaiveDateTime>> = conn.exec_first("SELECT deleted FROM sentences WHERE id = :id",
+                        params! { "id" => id.to_string() })?;
+
+                    // Only logged again when it changes anything, so it isn't sent back and forth forever
+                    match (current, deleted) {
+                        (Some(None), Some(deleted)) => trash::trash(&mut conn, std::slice::from_ref(id), NaiveDateTime::from_timestamp(*deleted, 0))?,
+                        (Some(Some(_)), None) => trash::restore(&mut conn, std::slice::from_ref(id))?,
+                        _ => continue,
+                    }
+
+                    sync::record_change(&mut conn, sync::TRASH_ROW, &id.to_string())?;
+                },
+            }
+        }
+
+        revision::bump(&mut self.pool.get_conn()?)?;
+
+        Ok(())
+    }
+
+    fn sync_revision(&self, name: &str) -> SrsResult<i64> {
+        sync::get_revision(&mut self.pool.get_conn()?, name)
+    }
+
+    fn set_sync_revision(&mut self, name: &str, revision: i64) -> SrsResult<()> {
+        sync::set_revision(&mut self.pool.get_conn()?, name, revision)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::QueueState;
+
+    /// The database for the tests that need one, which is wiped by them
+    const TEST_DB_URL_VAR: &str = "WORDIE_TEST_DB_URL";
+
+    fn test_algorithm() -> WordieSrsAlgorithm {
+        let db_url = std::env::var(TEST_DB_URL_VAR).expect("WORDIE_TEST_DB_URL should be set to a database to test with");
+
+        let mut srs = WordieSrsAlgorithm::builder()
+            .db_url(&db_url)
+            .new_card_limit(10)
+            .build()
+            .unwrap();
+        srs.reinitialize_db(true).unwrap();
+        srs
+    }
+
+    fn review_count(srs: &WordieSrsAlgorithm, word: &str) -> i64 {
+        srs.pool.get_conn().unwrap()
+            .exec_first("SELECT COUNT(*) FROM reviews INNER JOIN words ON words.id = reviews.word_id WHERE words.word = :word",
+                params! { "word" => word })
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    #[ignore = "needs a mysql database in WORDIE_TEST_DB_URL"]
+    fn merge_words_keeps_review_history() {
+        let mut srs = test_algorithm();
+
+        srs.add_sentences(&[Sentence { id: Uuid::new_v4(), text: "cat".to_string() }]).unwrap();
+        match srs.get_next_card().unwrap() {
+            QueueState::Review(review) => srs.review(review, Difficulty::Good).unwrap(),
+            state => panic!("Expected a review, got {state:?}"),
+        }
+
+        srs.add_sentences(&[Sentence { id: Uuid::new_v4(), text: "kitten".to_string() }]).unwrap();
+        srs.merge_words("cat", "kitten").unwrap();
 
-        Ok(ret)
+        assert_eq!(review_count(&srs, "kitten"), 1);
+        assert_eq!(srs.get_word_info("cat").unwrap().map(|info| info.word), None);
     }
 }